@@ -0,0 +1,10 @@
+fn main() {
+    // The gRPC code is generated from proto/chyes.proto, with a protoc that comes as a crate
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/chyes.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+        tonic_prost_build::compile_protos("proto/chyes.proto").expect("can't compile proto/chyes.proto");
+    }
+}
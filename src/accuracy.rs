@@ -0,0 +1,164 @@
+use crate::analysis::Analysis;
+use crate::chess::*;
+use crate::search::MATE_SCORE;
+
+// Chances of winning and how accurately each player played, for post-game reports
+/*
+A win model turns centipawns into the chance of winning, 1 / (1 + e^(-coefficient * cp)) with
+the score clamped to `clamp` (mates are counted as the clamp too). Lichess fitted the
+coefficient to its games, the Elo model is the one of ratings (and mcts::win_probability):
+400 centipawns are 10 to 1
+
+A move's accuracy comes from the winning chances (in percent) it gave away:
+103.1668 * e^(-0.04354 * (before - after)) - 3.1669, plus 1 for the uncertainty of the evals,
+between 0 and 100. A game's accuracy is the mean of the player's move accuracies weighted by
+how much the winning chances moved around them (the standard deviation over a window of
+positions, between 0.5 and 12), averaged with their harmonic mean, which bad moves drag down
+*/
+// source: https://lichess.org/page/accuracy
+// source: https://github.com/lichess-org/lila/blob/master/modules/analyse/src/main/AccuracyPercent.scala
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WinModel {
+	pub coefficient: f64,
+	// Centipawns, either way
+	pub clamp: i32,
+}
+
+impl WinModel {
+	pub const LICHESS: WinModel = WinModel { coefficient: 0.00368208, clamp: 1000 };
+	pub const ELO: WinModel = WinModel { coefficient: std::f64::consts::LN_10 / 400.0, clamp: 1000 };
+
+	pub fn new(coefficient: f64, clamp: i32) -> Self {
+		WinModel { coefficient, clamp }
+	}
+
+	pub fn win_probability(&self, score: i32) -> f64 {
+		// Between 0 and 1 for the side the score is for
+		let score = score.clamp(-self.clamp, self.clamp) as f64;
+		return 1.0 / (1.0 + (-self.coefficient * score).exp());
+	}
+
+	pub fn win_percent(&self, score: i32) -> f64 {
+		return self.win_probability(score) * 100.0;
+	}
+}
+
+impl Default for WinModel {
+	fn default() -> Self {
+		return WinModel::LICHESS;
+	}
+}
+
+pub fn move_accuracy(before: f64, after: f64) -> f64 {
+	// Win percentages of the player who moved, before and after the move
+	if after >= before {
+		return 100.0;
+	}
+	let accuracy = 103.1668100711649 * (-0.04354415386753951 * (before - after)).exp() - 3.166924740191411;
+	return (accuracy + 1.0).clamp(0.0, 100.0);
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GameAccuracy {
+	// Indexed by color, white first, the accuracy of every move of the player
+	pub moves: [Vec<f64>; 2],
+	// None for a player without moves
+	pub accuracy: [Option<f64>; 2],
+	// Average centipawn loss, clamped like the model
+	pub centipawn_loss: [Option<f64>; 2],
+}
+
+impl GameAccuracy {
+	pub fn of(&self, color: Color) -> Option<f64> {
+		return self.accuracy[color as usize];
+	}
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+	let mean = values.iter().sum::<f64>() / values.len() as f64;
+	return (values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt();
+}
+
+pub fn game_accuracy(first: Color, evals: &[i32], model: &WinModel) -> GameAccuracy {
+	// `evals` from white's point of view, of the position before the first move and of the
+	// position after every move, `first` is the side that played the first move
+	let mut result = GameAccuracy::default();
+	if evals.len() < 2 {
+		return result;
+	}
+
+	let evals: Vec<i32> = evals.iter().map(|eval| (*eval).clamp(-model.clamp, model.clamp)).collect();
+	let percents: Vec<f64> = evals.iter().map(|eval| model.win_percent(*eval)).collect();
+	let moves = evals.len() - 1;
+	let window = (moves / 10).clamp(2, 8).min(percents.len());
+	let mut windows: Vec<&[f64]> = vec![&percents[..window]; window - 2];
+	windows.extend(percents.windows(window));
+	let weights: Vec<f64> = windows.iter().map(|window| standard_deviation(window).clamp(0.5, 12.0)).collect();
+
+	let mut weighted = [(0.0, 0.0); 2];
+	let mut losses = [0.0; 2];
+	for ply in 0..moves {
+		let color = match ply % 2 {
+			0 => first,
+			_ => opponent(first),
+		};
+		let (before, after, loss) = match color {
+			Color::White => (percents[ply], percents[ply + 1], evals[ply] - evals[ply + 1]),
+			Color::Black => (100.0 - percents[ply], 100.0 - percents[ply + 1], evals[ply + 1] - evals[ply]),
+		};
+		let accuracy = move_accuracy(before, after);
+		result.moves[color as usize].push(accuracy);
+		weighted[color as usize].0 += accuracy * weights[ply];
+		weighted[color as usize].1 += weights[ply];
+		losses[color as usize] += loss.max(0) as f64;
+	}
+
+	for color in [Color::White, Color::Black] {
+		let accuracies = &result.moves[color as usize];
+		if accuracies.is_empty() {
+			continue;
+		}
+		let (sum, weight) = weighted[color as usize];
+		let harmonic = accuracies.len() as f64 / accuracies.iter().map(|accuracy| 1.0 / accuracy).sum::<f64>();
+		result.accuracy[color as usize] = Some((sum / weight + harmonic) / 2.0);
+		result.centipawn_loss[color as usize] = Some(losses[color as usize] / accuracies.len() as f64);
+	}
+	return result;
+}
+
+fn opponent(color: Color) -> Color {
+	return match color {
+		Color::White => Color::Black,
+		Color::Black => Color::White,
+	};
+}
+
+impl Analysis {
+	pub fn accuracy(&self, model: &WinModel) -> Option<GameAccuracy> {
+		// Of the main line from the root, None unless every position of it has an evaluation,
+		// a checkmate or a stalemate at the end needs none
+		let mut evals = Vec::new();
+		let mut node = Analysis::ROOT;
+		loop {
+			let current = self.node(node)?;
+			let board = &current.board;
+			let score = match current.eval.as_ref().and_then(|eval| eval.best()) {
+				Some(line) => line.score,
+				None if board.legal_moves().is_empty() && board.is_in_check(board.turn) => -MATE_SCORE,
+				None if board.legal_moves().is_empty() => 0,
+				None => return None,
+			};
+			evals.push(match board.turn {
+				Color::White => score,
+				Color::Black => -score,
+			});
+
+			match current.children.first() {
+				Some(child) => node = *child,
+				None => break,
+			}
+		}
+		return Some(game_accuracy(self.node(Analysis::ROOT)?.board.turn, &evals, model));
+	}
+}
@@ -0,0 +1,343 @@
+use std::io::{self, BufRead, Write};
+
+use crate::chess::*;
+use crate::engine::SearchLimits;
+use crate::error::ChessError;
+use crate::eval::Evaluator;
+use crate::game::Game;
+use crate::search::{AlphaBeta, SearchResult};
+
+// An analysis session, the tree of positions looked at with what the engine said about them
+/*
+Every node is a position, reached from its parent by a move, the root is where the analysis
+started. Playing a move follows the child it leads to or adds a new one, so going back and
+trying something else keeps what was explored before. The first child of a node is its main
+line, the others are variations
+
+Engine output is merged into the node of the position it is about. A node keeps the deepest
+evaluation it was given: the depth and the best lines, best first, every one with its score
+from the side to move and its moves from the node. A shallower search doesn't replace it, so
+coming back to a position doesn't lose what was found there
+*/
+/*
+File layout, one entry per line, nodes are numbered in the order they come, the root is 0:
+chyes analysis <version>
+root <FEN>
+node <parent> <move as UCI writes it>
+eval <node> <depth> <score> <moves of the line...>
+current <node>
+
+Every node comes after its parent, the eval lines of a node are its lines best first
+*/
+
+// Bumped with every change of the file layout, files of other versions are refused
+pub const ANALYSIS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnalysisLine {
+	pub score: i32,
+	pub pv: Vec<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeEval {
+	pub depth: u32,
+	// Best first
+	pub lines: Vec<AnalysisLine>,
+}
+
+impl NodeEval {
+	pub fn best(&self) -> Option<&AnalysisLine> {
+		return self.lines.first();
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalysisNode {
+	pub board: Board,
+	// The move from the parent, None for the root
+	pub mv: Option<Move>,
+	pub parent: Option<usize>,
+	// Main line first
+	pub children: Vec<usize>,
+	pub eval: Option<NodeEval>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+	nodes: Vec<AnalysisNode>,
+	current: usize,
+}
+
+impl Default for Analysis {
+	fn default() -> Self {
+		return Analysis::new(Board::default());
+	}
+}
+
+impl Analysis {
+	pub const ROOT: usize = 0;
+
+	pub fn new(board: Board) -> Self {
+		Analysis {
+			nodes: vec![AnalysisNode { board, mv: None, parent: None, children: Vec::new(), eval: None }],
+			current: Analysis::ROOT,
+		}
+	}
+
+	pub fn from_game(game: &Game) -> Self {
+		// The moves of the game as the main line, the session starts at its first position
+		let mut analysis = Analysis::new(*game.start());
+		for mv in game.moves() {
+			// The game only holds legal moves
+			analysis.play(*mv).unwrap();
+		}
+		analysis.current = Analysis::ROOT;
+		return analysis;
+	}
+
+	pub fn len(&self) -> usize {
+		// Number of nodes, the root included
+		return self.nodes.len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		// There is always the root
+		return false;
+	}
+
+	pub fn node(&self, node: usize) -> Option<&AnalysisNode> {
+		return self.nodes.get(node);
+	}
+
+	pub fn current(&self) -> usize {
+		return self.current;
+	}
+
+	pub fn current_node(&self) -> &AnalysisNode {
+		return &self.nodes[self.current];
+	}
+
+	pub fn board(&self) -> &Board {
+		return &self.nodes[self.current].board;
+	}
+
+	pub fn play(&mut self, mv: Move) -> Result<usize, ChessError> {
+		// Goes to the position after `mv`, adding it to the tree the first time
+		if let Some(child) = self.child(self.current, mv) {
+			self.current = child;
+			return Ok(child);
+		}
+		let mut board = self.nodes[self.current].board;
+		board.try_apply_move(mv).map_err(|_| ChessError::IllegalMove(mv))?;
+
+		let child = self.nodes.len();
+		self.nodes.push(AnalysisNode { board, mv: Some(mv), parent: Some(self.current), children: Vec::new(), eval: None });
+		self.nodes[self.current].children.push(child);
+		self.current = child;
+		return Ok(child);
+	}
+
+	pub fn child(&self, node: usize, mv: Move) -> Option<usize> {
+		return self.nodes.get(node)?.children.iter().find(|child| self.nodes[**child].mv == Some(mv)).copied();
+	}
+
+	pub fn go_to(&mut self, node: usize) -> bool {
+		// False when the tree doesn't have that node
+		if node >= self.nodes.len() {
+			return false;
+		}
+		self.current = node;
+		return true;
+	}
+
+	pub fn back(&mut self) -> bool {
+		// To the parent, false at the root
+		return match self.nodes[self.current].parent {
+			Some(parent) => self.go_to(parent),
+			None => false,
+		};
+	}
+
+	pub fn forward(&mut self) -> bool {
+		// Along the main line, false at its end
+		return match self.nodes[self.current].children.first() {
+			Some(child) => self.go_to(*child),
+			None => false,
+		};
+	}
+
+	pub fn moves_to(&self, node: usize) -> Vec<Move> {
+		// The moves from the root to the node
+		let mut moves = Vec::new();
+		let mut node = node;
+		while let Some(mv) = self.nodes.get(node).and_then(|node| node.mv) {
+			moves.push(mv);
+			node = self.nodes[node].parent.unwrap();
+		}
+		moves.reverse();
+		return moves;
+	}
+
+	pub fn main_line(&self, node: usize) -> Vec<Move> {
+		// The moves from the node to the end of its main line
+		let mut moves = Vec::new();
+		let mut node = node;
+		while let Some(child) = self.nodes.get(node).and_then(|node| node.children.first()) {
+			moves.push(self.nodes[*child].mv.unwrap());
+			node = *child;
+		}
+		return moves;
+	}
+
+	pub fn promote(&mut self, node: usize) -> bool {
+		// Makes the node the main line of its parent, false for the root
+		let parent = match self.nodes.get(node).and_then(|node| node.parent) {
+			Some(parent) => parent,
+			None => return false,
+		};
+		let children = &mut self.nodes[parent].children;
+		let index = children.iter().position(|child| *child == node).unwrap();
+		children[..=index].rotate_right(1);
+		return true;
+	}
+
+	pub fn history(&self, node: usize) -> Vec<u64> {
+		// Hashes of the positions before the node since the last capture or pawn move, oldest
+		// first, the way SearchLimits wants them
+		let mut hashes = Vec::new();
+		let clock = self.nodes[node].board.halfmove_clock() as usize;
+		let mut parent = self.nodes[node].parent;
+		while let Some(index) = parent {
+			if hashes.len() == clock {
+				break;
+			}
+			hashes.push(self.nodes[index].board.zobrist());
+			parent = self.nodes[index].parent;
+		}
+		hashes.reverse();
+		return hashes;
+	}
+
+	pub fn merge(&mut self, node: usize, results: &[SearchResult]) -> bool {
+		// Engine lines about the node's position, best first. Kept unless the node already
+		// has a deeper evaluation, false when they weren't
+		let depth = match results.first() {
+			Some(result) => result.depth,
+			None => return false,
+		};
+		let target = match self.nodes.get_mut(node) {
+			Some(target) => target,
+			None => return false,
+		};
+		if target.eval.as_ref().is_some_and(|eval| eval.depth > depth) {
+			return false;
+		}
+		let lines = results.iter().map(|result| AnalysisLine { score: result.score, pv: result.pv.clone() }).collect();
+		target.eval = Some(NodeEval { depth, lines });
+		return true;
+	}
+
+	pub fn merge_current(&mut self, results: &[SearchResult]) -> bool {
+		return self.merge(self.current, results);
+	}
+
+	pub fn analyze<E: Evaluator>(&mut self, engine: &mut AlphaBeta<E>, limits: &SearchLimits, lines: usize) -> Option<&NodeEval> {
+		// Searches the current position, with the moves that led to it for repetitions, and
+		// merges the lines found
+		let limits = SearchLimits {
+			history: self.history(self.current),
+			..limits.clone()
+		};
+		let results = engine.search_lines(self.board(), &limits, lines, |_| {});
+		self.merge_current(&results);
+		return self.current_node().eval.as_ref();
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writeln!(writer, "chyes analysis {}", ANALYSIS_VERSION)?;
+		writeln!(writer, "root {}", self.nodes[Analysis::ROOT].board.get_fen())?;
+		// Nodes are added after their parent, so their order already works
+		for node in &self.nodes[1..] {
+			writeln!(writer, "node {} {}", node.parent.unwrap(), node.mv.unwrap())?;
+		}
+		for (index, node) in self.nodes.iter().enumerate() {
+			for line in node.eval.iter().flat_map(|eval| eval.lines.iter()) {
+				write!(writer, "eval {} {} {}", index, node.eval.as_ref().unwrap().depth, line.score)?;
+				for mv in &line.pv {
+					write!(writer, " {}", mv)?;
+				}
+				writeln!(writer)?;
+			}
+		}
+		writeln!(writer, "current {}", self.current)?;
+		return Ok(());
+	}
+
+	pub fn read(reader: &mut impl BufRead) -> io::Result<Analysis> {
+		let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid analysis line \"{}\"", line));
+		// A legal move of the board, in the notation Move is displayed with
+		let legal = |board: &Board, text: &str| board.legal_moves().into_iter().find(|mv| mv.to_string() == text);
+
+		let mut lines = reader.lines();
+		let header = lines.next().transpose()?.unwrap_or_default();
+		match header.strip_prefix("chyes analysis ").map(|version| version.trim().parse::<u32>()) {
+			Some(Ok(ANALYSIS_VERSION)) => {}
+			Some(Ok(version)) => {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported analysis version {}", version)));
+			}
+			_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not an analysis file")),
+		}
+		let root = lines.next().transpose()?.unwrap_or_default();
+		let fen = root.strip_prefix("root ").ok_or_else(|| invalid(&root))?;
+		let board = Board::from_fen(fen).map_err(|_| invalid(&root))?;
+
+		let mut analysis = Analysis::new(board);
+		let mut current = Analysis::ROOT;
+		for line in lines {
+			let line = line?;
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			let number = |index: usize| fields.get(index).and_then(|field| field.parse::<usize>().ok()).ok_or_else(|| invalid(&line));
+			match fields.first() {
+				None => continue,
+				Some(&"node") if fields.len() == 3 => {
+					let parent = number(1)?;
+					let board = analysis.node(parent).ok_or_else(|| invalid(&line))?.board;
+					let mv = legal(&board, fields[2]).ok_or_else(|| invalid(&line))?;
+					// The same move twice would mix up the numbers of the nodes after it
+					if analysis.child(parent, mv).is_some() {
+						return Err(invalid(&line));
+					}
+					analysis.current = parent;
+					analysis.play(mv).map_err(|_| invalid(&line))?;
+				}
+				Some(&"eval") if fields.len() >= 4 => {
+					let node = number(1)?;
+					let depth = number(2)? as u32;
+					let score = fields[3].parse::<i32>().map_err(|_| invalid(&line))?;
+					let mut board = analysis.node(node).ok_or_else(|| invalid(&line))?.board;
+					let mut pv = Vec::new();
+					for text in &fields[4..] {
+						let mv = legal(&board, text).ok_or_else(|| invalid(&line))?;
+						board.make_move(mv);
+						pv.push(mv);
+					}
+
+					let eval = analysis.nodes[node].eval.get_or_insert(NodeEval { depth, lines: Vec::new() });
+					if eval.depth != depth {
+						return Err(invalid(&line));
+					}
+					eval.lines.push(AnalysisLine { score, pv });
+				}
+				Some(&"current") if fields.len() == 2 => {
+					current = number(1)?;
+				}
+				_ => return Err(invalid(&line)),
+			}
+		}
+		if !analysis.go_to(current) {
+			return Err(invalid(&format!("current {}", current)));
+		}
+		return Ok(analysis);
+	}
+}
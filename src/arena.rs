@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use crate::chess::*;
+use crate::engine::Engine;
+use crate::error::ChessError;
+use crate::game::Outcome;
+use crate::match_runner::{play_game_with, GameRecord, MatchConfig};
+use crate::score::Score;
+
+// Arena tournaments, the Lichess kind: players are paired again as soon as their game is over
+/*
+There are no rounds. A player whose game is over goes back to the waiting players, and those
+are paired by their place in the standings, neighbours with each other, but not with the
+opponent of their last game while someone else is waiting. White goes to the player who had
+it less often, to the one who had black last when that is even
+
+Points: 2 for a win, 1 for a draw. Two wins in a row set a player on fire: until the next game
+that isn't a win, wins are worth 4 and draws 2. Berserk halves the player's clock and takes the
+increment away, a berserk win of BERSERK_MIN_MOVES moves or more is worth a point more
+
+Standings go by points, then by game points, then by name
+*/
+// source: https://lichess.org/tournament/help
+
+pub const WIN_POINTS: u32 = 2;
+pub const DRAW_POINTS: u32 = 1;
+// Wins in a row that set a player on fire
+pub const STREAK_WINS: u32 = 2;
+// Full moves a berserk win needs for its extra point
+pub const BERSERK_MIN_MOVES: usize = 7;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct ArenaResult {
+	pub opponent: usize,
+	pub color: Color,
+	pub score: Score,
+	pub berserk: bool,
+	// Arena points the game was worth to the player
+	pub points: u32,
+}
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct ArenaPlayer {
+	pub name: String,
+	pub points: u32,
+	pub results: Vec<ArenaResult>,
+	// Wins in a row, back to 0 after anything else
+	pub streak: u32,
+	pub playing: bool,
+	// Paused players aren't paired, they keep their points
+	pub paused: bool,
+}
+
+impl ArenaPlayer {
+	pub fn on_fire(&self) -> bool {
+		return self.streak >= STREAK_WINS;
+	}
+
+	pub fn score(&self) -> Score {
+		// Game points, a point a win and a half a draw
+		return self.results.iter().map(|result| result.score).sum();
+	}
+
+	fn color_balance(&self) -> i32 {
+		// Whites less blacks
+		return self
+		.results
+		.iter()
+		.map(|result| match result.color {
+			Color::White => 1,
+			Color::Black => -1,
+		})
+		.sum();
+	}
+}
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug, Default)]
+pub struct Arena {
+	pub players: Vec<ArenaPlayer>,
+}
+
+impl Arena {
+	pub fn new(names: &[&str]) -> Self {
+		let mut arena = Arena::default();
+		for name in names {
+			arena.join(name);
+		}
+		return arena;
+	}
+
+	pub fn join(&mut self, name: &str) -> usize {
+		// Players can join while the arena runs, the index is theirs for the rest of it
+		self.players.push(ArenaPlayer {
+			name: name.to_string(),
+			points: 0,
+			results: Vec::new(),
+			streak: 0,
+			playing: false,
+			paused: false,
+		});
+		return self.players.len() - 1;
+	}
+
+	pub fn pause(&mut self, player: usize, paused: bool) {
+		self.players[player].paused = paused;
+	}
+
+	pub fn ranking(&self) -> Vec<usize> {
+		// Indices of the players, best first
+		let mut order: Vec<usize> = (0..self.players.len()).collect();
+		order.sort_by(|a, b| {
+			let (a, b) = (&self.players[*a], &self.players[*b]);
+			return b.points.cmp(&a.points).then(b.score().cmp(&a.score())).then(a.name.cmp(&b.name));
+		});
+		return order;
+	}
+
+	pub fn pair(&mut self) -> Vec<(usize, usize)> {
+		// (white, black) for the waiting players, an odd one out keeps waiting
+		let mut waiting: Vec<usize> = self.ranking().into_iter().filter(|player| !self.players[*player].playing && !self.players[*player].paused).collect();
+		let last_opponent = |player: usize| self.players[player].results.last().map(|result| result.opponent);
+
+		let mut pairs = Vec::new();
+		while waiting.len() >= 2 {
+			let first = waiting.remove(0);
+			let index = waiting
+			.iter()
+			.position(|other| last_opponent(first) != Some(*other) && last_opponent(*other) != Some(first))
+			.unwrap_or(0);
+			let second = waiting.remove(index);
+
+			let (a, b) = (&self.players[first], &self.players[second]);
+			let first_white = match a.color_balance().cmp(&b.color_balance()) {
+				Ordering::Less => true,
+				Ordering::Greater => false,
+				Ordering::Equal => a.results.last().is_none_or(|result| result.color == Color::Black),
+			};
+			pairs.push(if first_white { (first, second) } else { (second, first) });
+		}
+
+		for (white, black) in &pairs {
+			self.players[*white].playing = true;
+			self.players[*black].playing = true;
+		}
+		return pairs;
+	}
+
+	pub fn record(&mut self, white: usize, black: usize, outcome: Outcome, berserk: [bool; 2], moves: usize) -> [u32; 2] {
+		// The result of a paired game, both players wait for the next one after it. Returns the
+		// points of white and black
+		let mut points = [0; 2];
+		for (color, player, opponent) in [(Color::White, white, black), (Color::Black, black, white)] {
+			let player = &mut self.players[player];
+			let score = outcome.score(color);
+			let berserk = berserk[color as usize];
+
+			let mut earned = match score {
+				Score::WIN => WIN_POINTS,
+				Score::DRAW => DRAW_POINTS,
+				_ => 0,
+			};
+			if player.on_fire() {
+				earned *= 2;
+			}
+			if berserk && score == Score::WIN && moves >= BERSERK_MIN_MOVES {
+				earned += 1;
+			}
+			player.streak = if score == Score::WIN { player.streak + 1 } else { 0 };
+			player.points += earned;
+			player.playing = false;
+			player.results.push(ArenaResult { opponent, color, score, berserk, points: earned });
+			points[color as usize] = earned;
+		}
+		return points;
+	}
+}
+
+// An engine in a bot arena
+pub struct ArenaEntry<'a> {
+	pub name: String,
+	pub engine: &'a mut dyn Engine,
+	// Goes berserk every game, only when the limits have a clock
+	pub berserk: bool,
+}
+
+pub fn run_arena(entries: &mut [ArenaEntry], config: &MatchConfig, waves: usize) -> Result<(Arena, Vec<GameRecord>), ChessError> {
+	// `waves` times, the waiting engines are paired and their games played one after the other,
+	// the arena with the games in the order they were played comes back
+	let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+	let mut arena = Arena::new(&names);
+	let mut records = Vec::new();
+
+	for _ in 0..waves {
+		let pairs = arena.pair();
+		if pairs.is_empty() {
+			break;
+		}
+		for (white, black) in pairs {
+			let mut limits = config.limits.clone();
+			let mut berserk = [false; 2];
+			for (color, player) in [(Color::White, white), (Color::Black, black)] {
+				if let (true, Some(clock)) = (entries[player].berserk, limits.clock.as_mut()) {
+					clock.time[color as usize] /= 2;
+					clock.increment[color as usize] = Duration::ZERO;
+					berserk[color as usize] = true;
+				}
+			}
+
+			let (white_entry, black_entry) = two_mut(entries, white, black);
+			let record = play_game_with(white_entry.engine, black_entry.engine, config, limits)?;
+			arena.record(white, black, record.outcome, berserk, record.moves.len().div_ceil(2));
+			records.push(record);
+		}
+	}
+	return Ok((arena, records));
+}
+
+fn two_mut<'a, 'b>(entries: &'a mut [ArenaEntry<'b>], a: usize, b: usize) -> (&'a mut ArenaEntry<'b>, &'a mut ArenaEntry<'b>) {
+	// Both entries at once, they are never the same
+	if a < b {
+		let (left, right) = entries.split_at_mut(b);
+		return (&mut left[a], &mut right[0]);
+	}
+	let (left, right) = entries.split_at_mut(a);
+	return (&mut right[0], &mut left[b]);
+}
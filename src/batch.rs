@@ -0,0 +1,107 @@
+use std::thread;
+
+use crate::chess::*;
+
+// Legal moves of many positions at once, for MCTS and neural network pipelines
+/*
+The positions are cut into one run of neighbouring positions per thread, every thread writes
+the moves of its run one after the other into a single buffer with the end of every position's
+moves next to it, so the order of the positions is kept without sorting anything out. A
+MoveBatch keeps those buffers between calls: after the first batches nothing gets allocated for
+the output anymore. Batches smaller than MIN_PARALLEL_BATCH stay on the calling thread, starting
+threads would cost more than the moves
+*/
+
+pub const MIN_PARALLEL_BATCH: usize = 64;
+
+// The moves of the positions of one thread
+#[derive(Debug, Clone, Default)]
+struct Run {
+	moves: Vec<Move>,
+	// End of the moves of every position in `moves`
+	ends: Vec<usize>,
+}
+
+impl Run {
+	fn generate(&mut self, boards: &[Board]) {
+		self.moves.clear();
+		self.ends.clear();
+		for board in boards {
+			board.legal_moves_into(&mut self.moves);
+			self.ends.push(self.moves.len());
+		}
+	}
+
+	fn moves(&self, index: usize) -> &[Move] {
+		let start = match index {
+			0 => 0,
+			_ => self.ends[index - 1],
+		};
+		return &self.moves[start..self.ends[index]];
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MoveBatch {
+	runs: Vec<Run>,
+	// Positions per run, the last one may have less
+	run_size: usize,
+	len: usize,
+}
+
+impl MoveBatch {
+	pub fn new() -> Self {
+		return MoveBatch::default();
+	}
+
+	pub fn generate(&mut self, boards: &[Board], threads: usize) {
+		// The moves of the last batch are dropped, their memory is kept
+		let threads = match boards.len() < MIN_PARALLEL_BATCH {
+			true => 1,
+			false => threads.clamp(1, boards.len()),
+		};
+		self.len = boards.len();
+		self.run_size = boards.len().div_ceil(threads).max(1);
+		let runs = boards.len().div_ceil(self.run_size);
+		if self.runs.len() < runs {
+			self.runs.resize_with(runs, Run::default);
+		}
+
+		let chunks = boards.chunks(self.run_size);
+		match runs {
+			0 => (),
+			1 => self.runs[0].generate(boards),
+			_ => thread::scope(|scope| {
+				for (run, chunk) in self.runs.iter_mut().zip(chunks) {
+					scope.spawn(move || run.generate(chunk));
+				}
+			}),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		return self.len;
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.len == 0;
+	}
+
+	pub fn moves(&self, index: usize) -> &[Move] {
+		// The legal moves of the position at `index` of the last batch
+		assert!(index < self.len, "position {} is not in the batch", index);
+		return self.runs[index / self.run_size].moves(index % self.run_size);
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &[Move]> {
+		return (0..self.len).map(|index| self.moves(index));
+	}
+}
+
+pub fn generate_legal_moves_batch(boards: &[Board]) -> Vec<Vec<Move>> {
+	// On every core, see MoveBatch to keep the buffers between batches
+	let threads = thread::available_parallelism().map_or(1, |n| n.get());
+	let mut batch = MoveBatch::new();
+	batch.generate(boards, threads);
+	return batch.iter().map(|moves| moves.to_vec()).collect();
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::game::Outcome;
+use crate::match_runner::{GameRecord, MatchReport};
+use crate::options::{EngineOptions, UciOption};
+use crate::rng::{Rng, Seeded};
+
+// Opening book that learns from the games played out of it
+/*
+Positions are kept by Zobrist hash, every one with the moves played from it and their weights.
+Games add their first `max_plies` moves to the book (add_game), the results of the games then
+go back into the moves the book suggested (learn): every book move of a game gets LEARN_STEP
+more weight when the side that played it won, LEARN_STEP less when it lost. A move that lost
+its whole weight stays in the book, but it isn't played anymore
+
+A move is picked at random, in proportion to its weight
+*/
+/*
+File layout, one book move per line:
+<hash as 16 hex digits> <move as UCI writes it> <weight> <wins> <draws> <losses>
+*/
+
+// Weight of a move the first time a game adds it
+pub const INITIAL_WEIGHT: u32 = 10;
+// Weight won or lost by a book move with every game it was played in
+pub const LEARN_STEP: u32 = 2;
+// How deep games go into the book by default
+pub const BOOK_PLIES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BookMove {
+	pub mv: Move,
+	pub weight: u32,
+	// Results of the learned games, for the side that played the move
+	pub wins: u32,
+	pub draws: u32,
+	pub losses: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningBook {
+	pub max_plies: usize,
+	entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl Default for OpeningBook {
+	fn default() -> Self {
+		return OpeningBook::new(BOOK_PLIES);
+	}
+}
+
+fn parse_move(text: &str) -> Option<Move> {
+	// "e2e4", "e7e8q", the way Move is displayed
+	let from = Coordinate::from_algebraic(text.get(0..2)?).ok()?;
+	let to = Coordinate::from_algebraic(text.get(2..4)?).ok()?;
+	let promotion = match text.get(4..)? {
+		"" => None,
+		"q" => Some(Pieces::Queen),
+		"r" => Some(Pieces::Rook),
+		"b" => Some(Pieces::Bishop),
+		"n" => Some(Pieces::Knight),
+		_ => return None,
+	};
+	return Some(Move { from, to, promotion });
+}
+
+impl OpeningBook {
+	pub fn new(max_plies: usize) -> Self {
+		OpeningBook {
+			max_plies,
+			entries: HashMap::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		// Number of positions
+		return self.entries.len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.entries.is_empty();
+	}
+
+	pub fn moves(&self, board: &Board) -> &[BookMove] {
+		return self.entries.get(&board.zobrist()).map_or(&[], |moves| moves.as_slice());
+	}
+
+	pub fn insert(&mut self, board: &Board, mv: Move, weight: u32) {
+		// Sets the weight of a move, adding it if the book doesn't have it
+		let moves = self.entries.entry(board.zobrist()).or_default();
+		match moves.iter_mut().find(|book_move| book_move.mv == mv) {
+			Some(book_move) => book_move.weight = weight,
+			None => moves.push(BookMove { mv, weight, wins: 0, draws: 0, losses: 0 }),
+		}
+	}
+
+	pub fn add_game(&mut self, start: &Board, moves: &[Move]) -> Result<(), ChessError> {
+		// The first max_plies moves of a game go into the book, moves it already has keep their weight
+		let mut board = *start;
+		for mv in moves.iter().take(self.max_plies) {
+			if !board.legal_moves().contains(mv) {
+				return Err(ChessError::IllegalMove(*mv));
+			}
+			if !self.moves(&board).iter().any(|book_move| book_move.mv == *mv) {
+				self.insert(&board, *mv, INITIAL_WEIGHT);
+			}
+			board.make_move(*mv);
+		}
+		return Ok(());
+	}
+
+	pub fn learn(&mut self, start: &Board, moves: &[Move], outcome: Outcome) -> usize {
+		// Backs the result up into the book moves of the game, until the game leaves the book
+		// Returns how many book moves were updated
+		let mut board = *start;
+		let mut updated = 0;
+		for mv in moves.iter().take(self.max_plies) {
+			let won = match outcome {
+				Outcome::WhiteWins => Some(board.turn == Color::White),
+				Outcome::BlackWins => Some(board.turn == Color::Black),
+				Outcome::Draw => None,
+			};
+			let book_move = match self.entries.get_mut(&board.zobrist()).and_then(|moves| moves.iter_mut().find(|book_move| book_move.mv == *mv)) {
+				Some(book_move) => book_move,
+				None => break,
+			};
+
+			match won {
+				Some(true) => {
+					book_move.wins += 1;
+					book_move.weight += LEARN_STEP;
+				}
+				Some(false) => {
+					book_move.losses += 1;
+					book_move.weight = book_move.weight.saturating_sub(LEARN_STEP);
+				}
+				None => book_move.draws += 1,
+			}
+			updated += 1;
+			board.make_move(*mv);
+		}
+		return updated;
+	}
+
+	pub fn learn_record(&mut self, record: &GameRecord) -> Result<usize, ChessError> {
+		let start = Board::from_fen(&record.start_fen)?;
+		return Ok(self.learn(&start, &record.moves, record.outcome));
+	}
+
+	pub fn learn_match(&mut self, report: &MatchReport) -> Result<usize, ChessError> {
+		// Every game of a match, returns how many book moves were updated in all
+		let mut updated = 0;
+		for record in &report.games {
+			updated += self.learn_record(record)?;
+		}
+		return Ok(updated);
+	}
+
+	pub fn pick(&self, board: &Board, rng: &mut Rng) -> Option<Move> {
+		// A random legal book move in proportion to the weights, None out of the book
+		let legal = board.legal_moves();
+		let moves: Vec<&BookMove> = self.moves(board).iter().filter(|book_move| book_move.weight > 0 && legal.contains(&book_move.mv)).collect();
+		let total: u64 = moves.iter().map(|book_move| book_move.weight as u64).sum();
+		if total == 0 {
+			return None;
+		}
+
+		let mut ticket = rng.next_u64() % total;
+		for book_move in moves {
+			if ticket < book_move.weight as u64 {
+				return Some(book_move.mv);
+			}
+			ticket -= book_move.weight as u64;
+		}
+		return None;
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		// Sorted, so the same book always gives the same file
+		let mut hashes: Vec<&u64> = self.entries.keys().collect();
+		hashes.sort();
+		for hash in hashes {
+			for book_move in &self.entries[hash] {
+				writeln!(
+					writer,
+					"{:016x} {} {} {} {} {}",
+					hash, book_move.mv, book_move.weight, book_move.wins, book_move.draws, book_move.losses
+				)?;
+			}
+		}
+		return Ok(());
+	}
+
+	pub fn read(reader: &mut impl BufRead, max_plies: usize) -> io::Result<OpeningBook> {
+		let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid book line \"{}\"", line));
+
+		let mut book = OpeningBook::new(max_plies);
+		for line in reader.lines() {
+			let line = line?;
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			if fields.is_empty() {
+				continue;
+			}
+			if fields.len() != 6 {
+				return Err(invalid(&line));
+			}
+
+			let hash = u64::from_str_radix(fields[0], 16).map_err(|_| invalid(&line))?;
+			let mv = parse_move(fields[1]).ok_or_else(|| invalid(&line))?;
+			let mut numbers = [0u32; 4];
+			for (number, field) in numbers.iter_mut().zip(&fields[2..]) {
+				*number = field.parse().map_err(|_| invalid(&line))?;
+			}
+
+			let [weight, wins, draws, losses] = numbers;
+			book.entries.entry(hash).or_default().push(BookMove { mv, weight, wins, draws, losses });
+		}
+		return Ok(book);
+	}
+}
+
+// Plays from the book while it has a move, the engine takes over after that
+pub struct BookEngine<E: Engine> {
+	pub book: OpeningBook,
+	pub engine: E,
+	rng: Rng,
+	// Whether the last move came from the book, its score and line are the engine's otherwise
+	from_book: bool,
+}
+
+impl<E: Engine> BookEngine<E> {
+	pub fn new(book: OpeningBook, engine: E, rng: Rng) -> Self {
+		BookEngine {
+			book,
+			engine,
+			rng,
+			from_book: false,
+		}
+	}
+}
+
+impl<E: Engine + Seeded> Seeded for BookEngine<E> {
+	fn rng(&mut self) -> &mut Rng {
+		return &mut self.rng;
+	}
+
+	fn set_rng(&mut self, mut rng: Rng) {
+		self.engine.set_rng(Rng::new(rng.next_u64()));
+		self.rng = rng;
+	}
+}
+
+impl<E: Engine> Engine for BookEngine<E> {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+		// Book moves outside the searchmoves are left to the engine
+		if let Some(mv) = self.book.pick(board, &mut self.rng).filter(|mv| limits.root_moves(board).contains(mv)) {
+			self.from_book = true;
+			return Ok(mv);
+		}
+		self.from_book = false;
+		return self.engine.choose_move(board, limits);
+	}
+
+	fn score(&self) -> Option<i32> {
+		if self.from_book {
+			return None;
+		}
+		return self.engine.score();
+	}
+
+	fn pv(&self) -> Vec<Move> {
+		if self.from_book {
+			return Vec::new();
+		}
+		return self.engine.pv();
+	}
+
+	fn options(&self) -> Vec<UciOption> {
+		return self.engine.options();
+	}
+
+	fn set_options(&mut self, options: &EngineOptions) {
+		self.engine.set_options(options);
+	}
+}
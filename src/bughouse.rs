@@ -0,0 +1,303 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::chess::*;
+use crate::drops::{Drop, Pocket};
+use crate::error::ChessError;
+use crate::game::TerminationReason;
+use crate::notation::{Notation, ParseMode};
+
+// Bughouse, two games played side by side by two teams of two
+/*
+Partners play opposite colors on the two boards:
+
+Team A - white on board 0, black on board 1
+Team B - black on board 0, white on board 1
+
+A captured piece goes to the partner of the player who took it, to be dropped on the other
+board (see drops.rs): a black piece taken on board 0 goes into black's pocket on board 1. A
+piece that promoted goes over as the pawn it was. The first board to finish ends the match,
+a mate or a fallen flag loses it for the whole team, a stalemate is a draw (servers don't agree
+on stalemates, some give the win to the side that can't move)
+
+A king is mated when nothing on the board and nothing in the pocket saves it right now, the
+piece the partner might still send doesn't count
+
+All four clocks start together, the clock of the side to move runs on both boards at once
+*/
+// source: https://en.wikipedia.org/wiki/Bughouse_chess
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Team {
+	A,
+	B,
+}
+
+pub fn team_of(board: usize, color: Color) -> Team {
+	return match (board, color) {
+		(0, Color::White) | (1, Color::Black) => Team::A,
+		_ => Team::B,
+	};
+}
+
+pub fn partner(board: usize, color: Color) -> (usize, Color) {
+	// The board and the color of the partner
+	return (1 - board, opponent(color));
+}
+
+fn opponent(color: Color) -> Color {
+	return match color {
+		Color::White => Color::Black,
+		Color::Black => Color::White,
+	};
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BughouseMove {
+	Move(Move),
+	Drop(Drop),
+}
+
+impl fmt::Display for BughouseMove {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// UCI for moves, "N@f3" for drops
+		return match self {
+			BughouseMove::Move(mv) => write!(f, "{}", mv),
+			BughouseMove::Drop(drop) => write!(f, "{}", drop),
+		};
+	}
+}
+
+// One of the two boards, with the pockets of both sides
+#[derive(Debug, Clone, Copy)]
+pub struct BughouseBoard {
+	pub board: Board,
+	// Indexed by color
+	pub pockets: [Pocket; 2],
+	// Squares of the pieces that got there by promoting, as bits by square number
+	promoted: u64,
+}
+
+impl Default for BughouseBoard {
+	fn default() -> Self {
+		return BughouseBoard::new(Board::default());
+	}
+}
+
+impl BughouseBoard {
+	pub fn new(board: Board) -> Self {
+		BughouseBoard {
+			board,
+			pockets: [Pocket::new(); 2],
+			promoted: 0,
+		}
+	}
+
+	pub fn pocket(&self) -> &Pocket {
+		// Of the side to move
+		return &self.pockets[self.board.turn as usize];
+	}
+
+	pub fn is_promoted(&self, coord: Coordinate) -> bool {
+		return self.promoted & (1 << coord.as_number()) != 0;
+	}
+
+	pub fn legal_moves(&self) -> Vec<BughouseMove> {
+		// Board moves first, then drops
+		let moves = self.board.legal_moves().into_iter().map(BughouseMove::Move);
+		let drops = self.board.legal_drops(self.pocket()).into_iter().map(BughouseMove::Drop);
+		return moves.chain(drops).collect();
+	}
+
+	pub fn is_checkmate(&self) -> bool {
+		return self.board.is_in_check(self.board.turn) && self.legal_moves().is_empty();
+	}
+
+	pub fn is_stalemate(&self) -> bool {
+		return !self.board.is_in_check(self.board.turn) && self.legal_moves().is_empty();
+	}
+
+	pub fn parse_move(&self, text: &str) -> Option<BughouseMove> {
+		// "N@f3" for drops, moves in UCI or in SAN
+		if text.contains('@') {
+			return Drop::parse(text).map(BughouseMove::Drop);
+		}
+		let text = text.trim();
+		return match self.board.legal_moves().into_iter().find(|mv| mv.to_string() == text) {
+			Some(mv) => Some(BughouseMove::Move(mv)),
+			None => Notation::San.parse_with(&self.board, text, ParseMode::Lenient).map(BughouseMove::Move),
+		};
+	}
+
+	pub fn san(&self, mv: BughouseMove) -> String {
+		// Drops get the check marks moves get
+		return match mv {
+			BughouseMove::Move(mv) => Notation::San.format(&self.board, mv),
+			BughouseMove::Drop(drop) => {
+				let mut after = *self;
+				after.board.make_drop(drop);
+				let mark = if after.is_checkmate() {
+					"#"
+				} else if after.board.is_in_check(after.board.turn) {
+					"+"
+				} else {
+					""
+				};
+				format!("{}{}", drop, mark)
+			}
+		};
+	}
+
+	pub fn fen(&self) -> String {
+		// The Crazyhouse FEN, the pockets in brackets after the pieces: "...RNBQKBNR[Qnp] w KQkq - 0 1"
+		let fen = self.board.get_fen();
+		let (pieces, rest) = fen.split_once(' ').unwrap();
+		let pockets = format!("{}{}", self.pockets[0].to_fen(Color::White), self.pockets[1].to_fen(Color::Black));
+		return format!("{}[{}] {}", pieces, pockets, rest);
+	}
+
+	pub fn play(&mut self, mv: BughouseMove) -> Result<Option<Piece>, ChessError> {
+		// Returns the piece that goes to the partner, a promoted piece goes as a pawn
+		return match mv {
+			BughouseMove::Move(mv) => {
+				if !self.board.legal_moves().contains(&mv) {
+					return Err(ChessError::IllegalMove(mv));
+				}
+				let from = 1u64 << mv.from.as_number();
+				let to = 1u64 << mv.to.as_number();
+				let captured_promoted = self.promoted & to != 0;
+				let moved_promoted = self.promoted & from != 0;
+				self.promoted &= !(from | to);
+				if moved_promoted || mv.promotion.is_some() {
+					self.promoted |= to;
+				}
+
+				let captured = self.board.make_move(mv);
+				if captured_promoted {
+					return Ok(captured.map(|piece| Piece { breed: Pieces::Pawn, color: piece.color }));
+				}
+				Ok(captured)
+			}
+			BughouseMove::Drop(drop) => {
+				if !self.board.is_legal_drop(self.pocket(), drop) {
+					return Err(ChessError::IllegalDrop(drop));
+				}
+				self.pockets[self.board.turn as usize].take(drop.breed);
+				self.board.make_drop(drop);
+				Ok(None)
+			}
+		};
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BughouseResult {
+	// The board the match was decided on and how
+	Win { team: Team, board: usize, reason: TerminationReason },
+	Draw { board: usize, reason: TerminationReason },
+}
+
+#[derive(Debug, Clone)]
+pub struct BughouseGame {
+	boards: [BughouseBoard; 2],
+	// Indexed by board and then color
+	clocks: [[Duration; 2]; 2],
+	increment: Duration,
+	// Moves of both boards in the order they were played, with their board
+	moves: Vec<(usize, BughouseMove)>,
+	result: Option<BughouseResult>,
+}
+
+impl BughouseGame {
+	pub fn new(time: Duration, increment: Duration) -> Self {
+		return BughouseGame::from_boards([Board::default(), Board::default()], time, increment);
+	}
+
+	pub fn from_boards(boards: [Board; 2], time: Duration, increment: Duration) -> Self {
+		// Every clock starts with `time`, the pockets start empty
+		BughouseGame {
+			boards: boards.map(BughouseBoard::new),
+			clocks: [[time; 2]; 2],
+			increment,
+			moves: Vec::new(),
+			result: None,
+		}
+	}
+
+	pub fn board(&self, board: usize) -> &BughouseBoard {
+		return &self.boards[board];
+	}
+
+	pub fn moves(&self) -> &[(usize, BughouseMove)] {
+		return &self.moves;
+	}
+
+	pub fn result(&self) -> Option<BughouseResult> {
+		return self.result;
+	}
+
+	pub fn time_left(&self, board: usize, color: Color) -> Duration {
+		return self.clocks[board][color as usize];
+	}
+
+	pub fn make_move(&mut self, board: usize, mv: BughouseMove) -> Result<Option<BughouseResult>, ChessError> {
+		// Plays for the side to move on `board`, returns the result when the move ends the match
+		if self.result.is_some() {
+			return Err(ChessError::GameOver);
+		}
+		let color = self.boards[board].board.turn;
+		if let Some(piece) = self.boards[board].play(mv)? {
+			self.boards[1 - board].pockets[piece.color as usize].add(piece.breed);
+		}
+		self.clocks[board][color as usize] += self.increment;
+		self.moves.push((board, mv));
+
+		if self.boards[board].is_checkmate() {
+			self.result = Some(BughouseResult::Win {
+				team: team_of(board, color),
+				board,
+				reason: TerminationReason::Checkmate,
+			});
+		} else if self.boards[board].is_stalemate() {
+			self.result = Some(BughouseResult::Draw {
+				board,
+				reason: TerminationReason::Stalemate,
+			});
+		}
+		return Ok(self.result);
+	}
+
+	pub fn run_clocks(&mut self, elapsed: Duration) -> Option<BughouseResult> {
+		// Takes the time off the sides to move on both boards, the first flag to fall ends the match
+		if self.result.is_some() {
+			return self.result;
+		}
+		for board in 0..2 {
+			let color = self.boards[board].board.turn;
+			let clock = &mut self.clocks[board][color as usize];
+			*clock = clock.saturating_sub(elapsed);
+			if clock.is_zero() && self.result.is_none() {
+				self.result = Some(BughouseResult::Win {
+					team: team_of(board, opponent(color)),
+					board,
+					reason: TerminationReason::Timeout,
+				});
+			}
+		}
+		return self.result;
+	}
+
+	pub fn resign(&mut self, board: usize, color: Color) -> Result<BughouseResult, ChessError> {
+		// Either partner can resign for the team
+		if self.result.is_some() {
+			return Err(ChessError::GameOver);
+		}
+		let result = BughouseResult::Win {
+			team: team_of(board, opponent(color)),
+			board,
+			reason: TerminationReason::Resignation,
+		};
+		self.result = Some(result);
+		return Ok(result);
+	}
+}
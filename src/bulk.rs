@@ -0,0 +1,89 @@
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+
+// Analysing a large number of positions on a pool of threads
+/*
+Every worker builds its own engine with the factory and takes the next FEN as soon as it is
+done with the last one, results come back through a channel in the order they finish. The
+channel only holds a few results per worker, a slow reader holds the workers back instead of
+piling results up in memory, and the FENs are read as they are needed so a huge file never
+has to be loaded at once. Stopping the control in the limits stops every worker
+*/
+
+// Results waiting in the channel per worker before the workers wait for the reader
+const RESULTS_PER_WORKER: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+	// Position of the FEN in the input, results don't come back in input order
+	pub index: usize,
+	pub fen: String,
+	// Centipawns for the side to move, None when the engine doesn't score or there is no move
+	pub score: Option<i32>,
+	pub best_move: Option<Move>,
+	pub pv: Vec<Move>,
+}
+
+fn analyze<E: Engine>(engine: &mut E, index: usize, fen: String, limits: &SearchLimits) -> Result<Analysis, ChessError> {
+	let board = Board::from_fen(&fen)?;
+	let mut analysis = Analysis { index, fen, score: None, best_move: None, pv: Vec::new() };
+
+	// Mates and stalemates are analysed too, there is just nothing to play
+	match engine.choose_move(&board, limits) {
+		Ok(mv) => {
+			analysis.best_move = Some(mv);
+			analysis.score = engine.score();
+			analysis.pv = engine.pv();
+		}
+		Err(ChessError::NoLegalMoves) => {}
+		Err(error) => return Err(error),
+	}
+	return Ok(analysis);
+}
+
+pub fn analyze_positions<I, E, F>(fens: I, engine: F, limits: SearchLimits, threads: usize) -> Receiver<Result<Analysis, ChessError>>
+where
+	I: IntoIterator<Item = String>,
+	I::IntoIter: Send + 'static,
+	E: Engine,
+	F: Fn() -> E + Send + Sync + 'static,
+{
+	trace_span!(tracing::Level::DEBUG, "analyze_positions", threads);
+	let threads = threads.max(1);
+	let fens = Arc::new(Mutex::new(fens.into_iter().enumerate()));
+	let engine = Arc::new(engine);
+	let (sender, receiver) = mpsc::sync_channel(threads * RESULTS_PER_WORKER);
+
+	for _ in 0..threads {
+		let fens = Arc::clone(&fens);
+		let engine = Arc::clone(&engine);
+		let limits = limits.clone();
+		let sender = sender.clone();
+
+		thread::spawn(move || {
+			let mut worker = engine();
+			loop {
+				// The lock is only held to take the next FEN
+				let next = fens.lock().unwrap().next();
+				let Some((index, fen)) = next else {
+					break;
+				};
+				if limits.control.is_stopped() {
+					break;
+				}
+
+				// A reader that went away doesn't want the rest
+				if sender.send(analyze(&mut worker, index, fen, &limits)).is_err() {
+					break;
+				}
+			}
+		});
+	}
+
+	return receiver;
+}
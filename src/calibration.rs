@@ -0,0 +1,187 @@
+use std::fmt::Write;
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::match_runner::{play_game_with, MatchConfig};
+use crate::rating::MAX_PERFORMANCE_DIFFERENCE;
+use crate::score::Score;
+
+// How strong an engine is at a given depth or time control, measured against opponents of known strength
+/*
+Every setting of the engine (a depth, a node count, a time per move or a clock) plays
+`config.games` games against every reference, colors alternating, the engine starting with
+white. A reference is any engine with the Elo it is known to play at and the limits it plays
+with, a strength-limited AlphaBeta or an engine of its own
+
+The Elo of a setting is the rating that expects exactly the score it made against the
+references it met, each with its own rating (found by bisection, no more than 800 away from
+the weakest and the strongest). The margin comes the same way from the score two standard
+errors up and down, it says how far to trust the number
+
+A strength curve of the settings tells an app the weakest one that plays at a given Elo
+*/
+
+pub const MARGIN_ERRORS: f64 = 1.96;
+
+// A known opponent
+pub struct Reference<'a> {
+	pub name: String,
+	pub elo: f64,
+	pub engine: &'a mut dyn Engine,
+	// Depth, nodes and movetime, a clock comes from the game
+	pub limits: SearchLimits,
+}
+
+// A setting of the calibrated engine
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+	pub label: String,
+	// Depth, nodes and movetime limit the engine, a clock is the clock of all the setting's games
+	pub limits: SearchLimits,
+}
+
+impl Setting {
+	pub fn new(label: &str, limits: SearchLimits) -> Self {
+		Setting { label: label.to_string(), limits }
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrengthPoint {
+	pub label: String,
+	pub limits: SearchLimits,
+	pub games: usize,
+	pub score: Score,
+	pub elo: f64,
+	// The Elo is elo +- margin, about 95 times out of 100
+	pub margin: f64,
+	// Name, games and score against every reference
+	pub results: Vec<(String, usize, Score)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StrengthCurve {
+	// In the order of the settings
+	pub points: Vec<StrengthPoint>,
+}
+
+impl StrengthCurve {
+	pub fn point(&self, label: &str) -> Option<&StrengthPoint> {
+		return self.points.iter().find(|point| point.label == label);
+	}
+
+	pub fn setting_for(&self, elo: f64) -> Option<&StrengthPoint> {
+		// The weakest setting that plays at `elo` or better, the strongest one when none does
+		let at_least = self.points.iter().filter(|point| point.elo >= elo).min_by(|a, b| a.elo.total_cmp(&b.elo));
+		return at_least.or(self.points.iter().max_by(|a, b| a.elo.total_cmp(&b.elo)));
+	}
+
+	pub fn to_text(&self) -> String {
+		// One line per setting: label, games, score, Elo and margin
+		let width = self.points.iter().map(|point| point.label.chars().count()).max().unwrap_or(0).max(7);
+		let mut out = String::new();
+		let _ = writeln!(out, "{:<width$}  Games  Score    Elo  Margin", "Setting", width = width);
+		for point in &self.points {
+			let _ = writeln!(out, "{:<width$}  {:>5}  {:>5}  {:>5.0}  {:>6.0}", point.label, point.games, point.score, point.elo, point.margin, width = width);
+		}
+		return out;
+	}
+}
+
+pub fn estimate_elo(opponents: &[(f64, usize)], score: f64) -> f64 {
+	// The rating whose expected score against the (elo, games) opponents is `score`
+	if opponents.is_empty() {
+		return 0.0;
+	}
+	let lowest = opponents.iter().map(|(elo, _)| *elo).fold(f64::INFINITY, f64::min) - MAX_PERFORMANCE_DIFFERENCE;
+	let highest = opponents.iter().map(|(elo, _)| *elo).fold(f64::NEG_INFINITY, f64::max) + MAX_PERFORMANCE_DIFFERENCE;
+
+	let expected = |rating: f64| {
+		return opponents
+		.iter()
+		.map(|(elo, games)| *games as f64 / (1.0 + 10f64.powf((elo - rating) / 400.0)))
+		.sum::<f64>();
+	};
+	let (mut low, mut high) = (lowest, highest);
+	for _ in 0..50 {
+		let middle = (low + high) / 2.0;
+		if expected(middle) < score {
+			low = middle;
+		} else {
+			high = middle;
+		}
+	}
+	return (low + high) / 2.0;
+}
+
+// Plays with its own limits, with the clock, history and root moves of the game
+struct Limited<'a, 'b> {
+	engine: &'a mut (dyn Engine + 'b),
+	limits: &'a SearchLimits,
+}
+
+impl Engine for Limited<'_, '_> {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+		let mut own = limits.clone();
+		own.depth = self.limits.depth;
+		own.nodes = self.limits.nodes;
+		own.movetime = self.limits.movetime;
+		return self.engine.choose_move(board, &own);
+	}
+
+	fn score(&self) -> Option<i32> {
+		return self.engine.score();
+	}
+
+	fn pv(&self) -> Vec<Move> {
+		return self.engine.pv();
+	}
+}
+
+pub fn calibrate(engine: &mut dyn Engine, settings: &[Setting], references: &mut [Reference], config: &MatchConfig) -> Result<StrengthCurve, ChessError> {
+	let mut curve = StrengthCurve::default();
+	for setting in settings {
+		let mut limits = config.limits.clone();
+		if setting.limits.clock.is_some() {
+			limits.clock = setting.limits.clock;
+		}
+
+		let mut results = Vec::new();
+		for reference in references.iter_mut() {
+			let mut score = Score::ZERO;
+			for game in 0..config.games {
+				let mut ours = Limited { engine: &mut *engine, limits: &setting.limits };
+				let mut theirs = Limited { engine: &mut *reference.engine, limits: &reference.limits };
+				let (color, record) = match game % 2 {
+					0 => (Color::White, play_game_with(&mut ours, &mut theirs, config, limits.clone())?),
+					_ => (Color::Black, play_game_with(&mut theirs, &mut ours, config, limits.clone())?),
+				};
+				score += record.outcome.score(color);
+			}
+			results.push((reference.name.clone(), config.games, score));
+		}
+
+		let opponents: Vec<(f64, usize)> = references.iter().map(|reference| (reference.elo, config.games)).collect();
+		let games = config.games * references.len();
+		let score: Score = results.iter().map(|(_, _, score)| *score).sum();
+		let elo = estimate_elo(&opponents, score.points());
+
+		// Standard error of the score from the spread of the game results
+		let mean = score.points() / games.max(1) as f64;
+		let deviation = (mean - mean * mean).max(0.0).sqrt();
+		let error = MARGIN_ERRORS * deviation * (games as f64).sqrt();
+		let margin = (estimate_elo(&opponents, score.points() + error) - estimate_elo(&opponents, score.points() - error)) / 2.0;
+
+		curve.points.push(StrengthPoint {
+			label: setting.label.clone(),
+			limits: setting.limits.clone(),
+			games,
+			score,
+			elo,
+			margin,
+			results,
+		});
+	}
+	return Ok(curve);
+}
@@ -1,31 +1,97 @@
-use std::collections::HashMap;
+// The board code from before the library split still trips these, it is cleaned up as it gets rewritten
+#![allow(
+	clippy::len_zero,
+	clippy::manual_range_contains,
+	clippy::new_without_default,
+	clippy::should_implement_trait,
+	clippy::single_match
+)]
 
-// Sequence: King, Queen, Rook, Bishop, Knight, Pawn
-const BLACK_PIECES: [&str; 6] = ["♔", "♕", "♖", "♗", "♘", "♙"];
-const WHITE_PIECES: [&str; 6] = ["♚", "♛", "♜", "♝", "♞", "♟"];
+use std::fmt;
 
-// Macro to expand coord!(x, y) to Coordinate { row: x, col: y }
-macro_rules! coord {
-	($x:expr, $y:expr) => {
-		Coordinate { row: $x, col: $y }
-	};
+use crate::error::ChessError;
+use crate::game::{GameResult, MaterialRule};
+use crate::render::RenderTheme;
+use crate::training::can_piece_reach;
+
+// Piece steps as (row, col) differences
+pub(crate) const KING_STEPS: [(i8, i8); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+pub(crate) const KNIGHT_STEPS: [(i8, i8); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+pub(crate) const DIAGONAL_STEPS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+pub(crate) const LINEAR_STEPS: [(i8, i8); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+// Rook squares in the same order as the castling rights: h1, a1, h8, a8
+const CASTLING_ROOKS: [Coordinate; 4] = [
+	Coordinate::from_rank_file(Rank::First, File::H),
+	Coordinate::from_rank_file(Rank::First, File::A),
+	Coordinate::from_rank_file(Rank::Eighth, File::H),
+	Coordinate::from_rank_file(Rank::Eighth, File::A),
+];
+
+// FEN letters by color and then by piece, in the order of the Pieces variants
+const FEN_CHARS: [[char; 6]; 2] = [['K', 'Q', 'R', 'B', 'N', 'P'], ['k', 'q', 'r', 'b', 'n', 'p']];
+// Castling rights in the order they are stored
+const CASTLING_CHARS: [char; 4] = ['K', 'Q', 'k', 'q'];
+// Room for a full board, all castling rights and large clocks without growing the string
+const FEN_CAPACITY: usize = 96;
+
+// Files from a to h and ranks from 1 to 8, the way they are written on a board
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, PartialOrd, Ord)]
+pub enum File {
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
 }
 
-// Macro to expand piece!(piece, color) to Piece { breed: Pieces::piece, color: Color::color }
-macro_rules! piece {
-	($piece:ident, $color:ident) => {
-		Piece {
-			breed: Pieces::$piece,
-			color: Color::$color,
-		}
-	};
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, PartialOrd, Ord)]
+pub enum Rank {
+	First,
+	Second,
+	Third,
+	Fourth,
+	Fifth,
+	Sixth,
+	Seventh,
+	Eighth,
+}
+
+impl File {
+	pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+}
+
+impl Rank {
+	pub const ALL: [Rank; 8] = [
+		Rank::First,
+		Rank::Second,
+		Rank::Third,
+		Rank::Fourth,
+		Rank::Fifth,
+		Rank::Sixth,
+		Rank::Seventh,
+		Rank::Eighth,
+	];
 }
 
 // Coordinate struct
+/*
+Rows follow the FEN order, the way the board is printed: row 0 is the 8th rank, row 7 is the 1st.
+Columns are files, col 0 is the a file.
+
+    a8 = row 0, col 0        h8 = row 0, col 7
+    a1 = row 7, col 0        h1 = row 7, col 7
+
+NOTE: The fields are private to the crate, build coordinates with the constructors (from_algebraic,
+from_rank_file, from_number) and read them back with rank and file, the row order is easy to get backwards
+*/
 #[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
 pub struct Coordinate {
-	pub row: i8,
-	pub col: i8,
+	pub(crate) row: i8,
+	pub(crate) col: i8,
 }
 
 #[allow(dead_code)]
@@ -41,22 +107,42 @@ impl Coordinate {
 	00 01 02 03 04 05 06 07
 	*/
 	
-	pub fn to_string(&self) -> String {
-		// Convert coordinate to string
-		let mut result = String::new();
-		// Use cols to convert to letters
-		const COLS: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-		result.push(COLS[self.col as usize]);
-		result.push_str(&(8 - self.row).to_string());
-		return result;
-	}
-	
 	pub fn as_number(&self) -> i8 {
 		// Convert coordinate to number
 		// NOTE: This starts from left bottom corner, so we need to reverse the row
 		return (7 - self.row) * 8 + self.col;
 	}
 	
+	pub fn is_valid(&self) -> bool {
+		return (0..=7).contains(&self.row) && (0..=7).contains(&self.col);
+	}
+	
+	pub const fn from_rank_file(rank: Rank, file: File) -> Coordinate {
+		return coord!(7 - rank as i8, file as i8);
+	}
+	
+	pub fn from_algebraic(text: &str) -> Result<Coordinate, ChessError> {
+		// "e4" to the coordinate, the file letter may be upper case
+		let mut chars = text.chars();
+		return match (chars.next(), chars.next(), chars.next()) {
+			(Some(file), Some(rank @ '1'..='8'), None) if ('a'..='h').contains(&file.to_ascii_lowercase()) => {
+				let file = File::ALL[(file.to_ascii_lowercase() as u8 - b'a') as usize];
+				let rank = Rank::ALL[(rank as u8 - b'1') as usize];
+				Ok(Coordinate::from_rank_file(rank, file))
+			}
+			_ => Err(ChessError::InvalidSquare(text.to_string())),
+		};
+	}
+	
+	pub fn rank(&self) -> Rank {
+		// NOTE: The coordinate has to be on the board
+		return Rank::ALL[(7 - self.row) as usize];
+	}
+	
+	pub fn file(&self) -> File {
+		return File::ALL[self.col as usize];
+	}
+	
 	pub fn from_number(num: i8) -> Coordinate {
 		// Convert number to coordinate
 		// NOTE: This starts from left bottom corner, so we need to reverse the row
@@ -65,6 +151,20 @@ impl Coordinate {
 			col: num % 8,
 		}
 	}
+	
+	pub fn is_light(&self) -> bool {
+		// a8 and h1 are light squares
+		return (self.row + self.col) % 2 == 0;
+	}
+}
+
+impl fmt::Display for Coordinate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Convert coordinate to string
+		// Use cols to convert to letters
+		const COLS: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+		return write!(f, "{}{}", COLS[self.col as usize], 8 - self.row);
+	}
 }
 
 // Chess Pieces
@@ -76,7 +176,6 @@ pub enum Pieces {
 	Bishop,
 	Knight,
 	Pawn,
-	Empty,
 }
 
 #[derive(Hash, Eq, Clone, Copy, PartialEq, Debug)]
@@ -91,300 +190,804 @@ pub struct Piece {
 	pub color: Color,
 }
 
+// Expands to one constructor per piece, like Piece::white_knight()
+macro_rules! piece_constructors {
+	($($name:ident => $breed:ident, $color:ident;)*) => {
+		$(
+			pub fn $name() -> Piece {
+				return piece!($breed, $color);
+			}
+		)*
+	};
+}
+
+impl Piece {
+	pub fn new(color: Color, breed: Pieces) -> Piece {
+		return Piece { breed, color };
+	}
+	
+	piece_constructors! {
+		white_king => King, White;
+		white_queen => Queen, White;
+		white_rook => Rook, White;
+		white_bishop => Bishop, White;
+		white_knight => Knight, White;
+		white_pawn => Pawn, White;
+		black_king => King, Black;
+		black_queen => Queen, Black;
+		black_rook => Rook, Black;
+		black_bishop => Bishop, Black;
+		black_knight => Knight, Black;
+		black_pawn => Pawn, Black;
+	}
+	
+	pub fn is_slider(&self) -> bool {
+		// Pieces that move any number of squares along a line
+		return matches!(self.breed, Pieces::Queen | Pieces::Rook | Pieces::Bishop);
+	}
+	
+	pub fn value(&self) -> i32 {
+		// Centipawns, see eval::piece_value
+		return crate::eval::piece_value(self.breed);
+	}
+	
+	pub fn to_fen_char(&self) -> char {
+		// Upper case for white, lower case for black
+		return FEN_CHARS[self.color as usize][self.breed as usize];
+	}
+	
+	pub fn from_fen_char(chr: char) -> Option<Piece> {
+		use Pieces::*;
+		let breed = match chr.to_ascii_lowercase() {
+			'k' => King,
+			'q' => Queen,
+			'r' => Rook,
+			'b' => Bishop,
+			'n' => Knight,
+			'p' => Pawn,
+			_ => return None,
+		};
+		let color = if chr.is_ascii_uppercase() { Color::White } else { Color::Black };
+		
+		return Some(Piece { breed, color });
+	}
+}
+
+// A single move, promotion is only set for pawns reaching the last row
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct Move {
+	pub from: Coordinate,
+	pub to: Coordinate,
+	pub promotion: Option<Pieces>,
+}
+
+impl fmt::Display for Move {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Coordinate notation the way UCI writes it: e2e4, e7e8q
+		write!(f, "{}{}", self.from, self.to)?;
+		
+		use Pieces::*;
+		return match self.promotion {
+			Some(Queen) => write!(f, "q"),
+			Some(Rook) => write!(f, "r"),
+			Some(Bishop) => write!(f, "b"),
+			Some(Knight) => write!(f, "n"),
+			_ => Ok(()),
+		};
+	}
+}
+
+fn between(a: Coordinate, b: Coordinate) -> u64 {
+	// Squares strictly between two squares on the same line, nothing when they don't share one
+	let row_diff = b.row - a.row;
+	let col_diff = b.col - a.col;
+	if row_diff != 0 && col_diff != 0 && row_diff.abs() != col_diff.abs() {
+		return 0;
+	}
+	
+	let (row_step, col_step) = (row_diff.signum(), col_diff.signum());
+	let mut result: u64 = 0;
+	let mut square = coord!(a.row + row_step, a.col + col_step);
+	while square != b {
+		result |= 1 << square.as_number();
+		square = coord!(square.row + row_step, square.col + col_step);
+	}
+	return result;
+}
+
+// Why moves could not be listed for a square
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum MoveError {
+	OutOfBounds(Coordinate),
+	EmptySquare(Coordinate),
+}
+
+impl fmt::Display for MoveError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			MoveError::OutOfBounds(coord) => write!(f, "coordinates {} {} are outside of the board", coord.row, coord.col),
+			MoveError::EmptySquare(coord) => write!(f, "there is no piece on {}", coord),
+		};
+	}
+}
+
+impl std::error::Error for MoveError {}
+
+// Why try_apply_move refused a move
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum IllegalMove {
+	OutOfBounds(Move),
+	EmptySquare(Move),
+	// The piece belongs to the side that is not to move
+	NotYourTurn(Move),
+	// A pawn reaching the last row without a promotion, or a promotion anywhere else
+	BadPromotion(Move),
+	// The piece doesn't move that way
+	NotLegal(Move),
+	// The square has a piece of the same color
+	OwnPiece(Move),
+	// The piece moves that way, but something stands in between
+	Blocked(Move),
+	// The piece would leave the line it is pinned on, exposing its king
+	Pinned(Move),
+	// The king would step onto an attacked square
+	IntoCheck(Move),
+	// The own king would be in check after the move, it is in check and the move doesn't help
+	KingInCheck(Move),
+	NoCastlingRights(Move),
+	// Pieces between the king and the rook
+	CastlingBlocked(Move),
+	// The king is in check, castling can't get it out
+	CastlingInCheck(Move),
+	// The king would cross or land on an attacked square
+	CastlingThroughCheck(Move),
+}
+
+impl IllegalMove {
+	pub fn mv(&self) -> Move {
+		use IllegalMove::*;
+		return match self {
+			OutOfBounds(mv) | EmptySquare(mv) | NotYourTurn(mv) | BadPromotion(mv) | NotLegal(mv) | OwnPiece(mv) | Blocked(mv)
+			| Pinned(mv) | IntoCheck(mv) | KingInCheck(mv) | NoCastlingRights(mv) | CastlingBlocked(mv) | CastlingInCheck(mv)
+			| CastlingThroughCheck(mv) => *mv,
+		};
+	}
+}
+
+impl fmt::Display for IllegalMove {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use IllegalMove::*;
+		return match self {
+			OutOfBounds(mv) => write!(
+				f,
+				"the move from {} {} to {} {} leaves the board",
+				mv.from.row, mv.from.col, mv.to.row, mv.to.col
+			),
+			EmptySquare(mv) => write!(f, "there is no piece on {}", mv.from),
+			NotYourTurn(mv) => write!(f, "the piece on {} can't move, it is the other side's turn", mv.from),
+			BadPromotion(mv) => write!(f, "{} has a missing or invalid promotion", mv),
+			NotLegal(mv) => write!(f, "{} is not a legal move", mv),
+			OwnPiece(mv) => write!(f, "{} takes a piece of the same color", mv),
+			Blocked(mv) => write!(f, "{} is blocked by a piece in the way", mv),
+			Pinned(mv) => write!(f, "the piece on {} is pinned to its king", mv.from),
+			IntoCheck(mv) => write!(f, "{} moves the king into check", mv),
+			KingInCheck(mv) => write!(f, "{} leaves the king in check", mv),
+			NoCastlingRights(mv) => write!(f, "{} castles without the right to", mv),
+			CastlingBlocked(mv) => write!(f, "{} castles through pieces", mv),
+			CastlingInCheck(mv) => write!(f, "{} castles out of check", mv),
+			CastlingThroughCheck(mv) => write!(f, "{} castles through or into check", mv),
+		};
+	}
+}
+
+impl std::error::Error for IllegalMove {}
+
+// Where the state a board or a game keeps twice stopped agreeing with itself, see check_invariants
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum InvariantViolation {
+	// The square of the array and the piece maps don't have the same piece there
+	PieceMismatch(Coordinate),
+	// The count of a piece map isn't the number of pieces in it
+	PieceCount(Color),
+	// A castling right without the king and the rook in place, in the order of castling_rights
+	CastlingRights(usize),
+	// An en passant square without the pawn that just moved two squares past it
+	EnPassant(Coordinate),
+	MoveNumber,
+	// The attacks, checkers or pins worked out after the last change aren't the ones of the pieces
+	StaleAttacks(Color),
+	StaleCheckers(Color),
+	StalePin(Coordinate),
+	// A game's current board, or the stored one after this many plies, isn't the one its moves lead to
+	GameBoard(usize),
+	// The repetition counts of a game aren't the ones of its positions
+	Repetitions,
+}
+
+impl fmt::Display for InvariantViolation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use InvariantViolation::*;
+		return match self {
+			PieceMismatch(coord) => write!(f, "the board and the piece maps disagree on {}", coord),
+			PieceCount(color) => write!(f, "the piece count of {:?} is wrong", color),
+			CastlingRights(right) => write!(f, "castling right {} without the king and rook in place", right),
+			EnPassant(coord) => write!(f, "en passant square {} without a pawn that just moved", coord),
+			MoveNumber => write!(f, "the move number is 0"),
+			StaleAttacks(color) => write!(f, "the squares attacked by {:?} are out of date", color),
+			StaleCheckers(color) => write!(f, "the pieces checking {:?} are out of date", color),
+			StalePin(coord) => write!(f, "the pin of {} is out of date", coord),
+			GameBoard(ply) => write!(f, "the board after {} plies isn't the one the moves lead to", ply),
+			Repetitions => write!(f, "the repetition counts don't match the positions"),
+		};
+	}
+}
+
+impl std::error::Error for InvariantViolation {}
+
+// A legal move together with what it does, for sorting and annotating move lists
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct ClassifiedMove {
+	pub mv: Move,
+	pub piece: Piece,
+	// The pawn taken en passant counts too
+	pub captured_piece: Option<Piece>,
+	pub gives_check: bool,
+	pub castling: bool,
+	pub en_passant: bool,
+}
+
+impl ClassifiedMove {
+	pub fn is_capture(&self) -> bool {
+		return self.captured_piece.is_some();
+	}
+	
+	pub fn is_promotion(&self) -> bool {
+		return self.mv.promotion.is_some();
+	}
+	
+	pub fn is_quiet(&self) -> bool {
+		return !self.is_capture() && !self.is_promotion() && !self.gives_check;
+	}
+}
+
+// What a null move changes besides the turn, so it can be taken back
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct NullMoveUndo {
+	en_passant_target_sq: Option<i8>,
+	halfmove_clock: u32,
+	fullmove_number: u32,
+}
+
+// What happened on the board after a move
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct MoveOutcome {
+	pub captured: Option<Piece>,
+	pub check: bool,
+	pub checkmate: bool,
+	pub castling: bool,
+	pub en_passant: bool,
+	pub promotion: Option<Pieces>,
+	// Set when the move ended the game, with the default material rule
+	pub result: Option<GameResult>,
+}
+
+// Square numbers to hand out references to, PieceMap keys borrow from here
+const SQUARE_NUMBERS: [i8; 64] = {
+	let mut numbers = [0; 64];
+	let mut i = 0;
+	while i < 64 {
+		numbers[i] = i as i8;
+		i += 1;
+	}
+	numbers
+};
+
+// The pieces of one color by square number
+/*
+A fixed array rather than a HashMap so a Board copies without touching the heap, which search,
+perft and the parallel code do all the time. The interface follows the HashMap one it replaced,
+iteration goes by square number from a1 to h8
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PieceMap {
+	squares: [Option<Piece>; 64],
+	len: u8,
+}
+
+impl Default for PieceMap {
+	fn default() -> Self {
+		PieceMap::new()
+	}
+}
+
+impl PieceMap {
+	pub fn new() -> Self {
+		PieceMap { squares: [None; 64], len: 0 }
+	}
+	
+	pub fn len(&self) -> usize {
+		return self.len as usize;
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		return self.len == 0;
+	}
+	
+	pub fn clear(&mut self) {
+		*self = PieceMap::new();
+	}
+	
+	pub fn get(&self, num: &i8) -> Option<&Piece> {
+		return self.squares.get(*num as usize)?.as_ref();
+	}
+	
+	pub fn contains_key(&self, num: &i8) -> bool {
+		return self.get(num).is_some();
+	}
+	
+	pub fn insert(&mut self, num: i8, piece: Piece) -> Option<Piece> {
+		let old = self.squares[num as usize].replace(piece);
+		if old.is_none() {
+			self.len += 1;
+		}
+		return old;
+	}
+	
+	pub fn remove(&mut self, num: &i8) -> Option<Piece> {
+		let old = self.squares.get_mut(*num as usize)?.take();
+		if old.is_some() {
+			self.len -= 1;
+		}
+		return old;
+	}
+	
+	pub fn iter(&self) -> impl Iterator<Item = (&i8, &Piece)> {
+		return SQUARE_NUMBERS
+		.iter()
+		.zip(self.squares.iter())
+		.filter_map(|(num, square)| square.as_ref().map(|piece| (num, piece)));
+	}
+	
+	pub fn keys(&self) -> impl Iterator<Item = &i8> {
+		return self.iter().map(|(num, _)| num);
+	}
+	
+	pub fn values(&self) -> impl Iterator<Item = &Piece> {
+		return self.iter().map(|(_, piece)| piece);
+	}
+}
+
+impl std::ops::Index<&i8> for PieceMap {
+	type Output = Piece;
+	
+	fn index(&self, num: &i8) -> &Piece {
+		return self.get(num).expect("no piece on the square");
+	}
+}
+
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct Board {
-	pub board: [[Piece; 8]; 8], // 2D array of Pieces
+	pub board: [[Option<Piece>; 8]; 8], // 2D array of squares, None is an empty square
 	pub turn: Color,
 	pub castling_rights: [bool; 4], // 0: white king side, 1: white queen side, 2: black king side, 3: black queen side
-	pub white_pieces: Box<HashMap<i8, Piece>>,
-	pub black_pieces: Box<HashMap<i8, Piece>>,
+	pub white_pieces: PieceMap,
+	pub black_pieces: PieceMap,
 	pub en_passant_target_sq: Option<i8>,
-	halfmove_clock: i8,
-	fullmove_number: i8,
+	// Plies since the last capture or pawn move, and the move number that starts at 1
+	halfmove_clock: u32,
+	fullmove_number: u32,
+	// Attack state, rebuilt whenever the position changes (see rebuild_attacks)
+	// Bitboards use the square numbers as bits, a1 is bit 0; arrays of two are indexed by color
+	attacks: [u64; 2],
+	checkers: [u64; 2],
+	pin_masks: [u64; 64],
 }
 
 #[allow(dead_code)]
 impl Board {
 	pub fn new() -> Self {
 		Board {
-			board: [[piece!(Empty, White); 8]; 8],
+			board: [[None; 8]; 8],
 			turn: Color::White,
 			castling_rights: [false, false, false, false],
-			white_pieces: Box::new(HashMap::new()),
-			black_pieces: Box::new(HashMap::new()),
+			white_pieces: PieceMap::new(),
+			black_pieces: PieceMap::new(),
 			en_passant_target_sq: None,
 			halfmove_clock: 0,
 			fullmove_number: 1,
+			attacks: [0, 0],
+			checkers: [0, 0],
+			pin_masks: [u64::MAX; 64],
 		}
 	}
 	
 	pub fn clear(&mut self) {
 		for i in 0..8 {
 			for j in 0..8 {
-				self.board[i][j] = piece!(Empty, White);
+				self.board[i][j] = None;
 			}
 		}
 		self.turn = Color::White;
 		self.castling_rights = [false, false, false, false];
-		self.white_pieces = Box::new(HashMap::new());
-		self.black_pieces = Box::new(HashMap::new());
+		self.white_pieces.clear();
+		self.black_pieces.clear();
 		self.en_passant_target_sq = None;
 		self.halfmove_clock = 0;
 		self.fullmove_number = 1;
+		self.attacks = [0, 0];
+		self.checkers = [0, 0];
+		self.pin_masks = [u64::MAX; 64];
+	}
+	
+	pub fn from_fen(fen: &str) -> Result<Board, ChessError> {
+		let mut board = Board::new();
+		board.load_fen(fen)?;
+		return Ok(board);
 	}
 	
-	pub fn load_fen(&mut self, fen: &str) {
+	pub fn load_fen(&mut self, fen: &str) -> Result<(), ChessError> {
 		// function to parse fen string
 		// source: https://en.wikipedia.org/wiki/forsyth%e2%80%93edwards_notation
+		// NOTE: The board is left untouched when the string is invalid
+		trace_span!(tracing::Level::DEBUG, "load_fen", fen);
 		
-		let mut fen_array = fen.split(' ');
-		let fen_board = fen_array.next().unwrap();
-		let fen_turn = fen_array.next().unwrap();
-		let fen_castling = fen_array.next().unwrap();
-		let _fen_en_passant = fen_array.next().unwrap();
-		let _fen_half_move = fen_array.next().unwrap(); // todo
-		let _fen_full_move = fen_array.next().unwrap(); // todo
+		let invalid = |reason: &str| ChessError::InvalidFen(fen.to_string(), reason.to_string());
 		
-		self.clear();
+		let mut fen_array = fen.split_whitespace();
+		let fen_board = fen_array.next().ok_or_else(|| invalid("missing piece placement"))?;
+		let fen_turn = fen_array.next().ok_or_else(|| invalid("missing side to move"))?;
+		let fen_castling = fen_array.next().ok_or_else(|| invalid("missing castling rights"))?;
+		let fen_en_passant = fen_array.next().ok_or_else(|| invalid("missing en passant square"))?;
+		// The clocks are optional, EPD style strings stop before them
+		let fen_half_move = fen_array.next().unwrap_or("0");
+		let fen_full_move = fen_array.next().unwrap_or("1");
+		
+		let mut result = Board::new();
+		
+		result.halfmove_clock = fen_half_move.parse().map_err(|_| invalid("invalid halfmove clock"))?;
+		result.fullmove_number = match fen_full_move.parse() {
+			Ok(0) | Err(_) => return Err(invalid("invalid fullmove number")),
+			Ok(number) => number,
+		};
 		
 		// change the turn
-		if fen_turn == "w" {
-			self.turn = Color::White;
-		} else if fen_turn == "b" {
-			self.turn = Color::Black;
-		} else {
-			panic!("invalid turn");
-		}
+		result.turn = match fen_turn {
+			"w" => Color::White,
+			"b" => Color::Black,
+			_ => return Err(invalid("invalid turn")),
+		};
 		
 		// parse the Board
-		let mut row: i8 = 0;
-		let mut col: i8 = 0;
-		let mut piece: Option<Piece> = None;
-		
-		for c in fen_board.chars() {
-			if c == '/' {
-				row += 1;
-				col = 0; // because at the end of the loop, col will be incremented
-			} else if c.is_digit(10) {
-				col += c.to_digit(10).unwrap() as i8;
-			} else {
-				piece = match c {
-					'K' => Some(piece!(King, White)),
-					'Q' => Some(piece!(Queen, White)),
-					'R' => Some(piece!(Rook, White)),
-					'B' => Some(piece!(Bishop, White)),
-					'N' => Some(piece!(Knight, White)),
-					'P' => Some(piece!(Pawn, White)),
-					'k' => Some(piece!(King, Black)),
-					'q' => Some(piece!(Queen, Black)),
-					'r' => Some(piece!(Rook, Black)),
-					'b' => Some(piece!(Bishop, Black)),
-					'n' => Some(piece!(Knight, Black)),
-					'p' => Some(piece!(Pawn, Black)),
-					_ => Some(piece!(Empty, White)),
-				};
-			};
+		let ranks: Vec<&str> = fen_board.split('/').collect();
+		if ranks.len() != 8 {
+			return Err(invalid("expected 8 ranks"));
+		}
+		
+		for (row, rank) in ranks.iter().enumerate() {
+			let mut col: i8 = 0;
 			
-			// if Piece is not None, then insert the Piece
-			if piece != None {
-				self.place_piece(piece.unwrap(), coord!(row, col));
-				piece = None;
+			for c in rank.chars() {
+				if let Some(empty) = c.to_digit(10) {
+					// Checked before adding, a run of digits would overflow the column
+					if col as u32 + empty > 8 {
+						return Err(invalid("rank is longer than 8 squares"));
+					}
+					col += empty as i8;
+					continue;
+				}
+				
+				let piece = Piece::from_fen_char(c).ok_or_else(|| invalid("invalid piece"))?;
+				
+				if col > 7 {
+					return Err(invalid("rank is longer than 8 squares"));
+				}
+				result.put_piece(piece, coord!(row as i8, col));
 				col += 1;
 			}
+			
+			if col != 8 {
+				return Err(invalid("rank does not have 8 squares"));
+			}
 		}
 		
 		// Castling
-		let mut castling_rights = [false, false, false, false];
-		for c in fen_castling.chars() {
-			match c {
-				'K' => castling_rights[0] = true,
-				'Q' => castling_rights[1] = true,
-				'k' => castling_rights[2] = true,
-				'q' => castling_rights[3] = true,
-				_ => (),
-			};
+		if fen_castling != "-" {
+			for c in fen_castling.chars() {
+				match c {
+					'K' => result.castling_rights[0] = true,
+					'Q' => result.castling_rights[1] = true,
+					'k' => result.castling_rights[2] = true,
+					'q' => result.castling_rights[3] = true,
+					_ => return Err(invalid("invalid castling rights")),
+				};
+			}
 		}
-		self.castling_rights = castling_rights;
+		// Hand-edited FENs often keep "KQkq" after moving the pieces, rights without the king
+		// and the rook on their squares are dropped instead of trusted
+		let claimed = result.castling_rights;
+		result.strip_castling_rights();
+		if claimed != result.castling_rights {
+			trace_event!(tracing::Level::DEBUG, claimed = ?claimed, kept = ?result.castling_rights, "castling rights dropped");
+		}
+		
+		// En passant target square, like "e3", dropped like the castling rights when no pawn
+		// could have just moved past it
+		if fen_en_passant != "-" {
+			let square = Coordinate::from_algebraic(fen_en_passant).map_err(|_| invalid("invalid en passant square"))?;
+			if result.en_passant_in_place(square.as_number()) {
+				result.en_passant_target_sq = Some(square.as_number());
+			} else {
+				trace_event!(tracing::Level::DEBUG, square = %square, "en passant square dropped");
+			}
+		}
+		
+		result.rebuild_attacks();
+		*self = result;
+		return Ok(());
 	}
 	
 	pub fn get_fen(&self) -> String {
 		// function to convert the Board to FEN
 		// source: https://en.wikipedia.org/wiki/Forsyth%e2%80%93Edwards_notation
-		
-		let mut fen_board: String = String::new();
-		let mut empty_count: u8 = 0;
-		
-		for i in 0..8 {
-			for j in 0..8 {
-				let piece = self.board[i][j];
-				
-				if piece.breed == Pieces::Empty {
-					empty_count += 1;
-				} else {
-					if empty_count > 0 {
-						fen_board.push_str(&empty_count.to_string());
-						empty_count = 0;
-					}
-					
-					use Color::*;
-					use Pieces::*;
-					
-					let mut chr = match piece.breed {
-						King => 'k',
-						Queen => 'q',
-						Rook => 'r',
-						Bishop => 'b',
-						Knight => 'n',
-						Pawn => 'p',
-						_ => panic!("Invalid piece"),
-					};
-					
-					// If piece is white, transform to upper case
-					if piece.color == White {
-						chr = chr.to_ascii_uppercase();
+		let mut fen = String::with_capacity(FEN_CAPACITY);
+		self.write_fen(&mut fen).expect("writing to a String can't fail");
+		return fen;
+	}
+	
+	pub fn write_fen(&self, out: &mut impl fmt::Write) -> fmt::Result {
+		// Straight into the writer, one character at a time and without temporary strings
+		for (i, row) in self.board.iter().enumerate() {
+			let mut empty_count: u8 = 0;
+			for square in row {
+				match square {
+					Some(piece) => {
+						if empty_count > 0 {
+							out.write_char(char::from(b'0' + empty_count))?;
+							empty_count = 0;
+						}
+						out.write_char(FEN_CHARS[piece.color as usize][piece.breed as usize])?;
 					}
-					
-					fen_board.push_str(chr.to_string().as_str());
+					None => empty_count += 1,
 				}
 			}
 			
 			if empty_count > 0 {
-				fen_board.push_str(&empty_count.to_string());
-				empty_count = 0;
+				out.write_char(char::from(b'0' + empty_count))?;
 			}
-			
 			if i != 7 {
-				fen_board.push_str("/");
+				out.write_char('/')?;
 			}
 		}
 		
-		// Separated by spaces add info about the turn, castling, en passant, and halfmove clock
-		fen_board.push_str(" ");
-		if self.turn == Color::White {
-			fen_board.push_str("w");
-		} else {
-			fen_board.push_str("b");
-		}
+		// Separated by spaces add info about the turn, castling, en passant, and the clocks
+		out.write_str(if self.turn == Color::White { " w " } else { " b " })?;
 		
 		// TODO: check if castling is possible
-		fen_board.push_str(" ");
-		if self.castling_rights[0] {
-			fen_board.push_str("K");
-		}
-		if self.castling_rights[1] {
-			fen_board.push_str("Q");
-		}
-		if self.castling_rights[2] {
-			fen_board.push_str("k");
+		for (right, chr) in self.castling_rights.iter().zip(CASTLING_CHARS) {
+			if *right {
+				out.write_char(chr)?;
+			}
 		}
-		if self.castling_rights[3] {
-			fen_board.push_str("q");
+		if !self.castling_rights.contains(&true) {
+			out.write_char('-')?;
 		}
 		
-		fen_board.push_str(" ");
-		if self.en_passant_target_sq == None {
-			fen_board.push_str("-");
-		} else {
-			fen_board.push_str(&self.en_passant_target_sq.unwrap().to_string());
+		out.write_char(' ')?;
+		match self.en_passant_target_sq {
+			Some(sq) => write!(out, "{}", Coordinate::from_number(sq))?,
+			None => out.write_char('-')?,
 		}
 		
-		fen_board.push_str(" ");
-		fen_board.push_str(&self.halfmove_clock.to_string());
-		
-		fen_board.push_str(" ");
-		fen_board.push_str(&self.fullmove_number.to_string());
-		
-		return fen_board;
+		return write!(out, " {} {}", self.halfmove_clock, self.fullmove_number);
 	}
 	
 	pub fn default() -> Self {
 		let mut result = Board::new();
-		result.load_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+		result.load_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
 		return result;
 	}
 	
-	pub fn place_piece(&mut self, piece: Piece, coord: Coordinate) {
+	pub fn place_piece(&mut self, piece: Piece, coord: Coordinate) -> Result<(), ChessError> {
 		// Checking bounds
-		if coord.row > 7 || coord.col > 7 {
-			panic!("invalid Coordinates {} {}", coord.row, coord.col);
+		if !coord.is_valid() {
+			return Err(ChessError::OutOfBounds(coord));
 		}
 		
-		self.board[coord.row as usize][coord.col as usize] = piece;
-		
-		// Add to the piece map
+		self.put_piece(piece, coord);
+		self.rebuild_attacks();
+		return Ok(());
+	}
+	
+	pub fn remove_piece(&mut self, coord: Coordinate) -> Result<Piece, ChessError> {
+		// Takes the piece off the board, castling rights stay as they are
+		if !coord.is_valid() {
+			return Err(ChessError::OutOfBounds(coord));
+		}
+		let piece = self.modify_sq(coord, None).ok_or(ChessError::EmptySquare(coord))?;
 		match piece.color {
 			Color::White => &mut self.white_pieces,
 			Color::Black => &mut self.black_pieces,
 		}
-		.insert(coord.as_number(), piece);
+		.remove(&coord.as_number());
+		self.rebuild_attacks();
+		return Ok(piece);
 	}
 	
-	pub fn draw(&self) {
-		// function to draw the Board
-		/*
-		. . . . . . . .
-		. . . . . . . .
-		. . . . . . . .
-		. . . . . . . .
-		. . . . . . . .
-		. . . . . . . .
-		. . . . . . . .
-		. . . . . . . .
-		*/
+	pub fn validate(&self) -> Result<(), ChessError> {
+		// Checks the position could come up in a game: one king a side, no pawns on the first
+		// or last rank, no check against the side that just moved, castling rights and the
+		// en passant square that fit the pieces
+		let invalid = |reason: &str| Err(ChessError::InvalidPosition(reason.to_string()));
 		
-		let mut array_of_chars_for_pieces: &[&str; 6];
+		for pieces in [&self.white_pieces, &self.black_pieces] {
+			if pieces.values().filter(|piece| piece.breed == Pieces::King).count() != 1 {
+				return invalid("each side needs exactly one king");
+			}
+			if pieces.len() > 16 || pieces.values().filter(|piece| piece.breed == Pieces::Pawn).count() > 8 {
+				return invalid("too many pieces");
+			}
+			if pieces.iter().any(|(num, piece)| piece.breed == Pieces::Pawn && (num / 8 == 0 || num / 8 == 7)) {
+				return invalid("pawn on the first or last rank");
+			}
+		}
 		
-		for row in 0..8 {
-			for col in 0..8 {
-				let piece: Piece = self.board[row][col];
-				
-				if piece.color == Color::White {
-					array_of_chars_for_pieces = &WHITE_PIECES;
-				} else {
-					array_of_chars_for_pieces = &BLACK_PIECES;
-				}
-				
-				// match the Piece to the correct character
-				use Pieces::*;
-				match piece.breed {
-					King => {
-						print!("{} ", array_of_chars_for_pieces[0]);
-					}
-					
-					Queen => {
-						print!("{} ", array_of_chars_for_pieces[1]);
-					}
-					
-					Rook => {
-						print!("{} ", array_of_chars_for_pieces[2]);
-					}
-					
-					Bishop => {
-						print!("{} ", array_of_chars_for_pieces[3]);
-					}
-					
-					Knight => {
-						print!("{} ", array_of_chars_for_pieces[4]);
-					}
-					
-					Pawn => {
-						print!("{} ", array_of_chars_for_pieces[5]);
-					}
-					
-					Empty => {
-						print!("\x1b[39;49m.\x1b[0m ");
-					}
-				}
+		let waiting = match self.turn {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		if self.is_in_check(waiting) {
+			return invalid("the side not to move is in check");
+		}
+		
+		for i in 0..4 {
+			if self.castling_rights[i] && !self.castling_in_place(i) {
+				return invalid("castling rights without the king and rook in place");
 			}
-			println!();
 		}
+		
+		if self.en_passant_target_sq.is_some_and(|sq| !self.en_passant_in_place(sq)) {
+			return invalid("en passant square without a pawn that just moved");
+		}
+		
+		return Ok(());
+	}
+	
+	pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+		// Checks the state the board keeps twice agrees with itself, whatever happened to it: the
+		// square array and the piece maps, the castling rights and the en passant square with the
+		// pieces, and the attacks, checkers and pins with a fresh rebuild_attacks. The zobrist hash
+		// is worked out from the piece maps, so it is right when they are
+		// NOTE: Whether the position could come up in a game is validate's business
+		for num in 0..64 {
+			let coord = Coordinate::from_number(num);
+			let square = self.get_piece(coord);
+			let mapped = match (self.white_pieces.get(&num), self.black_pieces.get(&num)) {
+				(Some(white), None) if white.color == Color::White => Some(*white),
+				(None, Some(black)) if black.color == Color::Black => Some(*black),
+				(None, None) => None,
+				_ => return Err(InvariantViolation::PieceMismatch(coord)),
+			};
+			if square != mapped {
+				return Err(InvariantViolation::PieceMismatch(coord));
+			}
+		}
+		for (color, pieces) in [(Color::White, &self.white_pieces), (Color::Black, &self.black_pieces)] {
+			if pieces.len() != pieces.squares.iter().flatten().count() {
+				return Err(InvariantViolation::PieceCount(color));
+			}
+		}
+		
+		if let Some(right) = (0..4).find(|right| self.castling_rights[*right] && !self.castling_in_place(*right)) {
+			return Err(InvariantViolation::CastlingRights(right));
+		}
+		
+		if let Some(sq) = self.en_passant_target_sq.filter(|sq| !self.en_passant_in_place(*sq)) {
+			return Err(InvariantViolation::EnPassant(Coordinate::from_number(sq)));
+		}
+		
+		if self.fullmove_number == 0 {
+			return Err(InvariantViolation::MoveNumber);
+		}
+		
+		let mut fresh = *self;
+		fresh.rebuild_attacks();
+		for color in [Color::White, Color::Black] {
+			if fresh.attacks[color as usize] != self.attacks[color as usize] {
+				return Err(InvariantViolation::StaleAttacks(color));
+			}
+			if fresh.checkers[color as usize] != self.checkers[color as usize] {
+				return Err(InvariantViolation::StaleCheckers(color));
+			}
+		}
+		if let Some(num) = (0..64).find(|num| fresh.pin_masks[*num] != self.pin_masks[*num]) {
+			return Err(InvariantViolation::StalePin(Coordinate::from_number(num as i8)));
+		}
+		
+		return Ok(());
+	}
+	
+	pub fn debug_assert_consistent(&self) {
+		// Panics with what check_invariants found in debug builds, does nothing in release builds
+		if cfg!(debug_assertions) {
+			if let Err(violation) = self.check_invariants() {
+				panic!("inconsistent board: {}", violation);
+			}
+		}
+	}
+	
+	fn castling_in_place(&self, right: usize) -> bool {
+		// The king on the e file and the rook in the corner of the right, in the order of castling_rights
+		let corner = CASTLING_ROOKS[right];
+		let color = if right < 2 { Color::White } else { Color::Black };
+		return self.get_piece(coord!(corner.row, 4)) == Some(Piece { breed: Pieces::King, color })
+		&& self.get_piece(corner) == Some(Piece { breed: Pieces::Rook, color });
+	}
+	
+	fn en_passant_in_place(&self, sq: i8) -> bool {
+		// The pawn that just moved two squares stands in front of the target square, the square
+		// and the one the pawn came from are empty
+		let target = Coordinate::from_number(sq);
+		let (row, pawn, from, color) = match self.turn {
+			Color::White => (2, coord!(3, target.col), coord!(1, target.col), Color::Black),
+			Color::Black => (5, coord!(4, target.col), coord!(6, target.col), Color::White),
+		};
+		return target.row == row
+		&& self.get_piece(pawn) == Some(Piece { breed: Pieces::Pawn, color })
+		&& self.get_piece(target).is_none()
+		&& self.get_piece(from).is_none();
+	}
+	
+	pub fn infer_castling_rights(&mut self) {
+		// Every right the pieces allow, for positions set up from the pieces alone
+		// NOTE: Whether the king or the rook moved before can't be seen, the rights may be too generous
+		for i in 0..4 {
+			self.castling_rights[i] = self.castling_in_place(i);
+		}
+	}
+	
+	pub fn strip_castling_rights(&mut self) {
+		// Drops the rights the pieces don't allow, the others stay as they are
+		for i in 0..4 {
+			self.castling_rights[i] &= self.castling_in_place(i);
+		}
+	}
+	
+	pub fn from_placement(placement: &str, turn: Color) -> Result<Board, ChessError> {
+		// A position from the piece placement field of a FEN, with the castling rights inferred
+		// NOTE: The en passant square can't be inferred and is left empty
+		if placement.split_whitespace().count() != 1 {
+			return Err(ChessError::InvalidFen(placement.to_string(), "expected the piece placement alone".to_string()));
+		}
+		let turn = match turn {
+			Color::White => "w",
+			Color::Black => "b",
+		};
+		let mut board = Board::from_fen(&format!("{} {} - -", placement, turn))?;
+		board.infer_castling_rights();
+		return Ok(board);
+	}
+	
+	fn put_piece(&mut self, piece: Piece, coord: Coordinate) {
+		// place_piece without the bounds check and the attack state
+		self.board[coord.row as usize][coord.col as usize] = Some(piece);
+		
+		// Add to the piece map
+		match piece.color {
+			Color::White => &mut self.white_pieces,
+			Color::Black => &mut self.black_pieces,
+		}
+		.insert(coord.as_number(), piece);
+	}
+	
+	pub fn draw(&self) {
+		// function to draw the Board with the default theme, see render.rs for the others
+		print!("{}", self.render(&RenderTheme::default()));
 	}
 	
 	pub fn diagonal_moves(&self, coord: Coordinate, color: Color) -> Vec<Coordinate> {
 		// function to get all diagonal moves
 		let mut result: Vec<Coordinate> = Vec::new();
-		let mut piece: Piece;
 		let (row, col) = (coord.row, coord.col);
 		let (mut new_row, mut new_col): (i8, i8);
 		let (mut left_up, mut left_down, mut right_up, mut right_down) = (true, true, true, true);
@@ -396,9 +999,7 @@ impl Board {
 				new_col = col - delta;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							left_up = false;
@@ -419,9 +1020,7 @@ impl Board {
 				new_col = col - delta;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							left_down = false;
@@ -442,9 +1041,7 @@ impl Board {
 				new_col = col + delta;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							right_up = false;
@@ -465,9 +1062,7 @@ impl Board {
 				new_col = col + delta;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							right_down = false;
@@ -489,7 +1084,6 @@ impl Board {
 	pub fn linear_moves(&self, coord: Coordinate, color: Color) -> Vec<Coordinate> {
 		// function to get all linear moves
 		let mut result: Vec<Coordinate> = Vec::new();
-		let mut piece: Piece;
 		let row = coord.row;
 		let col = coord.col;
 		let (mut new_row, mut new_col): (i8, i8);
@@ -502,9 +1096,7 @@ impl Board {
 				new_col = col;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							up = false;
@@ -525,9 +1117,7 @@ impl Board {
 				new_col = col;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							down = false;
@@ -548,9 +1138,7 @@ impl Board {
 				new_col = col - delta;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							left = false;
@@ -571,9 +1159,7 @@ impl Board {
 				new_col = col + delta;
 				
 				if (new_row >= 0 && new_col >= 0) && (new_row < 8 && new_col < 8) {
-					piece = self.board[new_row as usize][new_col as usize];
-					
-					if piece.breed != Pieces::Empty {
+					if let Some(piece) = self.board[new_row as usize][new_col as usize] {
 						if piece.color != color {
 							result.push(coord!(new_row, new_col));
 							right = false;
@@ -592,32 +1178,49 @@ impl Board {
 		return result;
 	}
 	
-	pub fn modify_sq(&mut self, coord: Coordinate, new_piece: Piece) -> Option<Piece> {
-		// Returns piece from square if there was a piece
-		match self.get_piece(coord) {
-			Some(piece) => {
-				self.board[coord.row as usize][coord.col as usize] = new_piece;
-				return Some(piece);
-			}
-			None => {
-				self.board[coord.row as usize][coord.col as usize] = new_piece;
-				return None;
-			}
+	pub fn modify_sq(&mut self, coord: Coordinate, new_piece: Option<Piece>) -> Option<Piece> {
+		// Returns piece from square if there was a piece, None empties the square
+		// NOTE: Only the square changes, the piece maps and the attack state are left as they were
+		if !coord.is_valid() {
+			return None;
 		}
+		return std::mem::replace(&mut self.board[coord.row as usize][coord.col as usize], new_piece);
 	}
 	
 	pub fn apply_move(&mut self, starting: Coordinate, ending: Coordinate) -> Option<Piece> {
-		// Returns the piece that was captured
-		let piece = self.get_piece(starting);
-		
-		if piece.is_none() {
+		// Returns the piece that was captured, nothing happens when there is no piece to move
+		return self.move_piece(starting, ending, None);
+	}
+	
+	fn move_piece(&mut self, starting: Coordinate, ending: Coordinate, promotion: Option<Pieces>) -> Option<Piece> {
+		// apply_move that can also swap the pawn for the promotion piece, the attacks are rebuilt once
+		if !ending.is_valid() {
 			return None;
 		}
+		let piece = self.get_piece(starting)?;
+		let mut captured_piece = self.modify_sq(ending, Some(piece));
+		let mut captured_sq = ending;
+		self.modify_sq(starting, None);
 		
-		let piece = piece.unwrap();
-		let captured_piece = self.modify_sq(ending, piece);
-        self.modify_sq(starting, Piece { breed: Pieces::Empty, color: piece.color });
+		// En passant, the captured pawn is not on the square the pawn moves to
+		/*
+		* . # .
+		* . p P
+		* P - Pawn taking en passant
+		* p - Pawn that made a double move, it is removed
+		* # - Target square for en passant
+		*/
+		if piece.breed == Pieces::Pawn
+		&& captured_piece.is_none()
+		&& starting.col != ending.col
+		&& self.en_passant_target_sq == Some(ending.as_number())
+		&& self.get_piece(coord!(starting.row, ending.col)).is_some_and(|pawn| pawn.breed == Pieces::Pawn && pawn.color != piece.color)
+		{
+			captured_sq = coord!(starting.row, ending.col);
+			captured_piece = self.modify_sq(captured_sq, None);
+		}
 		
+		self.en_passant_target_sq = None;
 		if piece.breed == Pieces::Pawn && (ending.row - starting.row).abs() == 2 {
 			let inc = match piece.color {
 				Color::White => -1,
@@ -631,40 +1234,78 @@ impl Board {
 			* P - Pawn that made a double move
 			* # - Target square for en passant
 			*/
-			self.en_passant_target_sq = Some(coord!(ending.row + inc, ending.col).as_number());
+			self.en_passant_target_sq = Some(coord!(ending.row - inc, ending.col).as_number());
 		}
 		
 		// Modify the map of pieces
-		match piece.color {
+		let pieces = match piece.color {
 			Color::White => &mut self.white_pieces,
 			Color::Black => &mut self.black_pieces,
+		};
+		pieces.remove(&(starting.as_number()));
+		pieces.insert(ending.as_number(), piece);
+		
+		if let Some(captured) = captured_piece {
+			match captured.color {
+				Color::White => &mut self.white_pieces,
+				Color::Black => &mut self.black_pieces,
+			}
+			.remove(&(captured_sq.as_number()));
 		}
-		.insert(ending.as_number(), piece);
 		
-		match piece.color {
-			Color::White => &mut self.white_pieces,
-			Color::Black => &mut self.black_pieces,
+		// Castling, the king moves two squares and the rook jumps over it
+		if piece.breed == Pieces::King && (ending.col - starting.col).abs() == 2 {
+			let (rook_col, new_rook_col) = if ending.col > starting.col { (7, 5) } else { (0, 3) };
+			let rook_from = coord!(starting.row, rook_col);
+			let rook_to = coord!(starting.row, new_rook_col);
+			
+			if let Some(rook) = self.modify_sq(rook_from, None) {
+				self.modify_sq(rook_to, Some(rook));
+				let pieces = match rook.color {
+					Color::White => &mut self.white_pieces,
+					Color::Black => &mut self.black_pieces,
+				};
+				pieces.remove(&(rook_from.as_number()));
+				pieces.insert(rook_to.as_number(), rook);
+			}
 		}
-		.remove(&(starting.as_number()));
 		
-		match captured_piece {
-			Some(piece) => {
-				match piece.color {
-					Color::White => &mut self.black_pieces,
-					Color::Black => &mut self.white_pieces,
-				}
-				.remove(&(ending.as_number()));
-			},
-			None => {}
-		};
+		// Moving the king or a rook loses the right to castle, so does losing the rook
+		if piece.breed == Pieces::King {
+			match piece.color {
+				Color::White => (self.castling_rights[0], self.castling_rights[1]) = (false, false),
+				Color::Black => (self.castling_rights[2], self.castling_rights[3]) = (false, false),
+			}
+		}
+		for (i, corner) in CASTLING_ROOKS.iter().enumerate() {
+			if starting == *corner || ending == *corner {
+				self.castling_rights[i] = false;
+			}
+		}
 		
 		// Invert a turn
-        use Color::*;
-        self.turn = match self.turn {
-            White => Black,
-            Black => White
-        };
+		use Color::*;
+		self.turn = match self.turn {
+			White => Black,
+			Black => White
+		};
+		
+		// Clocks, a capture or a pawn move starts the fifty moves again
+		if piece.breed == Pieces::Pawn || captured_piece.is_some() {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+		}
+		// Counters loaded from a FEN may already be at the top
+		if piece.color == Color::Black {
+			self.fullmove_number = self.fullmove_number.saturating_add(1);
+		}
+		
+		if let Some(breed) = promotion {
+			self.put_piece(Piece { breed, color: piece.color }, ending);
+		}
 		
+		self.rebuild_attacks();
 		return captured_piece;
 	}
 	
@@ -686,31 +1327,49 @@ impl Board {
 		piece_coord: Coordinate,
 		moves: Vec<i8>,
 	) -> Vec<i8> {
-		let piece = self.get_piece(piece_coord);
-
-        if piece.is_none() {
-            return moves;
-        }
-
-        let piece = piece.unwrap();
-
-        // Cloning a board
-		let mut clone_board = Board::new();
-		clone_board.load_fen(&self.get_fen()[..]);
-
+		let piece = match self.get_piece(piece_coord) {
+			Some(piece) => piece,
+			None => return moves,
+		};
+		
+		// King moves and en passant can uncover lines the attack state doesn't know about,
+		// those are played on a copy of the board
+		let king = self.get_king_coord(piece.color);
+		let checkers = self.checkers[piece.color as usize];
+		
 		let mut result: Vec<i8> = Vec::new();
 		
 		for move_coord in moves {
-			let captured = clone_board.apply_move(piece_coord, Coordinate::from_number(move_coord));
-			if !clone_board.is_in_check(piece.color) {
-				result.push(move_coord);
+			let target = Coordinate::from_number(move_coord);
+			let en_passant = piece.breed == Pieces::Pawn
+			&& target.col != piece_coord.col
+			&& self.get_piece(target).is_none();
+			
+			if piece.breed == Pieces::King || en_passant || king.is_none() {
+				let mut after = *self;
+				after.apply_move(piece_coord, target);
+				if !after.is_in_check(piece.color) {
+					result.push(move_coord);
+				}
+				continue;
 			}
-
-			// Undo the move by swapping the pieces back
-			clone_board.apply_move(Coordinate::from_number(move_coord), piece_coord);
-
-			if captured != None {
-				clone_board.place_piece(captured.unwrap(), Coordinate::from_number(move_coord));
+			
+			// A pinned piece stays on the line between its king and the pinner
+			let bit = 1u64 << move_coord;
+			if self.pin_masks[piece_coord.as_number() as usize] & bit == 0 {
+				continue;
+			}
+			
+			// In check the move has to take the checker or stand in its way, a double check leaves only the king
+			match checkers.count_ones() {
+				0 => result.push(move_coord),
+				1 => {
+					let checker = Coordinate::from_number(checkers.trailing_zeros() as i8);
+					if (checkers | between(checker, king.unwrap())) & bit != 0 {
+						result.push(move_coord);
+					}
+				}
+				_ => (),
 			}
 		}
 		
@@ -720,14 +1379,14 @@ impl Board {
 	pub fn get_piece(&self, coord: Coordinate) -> Option<Piece> {
         // It returns None if the piece you are trying to get is empty, so there is no need to
         // check for a piece breed all the time if you can just check for None =D
-		let piece = self.board[coord.row as usize][coord.col as usize];
-		return match piece.breed {
-			Pieces::Empty => None,
-			_ => Some(piece),
-		};
+		if !coord.is_valid() {
+			return None;
+		}
+		return self.board[coord.row as usize][coord.col as usize];
 	}
 	
-	pub fn get_moves(&self, coord: Coordinate) -> Vec<i8> {
+	fn get_pseudo_moves(&self, coord: Coordinate) -> Vec<i8> {
+		// Moves by the piece rules only, own king safety is not considered
 		/*
 		56 57 58 59 60 61 62 63
 		48 49 50 51 52 53 54 55
@@ -833,92 +1492,526 @@ impl Board {
 					Pawn => {
 						let starting_row = if piece.color == Color::White { 6 } else { 1 };
 						let inc = if piece.color == Color::White { 1 } else { -1 };
+						// Rows grow downwards while numbers grow upwards, hence the minus
+						let front_row = coord.row - inc;
 						
-						if coord.row == starting_row {
+						if (0..=7).contains(&front_row) {
 							// If the square in front of the pawn is empty, add a move
-							if self
-							.get_piece(Coordinate::from_number(position + inc * 8))
-							.is_none()
-							{
+							if self.get_piece(coord!(front_row, coord.col)).is_none() {
 								moves.push(position + inc * 8);
 								// If the second square in front of the pawn is empty, add a move
-								if self.get_piece(Coordinate::from_number(position + inc * 16)).is_none() {
+								if coord.row == starting_row && self.get_piece(coord!(front_row - inc, coord.col)).is_none() {
 									moves.push(position + inc * 16);
 								}
 							}
-						} else {
-							// If the square in front of the pawn is empty, add a move
-							if self
-							.get_piece(Coordinate::from_number(position + inc * 8))
-							.is_none()
-							{
-								moves.push(position + inc * 8);
-							}
-						}
-						
-						// Attacking moves
-						match self.get_piece(Coordinate::from_number(position + inc * 7)) {
-							Some(under_attack_piece) => {
-								if under_attack_piece.color != piece.color {
-									moves.push(position + inc * 7);
+							
+							// Attacking moves, the column check keeps pawns on the edge from wrapping around
+							for col_diff in [-1, 1] {
+								let new_col = coord.col + col_diff;
+								if !(0..=7).contains(&new_col) {
+									continue;
+								}
+								
+								let target = coord!(front_row, new_col);
+								match self.get_piece(target) {
+									Some(under_attack_piece) => {
+										if under_attack_piece.color != piece.color {
+											moves.push(target.as_number());
+										}
+									}
+									None => {
+										// En passant
+										if self.en_passant_target_sq == Some(target.as_number()) {
+											moves.push(target.as_number());
+										}
+									}
 								}
 							}
-							None => {}
 						}
+					}
+				}
+			}
+			None => {}
+		}
+		
+		return moves;
+	}
+	
+	pub fn get_moves(&self, coord: Coordinate) -> Vec<i8> {
+		let mut moves = self.filter_check_moves(coord, self.get_pseudo_moves(coord));
+		moves.append(&mut self.castling_moves(coord));
+		return moves;
+	}
+	
+	fn castling_moves(&self, coord: Coordinate) -> Vec<i8> {
+		// Castling never attacks anything, so it is kept out of the pseudo moves
+		let mut result: Vec<i8> = Vec::new();
+		let king = match self.get_piece(coord) {
+			Some(piece) if piece.breed == Pieces::King => piece,
+			_ => return result,
+		};
+		
+		let (row, enemy, rights) = match king.color {
+			Color::White => (7, Color::Black, [0, 1]),
+			Color::Black => (0, Color::White, [2, 3]),
+		};
+		
+		if coord != coord!(row, 4) || self.is_square_attacked(coord, enemy) {
+			return result;
+		}
+		
+		// Columns that have to be empty, and the ones the king walks through (the last one is where it lands)
+		/*
+		. R . . K . . R
+		  e e e   e e
+		    s s   s s
+		*/
+		let sides: [(usize, i8, &[i8], &[i8]); 2] = [
+			(rights[0], 7, &[5, 6], &[5, 6]),
+			(rights[1], 0, &[1, 2, 3], &[3, 2]),
+		];
+		
+		for (right, rook_col, empty, safe) in sides {
+			if !self.castling_rights[right] {
+				continue;
+			}
+			if self.get_piece(coord!(row, rook_col)) != Some(Piece { breed: Pieces::Rook, color: king.color }) {
+				continue;
+			}
+			if empty.iter().any(|col| self.get_piece(coord!(row, *col)).is_some()) {
+				continue;
+			}
+			if safe.iter().any(|col| self.is_square_attacked(coord!(row, *col), enemy)) {
+				continue;
+			}
+			
+			result.push(coord!(row, safe[safe.len() - 1]).as_number());
+		}
+		
+		return result;
+	}
+	
+	pub(crate) fn piece_attacks(&self, coord: Coordinate, piece: Piece) -> u64 {
+		// Squares the piece attacks, whoever stands on them, sliders stop at the first piece in the way
+		let mut result: u64 = 0;
+		let mut walk = |steps: &[(i8, i8)], slide: bool| {
+			for (row_diff, col_diff) in steps {
+				let mut target = coord!(coord.row + row_diff, coord.col + col_diff);
+				while target.is_valid() {
+					result |= 1 << target.as_number();
+					if !slide || self.get_piece(target).is_some() {
+						break;
+					}
+					target = coord!(target.row + row_diff, target.col + col_diff);
+				}
+			}
+		};
+		
+		use Pieces::*;
+		match piece.breed {
+			King => walk(&KING_STEPS, false),
+			Knight => walk(&KNIGHT_STEPS, false),
+			Bishop => walk(&DIAGONAL_STEPS, true),
+			Rook => walk(&LINEAR_STEPS, true),
+			Queen => {
+				walk(&DIAGONAL_STEPS, true);
+				walk(&LINEAR_STEPS, true);
+			}
+			// Pawns attack diagonally even when there is nothing to take
+			Pawn => match piece.color {
+				Color::White => walk(&[(-1, -1), (-1, 1)], false),
+				Color::Black => walk(&[(1, -1), (1, 1)], false),
+			},
+		}
+		
+		return result;
+	}
+	
+	fn rebuild_attacks(&mut self) {
+		// Rebuilds the attacked squares, the checkers and the pins of both sides from scratch
+		// Called once after every change to the position, so is_in_check and the move filter
+		// only look things up instead of scanning the pieces again
+		self.attacks = [0, 0];
+		self.checkers = [0, 0];
+		self.pin_masks = [u64::MAX; 64];
+		
+		let kings = [self.get_king_coord(Color::White), self.get_king_coord(Color::Black)];
+		
+		for (color, pieces) in [(Color::White, &self.white_pieces), (Color::Black, &self.black_pieces)] {
+			let enemy_king = kings[1 - color as usize];
+			
+			for (num, piece) in pieces.iter() {
+				let attacks = self.piece_attacks(Coordinate::from_number(*num), *piece);
+				self.attacks[color as usize] |= attacks;
+				
+				if let Some(king) = enemy_king {
+					if attacks & (1 << king.as_number()) != 0 {
+						self.checkers[1 - color as usize] |= 1 << num;
+					}
+				}
+			}
+		}
+		
+		// Pins, walking out of each king: an own piece followed by an enemy slider on the same line
+		for (color, king) in [(Color::White, kings[0]), (Color::Black, kings[1])] {
+			let king = match king {
+				Some(king) => king,
+				None => continue,
+			};
+			
+			for (steps, slider) in [(DIAGONAL_STEPS, Pieces::Bishop), (LINEAR_STEPS, Pieces::Rook)] {
+				for (row_diff, col_diff) in steps {
+					let mut ray: u64 = 0;
+					let mut pinned: Option<Coordinate> = None;
+					let mut target = coord!(king.row + row_diff, king.col + col_diff);
+					
+					while target.is_valid() {
+						ray |= 1 << target.as_number();
 						
-						match self.get_piece(Coordinate::from_number(position + inc * 9)) {
-							Some(under_attack_piece) => {
-								if under_attack_piece.color != piece.color {
-									moves.push(position + inc * 9);
+						if let Some(piece) = self.get_piece(target) {
+							if piece.color == color {
+								if pinned.is_some() {
+									break;
 								}
+								pinned = Some(target);
+							} else {
+								if let Some(pinned) = pinned {
+									if piece.breed == slider || piece.breed == Pieces::Queen {
+										self.pin_masks[pinned.as_number() as usize] = ray;
+									}
+								}
+								break;
 							}
-							None => {}
 						}
 						
-						// En passant
-						if self.en_passant_target_sq != None {
-							if position + inc * 7 == self.en_passant_target_sq.unwrap() {
-								moves.push(position + inc * 7);
-							}
-							
-							if position + inc * 9 == self.en_passant_target_sq.unwrap() {
-								moves.push(position + inc * 9);
-							}
-						}
+						target = coord!(target.row + row_diff, target.col + col_diff);
 					}
-					
-					Empty => {}
 				}
 			}
-			None => {}
+		}
+	}
+	
+	pub fn is_square_attacked(&self, coord: Coordinate, by: Color) -> bool {
+		// Defended pieces count as attacked too
+		return coord.is_valid() && self.attacks[by as usize] & (1 << coord.as_number()) != 0;
+	}
+	
+	pub fn attacked_squares(&self, by: Color) -> u64 {
+		// Bitboard of the squares attacked by the color, a1 is bit 0
+		return self.attacks[by as usize];
+	}
+	
+	pub fn checkers(&self, color: Color) -> Vec<Coordinate> {
+		// Pieces giving check to the king of the color
+		let mut result: Vec<Coordinate> = Vec::new();
+		let mut checkers = self.checkers[color as usize];
+		while checkers != 0 {
+			result.push(Coordinate::from_number(checkers.trailing_zeros() as i8));
+			checkers &= checkers - 1;
+		}
+		return result;
+	}
+	
+	pub fn pin_mask(&self, coord: Coordinate) -> u64 {
+		// Squares a piece may move to without exposing its king, all bits are set for pieces that are not pinned
+		if !coord.is_valid() {
+			return u64::MAX;
+		}
+		return self.pin_masks[coord.as_number() as usize];
+	}
+	
+	fn targets_to_moves(&self, coord: Coordinate, targets: Vec<i8>) -> Result<Vec<Move>, MoveError> {
+		// Turns target squares into moves, pawns reaching the last row get one move per promotion piece
+		if !coord.is_valid() {
+			return Err(MoveError::OutOfBounds(coord));
+		}
+		let piece = self.get_piece(coord).ok_or(MoveError::EmptySquare(coord))?;
+		
+		let mut result: Vec<Move> = Vec::new();
+		for target in targets {
+			let to = Coordinate::from_number(target);
+			
+			if piece.breed == Pieces::Pawn && (to.row == 0 || to.row == 7) {
+				use Pieces::*;
+				for promotion in [Queen, Rook, Bishop, Knight] {
+					result.push(Move { from: coord, to, promotion: Some(promotion) });
+				}
+			} else {
+				result.push(Move { from: coord, to, promotion: None });
+			}
 		}
 		
-		return self.filter_check_moves(coord, moves);
+		return Ok(result);
 	}
 	
-	pub fn is_in_check(&self, color: Color) -> bool {
-		let king_coord: Option<Coordinate> = self.get_king_coord(color);
-		if king_coord.is_none() {
-			return false;
+	pub fn moves_from(&self, coord: Coordinate) -> Result<Vec<Move>, MoveError> {
+		// Legal moves of the piece on the square
+		return self.targets_to_moves(coord, self.get_moves(coord));
+	}
+	
+	pub fn pseudo_legal_moves_from(&self, coord: Coordinate) -> Result<Vec<Move>, MoveError> {
+		// Moves of the piece on the square by the piece rules only, pinned pieces still move
+		// and the king may step into check
+		// NOTE: Castling is left out, it depends on the squares being safe
+		return self.targets_to_moves(coord, self.get_pseudo_moves(coord));
+	}
+	
+	fn own_squares(&self) -> Vec<i8> {
+		// Squares of the side to move
+		let pieces = match self.turn {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		
+		// The map has no order, sort the squares to keep the output stable
+		let mut squares: Vec<i8> = pieces.keys().copied().collect();
+		squares.sort();
+		return squares;
+	}
+	
+	pub fn legal_moves(&self) -> Vec<Move> {
+		// All legal moves for the side to move
+		let mut result: Vec<Move> = Vec::new();
+		self.legal_moves_into(&mut result);
+		
+		trace_event!(tracing::Level::TRACE, moves = result.len(), "legal moves generated");
+		return result;
+	}
+	
+	pub fn legal_moves_into(&self, result: &mut Vec<Move>) {
+		// Same as legal_moves, added to the end of `result` so its memory can be used again
+		for num in self.own_squares() {
+			if let Ok(mut moves) = self.moves_from(Coordinate::from_number(num)) {
+				result.append(&mut moves);
+			}
+		}
+	}
+	
+	pub fn capture_moves(&self) -> Vec<Move> {
+		// Legal captures for the side to move, en passant included
+		// The targets are narrowed down before the check filter, which is the expensive part
+		let mut result: Vec<Move> = Vec::new();
+		for num in self.own_squares() {
+			let coord = Coordinate::from_number(num);
+			let is_pawn = self.get_piece(coord).map(|piece| piece.breed) == Some(Pieces::Pawn);
+			
+			// Pseudo moves never land on an own piece, so anything occupied is a capture
+			let targets: Vec<i8> = self
+			.get_pseudo_moves(coord)
+			.into_iter()
+			.filter(|target| {
+				self.get_piece(Coordinate::from_number(*target)).is_some()
+				|| (is_pawn && self.en_passant_target_sq == Some(*target))
+			})
+			.collect();
+			
+			if let Ok(mut moves) = self.targets_to_moves(coord, self.filter_check_moves(coord, targets)) {
+				result.append(&mut moves);
+			}
 		}
 		
-		for (coord, _) in match color {
-			Color::White => self.black_pieces.iter(),
-			Color::Black => self.white_pieces.iter(),
-		} {
-			if self
-			.get_moves(Coordinate::from_number(*coord))
-			.contains(&king_coord.unwrap().as_number())
-			{
-				return true;
+		return result;
+	}
+	
+	pub fn gives_check(&self, mv: Move) -> bool {
+		// Whether the move puts the opponent in check, the move is not checked for legality
+		let mut after = *self;
+		after.make_move(mv);
+		return after.is_in_check(after.turn);
+	}
+	
+	pub fn checking_moves(&self) -> Vec<Move> {
+		// Legal moves that give check
+		// Every pseudo move is played once, both kings are looked at on the same copy
+		let mut result: Vec<Move> = Vec::new();
+		for num in self.own_squares() {
+			let coord = Coordinate::from_number(num);
+			
+			let mut candidates = self.pseudo_legal_moves_from(coord).unwrap_or_default();
+			if let Ok(mut castling) = self.targets_to_moves(coord, self.castling_moves(coord)) {
+				candidates.append(&mut castling);
+			}
+			
+			for mv in candidates {
+				let mut after = *self;
+				after.make_move(mv);
+				if !after.is_in_check(self.turn) && after.is_in_check(after.turn) {
+					result.push(mv);
+				}
+			}
+		}
+		
+		return result;
+	}
+	
+	pub fn classified_moves(&self) -> Vec<ClassifiedMove> {
+		// Legal moves in the same order as legal_moves, each played once on a copy to tag it
+		let mut result: Vec<ClassifiedMove> = Vec::new();
+		for mv in self.legal_moves() {
+			let piece = match self.get_piece(mv.from) {
+				Some(piece) => piece,
+				None => continue,
+			};
+			
+			let mut after = *self;
+			let captured_piece = after.make_move(mv);
+			result.push(ClassifiedMove {
+				mv,
+				piece,
+				captured_piece,
+				gives_check: after.is_in_check(after.turn),
+				castling: piece.breed == Pieces::King && (mv.to.col - mv.from.col).abs() == 2,
+				en_passant: piece.breed == Pieces::Pawn && mv.from.col != mv.to.col && self.get_piece(mv.to).is_none(),
+			});
+		}
+		
+		return result;
+	}
+	
+	pub fn check_move(&self, mv: Move) -> Result<(), IllegalMove> {
+		// Whether the side to move can play the move, and why not, for GUIs to tell the user
+		if !mv.from.is_valid() || !mv.to.is_valid() {
+			return Err(IllegalMove::OutOfBounds(mv));
+		}
+		let piece = self.get_piece(mv.from).ok_or(IllegalMove::EmptySquare(mv))?;
+		if piece.color != self.turn {
+			return Err(IllegalMove::NotYourTurn(mv));
+		}
+		
+		let reaches_last_row = piece.breed == Pieces::Pawn && (mv.to.row == 0 || mv.to.row == 7);
+		let promotes_to_piece = matches!(mv.promotion, Some(Pieces::Queen | Pieces::Rook | Pieces::Bishop | Pieces::Knight));
+		if (reaches_last_row && !promotes_to_piece) || (!reaches_last_row && mv.promotion.is_some()) {
+			return Err(IllegalMove::BadPromotion(mv));
+		}
+		
+		if !self.get_moves(mv.from).contains(&mv.to.as_number()) {
+			return Err(self.illegal_reason(mv, piece));
+		}
+		return Ok(());
+	}
+	
+	fn illegal_reason(&self, mv: Move, piece: Piece) -> IllegalMove {
+		// The move is not among the legal ones, find out why
+		// The king going two squares along its first rank from the e file is a castling attempt
+		let (home, rights) = match piece.color {
+			Color::White => (7, [0, 1]),
+			Color::Black => (0, [2, 3]),
+		};
+		if piece.breed == Pieces::King && mv.from == coord!(home, 4) && mv.to.row == home && (mv.to.col - 4).abs() == 2 {
+			let (right, rook_col, empty): (usize, i8, &[i8]) = match mv.to.col {
+				6 => (rights[0], 7, &[5, 6]),
+				_ => (rights[1], 0, &[1, 2, 3]),
+			};
+			if !self.castling_rights[right] || self.get_piece(coord!(home, rook_col)) != Some(Piece { breed: Pieces::Rook, color: piece.color }) {
+				return IllegalMove::NoCastlingRights(mv);
+			}
+			if empty.iter().any(|col| self.get_piece(coord!(home, *col)).is_some()) {
+				return IllegalMove::CastlingBlocked(mv);
+			}
+			if self.is_in_check(piece.color) {
+				return IllegalMove::CastlingInCheck(mv);
+			}
+			return IllegalMove::CastlingThroughCheck(mv);
+		}
+		
+		if self.get_piece(mv.to).is_some_and(|target| target.color == piece.color) {
+			return IllegalMove::OwnPiece(mv);
+		}
+		
+		// Fine by the piece rules, so it is the king that would be in danger
+		if self.get_pseudo_moves(mv.from).contains(&mv.to.as_number()) {
+			if piece.breed == Pieces::King {
+				return IllegalMove::IntoCheck(mv);
+			}
+			if self.pin_mask(mv.from) & (1 << mv.to.as_number()) == 0 {
+				return IllegalMove::Pinned(mv);
 			}
+			return IllegalMove::KingInCheck(mv);
+		}
+		
+		// A piece that could go there on an empty board has something in the way
+		if can_piece_reach(piece, mv.from, mv.to, &[]) {
+			return IllegalMove::Blocked(mv);
+		}
+		return IllegalMove::NotLegal(mv);
+	}
+	
+	pub fn try_apply_move(&mut self, mv: Move) -> Result<MoveOutcome, IllegalMove> {
+		// Checked version of make_move, the board only changes when the move is legal
+		self.check_move(mv)?;
+		let piece = self.get_piece(mv.from).unwrap();
+		
+		let castling = piece.breed == Pieces::King && (mv.to.col - mv.from.col).abs() == 2;
+		let en_passant = piece.breed == Pieces::Pawn && mv.from.col != mv.to.col && self.get_piece(mv.to).is_none();
+		
+		let captured = self.make_move(mv);
+		let result = self.game_result(MaterialRule::default());
+		
+		return Ok(MoveOutcome {
+			captured,
+			check: self.is_in_check(self.turn),
+			checkmate: matches!(result, Some(GameResult::Checkmate { .. })),
+			castling,
+			en_passant,
+			promotion: mv.promotion,
+			result,
+		});
+	}
+	
+	pub fn make_move(&mut self, mv: Move) -> Option<Piece> {
+		// Same as apply_move, but also swaps the pawn for the promotion piece
+		// NOTE: Nothing is checked, this is the fast path for engines that only play generated moves
+		return self.move_piece(mv.from, mv.to, mv.promotion);
+	}
+	
+	pub fn make_null_move(&mut self) -> NullMoveUndo {
+		// Passes the turn, the en passant chance is lost like after any other move
+		// NOTE: Passing while in check leaves the king en prise, callers should check first
+		let undo = NullMoveUndo {
+			en_passant_target_sq: self.en_passant_target_sq,
+			halfmove_clock: self.halfmove_clock,
+			fullmove_number: self.fullmove_number,
+		};
+		
+		self.en_passant_target_sq = None;
+		self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+		if self.turn == Color::Black {
+			self.fullmove_number = self.fullmove_number.saturating_add(1);
 		}
+		self.turn = match self.turn {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
 		
-		return false;
+		// The attacks don't depend on the turn, nothing to rebuild
+		return undo;
+	}
+	
+	pub fn undo_null_move(&mut self, undo: NullMoveUndo) {
+		self.turn = match self.turn {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		self.en_passant_target_sq = undo.en_passant_target_sq;
+		self.halfmove_clock = undo.halfmove_clock;
+		self.fullmove_number = undo.fullmove_number;
+	}
+	
+	pub fn halfmove_clock(&self) -> u32 {
+		return self.halfmove_clock;
+	}
+	
+	pub fn fullmove_number(&self) -> u32 {
+		return self.fullmove_number;
+	}
+	
+	pub fn is_in_check(&self, color: Color) -> bool {
+		return self.checkers[color as usize] != 0;
 	}
 	
 	pub fn is_in_checkmate(&mut self, color: Color) -> bool {
-		if self.get_king_coord(color).is_none() {
+		// No moves without a check is a stalemate, not a mate
+		if !self.is_in_check(color) {
 			return false;
 		}
 		
@@ -0,0 +1,95 @@
+use crate::chess::*;
+use crate::engine::{score_after_move, Engine, SearchLimits};
+use crate::error::ChessError;
+
+// How sharp and how complex a position is, for trainers picking positions worth showing
+/*
+Every legal move is scored by the engine (see score_after_move), scores being clamped to
+SCORE_CLAMP so a mate doesn't drown the rest out. From the scores:
+Near best  - the moves within NEAR_BEST_MARGIN of the best one, the real choices the player has
+Gap        - how much the second best move gives away, a big one means an only move
+Volatility - the standard deviation of the TOP_MOVES best scores, how much the choice matters
+Sharpness  - the share of the legal moves losing MISTAKE_MARGIN or more against the best one,
+             how easy it is to go wrong
+
+Sharp positions have few good moves among many bad ones, complex ones have several moves of
+about the same value with a lot going on around them (high volatility). Quiet positions are
+neither
+*/
+
+pub const SCORE_CLAMP: i32 = 1000;
+pub const NEAR_BEST_MARGIN: i32 = 30;
+pub const MISTAKE_MARGIN: i32 = 100;
+pub const TOP_MOVES: usize = 5;
+// Sharp: at most this many near best moves, at least this share of mistakes
+pub const SHARP_MOVES: usize = 2;
+pub const SHARP_SHARE: f64 = 0.5;
+// Complex: at least this many near best moves and this volatility
+pub const COMPLEX_MOVES: usize = 3;
+pub const COMPLEX_VOLATILITY: f64 = 50.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Complexity {
+	// Best first, clamped, for the side to move
+	pub scores: Vec<(Move, i32)>,
+	pub near_best: usize,
+	// None with a single legal move
+	pub gap: Option<i32>,
+	pub volatility: f64,
+	// Between 0 and 1
+	pub sharpness: f64,
+}
+
+impl Complexity {
+	pub fn best(&self) -> Option<(Move, i32)> {
+		return self.scores.first().copied();
+	}
+
+	pub fn legal_moves(&self) -> usize {
+		return self.scores.len();
+	}
+
+	pub fn is_only_move(&self) -> bool {
+		return self.gap.is_some_and(|gap| gap >= MISTAKE_MARGIN);
+	}
+
+	pub fn is_sharp(&self) -> bool {
+		return self.near_best <= SHARP_MOVES && self.sharpness >= SHARP_SHARE;
+	}
+
+	pub fn is_complex(&self) -> bool {
+		return self.near_best >= COMPLEX_MOVES && self.volatility >= COMPLEX_VOLATILITY;
+	}
+}
+
+pub fn analyze_complexity(board: &Board, engine: &mut impl Engine, limits: &SearchLimits) -> Result<Complexity, ChessError> {
+	// The legal moves of `board` (or limits.searchmoves), an error when there are none
+	let moves = limits.root_moves(board);
+	if moves.is_empty() {
+		return Err(ChessError::NoLegalMoves);
+	}
+
+	let mut scores = Vec::new();
+	for mv in moves {
+		let score = score_after_move(engine, board, mv, limits)?;
+		scores.push((mv, score.clamp(-SCORE_CLAMP, SCORE_CLAMP)));
+	}
+	scores.sort_by_key(|(_, score)| -score);
+
+	let best = scores[0].1;
+	let near_best = scores.iter().filter(|(_, score)| *score >= best - NEAR_BEST_MARGIN).count();
+	let mistakes = scores.iter().filter(|(_, score)| *score <= best - MISTAKE_MARGIN).count();
+	let gap = scores.get(1).map(|(_, score)| best - score);
+
+	let top: Vec<f64> = scores.iter().take(TOP_MOVES).map(|(_, score)| *score as f64).collect();
+	let mean = top.iter().sum::<f64>() / top.len() as f64;
+	let volatility = (top.iter().map(|score| (score - mean).powi(2)).sum::<f64>() / top.len() as f64).sqrt();
+
+	return Ok(Complexity {
+		near_best,
+		gap,
+		volatility,
+		sharpness: mistakes as f64 / scores.len() as f64,
+		scores,
+	});
+}
@@ -0,0 +1,105 @@
+use crate::chess::*;
+
+// Who attacks and who defends every square
+/*
+A square's control is the pieces of both sides attacking it directly, the same attackers
+attackers_of finds: pieces behind them don't count, pawns attack diagonally even with nothing
+to take and a piece attacks the squares of its own pieces too (it defends them)
+
+square_control asks about one square, control_map works out the attacks of every piece once
+and hands them out to all 64 squares, for heatmaps and for the space and control terms of the
+evaluation
+*/
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug, Default)]
+pub struct Control {
+	// Sorted by square number, a1 first
+	pub white_attackers: Vec<Coordinate>,
+	pub black_attackers: Vec<Coordinate>,
+}
+
+impl Control {
+	pub fn attackers(&self, color: Color) -> &[Coordinate] {
+		return match color {
+			Color::White => &self.white_attackers,
+			Color::Black => &self.black_attackers,
+		};
+	}
+
+	pub fn balance(&self) -> i32 {
+		// White attackers less black ones, positive when white has the square
+		return self.white_attackers.len() as i32 - self.black_attackers.len() as i32;
+	}
+
+	pub fn controlled_by(&self) -> Option<Color> {
+		// The side with more attackers, None when they are even
+		return match self.balance() {
+			balance if balance > 0 => Some(Color::White),
+			balance if balance < 0 => Some(Color::Black),
+			_ => None,
+		};
+	}
+}
+
+// Control of all the squares, indexed by square number
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct ControlMap {
+	squares: Vec<Control>,
+}
+
+impl ControlMap {
+	pub fn get(&self, coord: Coordinate) -> &Control {
+		return &self.squares[coord.as_number() as usize];
+	}
+
+	pub fn controlled_squares(&self, color: Color) -> u64 {
+		// Bitboard of the squares `color` has more attackers on, a1 is bit 0
+		return self
+		.squares
+		.iter()
+		.enumerate()
+		.filter(|(_, control)| control.controlled_by() == Some(color))
+		.fold(0, |bits, (num, _)| bits | 1 << num);
+	}
+
+	pub fn heatmap(&self) -> [[i32; 8]; 8] {
+		// Balance of every square, rows and columns the way the board has them (rank 8 first)
+		let mut result = [[0; 8]; 8];
+		for (row, line) in result.iter_mut().enumerate() {
+			for (col, balance) in line.iter_mut().enumerate() {
+				*balance = self.get(coord!(row as i8, col as i8)).balance();
+			}
+		}
+		return result;
+	}
+}
+
+impl Board {
+	pub fn square_control(&self, coord: Coordinate) -> Control {
+		Control {
+			white_attackers: self.attackers_of(coord, Color::White),
+			black_attackers: self.attackers_of(coord, Color::Black),
+		}
+	}
+
+	pub fn control_map(&self) -> ControlMap {
+		let mut squares = vec![Control::default(); 64];
+		// Walking the pieces by square number keeps the attackers sorted
+		let mut pieces: Vec<(i8, Piece)> = self.white_pieces.iter().chain(self.black_pieces.iter()).map(|(num, piece)| (*num, *piece)).collect();
+		pieces.sort_by_key(|(num, _)| *num);
+
+		for (num, piece) in pieces {
+			let from = Coordinate::from_number(num);
+			let mut attacks = self.piece_attacks(from, piece);
+			while attacks != 0 {
+				let control = &mut squares[attacks.trailing_zeros() as usize];
+				match piece.color {
+					Color::White => control.white_attackers.push(from),
+					Color::Black => control.black_attackers.push(from),
+				}
+				attacks &= attacks - 1;
+			}
+		}
+		return ControlMap { squares };
+	}
+}
@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::game::{Game, GameResult, MaterialRule, TerminationReason};
+use crate::notation::Notation;
+use crate::pgn::{parse_result_token, result_token, PgnAnnotation, PgnOptions, PgnWriter};
+use crate::time::Clock;
+
+// A correspondence game in progress, and the file it is kept in between moves
+/*
+Besides the moves the game keeps its tags, the clocks, when the last move was made, the draw
+offer, how it ended when a player ended it, the conditional moves and the annotations. Times are
+unix times in seconds, the thinking time of a move is charged when it is made, and a move made
+after the time ran out loses on time instead
+
+Conditional moves are lines the player waiting for a move prepares: the opponent's move, the
+reply, the opponent's next move and so on. A move of the opponent keeps the lines starting with
+it and plays their reply at once, the lines first added first. Every other line is dropped
+
+File format
+Version FORMAT_VERSION is text, one "key value" line each, after a "chyes correspondence 1"
+line (the number is the version, newer ones are refused). Moves are in coordinate notation,
+text values have their backslashes and line breaks escaped as \\ and \n:
+tag <name> <value>                 - as many as the game has, in order
+rule fide | no_forced_mate         - see MaterialRule
+start <fen>
+moves <move>...                    - every move of the game
+clock <ms> <ms> <ms> <ms> <moves>  - time and increment of white and black, the moves to the time
+                                     control or "-", only with a clock
+last_move_at <seconds>
+draw_offer white | black
+termination <result> <reason>      - only when a player ended the game, results coming from the
+                                     board are found again by replaying it
+conditional <move>...              - one per line
+nag <ply> <n>, comment <ply> <text>, eval <ply> <centipawns>, clk <ply> <ms> and
+variation <ply> <move>...          - the annotations of the moves, see PgnAnnotation
+*/
+
+pub const FORMAT_VERSION: u32 = 1;
+const FORMAT_HEADER: &str = "chyes correspondence";
+
+// Termination reasons a player action gives, with their names in the file
+const REASONS: [(TerminationReason, &str); 7] = [
+	(TerminationReason::Resignation, "resignation"),
+	(TerminationReason::Timeout, "timeout"),
+	(TerminationReason::Agreement, "agreement"),
+	(TerminationReason::Abandonment, "abandonment"),
+	(TerminationReason::RulesInfraction, "rules_infraction"),
+	(TerminationReason::DrawRule(GameResult::ThreefoldRepetition), "threefold_repetition"),
+	(TerminationReason::DrawRule(GameResult::FiftyMoveRule), "fifty_move_rule"),
+];
+
+#[derive(Debug, Clone)]
+pub struct CorrespondenceGame {
+	game: Game,
+	pub tags: Vec<(String, String)>,
+	pub clock: Option<Clock>,
+	// Unix time in seconds, None before the first move
+	pub last_move_at: Option<u64>,
+	// Lines of the player waiting for a move, see above
+	conditional: Vec<Vec<Move>>,
+	// Indexed by ply
+	annotations: BTreeMap<usize, PgnAnnotation>,
+}
+
+fn color_name(color: Color) -> &'static str {
+	return match color {
+		Color::White => "white",
+		Color::Black => "black",
+	};
+}
+
+fn escape(text: &str) -> String {
+	return text.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r");
+}
+
+fn unescape(text: &str) -> String {
+	let mut result = String::new();
+	let mut chars = text.chars();
+	while let Some(chr) = chars.next() {
+		if chr != '\\' {
+			result.push(chr);
+			continue;
+		}
+		match chars.next() {
+			Some('n') => result.push('\n'),
+			Some('r') => result.push('\r'),
+			Some(other) => result.push(other),
+			None => result.push('\\'),
+		}
+	}
+	return result;
+}
+
+fn write_moves(moves: &[Move]) -> String {
+	return moves.iter().map(|mv| mv.to_string()).collect::<Vec<String>>().join(" ");
+}
+
+fn read_moves(board: &Board, text: &str) -> Option<Vec<Move>> {
+	// Legal moves one after the other from `board`
+	let mut board = *board;
+	let mut moves = Vec::new();
+	for word in text.split_whitespace() {
+		let mv = Notation::Coordinate.parse(&board, word)?;
+		board.make_move(mv);
+		moves.push(mv);
+	}
+	return Some(moves);
+}
+
+impl CorrespondenceGame {
+	pub fn new(game: Game) -> Self {
+		CorrespondenceGame {
+			game,
+			tags: Vec::new(),
+			clock: None,
+			last_move_at: None,
+			conditional: Vec::new(),
+			annotations: BTreeMap::new(),
+		}
+	}
+
+	pub fn game(&self) -> &Game {
+		return &self.game;
+	}
+
+	pub fn game_mut(&mut self) -> &mut Game {
+		// For the draw offers, claims and resignations, moves go through make_move so the clocks
+		// and the conditional moves follow them
+		return &mut self.game;
+	}
+
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		return self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+	}
+
+	pub fn make_move(&mut self, mv: Move, now: u64) -> Result<Vec<Move>, ChessError> {
+		// The move and the conditional reply it set off, GameOver when the time had run out
+		if self.game.termination().is_some() {
+			return Err(ChessError::GameOver);
+		}
+		if !self.game.board().legal_moves().contains(&mv) {
+			return Err(ChessError::IllegalMove(mv));
+		}
+		self.play(mv, now)?;
+		let mut played = vec![mv];
+
+		self.conditional.retain(|line| line[0] == mv);
+		if let Some(reply) = self.conditional.first().map(|line| line[1]) {
+			self.conditional.retain(|line| line[1] == reply);
+			for line in self.conditional.iter_mut() {
+				line.drain(..2);
+			}
+			self.conditional.retain(|line| !line.is_empty());
+			self.play(reply, now)?;
+			played.push(reply);
+		}
+		return Ok(played);
+	}
+
+	fn play(&mut self, mv: Move, now: u64) -> Result<(), ChessError> {
+		let mover = self.game.board().turn as usize;
+		if let Some(clock) = &mut self.clock {
+			let elapsed = Duration::from_secs(now.saturating_sub(self.last_move_at.unwrap_or(now)));
+			if elapsed > clock.time[mover] {
+				clock.time[mover] = Duration::ZERO;
+				self.game.flag(self.game.board().turn)?;
+				self.conditional.clear();
+				return Err(ChessError::GameOver);
+			}
+			clock.time[mover] = clock.time[mover] - elapsed + clock.increment[mover];
+		}
+		self.game.make_move(mv)?;
+		self.last_move_at = Some(now);
+		if self.game.termination().is_some() {
+			self.conditional.clear();
+		}
+		return Ok(());
+	}
+
+	pub fn conditional_moves(&self) -> &[Vec<Move>] {
+		// Nothing once the game is over, however it ended
+		if self.game.termination().is_some() {
+			return &[];
+		}
+		return &self.conditional;
+	}
+
+	pub fn add_conditional(&mut self, line: Vec<Move>) -> Result<(), ChessError> {
+		// A line from the current position, the opponent's move first and a reply last. Lines can
+		// share their start but not answer the same move two ways
+		let invalid = |reason: String| ChessError::InvalidCorrespondence(reason);
+		if self.game.termination().is_some() {
+			return Err(ChessError::GameOver);
+		}
+		if line.is_empty() || !line.len().is_multiple_of(2) {
+			return Err(invalid(format!("\"{}\" doesn't end with a reply", write_moves(&line))));
+		}
+		let mut board = *self.game.board();
+		for mv in &line {
+			board.try_apply_move(*mv)?;
+		}
+		for other in &self.conditional {
+			let shared = line.iter().zip(other).take_while(|(mv, other)| mv == other).count();
+			if shared % 2 == 1 && shared < line.len().min(other.len()) {
+				return Err(invalid(format!("\"{}\" answers {} another way than \"{}\"", write_moves(&line), line[shared - 1], write_moves(other))));
+			}
+		}
+		self.conditional.push(line);
+		return Ok(());
+	}
+
+	pub fn clear_conditional(&mut self) {
+		self.conditional.clear();
+	}
+
+	pub fn annotation(&self, ply: usize) -> Option<&PgnAnnotation> {
+		return self.annotations.get(&ply);
+	}
+
+	pub fn annotate(&mut self, ply: usize, annotation: PgnAnnotation) -> Result<(), ChessError> {
+		// The variations have to be legal from the position before the move
+		let board = self.game.position_at(ply).filter(|_| ply < self.game.moves().len()).ok_or(ChessError::PlyOutOfRange(ply))?;
+		for variation in &annotation.variations {
+			let mut board = board;
+			for mv in variation {
+				board.try_apply_move(*mv)?;
+			}
+		}
+		self.annotations.insert(ply, annotation);
+		return Ok(());
+	}
+
+	pub fn pgn(&self, options: PgnOptions) -> String {
+		// The game with its tags and annotations, for everything else that reads games
+		let mut writer = PgnWriter::new(options);
+		for (name, value) in &self.tags {
+			writer.tag(name, value);
+		}
+		for (ply, annotation) in &self.annotations {
+			writer.annotate(*ply, annotation.clone());
+		}
+		return writer.write(&self.game);
+	}
+
+	pub fn to_text(&self) -> String {
+		// See above
+		let mut lines = vec![format!("{} {}", FORMAT_HEADER, FORMAT_VERSION)];
+		for (name, value) in &self.tags {
+			lines.push(format!("tag {} {}", name, escape(value)));
+		}
+		lines.push(match self.game.rule {
+			MaterialRule::Fide => "rule fide".to_string(),
+			MaterialRule::NoForcedMate => "rule no_forced_mate".to_string(),
+		});
+		lines.push(format!("start {}", self.game.start().get_fen()));
+		lines.push(format!("moves {}", write_moves(self.game.moves())).trim_end().to_string());
+		if let Some(clock) = &self.clock {
+			let millis = |duration: Duration| duration.as_millis().to_string();
+			let moves_to_go = clock.moves_to_go.map_or("-".to_string(), |moves| moves.to_string());
+			lines.push(format!("clock {} {} {} {} {}", millis(clock.time[0]), millis(clock.time[1]), millis(clock.increment[0]), millis(clock.increment[1]), moves_to_go));
+		}
+		if let Some(time) = self.last_move_at {
+			lines.push(format!("last_move_at {}", time));
+		}
+		if let Some(color) = self.game.draw_offer() {
+			lines.push(format!("draw_offer {}", color_name(color)));
+		}
+		if let (None, Some((outcome, reason))) = (self.game.game_result(), self.game.termination()) {
+			if let Some((_, name)) = REASONS.iter().find(|(known, _)| *known == reason) {
+				lines.push(format!("termination {} {}", result_token(Some(outcome)), name));
+			}
+		}
+		for line in self.conditional_moves() {
+			lines.push(format!("conditional {}", write_moves(line)));
+		}
+		for (ply, annotation) in &self.annotations {
+			for nag in &annotation.nags {
+				lines.push(format!("nag {} {}", ply, nag));
+			}
+			if let Some(comment) = &annotation.comment {
+				lines.push(format!("comment {} {}", ply, escape(comment)));
+			}
+			if let Some(eval) = annotation.eval {
+				lines.push(format!("eval {} {}", ply, eval));
+			}
+			if let Some(clock) = annotation.clock {
+				lines.push(format!("clk {} {}", ply, clock.as_millis()));
+			}
+			for variation in &annotation.variations {
+				lines.push(format!("variation {} {}", ply, write_moves(variation)));
+			}
+		}
+		return lines.join("\n") + "\n";
+	}
+
+	pub fn from_text(text: &str) -> Result<Self, ChessError> {
+		let invalid = |line: usize, reason: &str| ChessError::InvalidCorrespondence(format!("line {}: {}", line + 1, reason));
+		let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+		let version = match lines.next().map(|(_, line)| line.trim().rsplit_once(' ')) {
+			Some(Some((header, version))) if header == FORMAT_HEADER => version.parse::<u32>().map_err(|_| invalid(0, "invalid version"))?,
+			_ => return Err(invalid(0, "not a correspondence game")),
+		};
+		if version == 0 || version > FORMAT_VERSION {
+			return Err(invalid(0, &format!("version {} is not supported, {} is the latest", version, FORMAT_VERSION)));
+		}
+
+		// The game goes first, everything else hangs on its positions
+		let entries: Vec<(usize, &str, &str)> = lines
+		.map(|(number, line)| {
+			let line = line.trim_start();
+			let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+			return (number, key, value.trim_end());
+		})
+		.collect();
+		let value = |key: &str| entries.iter().find(|(_, other, _)| *other == key).map(|(number, _, value)| (*number, *value));
+
+		let (number, fen) = value("start").ok_or_else(|| invalid(0, "no start position"))?;
+		let mut game = Game::new(Board::from_fen(fen).map_err(|error| invalid(number, &error.to_string()))?);
+		if let Some((number, rule)) = value("rule") {
+			game.rule = match rule {
+				"fide" => MaterialRule::Fide,
+				"no_forced_mate" => MaterialRule::NoForcedMate,
+				_ => return Err(invalid(number, "invalid material rule")),
+			};
+		}
+		if let Some((number, moves)) = value("moves") {
+			for word in moves.split_whitespace() {
+				let mv = Notation::Coordinate.parse(game.board(), word).ok_or_else(|| invalid(number, &format!("illegal move {}", word)))?;
+				game.make_move(mv).map_err(|error| invalid(number, &error.to_string()))?;
+			}
+		}
+
+		let mut correspondence = CorrespondenceGame::new(game);
+		for (number, key, value) in entries.iter().copied() {
+			let error = |error: ChessError| invalid(number, &error.to_string());
+			match key {
+				"start" | "rule" | "moves" => {}
+				"tag" => {
+					let (name, value) = value.split_once(' ').unwrap_or((value, ""));
+					correspondence.tags.push((name.to_string(), unescape(value)));
+				}
+				"clock" => {
+					let numbers: Vec<&str> = value.split_whitespace().collect();
+					let millis = |index: usize| numbers.get(index).and_then(|number| number.parse::<u64>().ok()).map(Duration::from_millis).ok_or_else(|| invalid(number, "invalid clock"));
+					correspondence.clock = Some(Clock {
+						time: [millis(0)?, millis(1)?],
+						increment: [millis(2)?, millis(3)?],
+						moves_to_go: match numbers.get(4) {
+							Some(&"-") => None,
+							moves => Some(moves.and_then(|moves| moves.parse().ok()).ok_or_else(|| invalid(number, "invalid clock"))?),
+						},
+					});
+				}
+				"last_move_at" => correspondence.last_move_at = Some(value.parse().map_err(|_| invalid(number, "invalid time"))?),
+				"draw_offer" => {
+					let color = match value {
+						"white" => Color::White,
+						"black" => Color::Black,
+						_ => return Err(invalid(number, "invalid color")),
+					};
+					correspondence.game.offer_draw(color).map_err(error)?;
+				}
+				"termination" => {
+					let (result, name) = value.split_once(' ').ok_or_else(|| invalid(number, "invalid termination"))?;
+					let outcome = parse_result_token(result).flatten().ok_or_else(|| invalid(number, "invalid result"))?;
+					let reason = REASONS.iter().find(|(_, known)| *known == name).ok_or_else(|| invalid(number, "invalid termination reason"))?.0;
+					correspondence.game.end(outcome, reason).map_err(error)?;
+				}
+				"conditional" => {
+					let line = read_moves(correspondence.game.board(), value).ok_or_else(|| invalid(number, "illegal conditional move"))?;
+					correspondence.add_conditional(line).map_err(error)?;
+				}
+				"nag" | "comment" | "eval" | "clk" | "variation" => {
+					let (ply, rest) = value.split_once(' ').unwrap_or((value, ""));
+					let ply = ply.parse::<usize>().map_err(|_| invalid(number, "invalid ply"))?;
+					let board = correspondence.game.position_at(ply).filter(|_| ply < correspondence.game.moves().len()).ok_or_else(|| error(ChessError::PlyOutOfRange(ply)))?;
+					let annotation = correspondence.annotations.entry(ply).or_default();
+					match key {
+						"nag" => annotation.nags.push(rest.parse().map_err(|_| invalid(number, "invalid NAG"))?),
+						"comment" => annotation.comment = Some(unescape(rest)),
+						"eval" => annotation.eval = Some(rest.parse().map_err(|_| invalid(number, "invalid evaluation"))?),
+						"clk" => annotation.clock = Some(Duration::from_millis(rest.parse().map_err(|_| invalid(number, "invalid clock"))?)),
+						_ => annotation.variations.push(read_moves(&board, rest).ok_or_else(|| invalid(number, "illegal variation move"))?),
+					}
+				}
+				_ => return Err(invalid(number, &format!("unknown key \"{}\"", key))),
+			}
+		}
+		return Ok(correspondence);
+	}
+
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		// Written next to the file first and renamed over it, a crash leaves the old game whole
+		let mut temporary = path.as_os_str().to_owned();
+		temporary.push(".tmp");
+		fs::write(&temporary, self.to_text())?;
+		return fs::rename(&temporary, path);
+	}
+
+	pub fn load(path: &Path) -> io::Result<Self> {
+		let text = fs::read_to_string(path)?;
+		return CorrespondenceGame::from_text(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()));
+	}
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::chess::*;
+use crate::game::Outcome;
+use crate::pgn::PgnGame;
+use crate::score::Score;
+
+// Crosstable of an event, every player against every other one
+/*
+Players are ranked by their score, ties are broken by:
+Sonneborn-Berger - the scores of the opponents, each weighed by what the player scored
+                   against them: beating the strong ones counts more
+Buchholz         - the scores of the opponents of every game, added up
+Wins             - the number of games won
+and by name when all of them are equal
+
+A cell has one character per game between the two players, in the order they were played:
+'1' won, '=' drawn, '0' lost. The player's own cell is '*'
+*/
+// source: https://en.wikipedia.org/wiki/Tie-breaking_in_Swiss-system_tournaments
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct FinishedGame {
+	pub white: String,
+	pub black: String,
+	pub outcome: Outcome,
+}
+
+impl FinishedGame {
+	pub fn new(white: &str, black: &str, outcome: Outcome) -> Self {
+		FinishedGame {
+			white: white.to_string(),
+			black: black.to_string(),
+			outcome,
+		}
+	}
+
+	pub fn from_pgn(pgn: &PgnGame) -> Option<FinishedGame> {
+		// None for games that aren't over, the players come from the White and Black tags
+		let game = &pgn.game;
+		let outcome = game.termination().map(|(outcome, _)| outcome).or(game.game_result().map(|result| result.outcome()))?;
+		return Some(FinishedGame::new(pgn.tag("White").unwrap_or("?"), pgn.tag("Black").unwrap_or("?"), outcome));
+	}
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CrosstableRow {
+	pub player: String,
+	pub score: Score,
+	pub games: usize,
+	pub wins: usize,
+	pub sonneborn_berger: f64,
+	pub buchholz: Score,
+	// Against every player, in the order of the rows
+	pub results: Vec<Vec<Score>>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Crosstable {
+	// Best first
+	pub rows: Vec<CrosstableRow>,
+}
+
+impl Crosstable {
+	pub fn new(games: &[FinishedGame]) -> Self {
+		let mut names: Vec<&str> = Vec::new();
+		for game in games {
+			for name in [game.white.as_str(), game.black.as_str()] {
+				if !names.contains(&name) {
+					names.push(name);
+				}
+			}
+		}
+		let index: HashMap<&str, usize> = names.iter().enumerate().map(|(index, name)| (*name, index)).collect();
+
+		// results[player][opponent], the scores of the player's games against the opponent
+		let mut results = vec![vec![Vec::new(); names.len()]; names.len()];
+		for game in games {
+			let (white, black) = (index[game.white.as_str()], index[game.black.as_str()]);
+			results[white][black].push(game.outcome.score(Color::White));
+			results[black][white].push(game.outcome.score(Color::Black));
+		}
+		let totals: Vec<Score> = results.iter().map(|row| row.iter().flatten().copied().sum()).collect();
+		let totals = &totals;
+
+		let mut rows: Vec<CrosstableRow> = names
+		.iter()
+		.enumerate()
+		.map(|(player, name)| {
+			let against = &results[player];
+			CrosstableRow {
+				player: name.to_string(),
+				score: totals[player],
+				games: against.iter().map(Vec::len).sum(),
+				wins: against.iter().flatten().filter(|score| **score == Score::WIN).count(),
+				sonneborn_berger: against
+				.iter()
+				.enumerate()
+				.flat_map(|(opponent, scores)| scores.iter().map(move |score| score.points() * totals[opponent].points()))
+				.sum(),
+				buchholz: against.iter().enumerate().map(|(opponent, scores)| Score::from_half_points(totals[opponent].half_points() * scores.len() as u32)).sum(),
+				results: against.clone(),
+			}
+		})
+		.collect();
+
+		// Ranked, then the columns follow the new order of the rows
+		let mut order: Vec<usize> = (0..rows.len()).collect();
+		order.sort_by(|a, b| {
+			let (a, b) = (&rows[*a], &rows[*b]);
+			return b
+			.score
+			.cmp(&a.score)
+			.then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+			.then(b.buchholz.cmp(&a.buchholz))
+			.then(b.wins.cmp(&a.wins))
+			.then(a.player.cmp(&b.player));
+		});
+		for row in rows.iter_mut() {
+			row.results = order.iter().map(|column| row.results[*column].clone()).collect();
+		}
+		return Crosstable { rows: order.iter().map(|index| rows[*index].clone()).collect() };
+	}
+
+	pub fn row(&self, player: &str) -> Option<&CrosstableRow> {
+		return self.rows.iter().find(|row| row.player == player);
+	}
+
+	pub fn cell(&self, row: usize, column: usize) -> String {
+		// The text of a cell, see above
+		if row == column {
+			return "*".to_string();
+		}
+		return self.rows[row].results[column]
+		.iter()
+		.map(|score| match score.half_points() {
+			2 => '1',
+			1 => '=',
+			_ => '0',
+		})
+		.collect();
+	}
+
+	fn table(&self) -> Vec<Vec<String>> {
+		// Header and rows, the cells of both renderings
+		let mut header: Vec<String> = vec!["#".to_string(), "Player".to_string()];
+		header.extend((1..=self.rows.len()).map(|number| number.to_string()));
+		header.extend(["Score", "Games", "SB", "Buchholz", "Wins"].map(str::to_string));
+
+		let mut table = vec![header];
+		for (index, row) in self.rows.iter().enumerate() {
+			let mut line = vec![(index + 1).to_string(), row.player.clone()];
+			line.extend((0..self.rows.len()).map(|column| self.cell(index, column)));
+			line.extend([
+				row.score.to_string(),
+				row.games.to_string(),
+				format!("{:.2}", row.sonneborn_berger),
+				row.buchholz.to_string(),
+				row.wins.to_string(),
+			]);
+			table.push(line);
+		}
+		return table;
+	}
+
+	pub fn to_text(&self) -> String {
+		// Columns padded to their widest cell, the player names left aligned, the rest right
+		let table = self.table();
+		let widths: Vec<usize> = (0..table[0].len()).map(|column| table.iter().map(|line| line[column].chars().count()).max().unwrap()).collect();
+
+		let mut out = String::new();
+		for line in &table {
+			let cells: Vec<String> = line
+			.iter()
+			.enumerate()
+			.map(|(column, cell)| match column {
+				1 => format!("{:<width$}", cell, width = widths[column]),
+				_ => format!("{:>width$}", cell, width = widths[column]),
+			})
+			.collect();
+			let _ = writeln!(out, "{}", cells.join("  ").trim_end());
+		}
+		return out;
+	}
+
+	pub fn to_markdown(&self) -> String {
+		let table = self.table();
+		let mut out = String::new();
+		for (index, line) in table.iter().enumerate() {
+			let _ = writeln!(out, "| {} |", line.join(" | "));
+			if index == 0 {
+				let rule: Vec<&str> = (0..line.len()).map(|column| if column == 1 { ":---" } else { "---:" }).collect();
+				let _ = writeln!(out, "| {} |", rule.join(" | "));
+			}
+		}
+		return out;
+	}
+}
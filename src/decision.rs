@@ -0,0 +1,143 @@
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::game::Game;
+use crate::options::EngineOptions;
+
+// When an engine playing a game should resign, offer a draw or take one
+/*
+Both rules look at the engine's own scores after its moves, a single bad or quiet score is
+not enough:
+
+Resign     - `resign_moves` own moves in a row scored at or below -`resign_score`
+Offer draw - `draw_moves` own moves in a row scored within `draw_score` of zero, from move
+             `draw_from_move` on
+Accept     - the same as offering, or whenever the engine scores its position below what a
+             draw is worth to it (-contempt)
+
+A rule with 0 moves is off, the defaults never resign and never offer a draw. The same
+settings come as UCI options (ResignScore, ResignMoves, DrawScore, DrawMoves, DrawFromMove
+and Contempt) so bots can read them from an engine configuration
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecisionPolicy {
+	pub resign_score: i32,
+	pub resign_moves: u32,
+	pub draw_score: i32,
+	pub draw_moves: u32,
+	pub draw_from_move: u32,
+	pub contempt: i32,
+}
+
+impl DecisionPolicy {
+	pub fn from_options(options: &EngineOptions) -> Self {
+		DecisionPolicy {
+			resign_score: options.resign_score,
+			resign_moves: options.resign_moves,
+			draw_score: options.draw_score,
+			draw_moves: options.draw_moves,
+			draw_from_move: options.draw_from_move,
+			contempt: options.contempt,
+		}
+	}
+}
+
+impl Default for DecisionPolicy {
+	fn default() -> Self {
+		return DecisionPolicy::from_options(&EngineOptions::default());
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+	Play,
+	Resign,
+	OfferDraw,
+	AcceptDraw,
+}
+
+// Keeps the running counters of one engine in one game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecisionTracker {
+	pub policy: DecisionPolicy,
+	resign_moves: u32,
+	draw_moves: u32,
+	// Score of the engine's last move, None if it had none
+	last_score: Option<i32>,
+}
+
+impl DecisionTracker {
+	pub fn new(policy: DecisionPolicy) -> Self {
+		DecisionTracker {
+			policy,
+			resign_moves: 0,
+			draw_moves: 0,
+			last_score: None,
+		}
+	}
+
+	pub fn reset(&mut self) {
+		// For a new game
+		*self = DecisionTracker::new(self.policy);
+	}
+
+	pub fn record(&mut self, score: Option<i32>, move_number: u32) {
+		// Called for every move of the engine with its score, moves without one break both streaks
+		match score {
+			Some(score) if score <= -self.policy.resign_score => self.resign_moves += 1,
+			_ => self.resign_moves = 0,
+		}
+		match score {
+			Some(score) if score.abs() <= self.policy.draw_score && move_number >= self.policy.draw_from_move => self.draw_moves += 1,
+			_ => self.draw_moves = 0,
+		}
+		self.last_score = score;
+	}
+
+	pub fn should_resign(&self) -> bool {
+		return self.policy.resign_moves > 0 && self.resign_moves >= self.policy.resign_moves;
+	}
+
+	pub fn should_offer_draw(&self) -> bool {
+		return self.policy.draw_moves > 0 && self.draw_moves >= self.policy.draw_moves;
+	}
+
+	pub fn accepts_draw(&self) -> bool {
+		if self.should_offer_draw() {
+			return true;
+		}
+		return match self.last_score {
+			Some(score) => score < -self.policy.contempt,
+			None => false,
+		};
+	}
+
+	pub fn decide(&self, draw_offered: bool) -> Decision {
+		// Resigning goes first, a draw offer is only taken when the engine wants one
+		if self.should_resign() {
+			return Decision::Resign;
+		}
+		if draw_offered && self.accepts_draw() {
+			return Decision::AcceptDraw;
+		}
+		if self.should_offer_draw() {
+			return Decision::OfferDraw;
+		}
+		return Decision::Play;
+	}
+
+	pub fn act(&mut self, game: &mut Game, color: Color, score: Option<i32>) -> Result<Decision, ChessError> {
+		// Called with the search score when `color` is to move, before its move is played
+		// Records the score and carries out the decision, the move is only played on Play or OfferDraw
+		self.record(score, game.board().fullmove_number());
+		let offered = matches!(game.draw_offer(), Some(offer) if offer != color);
+		let decision = self.decide(offered);
+		match decision {
+			Decision::Resign => game.resign(color)?,
+			Decision::AcceptDraw => game.accept_draw(color)?,
+			Decision::OfferDraw => game.offer_draw(color)?,
+			Decision::Play => {}
+		}
+		return Ok(decision);
+	}
+}
@@ -0,0 +1,157 @@
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::render::FIGURINES;
+
+// Positions pasted into the board editor, in whatever format they come
+/*
+FEN     - one line with the eight ranks split by '/', the side to move, the castling rights,
+          the en passant square and the two clocks
+EPD     - the first four fields of a FEN, then operations ending with ';' like bm Nf3; or
+          id "WAC.001"; the clocks come from the hmvc and fmvn operations when they are there
+Diagram - eight lines of eight squares, rank 8 first, the way diagram() and draw() write
+          them: FEN letters or figurines for the pieces, '.', '-' or '_' for the empty
+          squares. Rank numbers in front, a line of file letters, '|' and '+---+' borders and
+          the marks draw() puts after the last move and the king in check are skipped. A line
+          "w", "b", "White to move" or "Black to move" gives the side to move, white without one
+
+A diagram has no castling rights or en passant square to tell, a king and a rook on their
+first squares are taken to still have the right to castle
+*/
+// source: https://www.chessprogramming.org/Extended_Position_Description
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum PositionFormat {
+	Fen,
+	Epd,
+	Diagram,
+}
+
+impl PositionFormat {
+	pub fn detect(text: &str) -> Option<PositionFormat> {
+		// None for text that doesn't look like any of them
+		let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+		return match lines.as_slice() {
+			[] => None,
+			[line] if line.split_whitespace().next()?.matches('/').count() == 7 => {
+				let fields: Vec<&str> = line.split_whitespace().collect();
+				// Clocks or nothing after the first four fields
+				if fields.len() <= 6 && fields.iter().skip(4).all(|field| field.parse::<u32>().is_ok()) {
+					Some(PositionFormat::Fen)
+				} else {
+					Some(PositionFormat::Epd)
+				}
+			}
+			[_] => None,
+			_ => Some(PositionFormat::Diagram),
+		};
+	}
+}
+
+impl Board {
+	pub fn parse_position(text: &str) -> Result<Board, ChessError> {
+		// FEN, EPD or a diagram, whichever the text is
+		return match PositionFormat::detect(text) {
+			Some(PositionFormat::Fen) => Board::from_fen(text.trim()),
+			Some(PositionFormat::Epd) => Board::from_epd(text.trim()),
+			Some(PositionFormat::Diagram) => Board::from_diagram(text),
+			None => Err(ChessError::InvalidPosition(format!("\"{}\" is not a FEN, an EPD or a diagram", text.trim()))),
+		};
+	}
+
+	pub fn from_epd(epd: &str) -> Result<Board, ChessError> {
+		let fields: Vec<&str> = epd.split_whitespace().collect();
+		if fields.len() < 4 {
+			return Board::from_fen(epd);
+		}
+
+		let (mut halfmove, mut fullmove) = ("0", "1");
+		let operations = fields[4..].join(" ");
+		for operation in operations.split(';') {
+			let mut words = operation.split_whitespace();
+			match (words.next(), words.next()) {
+				(Some("hmvc"), Some(value)) => halfmove = value,
+				(Some("fmvn"), Some(value)) => fullmove = value,
+				_ => {}
+			}
+		}
+		return Board::from_fen(&format!("{} {} {}", fields[..4].join(" "), halfmove, fullmove));
+	}
+
+	pub fn from_diagram(text: &str) -> Result<Board, ChessError> {
+		// What diagram() and draw() write, and the diagrams of books and forums (see above)
+		let invalid = |reason: String| ChessError::InvalidDiagram(reason);
+
+		let mut placement: Vec<String> = Vec::new();
+		let mut turn = "w";
+		for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+			let lower = line.to_lowercase();
+			match lower.trim_end_matches('.') {
+				"w" | "white to move" => turn = "w",
+				"b" | "black to move" => turn = "b",
+				// The file letters, 'a' is never a piece
+				_ if line.contains('a') && line.chars().all(|chr| matches!(chr, 'a'..='h' | ' ')) => {}
+				// "+---+---+", the dashes aren't empty squares there
+				_ if line.starts_with('+') && line.chars().all(|chr| matches!(chr, '+' | '-' | '|' | ' ')) => {}
+				_ => {
+					let rank = line.trim_start_matches(|chr: char| chr.is_ascii_digit()).trim_start();
+					let mut squares = Vec::new();
+					for chr in rank.chars() {
+						match square(chr) {
+							Some(square) => squares.push(square),
+							// Spaces, borders and the marks after a square
+							None if matches!(chr, ' ' | '|' | '*' | '+') => {}
+							None => return Err(invalid(format!("'{}' in \"{}\" is not a square", chr, line))),
+						}
+					}
+					if squares.len() != 8 {
+						return Err(invalid(format!("\"{}\" does not have 8 squares", line)));
+					}
+					placement.push(fen_rank(&squares));
+				}
+			}
+		}
+		if placement.len() != 8 {
+			return Err(invalid(format!("expected 8 ranks, found {}", placement.len())));
+		}
+		return Board::from_fen(&format!("{} {} KQkq - 0 1", placement.join("/"), turn));
+	}
+}
+
+fn square(chr: char) -> Option<Option<Piece>> {
+	// Some(None) for an empty square, None for a character that isn't a square
+	if matches!(chr, '.' | '-' | '_') {
+		return Some(None);
+	}
+	if let Some(piece) = Piece::from_fen_char(chr) {
+		return Some(Some(piece));
+	}
+	for (color, figurines) in FIGURINES.iter().enumerate() {
+		if let Some(index) = figurines.iter().position(|figurine| figurine.starts_with(chr)) {
+			let letter = ['K', 'Q', 'R', 'B', 'N', 'P'][index];
+			let letter = if color == 0 { letter } else { letter.to_ascii_lowercase() };
+			return Some(Piece::from_fen_char(letter));
+		}
+	}
+	return None;
+}
+
+fn fen_rank(squares: &[Option<Piece>]) -> String {
+	let mut rank = String::new();
+	let mut empty = 0;
+	for square in squares {
+		match square {
+			Some(piece) => {
+				if empty > 0 {
+					rank.push_str(&empty.to_string());
+					empty = 0;
+				}
+				rank.push(piece.to_fen_char());
+			}
+			None => empty += 1,
+		}
+	}
+	if empty > 0 {
+		rank.push_str(&empty.to_string());
+	}
+	return rank;
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use crate::chess::*;
+
+// Dropping captured pieces back onto the board, the way Crazyhouse and Bughouse play
+/*
+A pocket holds the pieces a side can drop, kings never go into one. A drop puts one of them on
+an empty square and takes the turn like a move does:
+
+- pawns can't be dropped on the first or the last rank
+- a drop can't leave the own king in check, so in check only drops that block it are legal
+- a dropped piece gives no castling rights, a dropped pawn on its second rank can still make
+  a double step
+
+Drops are written the way Lichess and the BPGN standard write them, N@f3 for a knight and
+P@e6 for a pawn, in SAN and in UCI alike
+*/
+// source: https://en.wikipedia.org/wiki/Crazyhouse
+
+// What can be in a pocket, in the order pockets are written
+pub const POCKET_BREEDS: [Pieces; 5] = [Pieces::Queen, Pieces::Rook, Pieces::Bishop, Pieces::Knight, Pieces::Pawn];
+
+// Pieces a side has in hand, counted by breed in the order of Pieces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Pocket {
+	counts: [u8; 6],
+}
+
+impl Pocket {
+	pub fn new() -> Self {
+		return Pocket::default();
+	}
+
+	pub fn count(&self, breed: Pieces) -> u8 {
+		return self.counts[breed as usize];
+	}
+
+	pub fn add(&mut self, breed: Pieces) {
+		// Kings are never captured, asking to pocket one is a bug
+		debug_assert!(breed != Pieces::King, "kings don't go into pockets");
+		self.counts[breed as usize] += 1;
+	}
+
+	pub fn take(&mut self, breed: Pieces) -> bool {
+		// False when there is none to take
+		if self.counts[breed as usize] == 0 {
+			return false;
+		}
+		self.counts[breed as usize] -= 1;
+		return true;
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.counts.iter().all(|count| *count == 0);
+	}
+
+	pub fn breeds(&self) -> impl Iterator<Item = Pieces> + '_ {
+		// Every breed there is at least one of
+		return POCKET_BREEDS.into_iter().filter(|breed| self.count(*breed) > 0);
+	}
+
+	pub fn to_fen(&self, color: Color) -> String {
+		// The pieces as FEN letters, "QNPP" for white and "qnpp" for black
+		let mut out = String::new();
+		for breed in POCKET_BREEDS {
+			for _ in 0..self.count(breed) {
+				out.push(Piece { breed, color }.to_fen_char());
+			}
+		}
+		return out;
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Drop {
+	pub breed: Pieces,
+	pub to: Coordinate,
+}
+
+impl fmt::Display for Drop {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Upper case whatever the color, "N@f3"
+		let letter = Piece { breed: self.breed, color: Color::White }.to_fen_char();
+		return write!(f, "{}@{}", letter, self.to);
+	}
+}
+
+impl Drop {
+	pub fn parse(text: &str) -> Option<Drop> {
+		// "N@f3", a drop without a letter is a pawn "@e6"
+		let (letter, square) = text.trim().trim_end_matches(['+', '#']).split_once('@')?;
+		let mut letters = letter.chars();
+		let breed = match (letters.next(), letters.next()) {
+			(None, _) => Pieces::Pawn,
+			(Some(chr), None) => Piece::from_fen_char(chr)?.breed,
+			_ => return None,
+		};
+		if breed == Pieces::King {
+			return None;
+		}
+		let to = Coordinate::from_algebraic(square).ok()?;
+		return Some(Drop { breed, to });
+	}
+}
+
+impl Board {
+	pub fn is_legal_drop(&self, pocket: &Pocket, drop: Drop) -> bool {
+		// The side to move drops from `pocket`
+		if pocket.count(drop.breed) == 0 || !drop.to.is_valid() || self.get_piece(drop.to).is_some() {
+			return false;
+		}
+		if drop.breed == Pieces::Pawn && (drop.to.row == 0 || drop.to.row == 7) {
+			return false;
+		}
+
+		let mut after = *self;
+		after.place_piece(Piece { breed: drop.breed, color: self.turn }, drop.to).ok();
+		return !after.is_in_check(self.turn);
+	}
+
+	pub fn legal_drops(&self, pocket: &Pocket) -> Vec<Drop> {
+		// In the order of POCKET_BREEDS, and from a8 to h1 within one breed
+		let mut drops = Vec::new();
+		for breed in pocket.breeds() {
+			for row in 0..8 {
+				for col in 0..8 {
+					let drop = Drop { breed, to: coord!(row, col) };
+					if self.is_legal_drop(pocket, drop) {
+						drops.push(drop);
+					}
+				}
+			}
+		}
+		return drops;
+	}
+
+	pub fn make_drop(&mut self, drop: Drop) {
+		// Puts the piece down for the side to move and passes the turn
+		// NOTE: Nothing is checked and no pocket is touched, see is_legal_drop
+		self.place_piece(Piece { breed: drop.breed, color: self.turn }, drop.to).ok();
+		self.make_null_move();
+	}
+}
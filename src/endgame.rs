@@ -0,0 +1,270 @@
+use crate::chess::*;
+use crate::eval::Evaluator;
+use crate::options::{EngineOptions, UciOption};
+
+// Driving heuristics for the basic mates against a lone king
+/*
+Material alone scores every KR vs K (or KBN vs K) position the same, so a shallow search has
+nothing telling it how to make progress and walks around until the 50-move rule. The mating
+side gets a bonus for what actually wins:
+
+Rook           - the defending king pushed to the edge, the kings close to each other
+BishopKnight   - the defending king pushed to a corner the bishop can cover, the kings and the
+                 knight close to it, the fewer squares the defending king has the better,
+                 mating in the wrong corner isn't possible
+*/
+// source: https://www.chessprogramming.org/Mop-up_Evaluation
+
+// Centipawns per square the defending king is pushed towards its mating squares
+pub const DRIVE_BONUS: i32 = 40;
+// Centipawns per square the attacking king gets closer to the defending one
+pub const APPROACH_BONUS: i32 = 6;
+// Centipawns per square around the defending king it can't go to, bishop and knight only
+pub const CONFINE_BONUS: i32 = 10;
+// Centipawns per square the knight gets closer to the defending king
+pub const KNIGHT_BONUS: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BasicMate {
+	Rook,
+	BishopKnight,
+}
+
+fn manhattan(from: Coordinate, to: Coordinate) -> i32 {
+	return ((from.row - to.row).abs() + (from.col - to.col).abs()) as i32;
+}
+
+fn center_distance(coord: Coordinate) -> i32 {
+	// 0 on the four center squares, 6 in the corners
+	let row = (3 - coord.row).max(coord.row - 4);
+	let col = (3 - coord.col).max(coord.col - 4);
+	return (row + col) as i32;
+}
+
+impl Board {
+	pub fn basic_mate(&self) -> Option<(Color, BasicMate)> {
+		// The side that has one of the basic mates against a lone king, with which pieces
+		for (strong, weak) in [(Color::White, Color::Black), (Color::Black, Color::White)] {
+			let (pieces, lone) = match strong {
+				Color::White => (&self.white_pieces, &self.black_pieces),
+				Color::Black => (&self.black_pieces, &self.white_pieces),
+			};
+			if lone.len() != 1 || self.get_king_coord(weak).is_none() {
+				continue;
+			}
+
+			let mut breeds: Vec<Pieces> = pieces.values().map(|piece| piece.breed).filter(|breed| *breed != Pieces::King).collect();
+			breeds.sort_by_key(|breed| *breed as usize);
+			match breeds[..] {
+				[Pieces::Rook] => return Some((strong, BasicMate::Rook)),
+				[Pieces::Bishop, Pieces::Knight] => return Some((strong, BasicMate::BishopKnight)),
+				_ => {}
+			}
+		}
+		return None;
+	}
+
+	pub fn mating_bonus(&self) -> Option<i32> {
+		// Bonus of the basic mate on the board for the side that has it, from the side to move's
+		// point of view
+		let (strong, mate) = self.basic_mate()?;
+		let weak = match strong {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let pieces = match strong {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		let attacker = self.get_king_coord(strong)?;
+		let defender = self.get_king_coord(weak)?;
+
+		let drive = match mate {
+			BasicMate::Rook => center_distance(defender),
+			BasicMate::BishopKnight => {
+				// a8 and h1 are light, a1 and h8 dark, the bishop only mates in its own color's corners
+				let bishop = pieces
+				.iter()
+				.find(|(_, piece)| piece.breed == Pieces::Bishop)
+				.map(|(num, _)| Coordinate::from_number(*num))?;
+				let corners = if bishop.is_light() {
+					[Coordinate::from_rank_file(Rank::Eighth, File::A), Coordinate::from_rank_file(Rank::First, File::H)]
+				} else {
+					[Coordinate::from_rank_file(Rank::First, File::A), Coordinate::from_rank_file(Rank::Eighth, File::H)]
+				};
+				let nearest = corners.iter().map(|corner| manhattan(defender, *corner)).min()?;
+				14 - nearest
+			}
+		};
+		let mut bonus = DRIVE_BONUS * drive + APPROACH_BONUS * (14 - manhattan(attacker, defender));
+		if mate == BasicMate::BishopKnight {
+			// Squares the defending king still has, the fewer the closer the mate
+			let mut lone = *self;
+			lone.turn = weak;
+			lone.en_passant_target_sq = None;
+			bonus += CONFINE_BONUS * (8 - lone.legal_moves().len() as i32);
+
+			if let Some((num, _)) = pieces.iter().find(|(_, piece)| piece.breed == Pieces::Knight) {
+				bonus += KNIGHT_BONUS * (14 - manhattan(Coordinate::from_number(*num), defender));
+			}
+		}
+
+		if strong == self.turn {
+			return Some(bonus);
+		}
+		return Some(-bonus);
+	}
+}
+
+// Passed pawns and the rule of the square
+/*
+A passed pawn has no enemy pawn in front of it on its own file or the files next to it. With
+nothing but pawns left to the defender only its king can stop one: the king has to get into
+the square of the pawn, the square with the pawn's way to the promotion square as one side.
+A pawn on its starting rank can move two squares, its square starts one rank higher. The side
+to move is a tempo up, the defending king may be one file outside the square when it moves
+
+A passed pawn whose own pieces don't stand in its way and whose square the king can't get into
+is unstoppable. When both sides have one the race goes to the pawn that queens first
+*/
+// source: https://en.wikipedia.org/wiki/Rule_of_the_square
+
+fn distance(from: Coordinate, to: Coordinate) -> i32 {
+	// King moves between the squares
+	return (from.row - to.row).abs().max((from.col - to.col).abs()) as i32;
+}
+
+fn promotion_square(pawn: Coordinate, color: Color) -> Coordinate {
+	return match color {
+		Color::White => coord!(0, pawn.col),
+		Color::Black => coord!(7, pawn.col),
+	};
+}
+
+impl Board {
+	fn pawn_at(&self, coord: Coordinate) -> Option<Color> {
+		return self.get_piece(coord).filter(|piece| piece.breed == Pieces::Pawn).map(|piece| piece.color);
+	}
+
+	pub fn is_passed(&self, coord: Coordinate) -> bool {
+		// False when there is no pawn on the square
+		let color = match self.pawn_at(coord) {
+			Some(color) => color,
+			None => return false,
+		};
+		let ahead = match color {
+			Color::White => 0..coord.row,
+			Color::Black => coord.row + 1..8,
+		};
+		for row in ahead {
+			for col in coord.col - 1..=coord.col + 1 {
+				let square = coord!(row, col);
+				if square.is_valid() && self.pawn_at(square).is_some_and(|other| other != color) {
+					return false;
+				}
+			}
+		}
+		return true;
+	}
+
+	pub fn promotion_distance(&self, coord: Coordinate) -> Option<i32> {
+		// Pawn moves to the promotion square, the double step included, None without a pawn
+		let color = self.pawn_at(coord)?;
+		let (distance, start) = match color {
+			Color::White => (coord.row as i32, 6),
+			Color::Black => (7 - coord.row as i32, 1),
+		};
+		if coord.row == start {
+			return Some(distance - 1);
+		}
+		return Some(distance);
+	}
+
+	pub fn king_in_square_of_pawn(&self, king: Coordinate, pawn: Coordinate, turn: Color) -> bool {
+		// Whether a king on `king` catches the pawn on `pawn` with `turn` to move, false without a pawn
+		let (color, moves) = match (self.pawn_at(pawn), self.promotion_distance(pawn)) {
+			(Some(color), Some(moves)) => (color, moves),
+			_ => return false,
+		};
+		let tempo = if turn == color { 0 } else { 1 };
+		return distance(king, promotion_square(pawn, color)) - tempo <= moves;
+	}
+
+	pub fn unstoppable_pawns(&self, color: Color) -> Vec<Coordinate> {
+		// Passed pawns of `color` that promote whatever the opponent does, only against a king and pawns
+		let (pieces, opponents) = match color {
+			Color::White => (&self.white_pieces, &self.black_pieces),
+			Color::Black => (&self.black_pieces, &self.white_pieces),
+		};
+		let opponent = match color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let king = match self.get_king_coord(opponent) {
+			Some(king) => king,
+			None => return Vec::new(),
+		};
+		if opponents.values().any(|piece| piece.breed != Pieces::King && piece.breed != Pieces::Pawn) {
+			return Vec::new();
+		}
+
+		let mut result: Vec<Coordinate> = pieces
+		.iter()
+		.filter(|(_, piece)| piece.breed == Pieces::Pawn)
+		.map(|(num, _)| Coordinate::from_number(*num))
+		.filter(|pawn| self.is_passed(*pawn) && !self.king_in_square_of_pawn(king, *pawn, self.turn))
+		.filter(|pawn| {
+			// The way to the promotion square is free
+			let ahead = match color {
+				Color::White => 0..pawn.row,
+				Color::Black => pawn.row + 1..8,
+			};
+			ahead.into_iter().all(|row| self.get_piece(coord!(row, pawn.col)).is_none())
+		})
+		.collect();
+		result.sort_by_key(|pawn| pawn.as_number());
+		return result;
+	}
+
+	pub fn pawn_race(&self) -> Option<(Color, Coordinate)> {
+		// The side whose unstoppable pawn queens first and that pawn, None without one
+		let mut best: Option<(i32, Color, Coordinate)> = None;
+		for color in [Color::White, Color::Black] {
+			for pawn in self.unstoppable_pawns(color) {
+				// Plies until the pawn promotes
+				let moves = self.promotion_distance(pawn)?;
+				let plies = if color == self.turn { 2 * moves - 1 } else { 2 * moves };
+				if best.is_none_or(|(fastest, _, _)| plies < fastest) {
+					best = Some((plies, color, pawn));
+				}
+			}
+		}
+		return best.map(|(_, color, pawn)| (color, pawn));
+	}
+}
+
+// Any evaluator, with the mating bonus on top in the basic mates
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EndgameEvaluator<E: Evaluator> {
+	pub inner: E,
+}
+
+impl<E: Evaluator> EndgameEvaluator<E> {
+	pub fn new(inner: E) -> Self {
+		EndgameEvaluator { inner }
+	}
+}
+
+impl<E: Evaluator> Evaluator for EndgameEvaluator<E> {
+	fn evaluate(&self, board: &Board) -> i32 {
+		return self.inner.evaluate(board) + board.mating_bonus().unwrap_or(0);
+	}
+
+	fn options(&self) -> Vec<UciOption> {
+		return self.inner.options();
+	}
+
+	fn set_options(&mut self, options: &EngineOptions) {
+		self.inner.set_options(options);
+	}
+}
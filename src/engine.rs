@@ -0,0 +1,288 @@
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::eval::piece_value;
+use crate::options::{EngineOptions, UciOption};
+use crate::rng::{Rng, Seeded};
+use crate::search::{SearchResult, MATE_SCORE};
+use crate::time::Clock;
+
+// Lets another thread stop a search or tell it the ponder move was played
+/*
+While pondering the search ignores its node and time limits, they start counting at the
+ponderhit. A stopped search returns the best move it has as soon as it can
+*/
+#[derive(Debug, Clone, Default)]
+pub struct SearchControl {
+	stop: Arc<AtomicBool>,
+	pondering: Arc<AtomicBool>,
+}
+
+impl SearchControl {
+	pub fn new() -> Self {
+		return SearchControl::default();
+	}
+
+	pub fn pondering() -> Self {
+		let control = SearchControl::default();
+		control.pondering.store(true, Ordering::Relaxed);
+		return control;
+	}
+
+	pub fn stop(&self) {
+		self.stop.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_stopped(&self) -> bool {
+		return self.stop.load(Ordering::Relaxed);
+	}
+
+	pub fn ponderhit(&self) {
+		self.pondering.store(false, Ordering::Relaxed);
+	}
+
+	pub fn is_pondering(&self) -> bool {
+		return self.pondering.load(Ordering::Relaxed);
+	}
+}
+
+// Two controls are the same when they share their flags
+impl PartialEq for SearchControl {
+	fn eq(&self, other: &Self) -> bool {
+		return Arc::ptr_eq(&self.stop, &other.stop) && Arc::ptr_eq(&self.pondering, &other.pondering);
+	}
+}
+
+// How long an engine is allowed to think, None means no limit of that kind
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchLimits {
+	pub depth: Option<u32>,
+	pub nodes: Option<u64>,
+	pub movetime: Option<Duration>,
+	// Left to the engine's time management, movetime wins when both are given
+	pub clock: Option<Clock>,
+	pub control: SearchControl,
+	// Zobrist hashes of the positions played before the searched one, oldest first,
+	// repeating one of them in the search is a draw (see Game::search_history)
+	pub history: Vec<u64>,
+	// Root moves the search may choose from (UCI searchmoves), all of them when empty
+	pub searchmoves: Vec<Move>,
+	// Root moves the search must not play, to find out how good the best other move is
+	pub excluded: Vec<Move>,
+}
+
+impl SearchLimits {
+	pub fn depth(depth: u32) -> Self {
+		SearchLimits {
+			depth: Some(depth),
+			..SearchLimits::default()
+		}
+	}
+
+	pub fn movetime(movetime: Duration) -> Self {
+		SearchLimits {
+			movetime: Some(movetime),
+			..SearchLimits::default()
+		}
+	}
+
+	pub fn clock(clock: Clock) -> Self {
+		SearchLimits {
+			clock: Some(clock),
+			..SearchLimits::default()
+		}
+	}
+
+	pub fn root_moves(&self, board: &Board) -> Vec<Move> {
+		// The legal moves the search may play, searchmoves that aren't legal are left out and
+		// a list without a single legal move restricts nothing, the excluded moves go after that
+		let mut moves = board.legal_moves();
+		let allowed: Vec<Move> = moves.iter().filter(|mv| self.searchmoves.contains(mv)).copied().collect();
+		if !allowed.is_empty() {
+			moves = allowed;
+		}
+		moves.retain(|mv| !self.excluded.contains(mv));
+		return moves;
+	}
+}
+
+// Counters collected by searches and perft runs
+/*
+nodes, tt_hits and beta_cutoffs are filled by searches, the move kinds are counted by perft on the last ply, the same way
+the published perft tables do
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+	pub nodes: u64,
+	pub tt_hits: u64,
+	pub beta_cutoffs: u64,
+	pub captures: u64,
+	pub en_passants: u64,
+	pub castles: u64,
+	pub promotions: u64,
+	pub checks: u64,
+	pub checkmates: u64,
+}
+
+impl AddAssign for SearchStats {
+	fn add_assign(&mut self, other: SearchStats) {
+		self.nodes += other.nodes;
+		self.tt_hits += other.tt_hits;
+		self.beta_cutoffs += other.beta_cutoffs;
+		self.captures += other.captures;
+		self.en_passants += other.en_passants;
+		self.castles += other.castles;
+		self.promotions += other.promotions;
+		self.checks += other.checks;
+		self.checkmates += other.checkmates;
+	}
+}
+
+// Anything that can pick a move for the side to move
+// NOTE: Positions without legal moves give ChessError::NoLegalMoves
+pub trait Engine {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError>;
+
+	// Score of the last chosen move in centipawns for the side that played it,
+	// engines that don't score positions return None
+	fn score(&self) -> Option<i32> {
+		return None;
+	}
+
+	// The line the engine expects after its last chosen move, starting with that move
+	fn pv(&self) -> Vec<Move> {
+		return Vec::new();
+	}
+
+	// The best `lines` moves with their own scores and lines, best first (UCI MultiPV),
+	// engines that only look for their best move give nothing
+	fn multi_pv(&mut self, _board: &Board, _limits: &SearchLimits, _lines: usize) -> Vec<SearchResult> {
+		return Vec::new();
+	}
+
+	// Custom options on top of the standard ones, evaluator knobs included
+	fn options(&self) -> Vec<UciOption> {
+		return Vec::new();
+	}
+
+	// Called with every option after one of them changes
+	fn set_options(&mut self, _options: &EngineOptions) {}
+}
+
+pub fn score_after_move(engine: &mut impl Engine, board: &Board, mv: Move, limits: &SearchLimits) -> Result<i32, ChessError> {
+	// How good `mv` is for the side playing it, by the engine's score of the position after it
+	// Engines that don't score give 0 for every move that doesn't end the game
+	let mut after = *board;
+	after.try_apply_move(mv)?;
+	if after.legal_moves().is_empty() {
+		return Ok(if after.is_in_check(after.turn) { MATE_SCORE } else { 0 });
+	}
+	engine.choose_move(&after, limits)?;
+	return Ok(engine.score().map_or(0, |score| -score));
+}
+
+pub fn best_alternative(engine: &mut impl Engine, board: &Board, mv: Move, limits: &SearchLimits) -> Result<Option<(Move, i32)>, ChessError> {
+	// The engine's choice and score with `mv` excluded, None when `mv` is the only legal move
+	// The gap to the score of `mv` tells how much worse the second best option is
+	let mut excluded = limits.clone();
+	excluded.excluded.push(mv);
+	if excluded.root_moves(board).is_empty() {
+		return Ok(None);
+	}
+	let alternative = engine.choose_move(board, &excluded)?;
+	return Ok(Some((alternative, engine.score().unwrap_or(0))));
+}
+
+// Picks any legal move, every move has the same chance
+#[derive(Debug, Clone)]
+pub struct RandomMover {
+	rng: Rng,
+}
+
+impl RandomMover {
+	pub fn new() -> Self {
+		RandomMover { rng: Rng::from_time() }
+	}
+}
+
+impl Default for RandomMover {
+	fn default() -> Self {
+		return RandomMover::new();
+	}
+}
+
+impl Seeded for RandomMover {
+	fn rng(&mut self) -> &mut Rng {
+		return &mut self.rng;
+	}
+}
+
+impl Engine for RandomMover {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+		let moves = limits.root_moves(board);
+		if moves.is_empty() {
+			return Err(ChessError::NoLegalMoves);
+		}
+
+		return Ok(moves[self.rng.below(moves.len())]);
+	}
+}
+
+// Takes the most valuable thing it can grab right now, without looking at the reply
+#[derive(Debug, Clone)]
+pub struct GreedyMover {
+	rng: Rng,
+}
+
+impl GreedyMover {
+	pub fn new() -> Self {
+		GreedyMover { rng: Rng::from_time() }
+	}
+
+	fn gain(board: &Board, mv: Move) -> i32 {
+		// Material won by the move: the captured piece plus whatever a promotion adds
+		let mut gain = match board.get_piece(mv.to) {
+			Some(piece) => piece_value(piece.breed),
+			None => 0,
+		};
+
+		if let Some(breed) = mv.promotion {
+			gain += piece_value(breed) - piece_value(Pieces::Pawn);
+		}
+
+		return gain;
+	}
+}
+
+impl Default for GreedyMover {
+	fn default() -> Self {
+		return GreedyMover::new();
+	}
+}
+
+impl Seeded for GreedyMover {
+	fn rng(&mut self) -> &mut Rng {
+		return &mut self.rng;
+	}
+}
+
+impl Engine for GreedyMover {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+		let moves = limits.root_moves(board);
+		let best_gain = match moves.iter().map(|mv| Self::gain(board, *mv)).max() {
+			Some(gain) => gain,
+			None => return Err(ChessError::NoLegalMoves),
+		};
+
+		// Ties are broken randomly so quiet positions don't always play the same move
+		let best: Vec<Move> = moves
+		.into_iter()
+		.filter(|mv| Self::gain(board, *mv) == best_gain)
+		.collect();
+		return Ok(best[self.rng.below(best.len())]);
+	}
+}
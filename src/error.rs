@@ -0,0 +1,89 @@
+use std::fmt;
+
+use crate::chess::{Coordinate, IllegalMove, Move, MoveError};
+use crate::drops::Drop;
+
+// Everything the library can fail with
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ChessError {
+	// The FEN string and what is wrong with it
+	InvalidFen(String, String),
+	OutOfBounds(Coordinate),
+	// Text that is not a square name like "e4"
+	InvalidSquare(String),
+	EmptySquare(Coordinate),
+	NoLegalMoves,
+	IllegalMove(Move),
+	// A drop the pocket or the position doesn't allow
+	IllegalDrop(Drop),
+	GameOver,
+	NoDrawOffer,
+	// Why the draw claim can't be made
+	InvalidClaim(String),
+	UnknownOption(String),
+	// The option name and the value it can't take
+	InvalidOptionValue(String, String),
+	// What is wrong with the PGN text
+	InvalidPgn(String),
+	// The line of the move log that can't be read, and why
+	InvalidMoveLog(String),
+	// What is wrong with the style 12 line
+	InvalidStyle12(String),
+	// What is wrong with the saved correspondence game
+	InvalidCorrespondence(String),
+	// A ply past the end of the game
+	PlyOutOfRange(usize),
+	// A position that can't come up in a game, and why
+	InvalidPosition(String),
+	// What is wrong with the puzzle
+	InvalidPuzzle(String),
+	// What is wrong with the board diagram
+	InvalidDiagram(String),
+	// A team that is missing or doesn't have enough players
+	InvalidTeam(String),
+}
+
+impl fmt::Display for ChessError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			ChessError::InvalidFen(fen, reason) => write!(f, "invalid FEN \"{}\": {}", fen, reason),
+			ChessError::OutOfBounds(coord) => write!(f, "coordinates {} {} are outside of the board", coord.row, coord.col),
+			ChessError::InvalidSquare(text) => write!(f, "\"{}\" is not a square", text),
+			ChessError::EmptySquare(coord) => write!(f, "there is no piece on {}", coord),
+			ChessError::NoLegalMoves => write!(f, "no legal moves"),
+			ChessError::IllegalMove(mv) => write!(f, "illegal move {}", mv),
+			ChessError::IllegalDrop(drop) => write!(f, "illegal drop {}", drop),
+			ChessError::GameOver => write!(f, "the game is already over"),
+			ChessError::NoDrawOffer => write!(f, "there is no draw offer to accept"),
+			ChessError::InvalidClaim(reason) => write!(f, "invalid draw claim: {}", reason),
+			ChessError::UnknownOption(name) => write!(f, "unknown option \"{}\"", name),
+			ChessError::InvalidOptionValue(name, value) => write!(f, "invalid value \"{}\" for option \"{}\"", value, name),
+			ChessError::InvalidPgn(reason) => write!(f, "invalid PGN: {}", reason),
+			ChessError::InvalidMoveLog(reason) => write!(f, "invalid move log: {}", reason),
+			ChessError::InvalidStyle12(reason) => write!(f, "invalid style 12 line: {}", reason),
+			ChessError::InvalidCorrespondence(reason) => write!(f, "invalid correspondence game: {}", reason),
+			ChessError::PlyOutOfRange(ply) => write!(f, "the game has no ply {}", ply),
+			ChessError::InvalidPosition(reason) => write!(f, "invalid position: {}", reason),
+			ChessError::InvalidPuzzle(reason) => write!(f, "invalid puzzle: {}", reason),
+			ChessError::InvalidDiagram(reason) => write!(f, "invalid diagram: {}", reason),
+			ChessError::InvalidTeam(reason) => write!(f, "invalid team: {}", reason),
+		};
+	}
+}
+
+impl std::error::Error for ChessError {}
+
+impl From<MoveError> for ChessError {
+	fn from(error: MoveError) -> Self {
+		return match error {
+			MoveError::OutOfBounds(coord) => ChessError::OutOfBounds(coord),
+			MoveError::EmptySquare(coord) => ChessError::EmptySquare(coord),
+		};
+	}
+}
+
+impl From<IllegalMove> for ChessError {
+	fn from(error: IllegalMove) -> Self {
+		return ChessError::IllegalMove(error.mv());
+	}
+}
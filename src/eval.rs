@@ -0,0 +1,321 @@
+use std::fmt;
+use std::mem;
+
+use crate::chess::*;
+use crate::options::{EngineOptions, UciOption};
+use crate::phase::taper;
+
+// Scores are in centipawns, from the point of view of the side to move
+pub trait Evaluator {
+	fn evaluate(&self, board: &Board) -> i32;
+
+	// Tuning knobs to offer through EngineOptions, none by default
+	fn options(&self) -> Vec<UciOption> {
+		return Vec::new();
+	}
+
+	// Called with every option after one of them changes
+	fn set_options(&mut self, _options: &EngineOptions) {}
+}
+
+pub fn piece_value(breed: Pieces) -> i32 {
+	// The king can't be traded, so it is worth nothing in material counts
+	use Pieces::*;
+	return match breed {
+		Queen => 900,
+		Rook => 500,
+		Bishop => 330,
+		Knight => 320,
+		Pawn => 100,
+		King => 0,
+	};
+}
+
+// Counts material and nothing else
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+	fn evaluate(&self, board: &Board) -> i32 {
+		let white: i32 = board.white_pieces.values().map(|piece| piece_value(piece.breed)).sum();
+		let black: i32 = board.black_pieces.values().map(|piece| piece_value(piece.breed)).sum();
+		
+		return match board.turn {
+			Color::White => white - black,
+			Color::Black => black - white,
+		};
+	}
+}
+
+// What an evaluation is made of, term by term
+/*
+Every term is a score from white's point of view, the evaluation is their sum. Space and the
+center matter while there are pieces to use the squares, they fade out by the game phase and are
+worth nothing in a bare endgame. Passed pawns go the other way, they are worth twice as much in
+the endgame, and the pawn that wins a race to the promotion square is worth almost a queen.
+The pawn structure costs for every doubled, isolated and backward pawn and every island after
+the first, the files and holes it leaves are worth something to rooks on open and half-open
+files and to knights and bishops on outposts
+*/
+
+// Centipawns per point of Board::space and of Board::center_control, before the fading
+pub const SPACE_WEIGHT: i32 = 4;
+pub const CENTER_WEIGHT: i32 = 8;
+// Centipawns of a passed pawn in the endgame by the rank it is on, the second rank is index 1
+pub const PASSED_BONUS: [i32; 8] = [0, 10, 10, 20, 35, 60, 100, 0];
+// Centipawns of the pawn that wins the pawn race
+pub const RACE_BONUS: i32 = 800;
+// Centipawns lost per weak pawn and per pawn island after the first
+pub const DOUBLED_PENALTY: i32 = 12;
+pub const ISOLATED_PENALTY: i32 = 15;
+pub const BACKWARD_PENALTY: i32 = 10;
+pub const ISLAND_PENALTY: i32 = 8;
+// Centipawns of a rook on an open or half-open file and of a minor piece on an outpost
+pub const ROOK_OPEN_FILE: i32 = 20;
+pub const ROOK_HALF_OPEN_FILE: i32 = 10;
+pub const KNIGHT_OUTPOST: i32 = 25;
+pub const BISHOP_OUTPOST: i32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvalTerm {
+	Material,
+	Space,
+	Center,
+	PassedPawns,
+	PawnStructure,
+	RookFiles,
+	Outposts,
+}
+
+impl fmt::Display for EvalTerm {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match self {
+			EvalTerm::Material => "material",
+			EvalTerm::Space => "space",
+			EvalTerm::Center => "center control",
+			EvalTerm::PassedPawns => "passed pawns",
+			EvalTerm::PawnStructure => "pawn structure",
+			EvalTerm::RookFiles => "rooks on open files",
+			EvalTerm::Outposts => "outposts",
+		};
+		return write!(f, "{}", name);
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EvalBreakdown {
+	pub terms: Vec<(EvalTerm, i32)>,
+}
+
+impl EvalBreakdown {
+	pub fn get(&self, term: EvalTerm) -> i32 {
+		return self.terms.iter().filter(|(other, _)| *other == term).map(|(_, score)| score).sum();
+	}
+
+	pub fn total(&self) -> i32 {
+		return self.terms.iter().map(|(_, score)| score).sum();
+	}
+
+	pub fn biggest(&self, color: Color) -> Option<(EvalTerm, i32)> {
+		// The term that favors `color` the most, None when none of them does, the first one on a tie
+		let sign = match color {
+			Color::White => 1,
+			Color::Black => -1,
+		};
+		return self
+		.terms
+		.iter()
+		.rev()
+		.map(|(term, score)| (*term, score * sign))
+		.filter(|(_, score)| *score > 0)
+		.max_by_key(|(_, score)| *score);
+	}
+}
+
+impl Board {
+	pub fn eval_breakdown(&self) -> EvalBreakdown {
+		let side = |color: Color| -> i32 {
+			let pieces = match color {
+				Color::White => &self.white_pieces,
+				Color::Black => &self.black_pieces,
+			};
+			return pieces.values().map(|piece| piece_value(piece.breed)).sum();
+		};
+		let phase = self.phase_value();
+		let fade = |score: i32| taper(score, 0, phase);
+
+		let space = self.space(Color::White) - self.space(Color::Black);
+		let center = self.center_control(Color::White) - self.center_control(Color::Black);
+		let mut passed = 0;
+		for (num, piece) in self.white_pieces.iter().chain(self.black_pieces.iter()) {
+			let coord = Coordinate::from_number(*num);
+			if piece.breed != Pieces::Pawn || !self.is_passed(coord) {
+				continue;
+			}
+			let rank = match piece.color {
+				Color::White => 7 - coord.row,
+				Color::Black => coord.row,
+			};
+			let bonus = PASSED_BONUS[rank as usize];
+			let bonus = taper(bonus / 2, bonus, phase);
+			passed += match piece.color {
+				Color::White => bonus,
+				Color::Black => -bonus,
+			};
+		}
+		match self.pawn_race() {
+			Some((Color::White, _)) => passed += RACE_BONUS,
+			Some((Color::Black, _)) => passed -= RACE_BONUS,
+			None => {}
+		}
+
+		// Worked out once for the weak pawns and the files
+		let structures = [self.pawn_structure(Color::White), self.pawn_structure(Color::Black)];
+		let weaknesses = |color: Color| -> i32 {
+			let structure = &structures[color as usize];
+			return DOUBLED_PENALTY * structure.doubled.len() as i32
+			+ ISOLATED_PENALTY * structure.isolated.len() as i32
+			+ BACKWARD_PENALTY * structure.backward.len() as i32
+			+ ISLAND_PENALTY * (structure.islands.len() as i32 - 1).max(0);
+		};
+
+		let mut rook_files = 0;
+		let mut outposts = 0;
+		for (num, piece) in self.white_pieces.iter().chain(self.black_pieces.iter()) {
+			let coord = Coordinate::from_number(*num);
+			let sign = match piece.color {
+				Color::White => 1,
+				Color::Black => -1,
+			};
+			let structure = &structures[piece.color as usize];
+			match piece.breed {
+				Pieces::Rook if structure.is_open(coord.file()) => rook_files += sign * ROOK_OPEN_FILE,
+				Pieces::Rook if structure.is_half_open(coord.file()) => rook_files += sign * ROOK_HALF_OPEN_FILE,
+				Pieces::Knight if self.is_outpost(coord, piece.color) => outposts += sign * KNIGHT_OUTPOST,
+				Pieces::Bishop if self.is_outpost(coord, piece.color) => outposts += sign * BISHOP_OUTPOST,
+				_ => {}
+			}
+		}
+
+		EvalBreakdown {
+			terms: vec![
+				(EvalTerm::Material, side(Color::White) - side(Color::Black)),
+				(EvalTerm::Space, fade(space * SPACE_WEIGHT)),
+				(EvalTerm::Center, fade(center * CENTER_WEIGHT)),
+				(EvalTerm::PassedPawns, passed),
+				(EvalTerm::PawnStructure, weaknesses(Color::Black) - weaknesses(Color::White)),
+				(EvalTerm::RookFiles, rook_files),
+				(EvalTerm::Outposts, outposts),
+			],
+		}
+	}
+}
+
+// Material with the positional terms of EvalBreakdown on top
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PositionalEvaluator;
+
+impl Evaluator for PositionalEvaluator {
+	fn evaluate(&self, board: &Board) -> i32 {
+		let score = board.eval_breakdown().total();
+		return match board.turn {
+			Color::White => score,
+			Color::Black => -score,
+		};
+	}
+}
+
+// Static evaluations remembered by Zobrist hash
+/*
+Searches evaluate the same positions again and again (transpositions, every new iteration),
+with an evaluator that does more than count material the cache saves most of that work. One
+entry per slot, the newest evaluation wins, the whole hash is stored to tell positions sharing
+a slot apart. It is sized on its own, apart from the transposition table ("Hash")
+*/
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvalCacheStats {
+	pub probes: u64,
+	pub hits: u64,
+}
+
+impl EvalCacheStats {
+	pub fn hit_rate(&self) -> f64 {
+		if self.probes == 0 {
+			return 0.0;
+		}
+		return self.hits as f64 / self.probes as f64;
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EvalCache {
+	// A power of two slots, empty when the cache is off
+	entries: Vec<Option<(u64, i32)>>,
+	stats: EvalCacheStats,
+}
+
+impl EvalCache {
+	pub fn new(size_mb: u32) -> Self {
+		// As many slots as fit in the size, rounded down to a power of two
+		let slots = size_mb as usize * 1024 * 1024 / mem::size_of::<Option<(u64, i32)>>();
+		let slots = if slots == 0 { 0 } else { 1 << slots.ilog2() };
+		EvalCache {
+			entries: vec![None; slots],
+			stats: EvalCacheStats::default(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		// Number of slots
+		return self.entries.len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.entries.is_empty();
+	}
+
+	pub fn stats(&self) -> EvalCacheStats {
+		return self.stats;
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.fill(None);
+		self.stats = EvalCacheStats::default();
+	}
+
+	pub fn probe(&mut self, hash: u64) -> Option<i32> {
+		if self.entries.is_empty() {
+			return None;
+		}
+		self.stats.probes += 1;
+		let index = hash as usize & (self.entries.len() - 1);
+		let score = self.entries[index].filter(|(key, _)| *key == hash).map(|(_, score)| score);
+		if score.is_some() {
+			self.stats.hits += 1;
+		}
+		return score;
+	}
+
+	pub fn store(&mut self, hash: u64, score: i32) {
+		if self.entries.is_empty() {
+			return;
+		}
+		let index = hash as usize & (self.entries.len() - 1);
+		self.entries[index] = Some((hash, score));
+	}
+
+	pub fn evaluate(&mut self, evaluator: &impl Evaluator, board: &Board) -> i32 {
+		// The cached score if there is one, the evaluator's otherwise
+		if self.entries.is_empty() {
+			return evaluator.evaluate(board);
+		}
+		let hash = board.zobrist();
+		if let Some(score) = self.probe(hash) {
+			return score;
+		}
+		let score = evaluator.evaluate(board);
+		self.store(hash, score);
+		return score;
+	}
+}
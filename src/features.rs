@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+
+use crate::chess::*;
+use crate::game::Outcome;
+use crate::notation::ParseMode;
+use crate::pgn::{read_pgn, split_pgn};
+
+// Numeric features of a position, to feed machine learning models
+/*
+Version FEATURE_VERSION is FEATURE_COUNT numbers, whoever is to move:
+0..768    - piece planes, white's pieces then black's in the order of Pieces (king, queen, rook,
+            bishop, knight, pawn), 64 squares each from a1 to h8, 1 where the piece stands
+768       - side to move, 1 for white
+769..773  - castling rights: white king side, white queen side, black king side, black queen side
+773..781  - en passant, 1 on the file of the target square when the FEN has one
+781..783  - legal moves of white and black, as if it was their turn (see mobility.rs)
+783..799  - pawn structure of white then black (see pawns.rs), counts of: pawns, passed pawns,
+            doubled, isolated, backward and hanging pawns, pawn islands and half-open files
+
+Anything that changes the meaning of a number gets a new version. feature_names has a name for
+every number, the export writes them as its header
+
+Export
+One CSV line per position of every game of a PGN database: the game (from 0), the ply, white's
+result (1, 0.5, 0 or empty when the game has none) and the features
+*/
+
+pub const FEATURE_VERSION: u32 = 1;
+pub const PLANES: usize = 12 * 64;
+pub const FEATURE_COUNT: usize = PLANES + 1 + 4 + 8 + 2 + 16;
+
+const PIECES: [Pieces; 6] = [Pieces::King, Pieces::Queen, Pieces::Rook, Pieces::Bishop, Pieces::Knight, Pieces::Pawn];
+const PAWN_FEATURES: [&str; 8] = ["pawns", "passed", "doubled", "isolated", "backward", "hanging", "islands", "half_open_files"];
+
+fn color_name(color: Color) -> &'static str {
+	return match color {
+		Color::White => "white",
+		Color::Black => "black",
+	};
+}
+
+pub fn feature_names() -> Vec<String> {
+	let mut names = Vec::with_capacity(FEATURE_COUNT);
+	for color in [Color::White, Color::Black] {
+		for breed in PIECES {
+			for square in 0..64 {
+				names.push(format!("{}_{}_{}", color_name(color), format!("{:?}", breed).to_lowercase(), Coordinate::from_number(square)));
+			}
+		}
+	}
+	names.push("white_to_move".to_string());
+	names.extend(["white_king_side", "white_queen_side", "black_king_side", "black_queen_side"].map(String::from));
+	names.extend("abcdefgh".chars().map(|file| format!("en_passant_{}", file)));
+	names.extend([Color::White, Color::Black].map(|color| format!("{}_moves", color_name(color))));
+	for color in [Color::White, Color::Black] {
+		names.extend(PAWN_FEATURES.map(|name| format!("{}_{}", color_name(color), name)));
+	}
+	return names;
+}
+
+impl Board {
+	pub fn feature_vector(&self) -> Vec<f32> {
+		// See above
+		let mut features = vec![0.0; PLANES];
+		for (color, pieces) in [(Color::White, &self.white_pieces), (Color::Black, &self.black_pieces)] {
+			for (num, piece) in pieces.iter() {
+				let kind = PIECES.iter().position(|breed| *breed == piece.breed).unwrap();
+				features[(color as usize * 6 + kind) * 64 + *num as usize] = 1.0;
+			}
+		}
+
+		features.push(match self.turn {
+			Color::White => 1.0,
+			Color::Black => 0.0,
+		});
+		features.extend(self.castling_rights.map(|right| right as u8 as f32));
+		let mut en_passant = [0.0; 8];
+		if let Some(square) = self.en_passant_target_sq {
+			en_passant[Coordinate::from_number(square).col as usize] = 1.0;
+		}
+		features.extend(en_passant);
+		features.extend([Color::White, Color::Black].map(|color| self.mobility(color).moves as f32));
+
+		for color in [Color::White, Color::Black] {
+			let structure = self.pawn_structure(color);
+			let passed = structure.pawns.iter().filter(|pawn| self.is_passed(**pawn)).count();
+			features.extend([
+				structure.pawns.len(),
+				passed,
+				structure.doubled.len(),
+				structure.isolated.len(),
+				structure.backward.len(),
+				structure.hanging.len(),
+				structure.islands.len(),
+				structure.half_open_files.len(),
+			].map(|count| count as f32));
+		}
+		return features;
+	}
+}
+
+pub fn export_features(text: &str, mode: ParseMode, writer: &mut impl Write) -> io::Result<usize> {
+	// Every game of the database, see above, returns the number of positions written. A game that
+	// can't be read stops the export
+	write!(writer, "game,ply,result")?;
+	for name in feature_names() {
+		write!(writer, ",{}", name)?;
+	}
+	writeln!(writer)?;
+
+	let mut count = 0;
+	for (index, text) in split_pgn(text).iter().enumerate() {
+		let pgn = read_pgn(text, mode).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("game {}: {}", index, error)))?;
+		let game = pgn.game;
+		let result = game.termination().map(|(outcome, _)| outcome).or(game.game_result().map(|result| result.outcome()));
+		let result = match result {
+			Some(Outcome::WhiteWins) => "1",
+			Some(Outcome::Draw) => "0.5",
+			Some(Outcome::BlackWins) => "0",
+			None => "",
+		};
+		for (ply, board) in game.positions().enumerate() {
+			write!(writer, "{},{},{}", index, ply, result)?;
+			for feature in board.feature_vector() {
+				write!(writer, ",{}", feature)?;
+			}
+			writeln!(writer)?;
+			count += 1;
+		}
+	}
+	return Ok(count);
+}
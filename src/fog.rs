@@ -0,0 +1,128 @@
+use std::fmt::Write;
+
+use crate::chess::*;
+use crate::render::RenderTheme;
+
+// Fog of War (Dark Chess), where a player only sees part of the board
+/*
+A player sees the squares of their own pieces and every square one of them could move to by
+the piece rules, check or no check, the way kings get captured in this variant:
+
+- sliders see along their lines up to and including the first piece in the way
+- pawns see the squares in front of them they could step to, and a diagonal square only when
+  there is something to take on it (en passant included)
+- the rest of the board is hidden, an enemy piece there can't be told from an empty square
+
+White after 1. e4 d5, the pawn on e4 sees d5 because it can take there:
+
+8 ? ? ? ? ? ? ? ?
+7 ? ? ? ? ? ? ? ?
+6 . ? ? ? ? ? ? ?
+5 ? . ? p . ? ? .
+4 . . . . P . . .
+3 . . . . ? . . .
+2 P P P P . P P P
+1 R N B Q K B N R
+  a b c d e f g h
+*/
+// source: https://www.chess.com/terms/fog-of-war-chess
+
+// What a player knows about a square
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FogSquare {
+	Hidden,
+	Empty,
+	Piece(Piece),
+}
+
+impl Board {
+	pub fn visible_squares(&self, color: Color) -> u64 {
+		// Bitboard of the squares the color sees, a1 is bit 0 like attacked_squares
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+
+		let mut visible: u64 = 0;
+		for num in pieces.keys() {
+			visible |= 1 << num;
+			for mv in self.pseudo_legal_moves_from(Coordinate::from_number(*num)).unwrap_or_default() {
+				visible |= 1 << mv.to.as_number();
+			}
+		}
+		return visible;
+	}
+
+	pub fn fog_view(&self, color: Color) -> FogView {
+		return FogView {
+			viewer: color,
+			visible: self.visible_squares(color),
+			board: *self,
+		};
+	}
+}
+
+// The board the way one player sees it
+#[derive(Debug, Clone, Copy)]
+pub struct FogView {
+	pub viewer: Color,
+	// Bitboard of the visible squares
+	pub visible: u64,
+	// The whole position, for the game to keep going, what the player sees comes from square()
+	board: Board,
+}
+
+impl FogView {
+	pub fn is_visible(&self, coord: Coordinate) -> bool {
+		return coord.is_valid() && self.visible & (1 << coord.as_number()) != 0;
+	}
+
+	pub fn square(&self, coord: Coordinate) -> FogSquare {
+		if !self.is_visible(coord) {
+			return FogSquare::Hidden;
+		}
+		return match self.board.get_piece(coord) {
+			Some(piece) => FogSquare::Piece(piece),
+			None => FogSquare::Empty,
+		};
+	}
+
+	pub fn masked_board(&self) -> Board {
+		// Only the visible pieces and the side to move, for GUIs and engines that take a Board
+		// NOTE: The enemy king is often missing, the result is rarely a valid position
+		let mut masked = Board::new();
+		masked.turn = self.board.turn;
+		for row in 0..8 {
+			for col in 0..8 {
+				if let FogSquare::Piece(piece) = self.square(coord!(row, col)) {
+					masked.place_piece(piece, coord!(row, col)).ok();
+				}
+			}
+		}
+		return masked;
+	}
+
+	pub fn render(&self, theme: &RenderTheme) -> String {
+		// Like Board::render with Plain colors, hidden squares are '?'
+		let mut out = String::new();
+		for row in 0..8 {
+			if theme.labels {
+				let _ = write!(out, "{} ", 8 - row);
+			}
+			for col in 0..8 {
+				match self.square(coord!(row, col)) {
+					FogSquare::Hidden => out.push_str("? "),
+					FogSquare::Empty => out.push_str(". "),
+					FogSquare::Piece(piece) => {
+						let _ = write!(out, "{} ", theme.glyph(piece));
+					}
+				}
+			}
+			out.push('\n');
+		}
+		if theme.labels {
+			out.push_str("  a b c d e f g h\n");
+		}
+		return out;
+	}
+}
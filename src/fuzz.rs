@@ -0,0 +1,74 @@
+use crate::chess::*;
+use crate::notation::{Notation, ParseMode};
+use crate::perft::BENCH_POSITIONS;
+use crate::pgn::{read_pgn, read_pgn_tree, split_pgn, PgnOptions};
+
+// Entry points for fuzzers, one per parser
+/*
+Every function takes any bytes and only panics when the parser or the writer is wrong: input
+that doesn't parse is fine, input that does has to come back the same after being written out
+and read again, and give a consistent board (see check_invariants) when the position is one
+that could come up in a game (see validate). Bytes that aren't UTF-8 are read the lossy way.
+They fit cargo-fuzz targets as they are:
+
+fuzz_target!(|data: &[u8]| chess::fuzz::fuzz_fen(data));
+
+FEN - the text is a FEN
+SAN - the first byte picks the position from BENCH_POSITIONS, the rest is the move, read in
+      both parse modes
+PGN - the text is a database, every game of it is read in both parse modes, as a game and as a
+      tree with its variations
+*/
+
+pub fn fuzz_fen(data: &[u8]) {
+	let text = String::from_utf8_lossy(data);
+	let board = match Board::from_fen(&text) {
+		Ok(board) => board,
+		Err(_) => return,
+	};
+	if let (Ok(()), Err(violation)) = (board.validate(), board.check_invariants()) {
+		panic!("\"{}\" gives an inconsistent board: {}", text, violation);
+	}
+	let fen = board.get_fen();
+	let again = Board::from_fen(&fen).unwrap_or_else(|error| panic!("\"{}\" is written as \"{}\", which doesn't read: {}", text, fen, error));
+	assert_eq!(again.get_fen(), fen, "\"{}\" doesn't come back the same", text);
+}
+
+pub fn fuzz_san(data: &[u8]) {
+	let (first, rest) = match data.split_first() {
+		Some(split) => split,
+		None => return,
+	};
+	let board = Board::from_fen(BENCH_POSITIONS[*first as usize % BENCH_POSITIONS.len()]).unwrap();
+	let text = String::from_utf8_lossy(rest);
+	for mode in [ParseMode::Strict, ParseMode::Lenient] {
+		let mv = match Notation::San.parse_with(&board, &text, mode) {
+			Some(mv) => mv,
+			None => continue,
+		};
+		assert!(board.legal_moves().contains(&mv), "\"{}\" reads as {}, which isn't legal", text, mv);
+		let san = Notation::San.format(&board, mv);
+		assert_eq!(Notation::San.parse(&board, &san), Some(mv), "\"{}\" is written as \"{}\", which doesn't read back", text, san);
+	}
+}
+
+pub fn fuzz_pgn(data: &[u8]) {
+	let text = String::from_utf8_lossy(data);
+	for game in split_pgn(&text) {
+		for mode in [ParseMode::Strict, ParseMode::Lenient] {
+			let _ = read_pgn_tree(&game, mode);
+			let pgn = match read_pgn(&game, mode) {
+				Ok(pgn) => pgn,
+				Err(_) => continue,
+			};
+			if let (Ok(()), Err(violation)) = (pgn.game.start().validate(), pgn.game.check_invariants()) {
+				panic!("\"{}\" gives an inconsistent game: {}", game, violation);
+			}
+			let written = pgn.writer(PgnOptions::default()).write(&pgn.game);
+			let again = read_pgn(&written, ParseMode::Strict).unwrap_or_else(|error| panic!("\"{}\" is written as \"{}\", which doesn't read: {}", game, written, error));
+			assert_eq!(again.game.moves(), pgn.game.moves(), "the moves of \"{}\" don't come back the same", game);
+			assert_eq!(again.game.start().get_fen(), pgn.game.start().get_fen(), "the start of \"{}\" doesn't come back the same", game);
+			assert_eq!(again.game.termination(), pgn.game.termination(), "the result of \"{}\" doesn't come back the same", game);
+		}
+	}
+}
@@ -0,0 +1,734 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::chess::*;
+use crate::error::ChessError;
+
+// Halfmove clock values for the draw rules
+pub const FIFTY_MOVE_PLIES: u32 = 100;
+pub const SEVENTY_FIVE_MOVE_PLIES: u32 = 150;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+	WhiteWins,
+	BlackWins,
+	Draw,
+}
+
+// Which material counts as not enough to win
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum MaterialRule {
+	// Only dead positions, where no sequence of legal moves mates:
+	// K vs K, K+B vs K, K+N vs K, and kings with bishops that all stand on one square color
+	#[default]
+	Fide,
+	// Also positions where mate can't be forced, like K+N+N vs K or K+minor vs K+minor
+	// (the simplification used by many servers and by USCF for time forfeits)
+	NoForcedMate,
+}
+
+// How a finished game ended
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum GameResult {
+	Checkmate { winner: Color },
+	Stalemate,
+	InsufficientMaterial,
+	// Neither side can ever mate, even though there is material left (FIDE Article 5.2.2)
+	DeadPosition,
+	// Claimed by a player after 50 moves each without a capture or a pawn move
+	FiftyMoveRule,
+	// Automatic after 75 moves each, nobody has to claim it
+	SeventyFiveMoveRule,
+	// Claimed by a player when the same position comes up for the third time
+	ThreefoldRepetition,
+	// Automatic on the fifth time
+	FivefoldRepetition,
+}
+
+// The draws a player has to claim, the rest of the draw rules end the game on their own
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum DrawClaim {
+	ThreefoldRepetition,
+	FiftyMoveRule,
+}
+
+impl DrawClaim {
+	pub fn result(&self) -> GameResult {
+		return match self {
+			DrawClaim::ThreefoldRepetition => GameResult::ThreefoldRepetition,
+			DrawClaim::FiftyMoveRule => GameResult::FiftyMoveRule,
+		};
+	}
+}
+
+// Why a game ended, as far as the players are concerned
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum TerminationReason {
+	Checkmate,
+	Stalemate,
+	Resignation,
+	Timeout,
+	Agreement,
+	Abandonment,
+	RulesInfraction,
+	// Decided by someone other than the players, like a match runner's adjudication rules
+	Adjudication,
+	// One of the draw rules: material, dead position, move counts, repetitions
+	DrawRule(GameResult),
+}
+
+impl TerminationReason {
+	pub fn pgn_termination(&self) -> &'static str {
+		// Value of the PGN Termination tag
+		// source: http://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm#c9.8.1
+		return match self {
+			TerminationReason::Timeout => "time forfeit",
+			TerminationReason::Abandonment => "abandoned",
+			TerminationReason::RulesInfraction => "rules infraction",
+			TerminationReason::Adjudication => "adjudication",
+			_ => "normal",
+		};
+	}
+}
+
+impl From<GameResult> for TerminationReason {
+	fn from(result: GameResult) -> Self {
+		return match result {
+			GameResult::Checkmate { .. } => TerminationReason::Checkmate,
+			GameResult::Stalemate => TerminationReason::Stalemate,
+			_ => TerminationReason::DrawRule(result),
+		};
+	}
+}
+
+impl GameResult {
+	pub fn is_automatic(&self) -> bool {
+		// False for the draws that only end the game once a player claims them
+		return !matches!(self, GameResult::FiftyMoveRule | GameResult::ThreefoldRepetition);
+	}
+
+	pub fn outcome(&self) -> Outcome {
+		return match self {
+			GameResult::Checkmate { winner: Color::White } => Outcome::WhiteWins,
+			GameResult::Checkmate { winner: Color::Black } => Outcome::BlackWins,
+			_ => Outcome::Draw,
+		};
+	}
+}
+
+impl Board {
+	pub fn is_insufficient_material(&self, rule: MaterialRule) -> bool {
+		// Kings are not counted, anything besides bishops and knights is always enough
+		let mut minors: [Vec<(Pieces, Coordinate)>; 2] = [Vec::new(), Vec::new()];
+		for (color, pieces) in [(Color::White, &self.white_pieces), (Color::Black, &self.black_pieces)] {
+			for (num, piece) in pieces.iter() {
+				match piece.breed {
+					Pieces::King => (),
+					Pieces::Bishop | Pieces::Knight => minors[color as usize].push((piece.breed, Coordinate::from_number(*num))),
+					_ => return false,
+				}
+			}
+		}
+
+		// K vs K, a single minor piece, or bishops that all stand on one square color
+		if !self.can_mate(Color::White) && !self.can_mate(Color::Black) {
+			return true;
+		}
+
+		let [white, black] = &minors;
+		if rule == MaterialRule::NoForcedMate {
+			// One minor piece each
+			if white.len() == 1 && black.len() == 1 {
+				return true;
+			}
+
+			// Two knights against a bare king
+			let lone_knights = |side: &Vec<(Pieces, Coordinate)>| side.len() == 2 && side.iter().all(|(breed, _)| *breed == Pieces::Knight);
+			if (lone_knights(white) && black.is_empty()) || (lone_knights(black) && white.is_empty()) {
+				return true;
+			}
+		}
+
+		return false;
+	}
+
+	pub fn can_mate(&self, color: Color) -> bool {
+		// Whether the side has the material to mate with some series of legal moves, however
+		// unlikely, which is what decides a flag fall (FIDE Article 6.9)
+		// NOTE: A lone minor piece mates when an enemy piece blocks the king, and bishops of one square
+		// color need an enemy piece that can stand on the other color
+		let (own, enemy) = match color {
+			Color::White => (&self.white_pieces, &self.black_pieces),
+			Color::Black => (&self.black_pieces, &self.white_pieces),
+		};
+		let square_color = |num: &i8| {
+			let coord = Coordinate::from_number(*num);
+			return (coord.row + coord.col) % 2;
+		};
+
+		let mut knights = 0;
+		let mut bishop_colors: Vec<i8> = Vec::new();
+		for (num, piece) in own.iter() {
+			match piece.breed {
+				Pieces::King => (),
+				Pieces::Knight => knights += 1,
+				Pieces::Bishop => bishop_colors.push(square_color(num)),
+				_ => return true,
+			}
+		}
+
+		match (knights, bishop_colors.first()) {
+			(0, None) => return false,
+			(0, Some(first)) if bishop_colors.iter().all(|color| color == first) => {
+				return enemy
+				.iter()
+				.any(|(num, piece)| piece.breed != Pieces::King && (piece.breed != Pieces::Bishop || square_color(num) != *first));
+			}
+			(1, None) => return enemy.values().any(|piece| piece.breed != Pieces::King),
+			_ => return true,
+		}
+	}
+
+	pub fn is_dead_position(&self) -> bool {
+		// Best effort, a false answer doesn't mean a mate is still possible
+		/*
+		Recognised: dead material (see MaterialRule::Fide) and pawn walls, where
+		- there are only kings, pawns and bishops
+		- every pawn is blocked by an enemy pawn and has nothing to take
+		- no king can reach an enemy pawn that isn't protected by another pawn
+		- bishops all share one square color, and no enemy pawn stands on it
+		- with bishops on the board the kings are kept apart, and every square a king could be checked on
+		  has a flight square next to it
+		*/
+		if self.is_insufficient_material(MaterialRule::Fide) {
+			return true;
+		}
+
+		if self.en_passant_target_sq.is_some() {
+			return false;
+		}
+
+		let square_color = |coord: Coordinate| (coord.row + coord.col) % 2;
+		let mut bishops: Vec<(Color, i8)> = Vec::new();
+		// Squares attacked by pawns and the square colors the pawns stand on, indexed by color
+		let mut pawn_attacks: [u64; 2] = [0, 0];
+		let mut pawn_colors: [Vec<i8>; 2] = [Vec::new(), Vec::new()];
+
+		for num in 0..64 {
+			let coord = Coordinate::from_number(num);
+			let piece = match self.get_piece(coord) {
+				Some(piece) => piece,
+				None => continue,
+			};
+
+			match piece.breed {
+				Pieces::King => (),
+				Pieces::Bishop => bishops.push((piece.color, square_color(coord))),
+				Pieces::Pawn => {
+					let forward = match piece.color {
+						Color::White => -1,
+						Color::Black => 1,
+					};
+
+					// Blocked head on by an enemy pawn
+					match self.get_piece(coord!(coord.row + forward, coord.col)) {
+						Some(blocker) if blocker.breed == Pieces::Pawn && blocker.color != piece.color => (),
+						_ => return false,
+					}
+
+					for col_diff in [-1, 1] {
+						let target = coord!(coord.row + forward, coord.col + col_diff);
+						if !target.is_valid() {
+							continue;
+						}
+						if matches!(self.get_piece(target), Some(victim) if victim.color != piece.color) {
+							return false;
+						}
+						pawn_attacks[piece.color as usize] |= 1 << target.as_number();
+					}
+					pawn_colors[piece.color as usize].push(square_color(coord));
+				}
+				_ => return false,
+			}
+		}
+
+		if bishops.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+			return false;
+		}
+		let bishop_color = bishops.first().map(|(_, color)| *color);
+
+		// Bishops that could take a pawn, the squares where a bishop could give itself up to let
+		// a pawn take are of the same color as the pawn
+		for (color, bishop_color) in &bishops {
+			if pawn_colors[1 - *color as usize].contains(bishop_color) {
+				return false;
+			}
+		}
+
+		// Squares each king can walk to without stepping on pawns or squares that pawns guard
+		let mut regions: [u64; 2] = [0, 0];
+		for color in [Color::White, Color::Black] {
+			let king = match self.get_king_coord(color) {
+				Some(king) => king,
+				None => return false,
+			};
+			let enemy_attacks = pawn_attacks[1 - color as usize];
+
+			let mut stack = vec![king];
+			regions[color as usize] = 1 << king.as_number();
+			while let Some(square) = stack.pop() {
+				for (row_diff, col_diff) in KING_STEPS {
+					let next = coord!(square.row + row_diff, square.col + col_diff);
+					if !next.is_valid() || regions[color as usize] & (1 << next.as_number()) != 0 {
+						continue;
+					}
+
+					if let Some(piece) = self.get_piece(next) {
+						if piece.breed == Pieces::Pawn {
+							// An enemy pawn nobody protects can be taken, which breaks the wall
+							if piece.color != color && enemy_attacks & (1 << next.as_number()) == 0 {
+								return false;
+							}
+							continue;
+						}
+					}
+
+					if enemy_attacks & (1 << next.as_number()) != 0 {
+						continue;
+					}
+
+					regions[color as usize] |= 1 << next.as_number();
+					stack.push(next);
+				}
+			}
+		}
+
+		if let Some(color) = bishop_color {
+			// Kings that can stand next to each other could help each other into a mate
+			let reach = |region: u64| {
+				let mut result = region;
+				for num in 0..64 {
+					if region & (1 << num) != 0 {
+						let coord = Coordinate::from_number(num);
+						for (row_diff, col_diff) in KING_STEPS {
+							let next = coord!(coord.row + row_diff, coord.col + col_diff);
+							if next.is_valid() {
+								result |= 1 << next.as_number();
+							}
+						}
+					}
+				}
+				return result;
+			};
+			if reach(regions[0]) & regions[1] != 0 {
+				return false;
+			}
+
+			// Bishops never cover squares of the other color, so a king checked on a bishop colored
+			// square can always step sideways, as long as that square is in its region
+			for region in regions {
+				for num in 0..64 {
+					let coord = Coordinate::from_number(num);
+					if region & (1 << num) == 0 || square_color(coord) != color {
+						continue;
+					}
+
+					let has_flight = LINEAR_STEPS.iter().any(|(row_diff, col_diff)| {
+						let next = coord!(coord.row + row_diff, coord.col + col_diff);
+						next.is_valid() && region & (1 << next.as_number()) != 0
+					});
+					if !has_flight {
+						return false;
+					}
+				}
+			}
+		}
+
+		return true;
+	}
+
+	pub fn can_claim_fifty_move_rule(&self) -> bool {
+		return self.halfmove_clock() >= FIFTY_MOVE_PLIES;
+	}
+
+	pub fn can_claim_draw(&self) -> bool {
+		// The claims the board alone can check, Game::can_claim_draw also knows the repetitions
+		return self.can_claim_fifty_move_rule() && self.game_result(MaterialRule::default()).is_none();
+	}
+
+	pub fn game_result(&self, rule: MaterialRule) -> Option<GameResult> {
+		// None while the game goes on, draws that have to be claimed are not included
+		// NOTE: A mate on the last move before the 75 move rule still counts as a mate
+		if self.legal_moves().is_empty() {
+			if self.is_in_check(self.turn) {
+				return Some(GameResult::Checkmate { winner: opponent(self.turn) });
+			}
+			return Some(GameResult::Stalemate);
+		}
+
+		if self.is_insufficient_material(rule) {
+			return Some(GameResult::InsufficientMaterial);
+		}
+
+		if self.halfmove_clock() >= SEVENTY_FIVE_MOVE_PLIES {
+			return Some(GameResult::SeventyFiveMoveRule);
+		}
+
+		if self.is_dead_position() {
+			return Some(GameResult::DeadPosition);
+		}
+
+		return None;
+	}
+}
+
+fn opponent(color: Color) -> Color {
+	return match color {
+		Color::White => Color::Black,
+		Color::Black => Color::White,
+	};
+}
+
+fn win_for(color: Color) -> Outcome {
+	return match color {
+		Color::White => Outcome::WhiteWins,
+		Color::Black => Outcome::BlackWins,
+	};
+}
+
+// Plies between two stored boards, getting back to any position replays fewer moves than this
+pub const CHECKPOINT_INTERVAL: usize = 32;
+
+// A game from some starting position, with the positions it went through
+/*
+The history is the start, the moves and a board every CHECKPOINT_INTERVAL plies, positions in
+between are replayed from the checkpoint before them. Repetitions only look at the positions
+since the last capture or pawn move, nothing before it can come back, so those are counted by
+hash and the count goes when the next irreversible move is played
+*/
+#[derive(Debug, Clone)]
+pub struct Game {
+	pub rule: MaterialRule,
+	start: Board,
+	board: Board,
+	moves: Vec<Move>,
+	// The boards after 0, CHECKPOINT_INTERVAL, 2 * CHECKPOINT_INTERVAL... plies
+	checkpoints: Vec<Board>,
+	// Zobrist hash of the positions since the last irreversible move, with how often they came up
+	repetitions: HashMap<u64, usize>,
+	// Set by the player actions, results that come from the board are not stored
+	termination: Option<(Outcome, TerminationReason)>,
+	// The player whose draw offer is waiting for an answer
+	draw_offer: Option<Color>,
+}
+
+// Lazily replayed positions of a game, see Game::positions
+#[derive(Debug, Clone)]
+pub struct Positions<'a> {
+	game: &'a Game,
+	board: Board,
+	ply: usize,
+}
+
+impl Iterator for Positions<'_> {
+	type Item = Board;
+
+	fn next(&mut self) -> Option<Board> {
+		if self.ply > self.game.moves.len() {
+			return None;
+		}
+		let board = self.board;
+		if let Some(mv) = self.game.moves.get(self.ply) {
+			self.board.make_move(*mv);
+		}
+		self.ply += 1;
+		return Some(board);
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let left = (self.game.moves.len() + 1).saturating_sub(self.ply);
+		return (left, Some(left));
+	}
+}
+
+impl ExactSizeIterator for Positions<'_> {}
+
+impl Default for Game {
+	fn default() -> Self {
+		return Game::new(Board::default());
+	}
+}
+
+impl Game {
+	pub fn new(start: Board) -> Self {
+		Game {
+			rule: MaterialRule::default(),
+			checkpoints: vec![start],
+			repetitions: HashMap::from([(start.zobrist(), 1)]),
+			board: start,
+			start,
+			moves: Vec::new(),
+			termination: None,
+			draw_offer: None,
+		}
+	}
+
+	pub fn from_fen(fen: &str) -> Result<Self, ChessError> {
+		return Ok(Game::new(Board::from_fen(fen)?));
+	}
+
+	pub fn board(&self) -> &Board {
+		return &self.board;
+	}
+
+	pub fn start(&self) -> &Board {
+		return &self.start;
+	}
+
+	pub fn moves(&self) -> &[Move] {
+		return &self.moves;
+	}
+
+	pub fn make_move(&mut self, mv: Move) -> Result<MoveOutcome, ChessError> {
+		if self.termination().is_some() {
+			return Err(ChessError::GameOver);
+		}
+		let mover = self.board.turn;
+		let mut outcome = self.board.try_apply_move(mv)?;
+
+		// Moving instead of answering turns the opponent's offer down
+		if self.draw_offer.is_some() && self.draw_offer != Some(mover) {
+			self.draw_offer = None;
+		}
+
+		self.moves.push(mv);
+		if self.moves.len().is_multiple_of(CHECKPOINT_INTERVAL) {
+			self.checkpoints.push(self.board);
+		}
+		if self.board.halfmove_clock() == 0 {
+			self.repetitions.clear();
+		}
+		*self.repetitions.entry(self.board.zobrist()).or_insert(0) += 1;
+		// The board doesn't know about repetitions or the material rule of the game
+		outcome.result = self.game_result();
+		return Ok(outcome);
+	}
+
+	pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+		// Board::check_invariants for every position of the game, then the stored boards and the
+		// repetition counts against the ones replaying the moves gives
+		let same = |stored: Option<&Board>, board: &Board| stored.map(|stored| stored.get_fen()) == Some(board.get_fen());
+		let mut board = self.start;
+		let mut repetitions = HashMap::from([(board.zobrist(), 1)]);
+		board.check_invariants()?;
+		if !same(self.checkpoints.first(), &board) {
+			return Err(InvariantViolation::GameBoard(0));
+		}
+		for (ply, mv) in self.moves.iter().enumerate() {
+			board.make_move(*mv);
+			board.check_invariants()?;
+			let ply = ply + 1;
+			if ply.is_multiple_of(CHECKPOINT_INTERVAL) && !same(self.checkpoints.get(ply / CHECKPOINT_INTERVAL), &board) {
+				return Err(InvariantViolation::GameBoard(ply));
+			}
+			if board.halfmove_clock() == 0 {
+				repetitions.clear();
+			}
+			*repetitions.entry(board.zobrist()).or_insert(0) += 1;
+		}
+		if self.checkpoints.len() != self.moves.len() / CHECKPOINT_INTERVAL + 1 {
+			return Err(InvariantViolation::GameBoard(self.checkpoints.len() * CHECKPOINT_INTERVAL));
+		}
+		self.board.check_invariants()?;
+		if !same(Some(&self.board), &board) {
+			return Err(InvariantViolation::GameBoard(self.moves.len()));
+		}
+		if repetitions != self.repetitions {
+			return Err(InvariantViolation::Repetitions);
+		}
+		return Ok(());
+	}
+
+	pub fn repetition_count(&self) -> usize {
+		// How many times the current position has been on the board, this time included
+		return self.repetitions.get(&self.board.zobrist()).copied().unwrap_or(1);
+	}
+
+	pub fn position_at(&self, ply: usize) -> Option<Board> {
+		// The board after `ply` moves, 0 is the start, rebuilt from the checkpoint before it
+		if ply > self.moves.len() {
+			return None;
+		}
+		let mut board = self.checkpoints[ply / CHECKPOINT_INTERVAL];
+		for mv in &self.moves[ply / CHECKPOINT_INTERVAL * CHECKPOINT_INTERVAL..ply] {
+			board.make_move(*mv);
+		}
+		return Some(board);
+	}
+
+	pub fn hash_at(&self, ply: usize) -> Option<u64> {
+		// Zobrist hash of the position after `ply` moves, 0 is the start
+		return self.position_at(ply).map(|board| board.zobrist());
+	}
+
+	pub fn search_history(&self) -> Vec<u64> {
+		// Hashes of the positions before the current one that it could still repeat, the ones
+		// since the last capture or pawn move, for SearchLimits::history
+		let plies = self.moves.len();
+		let first = plies.saturating_sub(self.board.halfmove_clock() as usize);
+		return (first..plies).filter_map(|ply| self.hash_at(ply)).collect();
+	}
+
+	pub fn positions(&self) -> Positions<'_> {
+		// Every position of the game from the start, one move at a time
+		return Positions { game: self, board: self.start, ply: 0 };
+	}
+
+	pub fn replay_range(&self, range: Range<usize>, onto: &Board) -> Result<Board, ChessError> {
+		// Plays moves `range` of the game on another board, they have to be legal there too
+		let end = range.end;
+		let moves = self.moves.get(range).ok_or(ChessError::PlyOutOfRange(end))?;
+		let mut board = *onto;
+		for mv in moves {
+			board.try_apply_move(*mv)?;
+		}
+		return Ok(board);
+	}
+
+	pub fn can_claim_threefold(&self) -> bool {
+		return self.repetition_count() >= 3;
+	}
+
+	pub fn can_claim_fifty_move(&self) -> bool {
+		return self.board.can_claim_fifty_move_rule();
+	}
+
+	pub fn can_claim_draw(&self) -> Option<DrawClaim> {
+		// The draw the player to move could claim right now, repetitions first
+		if self.termination().is_some() {
+			return None;
+		}
+		if self.can_claim_threefold() {
+			return Some(DrawClaim::ThreefoldRepetition);
+		}
+		if self.can_claim_fifty_move() {
+			return Some(DrawClaim::FiftyMoveRule);
+		}
+		return None;
+	}
+
+	pub fn claim_draw(&mut self, color: Color, intended: Option<Move>) -> Result<Option<DrawClaim>, ChessError> {
+		// Claims a draw for the player to move, the way FIDE Article 9 lets them
+		/*
+		The claim is about the position on the board, or with an intended move about the position
+		after it, that move is not played when the claim is correct. A correct claim ends the game
+		and returns what was claimed. An incorrect one returns None and the game goes on, with the
+		intended move played, the player has to make it (Article 9.5)
+		*/
+		// source: https://handbook.fide.com/chapter/E012023
+		if self.termination().is_some() {
+			return Err(ChessError::GameOver);
+		}
+		if color != self.board.turn {
+			return Err(ChessError::InvalidClaim("only the player to move can claim a draw".to_string()));
+		}
+
+		let claim = match intended {
+			Some(mv) => {
+				let mut after = self.board;
+				after.try_apply_move(mv)?;
+				let seen = if after.halfmove_clock() == 0 { 0 } else { self.repetitions.get(&after.zobrist()).copied().unwrap_or(0) };
+				if seen + 1 >= 3 {
+					Some(DrawClaim::ThreefoldRepetition)
+				} else if after.can_claim_fifty_move_rule() {
+					Some(DrawClaim::FiftyMoveRule)
+				} else {
+					None
+				}
+			}
+			None => self.can_claim_draw(),
+		};
+
+		match claim {
+			Some(claim) => self.end(Outcome::Draw, TerminationReason::DrawRule(claim.result()))?,
+			None => {
+				if let Some(mv) = intended {
+					self.make_move(mv)?;
+				}
+			}
+		}
+		return Ok(claim);
+	}
+
+	pub fn game_result(&self) -> Option<GameResult> {
+		// Same as Board::game_result, with the repetitions the board alone doesn't know about
+		if let Some(result) = self.board.game_result(self.rule) {
+			return Some(result);
+		}
+
+		if self.repetition_count() >= 5 {
+			return Some(GameResult::FivefoldRepetition);
+		}
+
+		return None;
+	}
+
+	pub fn termination(&self) -> Option<(Outcome, TerminationReason)> {
+		// How the game ended, None while it is still going
+		if self.termination.is_some() {
+			return self.termination;
+		}
+		return self.game_result().map(|result| (result.outcome(), TerminationReason::from(result)));
+	}
+
+	pub fn draw_offer(&self) -> Option<Color> {
+		return self.draw_offer;
+	}
+
+	pub(crate) fn end(&mut self, outcome: Outcome, reason: TerminationReason) -> Result<(), ChessError> {
+		if self.termination().is_some() {
+			return Err(ChessError::GameOver);
+		}
+		self.termination = Some((outcome, reason));
+		self.draw_offer = None;
+		return Ok(());
+	}
+
+	pub fn offer_draw(&mut self, color: Color) -> Result<(), ChessError> {
+		// The offer stays until the opponent accepts it or makes a move
+		if self.termination().is_some() {
+			return Err(ChessError::GameOver);
+		}
+		self.draw_offer = Some(color);
+		return Ok(());
+	}
+
+	pub fn accept_draw(&mut self, color: Color) -> Result<(), ChessError> {
+		// Only the opponent of the player who offered can accept
+		if self.termination().is_none() && self.draw_offer != Some(opponent(color)) {
+			return Err(ChessError::NoDrawOffer);
+		}
+		return self.end(Outcome::Draw, TerminationReason::Agreement);
+	}
+
+	pub fn resign(&mut self, color: Color) -> Result<(), ChessError> {
+		return self.end(win_for(opponent(color)), TerminationReason::Resignation);
+	}
+
+	pub fn flag(&mut self, color: Color) -> Result<(), ChessError> {
+		// The player ran out of time, which is only a loss when the opponent could still mate
+		if !self.board.can_mate(opponent(color)) || self.board.is_dead_position() {
+			return self.end(Outcome::Draw, TerminationReason::Timeout);
+		}
+		return self.end(win_for(opponent(color)), TerminationReason::Timeout);
+	}
+
+	pub fn abandon(&mut self, color: Color) -> Result<(), ChessError> {
+		return self.end(win_for(opponent(color)), TerminationReason::Abandonment);
+	}
+
+	pub fn forfeit(&mut self, color: Color) -> Result<(), ChessError> {
+		// Lost by breaking the rules, like an illegal move in a blitz game or a phone going off
+		return self.end(win_for(opponent(color)), TerminationReason::RulesInfraction);
+	}
+}
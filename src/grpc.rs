@@ -0,0 +1,181 @@
+use std::io;
+use std::time::Duration;
+
+use tonic::{Request, Response, Status};
+
+use crate::chess::*;
+use crate::endgame::EndgameEvaluator;
+use crate::engine::SearchLimits;
+use crate::error::ChessError;
+use crate::eval::MaterialEvaluator;
+use crate::game::MaterialRule;
+use crate::notation::{Notation, ParseMode};
+use crate::pgn::result_token;
+use crate::search::AlphaBeta;
+
+// gRPC server for proto/chyes.proto (the `grpc` feature)
+/*
+The messages and the service traits are generated from the proto file by build.rs, clients in
+other languages are generated from the same file. Bad positions and moves are INVALID_ARGUMENT
+with the reason in the message
+
+Every Analyze gets its own engine on tokio's blocking threads, its depth, time and number of
+lines are capped so one call can't keep the server busy
+*/
+
+pub mod proto {
+	tonic::include_proto!("chyes.v1");
+}
+
+use proto::chess_server::{Chess, ChessServer};
+use proto::{AnalysisReply, AnalysisRequest, ApplyMoveReply, ApplyMoveRequest, LegalMovesReply, Line, Position, ValidateFenReply};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:50051";
+// Depth of Analyze when the request doesn't say
+pub const GRPC_DEPTH: u32 = 4;
+pub const MAX_GRPC_DEPTH: u32 = 8;
+pub const MAX_GRPC_MOVETIME: Duration = Duration::from_secs(10);
+pub const MAX_GRPC_LINES: usize = 8;
+
+impl From<ChessError> for Status {
+	fn from(error: ChessError) -> Self {
+		return Status::invalid_argument(error.to_string());
+	}
+}
+
+fn board_of(position: Option<&Position>) -> Result<Board, Status> {
+	let position = position.ok_or_else(|| Status::invalid_argument("missing position"))?;
+	return Ok(Board::from_fen(&position.fen)?);
+}
+
+fn move_message(board: &Board, mv: Move) -> proto::Move {
+	return proto::Move {
+		uci: mv.to_string(),
+		san: Notation::San.format(board, mv),
+	};
+}
+
+fn parse_move(board: &Board, text: &str) -> Option<Move> {
+	// UCI first, "e2e4" is never SAN
+	let text = text.trim();
+	return match board.legal_moves().into_iter().find(|mv| mv.to_string() == text) {
+		Some(mv) => Some(mv),
+		None => Notation::San.parse_with(board, text, ParseMode::Lenient),
+	};
+}
+
+pub fn legal_moves(position: &Position) -> Result<LegalMovesReply, Status> {
+	let board = board_of(Some(position))?;
+	let moves = board.legal_moves().into_iter().map(|mv| move_message(&board, mv)).collect();
+	return Ok(LegalMovesReply { moves });
+}
+
+pub fn apply_move(request: &ApplyMoveRequest) -> Result<ApplyMoveReply, Status> {
+	let mut board = board_of(request.position.as_ref())?;
+	let mv = parse_move(&board, &request.r#move).ok_or_else(|| Status::invalid_argument(format!("illegal move \"{}\"", request.r#move)))?;
+	let message = move_message(&board, mv);
+	board.make_move(mv);
+
+	let result = board.game_result(MaterialRule::default()).map_or("", |result| result_token(Some(result.outcome())));
+	return Ok(ApplyMoveReply {
+		position: Some(Position { fen: board.get_fen() }),
+		r#move: Some(message),
+		check: board.is_in_check(board.turn),
+		result: result.to_string(),
+	});
+}
+
+pub fn analyze(request: &AnalysisRequest) -> Result<AnalysisReply, Status> {
+	let board = board_of(request.position.as_ref())?;
+	if board.legal_moves().is_empty() {
+		return Err(ChessError::NoLegalMoves.into());
+	}
+
+	let depth = if request.depth == 0 { GRPC_DEPTH } else { request.depth.min(MAX_GRPC_DEPTH) };
+	let mut limits = SearchLimits::depth(depth);
+	limits.movetime = Some(match request.movetime_ms {
+		0 => MAX_GRPC_MOVETIME,
+		movetime => Duration::from_millis(movetime).min(MAX_GRPC_MOVETIME),
+	});
+	let count = (request.lines as usize).clamp(1, MAX_GRPC_LINES);
+
+	let mut engine = AlphaBeta::new(EndgameEvaluator::new(MaterialEvaluator));
+	let lines = engine
+	.search_lines(&board, &limits, count, |_| {})
+	.into_iter()
+	.map(|result| {
+		let mut played = board;
+		let pv = result
+		.pv
+		.iter()
+		.map(|mv| {
+			let message = move_message(&played, *mv);
+			played.make_move(*mv);
+			return message;
+		})
+		.collect();
+		return Line {
+			score: result.score,
+			depth: result.depth,
+			pv,
+		};
+	})
+	.collect();
+	return Ok(AnalysisReply { lines });
+}
+
+pub fn validate_fen(position: &Position) -> ValidateFenReply {
+	// A FEN that parses can still be a position no game reaches, both count as invalid
+	return match Board::from_fen(&position.fen).and_then(|board| board.validate().map(|_| board)) {
+		Ok(board) => ValidateFenReply {
+			valid: true,
+			fen: board.get_fen(),
+			error: String::new(),
+		},
+		Err(error) => ValidateFenReply {
+			valid: false,
+			fen: String::new(),
+			error: error.to_string(),
+		},
+	};
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChessService;
+
+#[tonic::async_trait]
+impl Chess for ChessService {
+	async fn legal_moves(&self, request: Request<Position>) -> Result<Response<LegalMovesReply>, Status> {
+		return legal_moves(request.get_ref()).map(Response::new);
+	}
+
+	async fn apply_move(&self, request: Request<ApplyMoveRequest>) -> Result<Response<ApplyMoveReply>, Status> {
+		return apply_move(request.get_ref()).map(Response::new);
+	}
+
+	async fn analyze(&self, request: Request<AnalysisRequest>) -> Result<Response<AnalysisReply>, Status> {
+		// The search would hold up the other calls on this thread
+		let request = request.into_inner();
+		return match tokio::task::spawn_blocking(move || analyze(&request)).await {
+			Ok(reply) => reply.map(Response::new),
+			Err(error) => Err(Status::internal(error.to_string())),
+		};
+	}
+
+	async fn validate_fen(&self, request: Request<Position>) -> Result<Response<ValidateFenReply>, Status> {
+		return Ok(Response::new(validate_fen(request.get_ref())));
+	}
+}
+
+pub fn serve(address: &str) -> io::Result<()> {
+	// Blocks and answers calls until the process ends
+	let address = address.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid address \"{}\"", address)))?;
+	let runtime = tokio::runtime::Runtime::new()?;
+	return runtime.block_on(async {
+		return tonic::transport::Server::builder()
+		.add_service(ChessServer::new(ChessService))
+		.serve(address)
+		.await
+		.map_err(io::Error::other);
+	});
+}
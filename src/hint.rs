@@ -0,0 +1,135 @@
+use std::fmt;
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::eval::piece_value;
+
+// Hints for tutoring front-ends, a move together with reasons a player can understand
+/*
+The engine picks the move, the reasons come from looking at the board before and after it:
+what the exchange on the target square wins, checks and mates, castling and promotions,
+minor pieces leaving the back rank, pieces stepping out of an exchange they would lose, rooks
+going to open and half-open files and knights and bishops settling on outposts
+*/
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum HintReason {
+	// Centipawns won once the exchanges are over
+	WinsMaterial(i32),
+	Checkmate,
+	Check,
+	Castles,
+	Promotes(Pieces),
+	Develops(Pieces),
+	EscapesAttack(Pieces),
+	OpenFile,
+	HalfOpenFile,
+	Outpost(Pieces),
+}
+
+fn piece_name(breed: Pieces) -> &'static str {
+	use Pieces::*;
+	return match breed {
+		King => "king",
+		Queen => "queen",
+		Rook => "rook",
+		Bishop => "bishop",
+		Knight => "knight",
+		Pawn => "pawn",
+	};
+}
+
+impl fmt::Display for HintReason {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			HintReason::WinsMaterial(value) => write!(f, "wins {} centipawns of material", value),
+			HintReason::Checkmate => write!(f, "delivers checkmate"),
+			HintReason::Check => write!(f, "gives check"),
+			HintReason::Castles => write!(f, "castles the king to safety"),
+			HintReason::Promotes(breed) => write!(f, "promotes to a {}", piece_name(*breed)),
+			HintReason::Develops(breed) => write!(f, "develops the {}", piece_name(*breed)),
+			HintReason::EscapesAttack(breed) => write!(f, "moves the attacked {} to safety", piece_name(*breed)),
+			HintReason::OpenFile => write!(f, "puts the rook on the open file"),
+			HintReason::HalfOpenFile => write!(f, "puts the rook on the half-open file"),
+			HintReason::Outpost(breed) => write!(f, "puts the {} on an outpost", piece_name(*breed)),
+		};
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+	pub mv: Move,
+	// The engine's score for the move, if it gives one
+	pub score: Option<i32>,
+	// Most important first, may be empty for quiet positional moves
+	pub reasons: Vec<HintReason>,
+}
+
+impl Board {
+	pub fn hint(&self, engine: &mut impl Engine, limits: &SearchLimits) -> Result<Hint, ChessError> {
+		let mv = engine.choose_move(self, limits)?;
+		return Ok(Hint {
+			mv,
+			score: engine.score(),
+			reasons: self.explain_move(mv)?,
+		});
+	}
+
+	pub fn explain_move(&self, mv: Move) -> Result<Vec<HintReason>, ChessError> {
+		// Reasons that make a legal move good, an illegal move is an error
+		let piece = self.get_piece(mv.from).ok_or(ChessError::EmptySquare(mv.from))?;
+		let mut after = *self;
+		let outcome = after.try_apply_move(mv)?;
+		let mut reasons: Vec<HintReason> = Vec::new();
+
+		if outcome.checkmate {
+			reasons.push(HintReason::Checkmate);
+		}
+
+		// The promotion is its own reason, only what gets captured counts here
+		let promotion_gain = mv.promotion.map_or(0, |breed| piece_value(breed) - piece_value(Pieces::Pawn));
+		let gain = self.see(mv) - promotion_gain;
+		if gain > 0 {
+			reasons.push(HintReason::WinsMaterial(gain));
+		}
+
+		if outcome.check && !outcome.checkmate {
+			reasons.push(HintReason::Check);
+		}
+		if let Some(breed) = outcome.promotion {
+			reasons.push(HintReason::Promotes(breed));
+		}
+		if outcome.castling {
+			reasons.push(HintReason::Castles);
+		}
+
+		let back_row = if piece.color == Color::White { 7 } else { 0 };
+		if matches!(piece.breed, Pieces::Knight | Pieces::Bishop) && mv.from.row == back_row {
+			reasons.push(HintReason::Develops(piece.breed));
+		}
+
+		// Squares the pawns leave to the pieces, only when the piece wasn't using them already
+		if piece.breed == Pieces::Rook && mv.from.col != mv.to.col {
+			if after.open_files().contains(&mv.to.file()) {
+				reasons.push(HintReason::OpenFile);
+			} else if after.half_open_files(piece.color).contains(&mv.to.file()) {
+				reasons.push(HintReason::HalfOpenFile);
+			}
+		}
+		if matches!(piece.breed, Pieces::Knight | Pieces::Bishop) && after.is_outpost(mv.to, piece.color) && !self.is_outpost(mv.from, piece.color) {
+			reasons.push(HintReason::Outpost(piece.breed));
+		}
+
+		// The piece would have been lost if it stayed, and it is safe where it goes
+		let mut passed = *self;
+		if !self.is_in_check(self.turn) && piece.breed != Pieces::King {
+			passed.make_null_move();
+			if passed.see_square(mv.from) > 0 && after.see_square(mv.to) == 0 {
+				reasons.push(HintReason::EscapesAttack(piece.breed));
+			}
+		}
+
+		return Ok(reasons);
+	}
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+
+use crate::chess::*;
+use crate::error::ChessError;
+
+// The "style 12" board line of Internet Chess Servers (FICS, ICC), for writing clients and bots
+/*
+One line per position the server sends, fields split by spaces:
+<12> rnbqkbnr pppppppp -------- -------- ----P--- -------- PPPP-PPP RNBQKBNR B 4 1 1 1 1 0 7 Newton Einstein 1 2 12 39 39 119 122 1 P/e2-e4 (0:06) e4 0 1 0
+
+"<12>"          - starts the line
+8 ranks         - rank 8 first, FEN letters for the pieces and '-' for the empty squares
+W or B          - the side to move
+-1 or 0..7      - the file of a pawn that just moved two squares, -1 without one
+4 times 0 or 1  - castling rights: white king side, white queen side, black king side, black
+                  queen side
+halfmove clock  - moves since the last capture or pawn move
+game number, white's name, black's name
+relation        - of the client to the game, see Relation
+initial time and increment of the clocks, in minutes and seconds
+material        - of white and black: pawns 1, knights and bishops 3, rooks 5 and queens 9
+clocks          - of white and black, in seconds or in milliseconds when the server is asked
+                  for them, below 0 when the flag fell
+move number     - of the move about to be made
+the last move   - verbose ("P/e2-e4", "o-o", "none"), the time it took ("(0:06)") and its SAN
+                  ("e4", "none")
+flip            - 1 when black is at the bottom of the board
+FICS adds whether the clock is ticking and the lag in milliseconds, older servers don't
+*/
+// source: https://www.freechess.org/Help/HelpFiles/style12.html
+
+// What the client has to do with the game
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Relation {
+	// A position on its own, like the ones of "ref 3" or "sposition"
+	Isolated,
+	ObservingExamined,
+	Examining,
+	OpponentToMove,
+	MyMove,
+	Observing,
+}
+
+impl Relation {
+	pub fn from_code(code: i8) -> Option<Relation> {
+		use Relation::*;
+		return match code {
+			-3 => Some(Isolated),
+			-2 => Some(ObservingExamined),
+			2 => Some(Examining),
+			-1 => Some(OpponentToMove),
+			1 => Some(MyMove),
+			0 => Some(Observing),
+			_ => None,
+		};
+	}
+
+	pub fn code(&self) -> i8 {
+		use Relation::*;
+		return match self {
+			Isolated => -3,
+			ObservingExamined => -2,
+			Examining => 2,
+			OpponentToMove => -1,
+			MyMove => 1,
+			Observing => 0,
+		};
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Style12 {
+	pub board: Board,
+	pub game_number: u32,
+	pub white: String,
+	pub black: String,
+	pub relation: Relation,
+	// Minutes
+	pub initial_time: u32,
+	// Seconds
+	pub increment: u32,
+	pub white_material: u32,
+	pub black_material: u32,
+	// The way the server sends them, see above
+	pub white_clock: i64,
+	pub black_clock: i64,
+	// Verbose, time it took in milliseconds and SAN, None when the game has no move yet
+	pub last_move: Option<String>,
+	pub last_move_time: u64,
+	pub last_move_san: Option<String>,
+	pub flipped: bool,
+	pub clock_ticking: Option<bool>,
+	// Milliseconds
+	pub lag: Option<u32>,
+}
+
+fn material(board: &Board, color: Color) -> u32 {
+	let pieces = match color {
+		Color::White => &board.white_pieces,
+		Color::Black => &board.black_pieces,
+	};
+	return pieces
+	.values()
+	.map(|piece| match piece.breed {
+		Pieces::Queen => 9,
+		Pieces::Rook => 5,
+		Pieces::Bishop | Pieces::Knight => 3,
+		Pieces::Pawn => 1,
+		Pieces::King => 0,
+	})
+	.sum();
+}
+
+fn move_time(text: &str) -> Option<u64> {
+	// "(min:sec)" or "(min:sec.ms)" to milliseconds
+	let (minutes, seconds) = text.strip_prefix('(')?.strip_suffix(')')?.split_once(':')?;
+	let (seconds, millis) = match seconds.split_once('.') {
+		Some((seconds, millis)) if millis.len() == 3 => (seconds, millis.parse::<u64>().ok()?),
+		Some(_) => return None,
+		None => (seconds, 0),
+	};
+	return Some((minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?) * 1000 + millis);
+}
+
+fn optional_move(text: &str) -> Option<String> {
+	return Some(text.to_string()).filter(|text| text != "none");
+}
+
+impl Style12 {
+	pub fn from_board(board: &Board) -> Self {
+		// The position on its own, with the material of the board and nothing else known
+		Style12 {
+			board: *board,
+			game_number: 0,
+			white: "White".to_string(),
+			black: "Black".to_string(),
+			relation: Relation::Isolated,
+			initial_time: 0,
+			increment: 0,
+			white_material: material(board, Color::White),
+			black_material: material(board, Color::Black),
+			white_clock: 0,
+			black_clock: 0,
+			last_move: None,
+			last_move_time: 0,
+			last_move_san: None,
+			flipped: false,
+			clock_ticking: None,
+			lag: None,
+		}
+	}
+
+	pub fn parse(line: &str) -> Result<Self, ChessError> {
+		let invalid = |reason: &str| ChessError::InvalidStyle12(format!("{}: {}", reason, line.trim()));
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		if fields.first() != Some(&"<12>") {
+			return Err(invalid("expected \"<12>\" first"));
+		}
+		if !matches!(fields.len(), 31..=33) {
+			return Err(invalid("expected 31 to 33 fields"));
+		}
+
+		let mut placement = Vec::new();
+		for rank in &fields[1..9] {
+			if rank.chars().count() != 8 || !rank.chars().all(|chr| chr == '-' || Piece::from_fen_char(chr).is_some()) {
+				return Err(invalid("invalid rank"));
+			}
+			// Runs of empty squares become their count
+			let mut fen = String::new();
+			let mut empty = 0;
+			for chr in rank.chars() {
+				if chr == '-' {
+					empty += 1;
+					continue;
+				}
+				if empty > 0 {
+					fen.push_str(&empty.to_string());
+					empty = 0;
+				}
+				fen.push(chr);
+			}
+			if empty > 0 {
+				fen.push_str(&empty.to_string());
+			}
+			placement.push(fen);
+		}
+
+		let turn = match fields[9] {
+			"W" => Color::White,
+			"B" => Color::Black,
+			_ => return Err(invalid("invalid side to move")),
+		};
+		let number = |index: usize| fields[index].parse::<i64>().map_err(|_| invalid("invalid number"));
+		let flag = |index: usize| match fields[index] {
+			"0" => Ok(false),
+			"1" => Ok(true),
+			_ => Err(invalid("invalid flag")),
+		};
+		let count = |index: usize| fields[index].parse::<u32>().map_err(|_| invalid("invalid number"));
+
+		let en_passant = match number(10)? {
+			-1 => "-".to_string(),
+			file @ 0..=7 => {
+				let row = match turn {
+					Color::White => 2,
+					Color::Black => 5,
+				};
+				coord!(row, file as i8).to_string()
+			}
+			_ => return Err(invalid("invalid double pawn push file")),
+		};
+		let mut castling: String = ["K", "Q", "k", "q"]
+		.iter()
+		.enumerate()
+		.map(|(index, right)| flag(11 + index).map(|set| if set { *right } else { "" }))
+		.collect::<Result<String, ChessError>>()?;
+		if castling.is_empty() {
+			castling = "-".to_string();
+		}
+		let turn_letter = match turn {
+			Color::White => "w",
+			Color::Black => "b",
+		};
+		let fen = format!("{} {} {} {} {} {}", placement.join("/"), turn_letter, castling, en_passant, count(15)?, count(26)?);
+		let board = Board::from_fen(&fen)?;
+
+		let relation = fields[19].parse().ok().and_then(Relation::from_code).ok_or_else(|| invalid("invalid relation"))?;
+		return Ok(Style12 {
+			board,
+			game_number: count(16)?,
+			white: fields[17].to_string(),
+			black: fields[18].to_string(),
+			relation,
+			initial_time: count(20)?,
+			increment: count(21)?,
+			white_material: count(22)?,
+			black_material: count(23)?,
+			white_clock: number(24)?,
+			black_clock: number(25)?,
+			last_move: optional_move(fields[27]),
+			last_move_time: move_time(fields[28]).ok_or_else(|| invalid("invalid move time"))?,
+			last_move_san: optional_move(fields[29]),
+			flipped: flag(30)?,
+			clock_ticking: (fields.len() > 31).then(|| flag(31)).transpose()?,
+			lag: (fields.len() > 32).then(|| count(32)).transpose()?,
+		});
+	}
+}
+
+impl fmt::Display for Style12 {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "<12>")?;
+		for row in 0..8 {
+			write!(f, " ")?;
+			for col in 0..8 {
+				match self.board.get_piece(coord!(row, col)) {
+					Some(piece) => write!(f, "{}", piece.to_fen_char())?,
+					None => write!(f, "-")?,
+				}
+			}
+		}
+
+		let turn = match self.board.turn {
+			Color::White => "W",
+			Color::Black => "B",
+		};
+		let file = self.board.en_passant_target_sq.map_or(-1, |square| Coordinate::from_number(square).col);
+		write!(f, " {} {}", turn, file)?;
+		for right in self.board.castling_rights {
+			write!(f, " {}", right as u8)?;
+		}
+		write!(f, " {} {} {} {} {}", self.board.halfmove_clock(), self.game_number, self.white, self.black, self.relation.code())?;
+		write!(f, " {} {} {} {}", self.initial_time, self.increment, self.white_material, self.black_material)?;
+		write!(f, " {} {} {}", self.white_clock, self.black_clock, self.board.fullmove_number())?;
+
+		let seconds = self.last_move_time / 1000;
+		let millis = match self.last_move_time % 1000 {
+			0 => String::new(),
+			millis => format!(".{:03}", millis),
+		};
+		write!(f, " {} ({}:{:02}{}) {}", self.last_move.as_deref().unwrap_or("none"), seconds / 60, seconds % 60, millis, self.last_move_san.as_deref().unwrap_or("none"))?;
+		write!(f, " {}", self.flipped as u8)?;
+		if let Some(ticking) = self.clock_ticking {
+			write!(f, " {}", ticking as u8)?;
+		}
+		if let Some(lag) = self.lag {
+			write!(f, " {}", lag)?;
+		}
+		return Ok(());
+	}
+}
@@ -0,0 +1,107 @@
+// Explicit returns are the house style
+#![allow(clippy::needless_return)]
+
+// Macro to expand coord!(x, y) to Coordinate { row: x, col: y }
+macro_rules! coord {
+	($x:expr, $y:expr) => {
+		Coordinate { row: $x, col: $y }
+	};
+}
+
+// Macro to expand piece!(piece, color) to Piece { breed: Pieces::piece, color: Color::color }
+macro_rules! piece {
+	($piece:ident, $color:ident) => {
+		Piece {
+			breed: Pieces::$piece,
+			color: Color::$color,
+		}
+	};
+}
+
+// Tracing spans and events, they compile to nothing unless the `tracing` feature is on
+macro_rules! trace_span {
+	($($arg:tt)*) => {
+		#[cfg(feature = "tracing")]
+		let _span = tracing::span!($($arg)*).entered();
+	};
+}
+
+macro_rules! trace_event {
+	($($arg:tt)*) => {
+		#[cfg(feature = "tracing")]
+		tracing::event!($($arg)*);
+	};
+}
+
+pub mod accuracy;
+pub mod analysis;
+pub mod arena;
+pub mod batch;
+pub mod book;
+pub mod bughouse;
+pub mod bulk;
+pub mod calibration;
+pub mod chess;
+pub mod complexity;
+pub mod control;
+pub mod correspondence;
+pub mod crosstable;
+pub mod decision;
+pub mod diagram;
+pub mod drops;
+pub mod engine;
+pub mod error;
+pub mod endgame;
+pub mod eval;
+pub mod features;
+pub mod fog;
+pub mod fuzz;
+pub mod game;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hint;
+pub mod ics;
+pub mod match_runner;
+pub mod mcts;
+pub mod mobility;
+pub mod moments;
+pub mod notation;
+pub mod opening;
+pub mod odds;
+pub mod options;
+pub mod packed;
+pub mod pawns;
+pub mod perft;
+pub mod phase;
+pub mod pgn;
+pub mod position;
+pub mod promotion;
+pub mod puzzle;
+pub mod rating;
+pub mod render;
+pub mod repertoire;
+pub mod report;
+pub mod rng;
+pub mod score;
+pub mod search;
+pub mod see;
+pub mod selfplay;
+pub mod space;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod skill;
+pub mod tactics;
+pub mod team;
+pub mod time;
+pub mod training;
+pub mod tt;
+pub mod tui;
+pub mod uci;
+pub mod zobrist;
+
+pub use crate::chess::*;
+pub use crate::error::ChessError;
+
+#[cfg(test)]
+#[path = "./tests.rs"]
+mod tests;
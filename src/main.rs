@@ -1,25 +1,164 @@
-mod chess;
-use chess::*;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+use std::thread;
 
-// Macro to expand coord!(x, y) to Coordinate { row: x, col: y }
-macro_rules! coord {
-    ($x:expr, $y:expr) => {
-        Coordinate { row: $x, col: $y }
-    };
+use chess::endgame::EndgameEvaluator;
+use chess::eval::MaterialEvaluator;
+use chess::features::export_features;
+use chess::notation::ParseMode;
+use chess::pgn::read_pgn;
+use chess::perft::{bench, divide_parallel, perft_stats, BENCH_DEPTH};
+use chess::render::RenderTheme;
+use chess::search::AlphaBeta;
+use chess::tui::AnalysisScreen;
+use chess::uci::Uci;
+use chess::Board;
+
+const USAGE: &str = "usage:
+    chyes perft <depth> [fen] [--threads <n>] [--stats]
+    chyes bench [depth]
+    chyes uci
+    chyes analyze <pgn file>
+    chyes features <pgn file>
+    chyes serve [address]   (built with --features serve)
+    chyes grpc [address]    (built with --features grpc)";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn parse_depth(arg: Option<&String>, default: Option<u32>) -> u32 {
+    match arg {
+        Some(arg) => match arg.parse() {
+            Ok(depth) => depth,
+            Err(_) => fail(&format!("invalid depth: {}", arg)),
+        },
+        None => match default {
+            Some(depth) => depth,
+            None => fail("missing depth"),
+        },
+    }
 }
 
-// Macro to expand piece!(piece, color) to Piece { breed: Pieces::piece, color: Color::color }
-macro_rules! piece {
-    ($piece:ident, $color:ident) => {
-        Piece {
-            breed: Pieces::$piece,
-            color: Color::$color,
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // Breakdown of the last ply by move kind, counted on a single thread
+    let show_stats = match args.iter().position(|arg| arg == "--stats") {
+        Some(index) => {
+            args.remove(index);
+            true
         }
+        None => false,
     };
-}
 
-fn main() {}
+    // Perft runs on every core unless told otherwise
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if let Some(index) = args.iter().position(|arg| arg == "--threads") {
+        threads = match args.get(index + 1).map(|arg| arg.parse()) {
+            Some(Ok(threads)) if threads > 0 => threads,
+            _ => fail("invalid thread count"),
+        };
+        args.drain(index..index + 2);
+    }
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("perft") => {
+            let depth = parse_depth(args.get(1), None);
+            let board = match args.len() {
+                2 => Board::default(),
+                // The FEN may come as one quoted argument or split on spaces
+                _ => match Board::from_fen(&args[2..].join(" ")) {
+                    Ok(board) => board,
+                    Err(error) => fail(&error.to_string()),
+                },
+            };
+
+            let mut total = 0;
+            for (mv, nodes) in divide_parallel(&board, depth, threads) {
+                println!("{}: {}", mv, nodes);
+                total += nodes;
+            }
+            println!();
+            println!("Nodes searched: {}", total);
 
-#[cfg(test)]
-#[path = "./tests.rs"]
-mod tests;
+            if show_stats {
+                let stats = perft_stats(&board, depth);
+                println!("Captures      : {}", stats.captures);
+                println!("En passants   : {}", stats.en_passants);
+                println!("Castles       : {}", stats.castles);
+                println!("Promotions    : {}", stats.promotions);
+                println!("Checks        : {}", stats.checks);
+                println!("Checkmates    : {}", stats.checkmates);
+            }
+        }
+        Some("bench") => {
+            let report = bench(parse_depth(args.get(1), Some(BENCH_DEPTH)));
+            println!("Nodes searched: {}", report.stats.nodes);
+            println!("Beta cutoffs  : {}", report.stats.beta_cutoffs);
+            println!("Time (ms)     : {}", report.elapsed.as_millis());
+            println!("Nodes/second  : {}", report.nps());
+        }
+        Some("uci") => {
+            let mut uci = Uci::new(AlphaBeta::new(EndgameEvaluator::new(MaterialEvaluator)));
+            if let Err(error) = uci.run(io::BufReader::new(io::stdin()), io::stdout()) {
+                fail(&error.to_string());
+            }
+        }
+        Some("analyze") => {
+            let path = match args.get(1) {
+                Some(path) => path,
+                None => fail("missing pgn file"),
+            };
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(error) => fail(&format!("{}: {}", path, error)),
+            };
+            let pgn = match read_pgn(&text, ParseMode::Lenient) {
+                Ok(pgn) => pgn,
+                Err(error) => fail(&error.to_string()),
+            };
+
+            let engine = AlphaBeta::new(EndgameEvaluator::new(MaterialEvaluator));
+            let mut screen = AnalysisScreen::new(pgn.game, engine, RenderTheme::default());
+            if let Err(error) = screen.run(io::BufReader::new(io::stdin()), io::stdout()) {
+                fail(&error.to_string());
+            }
+        }
+        Some("features") => {
+            let path = match args.get(1) {
+                Some(path) => path,
+                None => fail("missing pgn file"),
+            };
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(error) => fail(&format!("{}: {}", path, error)),
+            };
+            if let Err(error) = export_features(&text, ParseMode::Lenient, &mut io::BufWriter::new(io::stdout())) {
+                fail(&error.to_string());
+            }
+        }
+        #[cfg(feature = "serve")]
+        Some("serve") => {
+            let address = args.get(1).map_or(chess::serve::DEFAULT_ADDRESS, |address| address.as_str());
+            eprintln!("listening on {}", address);
+            if let Err(error) = chess::serve::serve(address) {
+                fail(&error.to_string());
+            }
+        }
+        #[cfg(feature = "grpc")]
+        Some("grpc") => {
+            let address = args.get(1).map_or(chess::grpc::DEFAULT_ADDRESS, |address| address.as_str());
+            eprintln!("listening on {}", address);
+            if let Err(error) = chess::grpc::serve(address) {
+                fail(&error.to_string());
+            }
+        }
+        Some(command) => fail(&format!("unknown command: {}", command)),
+        None => fail("missing command"),
+    }
+}
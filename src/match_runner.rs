@@ -0,0 +1,369 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::game::{Game, GameResult, MaterialRule, TerminationReason};
+use crate::pgn::{PgnAnnotation, PgnOptions, PgnWriter};
+use crate::score::{match_score, Score};
+
+pub use crate::game::Outcome;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Reason {
+	Checkmate,
+	Stalemate,
+	InsufficientMaterial,
+	DeadPosition,
+	SeventyFiveMoveRule,
+	FivefoldRepetition,
+	MoveLimit,
+	// The engine used more time than it had on the clock, a draw when the opponent can't mate
+	TimeForfeit,
+	DrawAdjudication,
+	ResignAdjudication,
+	TablebaseAdjudication,
+}
+
+// Win/draw/loss from the point of view of the side to move
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Wdl {
+	Win,
+	Draw,
+	Loss,
+}
+
+// Anything that knows the result of small endgames
+pub trait Tablebase {
+	// Positions with more pieces (kings included) are never probed
+	fn max_pieces(&self) -> usize;
+	fn probe_wdl(&self, board: &Board) -> Option<Wdl>;
+}
+
+// Draw when both engines keep their score within `score` of zero for `moves` moves each,
+// starting from move number `from_move`, 0 moves turns the rule off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawAdjudication {
+	pub score: i32,
+	pub moves: u32,
+	pub from_move: u32,
+}
+
+// Loss for an engine whose score stays at or below -`score` for `moves` of its moves in a row,
+// 0 moves turns the rule off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResignAdjudication {
+	pub score: i32,
+	pub moves: u32,
+}
+
+// Every rule is off by default, the draws the rules of chess make automatic (see Game) don't
+// need one
+#[derive(Default)]
+pub struct Adjudication {
+	pub draw: Option<DrawAdjudication>,
+	pub resign: Option<ResignAdjudication>,
+	pub tablebase: Option<Box<dyn Tablebase>>,
+	pub insufficient_material: Option<MaterialRule>,
+}
+
+pub struct MatchConfig {
+	pub games: usize,
+	// A clock in the limits is run for real, the engines get the time they have left
+	// NOTE: moves_to_go is passed on as it is, only sudden death and increments are counted
+	pub limits: SearchLimits,
+	// Games still going after this many plies are drawn
+	pub max_plies: u32,
+	pub start_fen: String,
+	pub adjudication: Adjudication,
+}
+
+impl Default for MatchConfig {
+	fn default() -> Self {
+		MatchConfig {
+			games: 2,
+			limits: SearchLimits::depth(2),
+			max_plies: 400,
+			start_fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+			adjudication: Adjudication::default(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+	pub start_fen: String,
+	pub moves: Vec<Move>,
+	// Score reported by the engine after each move, if it had one
+	pub scores: Vec<Option<i32>>,
+	// Time left on the mover's clock after each move, when the game was played on a clock
+	pub clocks: Vec<Option<Duration>>,
+	pub outcome: Outcome,
+	pub reason: Reason,
+}
+
+impl GameRecord {
+	pub fn game(&self) -> Result<Game, ChessError> {
+		// The game replayed, a result the board doesn't show comes from the flag of the side to
+		// move or from the match runner
+		let mut game = Game::from_fen(&self.start_fen)?;
+		for mv in &self.moves {
+			game.make_move(*mv)?;
+		}
+		if game.termination().is_some() {
+			return Ok(game);
+		}
+
+		if self.reason == Reason::TimeForfeit {
+			let color = game.board().turn;
+			game.flag(color)?;
+		} else {
+			// Adjudications and the move limit
+			game.end(self.outcome, TerminationReason::Adjudication)?;
+		}
+		return Ok(game);
+	}
+
+	pub fn writer(&self, options: PgnOptions) -> Result<PgnWriter, ChessError> {
+		// A writer with a [%eval] and a [%clk] for every move that has them, the tags are left
+		// to the caller
+		let mut writer = PgnWriter::new(options);
+		let mut turn = Board::from_fen(&self.start_fen)?.turn;
+		for ply in 0..self.moves.len() {
+			// Engines score for the side that moved, PGN from white's point of view
+			let score = self.scores.get(ply).copied().flatten();
+			let eval = match turn {
+				Color::White => score,
+				Color::Black => score.map(|score| -score),
+			};
+			let clock = self.clocks.get(ply).copied().flatten();
+			if eval.is_some() || clock.is_some() {
+				writer.annotate(ply, PgnAnnotation { eval, clock, ..PgnAnnotation::default() });
+			}
+			turn = opponent(turn);
+		}
+		return Ok(writer);
+	}
+
+	pub fn to_pgn(&self, options: PgnOptions) -> Result<String, ChessError> {
+		return Ok(self.writer(options)?.write(&self.game()?));
+	}
+}
+
+// Results are counted from the point of view of the first engine
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchReport {
+	pub games: Vec<GameRecord>,
+	pub wins: usize,
+	pub losses: usize,
+	pub draws: usize,
+}
+
+impl MatchReport {
+	pub fn score(&self) -> Score {
+		// Points of the first engine
+		return Score::from_half_points((self.wins * 2 + self.draws) as u32);
+	}
+
+	pub fn opponent_score(&self) -> Score {
+		return Score::from_half_points((self.losses * 2 + self.draws) as u32);
+	}
+}
+
+impl fmt::Display for MatchReport {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// "2.5-1.5 (+2 =1 -1)"
+		return write!(f, "{} (+{} ={} -{})", match_score(self.score(), self.opponent_score()), self.wins, self.draws, self.losses);
+	}
+}
+
+fn winner(color: Color) -> Outcome {
+	return match color {
+		Color::White => Outcome::WhiteWins,
+		Color::Black => Outcome::BlackWins,
+	};
+}
+
+fn opponent(color: Color) -> Color {
+	return match color {
+		Color::White => Color::Black,
+		Color::Black => Color::White,
+	};
+}
+
+fn rule_reason(reason: TerminationReason) -> Reason {
+	// The ends Game comes to on its own, nobody claims, resigns or flags in play_game
+	return match reason {
+		TerminationReason::Checkmate => Reason::Checkmate,
+		TerminationReason::Stalemate => Reason::Stalemate,
+		TerminationReason::DrawRule(GameResult::InsufficientMaterial) => Reason::InsufficientMaterial,
+		TerminationReason::DrawRule(GameResult::DeadPosition) => Reason::DeadPosition,
+		TerminationReason::DrawRule(GameResult::SeventyFiveMoveRule) => Reason::SeventyFiveMoveRule,
+		TerminationReason::DrawRule(GameResult::FivefoldRepetition) => Reason::FivefoldRepetition,
+		reason => unreachable!("{:?} doesn't end a game on its own", reason),
+	};
+}
+
+// Keeps the running counters the adjudication rules need
+struct Adjudicator<'a> {
+	rules: &'a Adjudication,
+	draw_plies: u32,
+	// Indexed by color, white first
+	resign_moves: [u32; 2],
+}
+
+impl Adjudicator<'_> {
+	fn after_move(&mut self, board: &Board, mover: Color, score: Option<i32>, ply: u32) -> Option<(Outcome, Reason)> {
+		// Called after every move, `board` is the position after it
+		if let Some(rule) = self.rules.resign.filter(|rule| rule.moves > 0) {
+			let counter = &mut self.resign_moves[mover as usize];
+			match score {
+				Some(score) if score <= -rule.score => *counter += 1,
+				_ => *counter = 0,
+			}
+
+			if *counter >= rule.moves {
+				return Some((winner(opponent(mover)), Reason::ResignAdjudication));
+			}
+		}
+
+		if let Some(rule) = self.rules.draw.filter(|rule| rule.moves > 0) {
+			let move_number = ply / 2 + 1;
+			match score {
+				Some(score) if score.abs() <= rule.score && move_number >= rule.from_move => self.draw_plies += 1,
+				_ => self.draw_plies = 0,
+			}
+
+			if self.draw_plies >= rule.moves * 2 {
+				return Some((Outcome::Draw, Reason::DrawAdjudication));
+			}
+		}
+
+		if let Some(rule) = self.rules.insufficient_material {
+			if board.is_insufficient_material(rule) {
+				return Some((Outcome::Draw, Reason::InsufficientMaterial));
+			}
+		}
+
+		if let Some(tablebase) = &self.rules.tablebase {
+			let pieces = board.white_pieces.len() + board.black_pieces.len();
+			if pieces <= tablebase.max_pieces() {
+				let result = match tablebase.probe_wdl(board) {
+					Some(Wdl::Win) => Some(winner(board.turn)),
+					Some(Wdl::Loss) => Some(winner(opponent(board.turn))),
+					Some(Wdl::Draw) => Some(Outcome::Draw),
+					None => None,
+				};
+
+				if let Some(result) = result {
+					return Some((result, Reason::TablebaseAdjudication));
+				}
+			}
+		}
+
+		return None;
+	}
+}
+
+pub fn play_game(white: &mut dyn Engine, black: &mut dyn Engine, config: &MatchConfig) -> Result<GameRecord, ChessError> {
+	return play_game_with(white, black, config, config.limits.clone());
+}
+
+pub(crate) fn play_game_with(white: &mut dyn Engine, black: &mut dyn Engine, config: &MatchConfig, mut limits: SearchLimits) -> Result<GameRecord, ChessError> {
+	// play_game with other limits than the config's, the arena cuts the clock of berserk players
+	trace_span!(tracing::Level::INFO, "play_game", start_fen = %config.start_fen);
+	// The game ends the way Game ends it, so the record always replays
+	let mut game = Game::from_fen(&config.start_fen)?;
+
+	let mut adjudicator = Adjudicator {
+		rules: &config.adjudication,
+		draw_plies: 0,
+		resign_moves: [0, 0],
+	};
+
+	let mut record = GameRecord {
+		start_fen: config.start_fen.clone(),
+		moves: Vec::new(),
+		scores: Vec::new(),
+		clocks: Vec::new(),
+		outcome: Outcome::Draw,
+		reason: Reason::MoveLimit,
+	};
+
+	for ply in 0..=config.max_plies {
+		if let Some((outcome, reason)) = game.termination() {
+			record.outcome = outcome;
+			record.reason = rule_reason(reason);
+			return Ok(record);
+		}
+		if ply == config.max_plies {
+			break;
+		}
+
+		let mover = game.board().turn;
+		let engine: &mut dyn Engine = match mover {
+			Color::White => white,
+			Color::Black => black,
+		};
+		// The search knows the positions it can't repeat without drawing
+		limits.history = game.search_history();
+		let start = Instant::now();
+		let mv = engine.choose_move(game.board(), &limits)?;
+		let elapsed = start.elapsed();
+		let score = engine.score();
+
+		let mut left = None;
+		if let Some(clock) = limits.clock.as_mut() {
+			let time = clock.time[mover as usize];
+			if elapsed > time {
+				// A loss unless the opponent can't mate, Game decides which
+				game.flag(mover)?;
+				record.outcome = game.termination().map_or(Outcome::Draw, |(outcome, _)| outcome);
+				record.reason = Reason::TimeForfeit;
+				return Ok(record);
+			}
+			clock.time[mover as usize] = time - elapsed + clock.increment[mover as usize];
+			left = Some(clock.time[mover as usize]);
+		}
+
+		game.make_move(mv)?;
+		record.moves.push(mv);
+		record.scores.push(score);
+		record.clocks.push(left);
+
+		trace_event!(tracing::Level::DEBUG, ply, mv = %mv, score = ?score, "move played");
+
+		// A move that ended the game is left to the check above
+		if let (None, Some((outcome, reason))) = (game.termination(), adjudicator.after_move(game.board(), mover, score, ply)) {
+			trace_event!(tracing::Level::INFO, outcome = ?outcome, reason = ?reason, "game adjudicated");
+			record.outcome = outcome;
+			record.reason = reason;
+			return Ok(record);
+		}
+	}
+
+	return Ok(record);
+}
+
+pub fn run_match(first: &mut dyn Engine, second: &mut dyn Engine, config: &MatchConfig) -> Result<MatchReport, ChessError> {
+	// The engines swap colors every game, the first engine starts with white
+	let mut report = MatchReport::default();
+
+	for game in 0..config.games {
+		let first_color = if game % 2 == 0 { Color::White } else { Color::Black };
+		let record = match first_color {
+			Color::White => play_game(first, second, config)?,
+			Color::Black => play_game(second, first, config)?,
+		};
+
+		match record.outcome {
+			Outcome::Draw => report.draws += 1,
+			outcome if outcome == winner(first_color) => report.wins += 1,
+			_ => report.losses += 1,
+		}
+		report.games.push(record);
+	}
+
+	return Ok(report);
+}
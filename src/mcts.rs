@@ -0,0 +1,229 @@
+use std::time::Instant;
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::eval::Evaluator;
+use crate::options::{EngineOptions, UciOption};
+use crate::rng::{Rng, Seeded};
+use crate::time::TimeBudget;
+
+// Iterations used when the limits don't say anything
+pub const DEFAULT_ITERATIONS: u64 = 200;
+
+// How a freshly expanded position is scored
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Playout {
+	// Random moves up to the playout depth, then the evaluator scores what is left
+	Random,
+	// The evaluator scores the position straight away
+	Evaluation,
+}
+
+struct Node {
+	mv: Option<Move>,
+	parent: Option<usize>,
+	children: Vec<usize>,
+	untried: Vec<Move>,
+	visits: u32,
+	// Sum of results for the side that made the move leading here, 1 is a win
+	reward: f64,
+}
+
+// Monte Carlo Tree Search with the UCT selection rule
+// source: https://en.wikipedia.org/wiki/Monte_Carlo_tree_search
+pub struct Mcts<E: Evaluator> {
+	pub evaluator: E,
+	pub playout: Playout,
+	pub exploration: f64,
+	pub playout_depth: u32,
+	pub options: EngineOptions,
+	rng: Rng,
+}
+
+pub fn win_probability(score: i32) -> f64 {
+	// Centipawns to the chance of winning, 400 centipawns is 10 to 1
+	return 1.0 / (1.0 + 10f64.powf(-score as f64 / 400.0));
+}
+
+impl<E: Evaluator> Mcts<E> {
+	pub fn new(evaluator: E, playout: Playout) -> Self {
+		Mcts {
+			evaluator,
+			playout,
+			exploration: std::f64::consts::SQRT_2,
+			playout_depth: 16,
+			options: EngineOptions::default(),
+			rng: Rng::from_time(),
+		}
+	}
+
+	fn uct(&self, parent_visits: u32, node: &Node) -> f64 {
+		let mean = node.reward / node.visits as f64;
+		return mean + self.exploration * ((parent_visits as f64).ln() / node.visits as f64).sqrt();
+	}
+
+	fn terminal_value(board: &Board) -> f64 {
+		// Value of a position without moves for the side to move
+		if board.is_in_check(board.turn) {
+			return 0.0;
+		}
+		return 0.5;
+	}
+
+	fn simulate(&mut self, mut board: Board, mut moves: Vec<Move>) -> f64 {
+		// Returns the result for the side to move in the given position
+		let side = board.turn;
+
+		if self.playout == Playout::Random {
+			for _ in 0..self.playout_depth {
+				if moves.is_empty() {
+					break;
+				}
+				board.make_move(moves[self.rng.below(moves.len())]);
+				moves = board.legal_moves();
+			}
+		}
+
+		let value = if moves.is_empty() {
+			Self::terminal_value(&board)
+		} else {
+			win_probability(self.evaluator.evaluate(&board))
+		};
+
+		return if board.turn == side { value } else { 1.0 - value };
+	}
+
+	pub fn search(&mut self, board: &Board, limits: &SearchLimits) -> Option<Move> {
+		trace_span!(tracing::Level::DEBUG, "mcts", nodes = ?limits.nodes, movetime = ?limits.movetime);
+		let mut start = Instant::now();
+		let mut pondering = limits.control.is_pondering();
+		// Iterations are cheap and all alike, so the search simply runs until the soft limit
+		let movetime = TimeBudget::new(limits, board.turn, self.options.move_overhead).map(|budget| budget.soft);
+		let iterations = match (limits.nodes, movetime) {
+			(Some(nodes), _) => nodes,
+			(None, Some(_)) => u64::MAX,
+			(None, None) => DEFAULT_ITERATIONS,
+		};
+
+		let mut tree: Vec<Node> = vec![Node {
+			mv: None,
+			parent: None,
+			children: Vec::new(),
+			untried: limits.root_moves(board),
+			visits: 0,
+			reward: 0.0,
+		}];
+
+		if tree[0].untried.is_empty() {
+			return None;
+		}
+
+		let mut iteration: u64 = 0;
+		loop {
+			// The first iteration always runs, so there is a move to give back
+			if iteration > 0 && limits.control.is_stopped() {
+				break;
+			}
+
+			if pondering && !limits.control.is_pondering() {
+				// Ponderhit, the time counts from now on
+				pondering = false;
+				start = Instant::now();
+			}
+
+			if !pondering {
+				if iteration >= iterations {
+					break;
+				}
+				if let Some(movetime) = movetime {
+					if iteration > 0 && start.elapsed() >= movetime {
+						break;
+					}
+				}
+			}
+			iteration += 1;
+
+			// Selection: walk down through fully expanded nodes
+			let mut node = 0;
+			let mut position = *board;
+			while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+				let parent_visits = tree[node].visits;
+				node = *tree[node]
+				.children
+				.iter()
+				.max_by(|a, b| {
+					self.uct(parent_visits, &tree[**a])
+					.total_cmp(&self.uct(parent_visits, &tree[**b]))
+				})
+				.unwrap();
+				position.make_move(tree[node].mv.unwrap());
+			}
+
+			// Expansion: add one of the moves that was not tried yet
+			let moves;
+			if tree[node].untried.is_empty() {
+				moves = Vec::new();
+			} else {
+				let index = self.rng.below(tree[node].untried.len());
+				let mv = tree[node].untried.swap_remove(index);
+				position.make_move(mv);
+				moves = position.legal_moves();
+
+				tree.push(Node {
+					mv: Some(mv),
+					parent: Some(node),
+					children: Vec::new(),
+					untried: moves.clone(),
+					visits: 0,
+					reward: 0.0,
+				});
+				let child = tree.len() - 1;
+				tree[node].children.push(child);
+				node = child;
+			}
+
+			// Simulation, the reward belongs to the side that moved into the node
+			let mut reward = 1.0 - self.simulate(position, moves);
+
+			// Backpropagation, flipping the point of view at every level
+			let mut current = Some(node);
+			while let Some(index) = current {
+				tree[index].visits += 1;
+				tree[index].reward += reward;
+				reward = 1.0 - reward;
+				current = tree[index].parent;
+			}
+		}
+
+		trace_event!(tracing::Level::DEBUG, iterations = tree[0].visits, tree_size = tree.len(), "mcts finished");
+
+		// The most visited move is the most trusted one
+		return tree[0]
+		.children
+		.iter()
+		.max_by_key(|child| tree[**child].visits)
+		.and_then(|child| tree[*child].mv);
+	}
+}
+
+impl<E: Evaluator> Seeded for Mcts<E> {
+	fn rng(&mut self) -> &mut Rng {
+		return &mut self.rng;
+	}
+}
+
+impl<E: Evaluator> Engine for Mcts<E> {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+		return self.search(board, limits).ok_or(ChessError::NoLegalMoves);
+	}
+
+	fn options(&self) -> Vec<UciOption> {
+		return self.evaluator.options();
+	}
+
+	fn set_options(&mut self, options: &EngineOptions) {
+		self.options = options.clone();
+		self.evaluator.set_options(options);
+	}
+}
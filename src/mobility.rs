@@ -0,0 +1,53 @@
+use crate::chess::*;
+
+// How freely the pieces of one side can move
+/*
+moves            - legal moves, as if it was that side's turn
+by_piece         - the same moves by the kind of piece making them, in the order of Pieces
+attacked_squares - squares the side attacks, its own pieces included
+defended_pieces  - own pieces the side attacks, so it could take back on them
+*/
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MobilityStats {
+	pub moves: usize,
+	pub by_piece: [usize; 6],
+	pub attacked_squares: u32,
+	pub defended_pieces: u32,
+}
+
+impl MobilityStats {
+	pub fn moves_of(&self, breed: Pieces) -> usize {
+		return self.by_piece[breed as usize];
+	}
+}
+
+impl Board {
+	pub fn mobility(&self, color: Color) -> MobilityStats {
+		// Moves are counted for `color` whoever is to move, without an en passant capture then
+		let mut board = *self;
+		if board.turn != color {
+			board.turn = color;
+			board.en_passant_target_sq = None;
+		}
+
+		let mut stats = MobilityStats::default();
+		for mv in board.legal_moves() {
+			if let Some(piece) = board.get_piece(mv.from) {
+				stats.moves += 1;
+				stats.by_piece[piece.breed as usize] += 1;
+			}
+		}
+
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		let own: u64 = pieces.keys().fold(0, |bits, num| bits | 1 << num);
+		let attacked = self.attacked_squares(color);
+		stats.attacked_squares = attacked.count_ones();
+		stats.defended_pieces = (attacked & own).count_ones();
+
+		return stats;
+	}
+}
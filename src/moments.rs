@@ -0,0 +1,184 @@
+use crate::accuracy::WinModel;
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::game::{Game, Outcome};
+use crate::search::MATE_SCORE;
+
+// The moments a game turned, for reports and highlights
+/*
+Every position of the game is evaluated and put in somebody's hands by white's winning
+chances: White's above WINNING, Black's below LOSING, nobody's in between. Then:
+Swing            - a move after which the position is in other hands
+Decisive mistake - the loser's move after which the position stayed in the winner's hands
+                   until the end, the position before it was the last chance to hold
+Missed win       - the last move that let a winning position go, for a player who didn't win
+
+The winner is the one of the game's result, or the one the last position belongs to when the
+game has no result. A game won on the clock or by resignation in a position that isn't lost
+has no decisive mistake
+*/
+
+// White's winning chances in percent
+pub const WINNING: f64 = 70.0;
+pub const LOSING: f64 = 30.0;
+
+// Who the game belongs to, by white's winning chances
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Advantage {
+	White,
+	Equal,
+	Black,
+}
+
+impl Advantage {
+	pub fn from_percent(white: f64) -> Self {
+		return match white {
+			white if white > WINNING => Advantage::White,
+			white if white < LOSING => Advantage::Black,
+			_ => Advantage::Equal,
+		};
+	}
+
+	pub fn of(color: Color) -> Self {
+		return match color {
+			Color::White => Advantage::White,
+			Color::Black => Advantage::Black,
+		};
+	}
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum MomentKind {
+	Swing { from: Advantage, to: Advantage },
+	DecisiveMistake,
+	MissedWin,
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct KeyMoment {
+	// The move of the moment, the first move of the game is ply 0
+	pub ply: usize,
+	pub color: Color,
+	pub kind: MomentKind,
+	// Centipawns from white's point of view, of the positions before and after the move
+	pub before: i32,
+	pub after: i32,
+	// What the engine wanted to play instead, when it wanted something else
+	pub best: Option<Move>,
+}
+
+impl KeyMoment {
+	pub fn description(&self) -> &'static str {
+		return match self.kind {
+			MomentKind::Swing { to: Advantage::White, .. } => "White takes over",
+			MomentKind::Swing { to: Advantage::Black, .. } => "Black takes over",
+			MomentKind::Swing { to: Advantage::Equal, .. } => "the game is level again",
+			MomentKind::DecisiveMistake => "the decisive mistake",
+			MomentKind::MissedWin => "the win slips away",
+		};
+	}
+}
+
+pub fn key_moments(game: &Game, evals: &[(i32, Option<Move>)], model: &WinModel) -> Vec<KeyMoment> {
+	// `evals` has the score for the side to move and the best move of every position of the
+	// game, the moves without the positions on both sides evaluated are left out
+	let boards: Vec<Board> = game.positions().take(evals.len()).collect();
+	let white: Vec<i32> = boards
+	.iter()
+	.zip(evals)
+	.map(|(board, (score, _))| match board.turn {
+		Color::White => *score,
+		Color::Black => -*score,
+	})
+	.collect();
+	let hands: Vec<Advantage> = white.iter().map(|eval| Advantage::from_percent(model.win_percent(*eval))).collect();
+	if hands.len() < 2 {
+		return Vec::new();
+	}
+
+	let moment = |ply: usize, kind: MomentKind| {
+		let mv = game.moves()[ply];
+		return KeyMoment {
+			ply,
+			color: boards[ply].turn,
+			kind,
+			before: white[ply],
+			after: white[ply + 1],
+			best: evals[ply].1.filter(|best| *best != mv),
+		};
+	};
+
+	let mut moments = Vec::new();
+	for ply in 0..hands.len() - 1 {
+		if hands[ply] != hands[ply + 1] {
+			moments.push(moment(ply, MomentKind::Swing { from: hands[ply], to: hands[ply + 1] }));
+		}
+	}
+
+	let last = *hands.last().unwrap();
+	let result = game.termination().map(|(outcome, _)| outcome).or(game.game_result().map(|result| result.outcome()));
+	let winner = match result {
+		Some(Outcome::WhiteWins) => Some(Color::White),
+		Some(Outcome::BlackWins) => Some(Color::Black),
+		Some(Outcome::Draw) => None,
+		None => match last {
+			Advantage::White => Some(Color::White),
+			Advantage::Black => Some(Color::Black),
+			Advantage::Equal => None,
+		},
+	};
+
+	if let Some(winner) = winner {
+		// The last position out of the winner's hands, the loser's move after it (or before it,
+		// when the winner's own move took the position over) decided the game
+		let won = Advantage::of(winner);
+		if let Some(position) = hands.iter().rposition(|hand| *hand != won).filter(|position| *position < hands.len() - 1) {
+			let ply = match boards[position].turn == winner {
+				true => position.checked_sub(1),
+				false => Some(position),
+			};
+			if let Some(ply) = ply {
+				moments.push(moment(ply, MomentKind::DecisiveMistake));
+			}
+		}
+	}
+
+	for color in [Color::White, Color::Black] {
+		if winner == Some(color) {
+			continue;
+		}
+		let missed = (0..hands.len() - 1).rev().find(|ply| boards[*ply].turn == color && hands[*ply] == Advantage::of(color) && hands[ply + 1] != Advantage::of(color));
+		if let Some(ply) = missed {
+			moments.push(moment(ply, MomentKind::MissedWin));
+		}
+	}
+
+	moments.sort_by_key(|moment| moment.ply);
+	return moments;
+}
+
+impl Game {
+	pub fn evaluate(&self, engine: &mut impl Engine, limits: &SearchLimits) -> Result<Vec<(i32, Option<Move>)>, ChessError> {
+		// The engine's score for the side to move and choice in every position of the game,
+		// checkmates and stalemates are scored without asking it
+		let mut evals = Vec::new();
+		for board in self.positions() {
+			let eval = match board.legal_moves().is_empty() {
+				true if board.is_in_check(board.turn) => (-MATE_SCORE, None),
+				true => (0, None),
+				false => {
+					let mv = engine.choose_move(&board, limits)?;
+					(engine.score().unwrap_or(0), Some(mv))
+				}
+			};
+			evals.push(eval);
+		}
+		return Ok(evals);
+	}
+
+	pub fn key_moments(&self, engine: &mut impl Engine, limits: &SearchLimits) -> Result<Vec<KeyMoment>, ChessError> {
+		// See above, with the Lichess win model
+		return Ok(key_moments(self, &self.evaluate(engine, limits)?, &WinModel::default()));
+	}
+}
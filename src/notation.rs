@@ -0,0 +1,357 @@
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::game::Game;
+
+// Supported ways of writing a move down
+/*
+San        - standard algebraic notation: Nf3, exd5, e8=Q+, O-O
+Lan        - long algebraic notation: Ng1-f3, e4xd5, e7-e8=Q+, O-O
+Iccf       - ICCF numeric notation: 7163, 5445, 57581
+Smith      - both squares, then the captured piece, c or C for castling king or queen side, E
+             for en passant and the promotion piece: g1f3, e4d5p, e1g1c, e5d6E, b7a8rQ
+Coordinate - both squares and the promotion piece, the way UCI writes it: g1f3, e1g1, b7a8q
+
+Move logs are games written one move per line in any of them, like engine debugging tools and
+ICS logs keep them
+*/
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Notation {
+	San,
+	Lan,
+	Iccf,
+	Smith,
+	Coordinate,
+}
+
+// How forgiving parse_with is about the way a move is written
+/*
+Strict  - the move as this crate writes it, check and annotation marks may be left out
+Lenient - also what turns up in scraped PGNs: 0-0 with zeros, "e.p." after en passant,
+          e8Q or e8(Q) promotions (a missing piece is a queen), missing or extra capture
+          marks, more disambiguation than needed, and Bxc6 for a b-pawn capture when no
+          bishop can make it. Smith and coordinate moves are read in both ways, with any case
+          and with "-" or "x" between the squares, a log can have move numbers before its moves
+*/
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum ParseMode {
+	#[default]
+	Strict,
+	Lenient,
+}
+
+fn piece_letter(breed: Pieces) -> Option<char> {
+	// Pawns go without a letter, the rest use their white FEN letter
+	if breed == Pieces::Pawn {
+		return None;
+	}
+	return Some(Piece::new(Color::White, breed).to_fen_char());
+}
+
+fn iccf_promotion_digit(breed: Pieces) -> Option<char> {
+	// ICCF writes the promotion piece as a fifth digit
+	use Pieces::*;
+	return match breed {
+		Queen => Some('1'),
+		Rook => Some('2'),
+		Bishop => Some('3'),
+		Knight => Some('4'),
+		_ => None,
+	};
+}
+
+fn iccf_square(coord: Coordinate) -> String {
+	// File and rank as digits, a1 is "11" and h8 is "88"
+	return format!("{}{}", coord.col + 1, 8 - coord.row);
+}
+
+fn smith_suffix(board: &Board, mv: Move, piece: Piece) -> String {
+	// Captured piece, castling or en passant, then the promotion piece
+	let mut suffix = String::new();
+	if let Some(captured) = board.get_piece(mv.to) {
+		suffix.push(captured.to_fen_char().to_ascii_lowercase());
+	} else if piece.breed == Pieces::Pawn && mv.from.col != mv.to.col {
+		suffix.push('E');
+	} else if piece.breed == Pieces::King && (mv.to.col - mv.from.col).abs() == 2 {
+		suffix.push(if mv.to.col > mv.from.col { 'c' } else { 'C' });
+	}
+	if let Some(letter) = mv.promotion.and_then(piece_letter) {
+		suffix.push(letter);
+	}
+	return suffix;
+}
+
+fn move_number_end(text: &str) -> usize {
+	// Length of a "12." or "12..." in front of a move, 0 without one
+	let digits = text.len() - text.trim_start_matches(|chr: char| chr.is_ascii_digit()).len();
+	let dots = text[digits..].len() - text[digits..].trim_start_matches('.').len();
+	return match digits > 0 && dots > 0 {
+		true => digits + dots,
+		false => 0,
+	};
+}
+
+// Check and annotation marks don't change the move itself
+fn strip_suffixes(text: &str) -> &str {
+	return text.trim().trim_end_matches(['+', '#', '!', '?']);
+}
+
+impl Notation {
+	pub fn format(&self, board: &Board, mv: Move) -> String {
+		// function to write down a move made from the given position
+		let piece = match board.get_piece(mv.from) {
+			Some(piece) => piece,
+			None => return String::new(),
+		};
+
+		match self {
+			Notation::Iccf => {
+				let mut result = iccf_square(mv.from) + &iccf_square(mv.to);
+				if let Some(digit) = mv.promotion.and_then(iccf_promotion_digit) {
+					result.push(digit);
+				}
+				return result;
+			}
+			Notation::Smith => return format!("{}{}{}", mv.from, mv.to, smith_suffix(board, mv, piece)),
+			Notation::Coordinate => return mv.to_string(),
+			Notation::San | Notation::Lan => (),
+		}
+
+		let mut result = String::new();
+
+		// A king moving two squares is castling
+		if piece.breed == Pieces::King && (mv.to.col - mv.from.col).abs() == 2 {
+			result.push_str(if mv.to.col > mv.from.col { "O-O" } else { "O-O-O" });
+		} else {
+			let is_capture = board.get_piece(mv.to).is_some()
+			|| (piece.breed == Pieces::Pawn && mv.from.col != mv.to.col);
+
+			if let Some(letter) = piece_letter(piece.breed) {
+				result.push(letter);
+			}
+
+			if *self == Notation::Lan {
+				result.push_str(&mv.from.to_string());
+				result.push(if is_capture { 'x' } else { '-' });
+			} else {
+				if piece.breed == Pieces::Pawn {
+					// Pawn captures are named by the file the pawn came from
+					if is_capture {
+						result.push_str(&mv.from.to_string()[..1]);
+					}
+				} else {
+					result.push_str(&Self::disambiguation(board, mv, piece));
+				}
+
+				if is_capture {
+					result.push('x');
+				}
+			}
+
+			result.push_str(&mv.to.to_string());
+
+			if let Some(letter) = mv.promotion.and_then(piece_letter) {
+				result.push('=');
+				result.push(letter);
+			}
+		}
+
+		// Check and mate marks
+		let mut after = *board;
+		after.make_move(mv);
+		let opponent = match piece.color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		if after.is_in_checkmate(opponent) {
+			result.push('#');
+		} else if after.is_in_check(opponent) {
+			result.push('+');
+		}
+
+		return result;
+	}
+
+	fn disambiguation(board: &Board, mv: Move, piece: Piece) -> String {
+		// Other pieces of the same kind that can legally go to the same square
+		// source: http://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm#c8.2.3.4
+		// A pinned piece that can't make the move doesn't count, other tools reject "Nbd2" there
+		let pieces = match piece.color {
+			Color::White => &board.white_pieces,
+			Color::Black => &board.black_pieces,
+		};
+		let others: Vec<Coordinate> = pieces
+		.iter()
+		.filter(|(num, other)| **other == piece && **num != mv.from.as_number())
+		.map(|(num, _)| Coordinate::from_number(*num))
+		.filter(|from| board.moves_from(*from).unwrap_or_default().iter().any(|other| other.to == mv.to))
+		.collect();
+
+		let square = mv.from.to_string();
+		if others.is_empty() {
+			return String::new();
+		}
+
+		// File first, then rank, then both
+		if others.iter().all(|other| other.col != mv.from.col) {
+			return square[..1].to_string();
+		}
+		if others.iter().all(|other| other.row != mv.from.row) {
+			return square[1..].to_string();
+		}
+		return square;
+	}
+
+	pub fn parse(&self, board: &Board, text: &str) -> Option<Move> {
+		// function to find the legal move written down as text
+		// Every legal move is written in the same notation and compared, so whatever the
+		// formatter accepts the parser accepts too
+		let text = strip_suffixes(text);
+		if text.is_empty() {
+			return None;
+		}
+
+		return board
+		.legal_moves()
+		.into_iter()
+		.find(|mv| strip_suffixes(&self.format(board, *mv)) == text);
+	}
+
+	pub fn parse_with(&self, board: &Board, text: &str, mode: ParseMode) -> Option<Move> {
+		if let Some(mv) = self.parse(board, text) {
+			return Some(mv);
+		}
+		if mode == ParseMode::Strict || *self == Notation::Iccf {
+			return None;
+		}
+		if matches!(self, Notation::Smith | Notation::Coordinate) {
+			return Self::parse_coordinates(board, text);
+		}
+
+		// En passant marks go first, they may hide check marks in front of them
+		let mut text = strip_suffixes(text);
+		for suffix in ["e.p.", "ep"] {
+			if let Some(rest) = text.strip_suffix(suffix) {
+				text = strip_suffixes(rest);
+			}
+		}
+
+		if !text.is_empty() && text.chars().all(|chr| matches!(chr, '0' | 'O' | 'o' | '-')) {
+			return Self::parse_castling(board, text.chars().filter(|chr| *chr != '-').count());
+		}
+
+		let mv = Self::parse_parts(board, text, false);
+		if mv.is_none() && text.starts_with('B') {
+			// Some writers capitalise the b file of pawn captures
+			return Self::parse_parts(board, text, true);
+		}
+		return mv;
+	}
+
+	fn parse_coordinates(board: &Board, text: &str) -> Option<Move> {
+		// Both squares, anything Smith adds after them is left out but the promotion piece
+		let chars: Vec<char> = strip_suffixes(text).chars().filter(|chr| !matches!(chr, '-' | 'x')).collect();
+		if chars.len() < 4 {
+			return None;
+		}
+		let square = |chars: &[char]| Coordinate::from_algebraic(&chars.iter().collect::<String>().to_ascii_lowercase()).ok();
+		let (from, to) = (square(&chars[..2])?, square(&chars[2..4])?);
+		let promotion = chars[4..]
+		.last()
+		.and_then(|chr| Piece::from_fen_char(chr.to_ascii_uppercase()))
+		.map(|piece| piece.breed)
+		.filter(|breed| !matches!(breed, Pieces::King | Pieces::Pawn))
+		.unwrap_or(Pieces::Queen);
+		return board
+		.legal_moves()
+		.into_iter()
+		.find(|mv| mv.from == from && mv.to == to && mv.promotion.is_none_or(|breed| breed == promotion));
+	}
+
+	pub fn write_log(&self, game: &Game) -> String {
+		// Every move of the game on its own line
+		let mut log = String::new();
+		for (board, mv) in game.positions().zip(game.moves()) {
+			log.push_str(&self.format(&board, *mv));
+			log.push('\n');
+		}
+		return log;
+	}
+
+	pub fn read_log(&self, start: Board, text: &str, mode: ParseMode) -> Result<Game, ChessError> {
+		// The game played from `start` with the moves of the log, blank lines are left out
+		let mut game = Game::new(start);
+		for (number, line) in text.lines().enumerate() {
+			let mut line = line.trim();
+			if mode == ParseMode::Lenient {
+				line = line[move_number_end(line)..].trim_start();
+			}
+			if line.is_empty() {
+				continue;
+			}
+			let invalid = |reason: &str| ChessError::InvalidMoveLog(format!("line {}: {} \"{}\"", number + 1, reason, line));
+			let mv = self.parse_with(game.board(), line, mode).ok_or_else(|| invalid("no legal move"))?;
+			game.make_move(mv).map_err(|error| invalid(&error.to_string()))?;
+		}
+		return Ok(game);
+	}
+
+	fn parse_castling(board: &Board, length: usize) -> Option<Move> {
+		// Two letters for the king side, three for the queen side
+		return board.legal_moves().into_iter().find(|mv| {
+			let is_king = board.get_piece(mv.from).is_some_and(|piece| piece.breed == Pieces::King);
+			let side = mv.to.col - mv.from.col;
+			return is_king && ((length == 2 && side == 2) || (length == 3 && side == -2));
+		});
+	}
+
+	fn parse_parts(board: &Board, text: &str, b_pawn: bool) -> Option<Move> {
+		// function to read a move as piece, hints about where it comes from, target and promotion,
+		// the one legal move that fits is the answer
+		let mut chars: Vec<char> = text.chars().filter(|chr| !matches!(chr, 'x' | ':' | '-' | '=' | '(' | ')' | '/')).collect();
+
+		let mut promotion: Option<Pieces> = None;
+		if chars.len() > 2 && chars[chars.len() - 2].is_ascii_digit() {
+			let letter = chars.pop()?.to_ascii_uppercase();
+			promotion = Some(Piece::from_fen_char(letter)?.breed).filter(|breed| !matches!(breed, Pieces::King | Pieces::Pawn));
+			promotion?;
+		}
+
+		if chars.len() < 2 {
+			return None;
+		}
+		let to = Coordinate::from_algebraic(&chars.split_off(chars.len() - 2).iter().collect::<String>()).ok()?;
+
+		let mut breed = Pieces::Pawn;
+		if b_pawn {
+			// Nothing is left when the B was the file of the target square
+			*chars.first_mut()? = 'b';
+		} else if let Some(piece) = chars.first().filter(|chr| chr.is_ascii_uppercase()).and_then(|chr| Piece::from_fen_char(*chr)) {
+			breed = piece.breed;
+			chars.remove(0);
+		}
+
+		// Whatever is left narrows down the square the piece comes from
+		let mut col: Option<i8> = None;
+		let mut row: Option<i8> = None;
+		for chr in chars {
+			match chr {
+				'a'..='h' => col = Some(chr as i8 - 'a' as i8),
+				'1'..='8' => row = Some(8 - (chr as i8 - '0' as i8)),
+				_ => return None,
+			}
+		}
+
+		let candidates: Vec<Move> = board
+		.legal_moves()
+		.into_iter()
+		.filter(|mv| mv.to == to && board.get_piece(mv.from).is_some_and(|piece| piece.breed == breed))
+		.filter(|mv| col.is_none_or(|col| mv.from.col == col) && row.is_none_or(|row| mv.from.row == row))
+		.filter(|mv| mv.promotion == promotion || (promotion.is_none() && mv.promotion == Some(Pieces::Queen)))
+		.collect();
+
+		if candidates.len() != 1 {
+			return None;
+		}
+		return candidates.first().copied();
+	}
+}
@@ -0,0 +1,73 @@
+use crate::chess::*;
+use crate::error::ChessError;
+
+// Odds games, the stronger player gives away material to even the game out
+// source: https://en.wikipedia.org/wiki/Handicap_(chess)
+/*
+PawnAndMove - the f pawn and the first move
+Pawn        - the f pawn
+Knight      - the queen's knight
+Rook        - the queen's rook, castling on that side goes with it
+Queen       - the queen
+
+The giver plays either color, black giving pawn and move means white starts as usual, white
+giving it means black plays first. Every position is checked with Board::validate
+*/
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Odds {
+	PawnAndMove,
+	Pawn,
+	Knight,
+	Rook,
+	Queen,
+}
+
+impl Odds {
+	pub fn removed_squares(&self, giver: Color) -> Vec<Coordinate> {
+		// Squares emptied in the starting position
+		let (back, pawns) = match giver {
+			Color::White => (7, 6),
+			Color::Black => (0, 1),
+		};
+		return match self {
+			Odds::PawnAndMove | Odds::Pawn => vec![coord!(pawns, 5)],
+			Odds::Knight => vec![coord!(back, 1)],
+			Odds::Rook => vec![coord!(back, 0)],
+			Odds::Queen => vec![coord!(back, 3)],
+		};
+	}
+}
+
+impl Board {
+	pub fn odds(odds: Odds, giver: Color) -> Board {
+		let mut board = Board::handicap(giver, &odds.removed_squares(giver)).expect("odds positions are legal");
+		if odds == Odds::PawnAndMove {
+			board.turn = match giver {
+				Color::White => Color::Black,
+				Color::Black => Color::White,
+			};
+		}
+		return board;
+	}
+
+	pub fn handicap(giver: Color, squares: &[Coordinate]) -> Result<Board, ChessError> {
+		// The starting position without the giver's pieces on `squares`, the king stays
+		let mut board = Board::default();
+		for coord in squares {
+			match board.get_piece(*coord) {
+				Some(piece) if piece.color == giver && piece.breed != Pieces::King => {
+					board.remove_piece(*coord)?;
+				}
+				Some(_) => return Err(ChessError::InvalidPosition(format!("can't give away the piece on {}", coord))),
+				None => return Err(ChessError::EmptySquare(*coord)),
+			}
+		}
+
+		// No castling with a rook that isn't there
+		board.strip_castling_rights();
+
+		board.validate()?;
+		return Ok(board);
+	}
+}
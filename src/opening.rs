@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::chess::*;
+use crate::game::Game;
+use crate::notation::Notation;
+
+// Names of the common openings, to tell which one a game went into
+/*
+Every opening is its ECO code, its name and the moves of its main line, in SAN from the start
+position. A game is in an opening when it reaches the position at the end of those moves, in
+any move order, so transpositions get their name too. The name of a game is the one of the
+last opening position it went through: the Sicilian Defence becomes the Najdorf Variation once
+the game gets there
+
+The list only has the best known lines, a game that leaves the book early keeps the name of
+the last one it was in
+*/
+// source: https://github.com/lichess-org/chess-openings
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct Opening {
+	pub eco: &'static str,
+	pub name: &'static str,
+	pub moves: &'static str,
+}
+
+pub const OPENINGS: &[Opening] = &[
+	Opening { eco: "A00", name: "Polish Opening", moves: "b4" },
+	Opening { eco: "A01", name: "Nimzo-Larsen Attack", moves: "b3" },
+	Opening { eco: "A02", name: "Bird Opening", moves: "f4" },
+	Opening { eco: "A04", name: "Zukertort Opening", moves: "Nf3" },
+	Opening { eco: "A05", name: "Zukertort Opening: King's Indian Attack", moves: "Nf3 Nf6" },
+	Opening { eco: "A10", name: "English Opening", moves: "c4" },
+	Opening { eco: "A20", name: "English Opening: King's English Variation", moves: "c4 e5" },
+	Opening { eco: "A40", name: "Queen's Pawn Game", moves: "d4" },
+	Opening { eco: "A45", name: "Indian Defence", moves: "d4 Nf6" },
+	Opening { eco: "A80", name: "Dutch Defence", moves: "d4 f5" },
+	Opening { eco: "B00", name: "King's Pawn Game", moves: "e4" },
+	Opening { eco: "B01", name: "Scandinavian Defence", moves: "e4 d5" },
+	Opening { eco: "B02", name: "Alekhine Defence", moves: "e4 Nf6" },
+	Opening { eco: "B06", name: "Modern Defence", moves: "e4 g6" },
+	Opening { eco: "B07", name: "Pirc Defence", moves: "e4 d6 d4 Nf6" },
+	Opening { eco: "B10", name: "Caro-Kann Defence", moves: "e4 c6" },
+	Opening { eco: "B12", name: "Caro-Kann Defence: Advance Variation", moves: "e4 c6 d4 d5 e5" },
+	Opening { eco: "B20", name: "Sicilian Defence", moves: "e4 c5" },
+	Opening { eco: "B22", name: "Sicilian Defence: Alapin Variation", moves: "e4 c5 c3" },
+	Opening { eco: "B23", name: "Sicilian Defence: Closed", moves: "e4 c5 Nc3" },
+	Opening { eco: "B27", name: "Sicilian Defence: Hyperaccelerated Dragon", moves: "e4 c5 Nf3 g6" },
+	Opening { eco: "B30", name: "Sicilian Defence: Old Sicilian", moves: "e4 c5 Nf3 Nc6" },
+	Opening { eco: "B33", name: "Sicilian Defence: Open", moves: "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4" },
+	Opening { eco: "B40", name: "Sicilian Defence: French Variation", moves: "e4 c5 Nf3 e6" },
+	Opening { eco: "B50", name: "Sicilian Defence: Modern Variations", moves: "e4 c5 Nf3 d6" },
+	Opening { eco: "B54", name: "Sicilian Defence: Open", moves: "e4 c5 Nf3 d6 d4 cxd4 Nxd4" },
+	Opening { eco: "B70", name: "Sicilian Defence: Dragon Variation", moves: "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6" },
+	Opening { eco: "B90", name: "Sicilian Defence: Najdorf Variation", moves: "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6" },
+	Opening { eco: "C00", name: "French Defence", moves: "e4 e6" },
+	Opening { eco: "C02", name: "French Defence: Advance Variation", moves: "e4 e6 d4 d5 e5" },
+	Opening { eco: "C03", name: "French Defence: Tarrasch Variation", moves: "e4 e6 d4 d5 Nd2" },
+	Opening { eco: "C10", name: "French Defence: Paulsen Variation", moves: "e4 e6 d4 d5 Nc3" },
+	Opening { eco: "C15", name: "French Defence: Winawer Variation", moves: "e4 e6 d4 d5 Nc3 Bb4" },
+	Opening { eco: "C20", name: "King's Pawn Game", moves: "e4 e5" },
+	Opening { eco: "C23", name: "Bishop's Opening", moves: "e4 e5 Bc4" },
+	Opening { eco: "C25", name: "Vienna Game", moves: "e4 e5 Nc3" },
+	Opening { eco: "C30", name: "King's Gambit", moves: "e4 e5 f4" },
+	Opening { eco: "C40", name: "King's Knight Opening", moves: "e4 e5 Nf3" },
+	Opening { eco: "C41", name: "Philidor Defence", moves: "e4 e5 Nf3 d6" },
+	Opening { eco: "C42", name: "Petrov's Defence", moves: "e4 e5 Nf3 Nf6" },
+	Opening { eco: "C44", name: "King's Knight Opening: Normal Variation", moves: "e4 e5 Nf3 Nc6" },
+	Opening { eco: "C45", name: "Scotch Game", moves: "e4 e5 Nf3 Nc6 d4" },
+	Opening { eco: "C46", name: "Three Knights Opening", moves: "e4 e5 Nf3 Nc6 Nc3" },
+	Opening { eco: "C47", name: "Four Knights Game", moves: "e4 e5 Nf3 Nc6 Nc3 Nf6" },
+	Opening { eco: "C50", name: "Italian Game", moves: "e4 e5 Nf3 Nc6 Bc4" },
+	Opening { eco: "C50", name: "Italian Game: Giuoco Piano", moves: "e4 e5 Nf3 Nc6 Bc4 Bc5" },
+	Opening { eco: "C51", name: "Italian Game: Evans Gambit", moves: "e4 e5 Nf3 Nc6 Bc4 Bc5 b4" },
+	Opening { eco: "C55", name: "Italian Game: Two Knights Defence", moves: "e4 e5 Nf3 Nc6 Bc4 Nf6" },
+	Opening { eco: "C60", name: "Ruy Lopez", moves: "e4 e5 Nf3 Nc6 Bb5" },
+	Opening { eco: "C65", name: "Ruy Lopez: Berlin Defence", moves: "e4 e5 Nf3 Nc6 Bb5 Nf6" },
+	Opening { eco: "C68", name: "Ruy Lopez: Exchange Variation", moves: "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6" },
+	Opening { eco: "C70", name: "Ruy Lopez: Morphy Defence", moves: "e4 e5 Nf3 Nc6 Bb5 a6" },
+	Opening { eco: "C84", name: "Ruy Lopez: Closed", moves: "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7" },
+	Opening { eco: "D00", name: "Queen's Pawn Game", moves: "d4 d5" },
+	Opening { eco: "D02", name: "Queen's Pawn Game: London System", moves: "d4 d5 Nf3 Nf6 Bf4" },
+	Opening { eco: "D06", name: "Queen's Gambit", moves: "d4 d5 c4" },
+	Opening { eco: "D10", name: "Slav Defence", moves: "d4 d5 c4 c6" },
+	Opening { eco: "D20", name: "Queen's Gambit Accepted", moves: "d4 d5 c4 dxc4" },
+	Opening { eco: "D30", name: "Queen's Gambit Declined", moves: "d4 d5 c4 e6" },
+	Opening { eco: "D35", name: "Queen's Gambit Declined: Normal Defence", moves: "d4 d5 c4 e6 Nc3 Nf6" },
+	Opening { eco: "D80", name: "Grünfeld Defence", moves: "d4 Nf6 c4 g6 Nc3 d5" },
+	Opening { eco: "E00", name: "Indian Defence: East Indian Defence", moves: "d4 Nf6 c4 e6" },
+	Opening { eco: "E10", name: "Indian Defence: Normal Variation", moves: "d4 Nf6 c4 e6 Nf3" },
+	Opening { eco: "E12", name: "Queen's Indian Defence", moves: "d4 Nf6 c4 e6 Nf3 b6" },
+	Opening { eco: "E20", name: "Nimzo-Indian Defence", moves: "d4 Nf6 c4 e6 Nc3 Bb4" },
+	Opening { eco: "E60", name: "King's Indian Defence", moves: "d4 Nf6 c4 g6" },
+	Opening { eco: "E61", name: "King's Indian Defence", moves: "d4 Nf6 c4 g6 Nc3 Bg7" },
+	Opening { eco: "E90", name: "King's Indian Defence: Normal Variation", moves: "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3" },
+];
+
+impl Opening {
+	pub fn board(&self) -> Option<Board> {
+		// The position at the end of the moves, None when one of them doesn't parse
+		let mut board = Board::default();
+		for text in self.moves.split_whitespace() {
+			let mv = Notation::San.parse(&board, text)?;
+			board.make_move(mv);
+		}
+		return Some(board);
+	}
+}
+
+pub fn opening_positions() -> HashMap<u64, Opening> {
+	// Zobrist hash of the position of every opening, the longer line wins when two meet
+	let mut positions: HashMap<u64, Opening> = HashMap::new();
+	for opening in OPENINGS {
+		if let Some(board) = opening.board() {
+			let entry = positions.entry(board.zobrist()).or_insert(*opening);
+			if opening.moves.len() > entry.moves.len() {
+				*entry = *opening;
+			}
+		}
+	}
+	return positions;
+}
+
+pub fn identify_opening(game: &Game) -> Option<(Opening, usize)> {
+	// The opening of the game with the ply it was reached at, see above
+	let positions = opening_positions();
+	let mut found = None;
+	for (ply, board) in game.positions().enumerate() {
+		if let Some(opening) = positions.get(&board.zobrist()) {
+			found = Some((*opening, ply));
+		}
+	}
+	return found;
+}
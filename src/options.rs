@@ -0,0 +1,319 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::ChessError;
+use crate::skill::{MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+
+// Engine settings a GUI can change, in the shape the UCI protocol describes them
+// source: https://backscattering.de/chess/uci/#engine-option
+/*
+option name Hash type spin default 16 min 1 max 65536
+option name Ponder type check default false
+option name MultiPV type spin default 1 min 1 max 256
+option name SyzygyPath type string default <empty>
+*/
+
+pub const HASH: &str = "Hash";
+pub const EVAL_CACHE: &str = "EvalCache";
+pub const THREADS: &str = "Threads";
+pub const MULTI_PV: &str = "MultiPV";
+pub const PONDER: &str = "Ponder";
+pub const MOVE_OVERHEAD: &str = "Move Overhead";
+pub const SYZYGY_PATH: &str = "SyzygyPath";
+pub const SKILL_LEVEL: &str = "Skill Level";
+pub const LIMIT_STRENGTH: &str = "UCI_LimitStrength";
+pub const ELO: &str = "UCI_Elo";
+pub const CONTEMPT: &str = "Contempt";
+pub const RESIGN_SCORE: &str = "ResignScore";
+pub const RESIGN_MOVES: &str = "ResignMoves";
+pub const DRAW_SCORE: &str = "DrawScore";
+pub const DRAW_MOVES: &str = "DrawMoves";
+pub const DRAW_FROM_MOVE: &str = "DrawFromMove";
+
+// UCI writes empty strings this way
+const EMPTY: &str = "<empty>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionKind {
+	Check { default: bool },
+	Spin { default: i64, min: i64, max: i64 },
+	Combo { default: String, vars: Vec<String> },
+	// Triggers something, it has no value
+	Button,
+	String { default: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+	Check(bool),
+	Spin(i64),
+	Combo(String),
+	Button,
+	String(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UciOption {
+	pub name: String,
+	pub kind: OptionKind,
+}
+
+impl UciOption {
+	pub fn check(name: &str, default: bool) -> Self {
+		UciOption {
+			name: name.to_string(),
+			kind: OptionKind::Check { default },
+		}
+	}
+
+	pub fn spin(name: &str, default: i64, min: i64, max: i64) -> Self {
+		UciOption {
+			name: name.to_string(),
+			kind: OptionKind::Spin { default, min, max },
+		}
+	}
+
+	pub fn string(name: &str, default: &str) -> Self {
+		UciOption {
+			name: name.to_string(),
+			kind: OptionKind::String { default: default.to_string() },
+		}
+	}
+
+	pub fn default_value(&self) -> OptionValue {
+		return match &self.kind {
+			OptionKind::Check { default } => OptionValue::Check(*default),
+			OptionKind::Spin { default, .. } => OptionValue::Spin(*default),
+			OptionKind::Combo { default, .. } => OptionValue::Combo(default.clone()),
+			OptionKind::Button => OptionValue::Button,
+			OptionKind::String { default } => OptionValue::String(default.clone()),
+		};
+	}
+
+	pub fn parse(&self, value: &str) -> Result<OptionValue, ChessError> {
+		// function to check a `setoption` value against the option's type and bounds
+		let invalid = || ChessError::InvalidOptionValue(self.name.clone(), value.to_string());
+		let value = value.trim();
+
+		return match &self.kind {
+			OptionKind::Check { .. } => match value.to_ascii_lowercase().as_str() {
+				"true" => Ok(OptionValue::Check(true)),
+				"false" => Ok(OptionValue::Check(false)),
+				_ => Err(invalid()),
+			},
+			OptionKind::Spin { min, max, .. } => match value.parse::<i64>() {
+				Ok(number) if number >= *min && number <= *max => Ok(OptionValue::Spin(number)),
+				_ => Err(invalid()),
+			},
+			OptionKind::Combo { vars, .. } => match vars.iter().find(|var| var.eq_ignore_ascii_case(value)) {
+				Some(var) => Ok(OptionValue::Combo(var.clone())),
+				None => Err(invalid()),
+			},
+			OptionKind::Button => Ok(OptionValue::Button),
+			OptionKind::String { .. } if value == EMPTY => Ok(OptionValue::String(String::new())),
+			OptionKind::String { .. } => Ok(OptionValue::String(value.to_string())),
+		};
+	}
+}
+
+impl fmt::Display for UciOption {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// The line sent to the GUI after `uci`
+		write!(f, "option name {} type ", self.name)?;
+		return match &self.kind {
+			OptionKind::Check { default } => write!(f, "check default {}", default),
+			OptionKind::Spin { default, min, max } => write!(f, "spin default {} min {} max {}", default, min, max),
+			OptionKind::Combo { default, vars } => {
+				write!(f, "combo default {}", default)?;
+				for var in vars {
+					write!(f, " var {}", var)?;
+				}
+				return Ok(());
+			}
+			OptionKind::Button => write!(f, "button"),
+			OptionKind::String { default } if default.is_empty() => write!(f, "string default {}", EMPTY),
+			OptionKind::String { default } => write!(f, "string default {}", default),
+		};
+	}
+}
+
+fn standard_options() -> Vec<UciOption> {
+	return vec![
+		UciOption::spin(HASH, 16, 1, 65536),
+		UciOption::spin(EVAL_CACHE, 1, 0, 1024),
+		UciOption::spin(THREADS, 1, 1, 256),
+		UciOption::spin(MULTI_PV, 1, 1, 256),
+		UciOption::check(PONDER, false),
+		UciOption::spin(MOVE_OVERHEAD, 10, 0, 5000),
+		UciOption::string(SYZYGY_PATH, ""),
+		UciOption::spin(SKILL_LEVEL, MAX_SKILL_LEVEL as i64, 0, MAX_SKILL_LEVEL as i64),
+		UciOption::check(LIMIT_STRENGTH, false),
+		UciOption::spin(ELO, MAX_ELO as i64, MIN_ELO as i64, MAX_ELO as i64),
+		UciOption::spin(CONTEMPT, 0, -1000, 1000),
+		UciOption::spin(RESIGN_SCORE, 1000, 0, 100_000),
+		UciOption::spin(RESIGN_MOVES, 0, 0, 100),
+		UciOption::spin(DRAW_SCORE, 10, 0, 1000),
+		UciOption::spin(DRAW_MOVES, 0, 0, 100),
+		UciOption::spin(DRAW_FROM_MOVE, 40, 1, 1000),
+	];
+}
+
+// The standard options as typed fields, plus whatever knobs engines and evaluators add
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineOptions {
+	pub hash_mb: u32,
+	// Sized apart from the hash table, 0 turns the cache off
+	pub eval_cache_mb: u32,
+	// GUIs send it to every engine, the search runs on one thread whatever it says
+	pub threads: usize,
+	// Lines the UCI search output shows, see Engine::multi_pv
+	pub multi_pv: usize,
+	pub ponder: bool,
+	// Time kept aside for every move to cover the GUI and network lag
+	pub move_overhead: Duration,
+	// Kept for GUIs that set it, no tablebase files are read yet
+	pub syzygy_path: Option<String>,
+	// Weaker play on purpose, see SkillLevel
+	pub skill_level: u32,
+	pub limit_strength: bool,
+	pub elo: u32,
+	// Centipawns a draw is worth less than an equal position to the engine, negative to seek draws
+	pub contempt: i32,
+	// When to give up or take a draw, see DecisionPolicy
+	pub resign_score: i32,
+	pub resign_moves: u32,
+	pub draw_score: i32,
+	pub draw_moves: u32,
+	pub draw_from_move: u32,
+	custom: Vec<(UciOption, OptionValue)>,
+}
+
+impl Default for EngineOptions {
+	fn default() -> Self {
+		EngineOptions {
+			hash_mb: 16,
+			eval_cache_mb: 1,
+			threads: 1,
+			multi_pv: 1,
+			ponder: false,
+			move_overhead: Duration::from_millis(10),
+			syzygy_path: None,
+			skill_level: MAX_SKILL_LEVEL,
+			limit_strength: false,
+			elo: MAX_ELO,
+			contempt: 0,
+			resign_score: 1000,
+			resign_moves: 0,
+			draw_score: 10,
+			draw_moves: 0,
+			draw_from_move: 40,
+			custom: Vec::new(),
+		}
+	}
+}
+
+impl EngineOptions {
+	pub fn new() -> Self {
+		return EngineOptions::default();
+	}
+
+	pub fn register(&mut self, option: UciOption) {
+		// Adds a custom knob with its default value, registering a name again replaces it
+		self.custom.retain(|(known, _)| !known.name.eq_ignore_ascii_case(&option.name));
+		let value = option.default_value();
+		self.custom.push((option, value));
+	}
+
+	pub fn options(&self) -> Vec<UciOption> {
+		// Everything to announce after `uci`, the standard ones first
+		let mut options = standard_options();
+		options.extend(self.custom.iter().map(|(option, _)| option.clone()));
+		return options;
+	}
+
+	pub fn set(&mut self, name: &str, value: &str) -> Result<(), ChessError> {
+		// Option names are not case sensitive
+		let option = match self.options().into_iter().find(|option| option.name.eq_ignore_ascii_case(name.trim())) {
+			Some(option) => option,
+			None => return Err(ChessError::UnknownOption(name.to_string())),
+		};
+		let parsed = option.parse(value)?;
+
+		match (option.name.as_str(), parsed) {
+			(HASH, OptionValue::Spin(mb)) => self.hash_mb = mb as u32,
+			(EVAL_CACHE, OptionValue::Spin(mb)) => self.eval_cache_mb = mb as u32,
+			(THREADS, OptionValue::Spin(threads)) => self.threads = threads as usize,
+			(MULTI_PV, OptionValue::Spin(lines)) => self.multi_pv = lines as usize,
+			(PONDER, OptionValue::Check(ponder)) => self.ponder = ponder,
+			(MOVE_OVERHEAD, OptionValue::Spin(ms)) => self.move_overhead = Duration::from_millis(ms as u64),
+			(SYZYGY_PATH, OptionValue::String(path)) => {
+				self.syzygy_path = if path.is_empty() { None } else { Some(path) };
+			}
+			(SKILL_LEVEL, OptionValue::Spin(level)) => self.skill_level = level as u32,
+			(LIMIT_STRENGTH, OptionValue::Check(limit)) => self.limit_strength = limit,
+			(ELO, OptionValue::Spin(elo)) => self.elo = elo as u32,
+			(CONTEMPT, OptionValue::Spin(contempt)) => self.contempt = contempt as i32,
+			(RESIGN_SCORE, OptionValue::Spin(score)) => self.resign_score = score as i32,
+			(RESIGN_MOVES, OptionValue::Spin(moves)) => self.resign_moves = moves as u32,
+			(DRAW_SCORE, OptionValue::Spin(score)) => self.draw_score = score as i32,
+			(DRAW_MOVES, OptionValue::Spin(moves)) => self.draw_moves = moves as u32,
+			(DRAW_FROM_MOVE, OptionValue::Spin(number)) => self.draw_from_move = number as u32,
+			(_, parsed) => {
+				if let Some(entry) = self.custom.iter_mut().find(|(known, _)| known.name == option.name) {
+					entry.1 = parsed;
+				}
+			}
+		}
+
+		return Ok(());
+	}
+
+	pub fn value(&self, name: &str) -> Option<OptionValue> {
+		// Current value of any option, standard or custom
+		let name = name.trim();
+		let standard = match standard_options().into_iter().find(|option| option.name.eq_ignore_ascii_case(name)) {
+			Some(option) => option.name,
+			None => {
+				return self
+				.custom
+				.iter()
+				.find(|(option, _)| option.name.eq_ignore_ascii_case(name))
+				.map(|(_, value)| value.clone());
+			}
+		};
+
+		return Some(match standard.as_str() {
+			HASH => OptionValue::Spin(self.hash_mb as i64),
+			EVAL_CACHE => OptionValue::Spin(self.eval_cache_mb as i64),
+			THREADS => OptionValue::Spin(self.threads as i64),
+			MULTI_PV => OptionValue::Spin(self.multi_pv as i64),
+			PONDER => OptionValue::Check(self.ponder),
+			MOVE_OVERHEAD => OptionValue::Spin(self.move_overhead.as_millis() as i64),
+			SKILL_LEVEL => OptionValue::Spin(self.skill_level as i64),
+			LIMIT_STRENGTH => OptionValue::Check(self.limit_strength),
+			ELO => OptionValue::Spin(self.elo as i64),
+			CONTEMPT => OptionValue::Spin(self.contempt as i64),
+			RESIGN_SCORE => OptionValue::Spin(self.resign_score as i64),
+			RESIGN_MOVES => OptionValue::Spin(self.resign_moves as i64),
+			DRAW_SCORE => OptionValue::Spin(self.draw_score as i64),
+			DRAW_MOVES => OptionValue::Spin(self.draw_moves as i64),
+			DRAW_FROM_MOVE => OptionValue::Spin(self.draw_from_move as i64),
+			_ => OptionValue::String(self.syzygy_path.clone().unwrap_or_default()),
+		});
+	}
+
+	// Shortcuts for knobs that are plain numbers or flags
+	pub fn spin(&self, name: &str) -> Option<i64> {
+		return match self.value(name) {
+			Some(OptionValue::Spin(value)) => Some(value),
+			_ => None,
+		};
+	}
+
+	pub fn check(&self, name: &str) -> Option<bool> {
+		return match self.value(name) {
+			Some(OptionValue::Check(value)) => Some(value),
+			_ => None,
+		};
+	}
+}
@@ -0,0 +1,161 @@
+use crate::chess::*;
+
+// Compact binary encoding of a position
+/*
+bytes 0..8   - occupancy, bit N is set when square N has a piece (a1 is bit 0)
+bytes 8..24  - one nibble per occupied square in square order, low nibble first
+			   bits 0..3 are the piece (King, Queen, Rook, Bishop, Knight, Pawn), bit 3 is black
+byte 24      - bit 0 is set when black is to move, bits 1..5 are the castling rights
+byte 25      - en passant target square, 0xff when there is none
+
+Clocks are not stored, the encoding is meant for positions rather than games
+*/
+pub const PACKED_SIZE: usize = 26;
+
+// Position codes, the packed bytes in URL-safe base64 without padding, safe in query strings
+// and chat messages where the spaces and slashes of a FEN get in the way
+// source: https://datatracker.ietf.org/doc/html/rfc4648#section-5
+pub const CODE_LENGTH: usize = (PACKED_SIZE * 8).div_ceil(6);
+const CODE_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const BREEDS: [Pieces; 6] = [
+	Pieces::King,
+	Pieces::Queen,
+	Pieces::Rook,
+	Pieces::Bishop,
+	Pieces::Knight,
+	Pieces::Pawn,
+];
+
+impl Board {
+	pub fn to_packed(&self) -> Option<[u8; PACKED_SIZE]> {
+		// Returns None for boards with more than 32 pieces, they don't fit
+		let mut bytes = [0u8; PACKED_SIZE];
+		let mut occupancy: u64 = 0;
+		let mut count = 0;
+
+		for num in 0..64 {
+			let piece = match self.get_piece(Coordinate::from_number(num)) {
+				Some(piece) => piece,
+				None => continue,
+			};
+
+			if count == 32 {
+				return None;
+			}
+
+			let mut nibble = BREEDS.iter().position(|breed| *breed == piece.breed)? as u8;
+			if piece.color == Color::Black {
+				nibble |= 0b1000;
+			}
+
+			bytes[8 + count / 2] |= nibble << (4 * (count % 2));
+			occupancy |= 1 << num;
+			count += 1;
+		}
+
+		bytes[..8].copy_from_slice(&occupancy.to_le_bytes());
+
+		let mut flags = 0u8;
+		if self.turn == Color::Black {
+			flags |= 1;
+		}
+		for (i, right) in self.castling_rights.iter().enumerate() {
+			if *right {
+				flags |= 1 << (i + 1);
+			}
+		}
+		bytes[24] = flags;
+		bytes[25] = match self.en_passant_target_sq {
+			Some(sq) => sq as u8,
+			None => 0xff,
+		};
+
+		return Some(bytes);
+	}
+
+	pub fn from_packed(bytes: &[u8; PACKED_SIZE]) -> Option<Board> {
+		// Returns None when the bytes don't describe a board
+		let mut board = Board::new();
+		let occupancy = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+
+		if occupancy.count_ones() > 32 {
+			return None;
+		}
+
+		let mut count = 0;
+		for num in 0..64 {
+			if occupancy & (1 << num) == 0 {
+				continue;
+			}
+
+			let nibble = (bytes[8 + count / 2] >> (4 * (count % 2))) & 0b1111;
+			let breed = *BREEDS.get((nibble & 0b111) as usize)?;
+			let color = if nibble & 0b1000 == 0 { Color::White } else { Color::Black };
+
+			board.place_piece(Piece { breed, color }, Coordinate::from_number(num)).ok()?;
+			count += 1;
+		}
+
+		board.turn = if bytes[24] & 1 == 0 { Color::White } else { Color::Black };
+		for i in 0..4 {
+			board.castling_rights[i] = bytes[24] & (1 << (i + 1)) != 0;
+		}
+		board.en_passant_target_sq = match bytes[25] {
+			0xff => None,
+			sq if sq < 64 => Some(sq as i8),
+			_ => return None,
+		};
+
+		return Some(board);
+	}
+
+	pub fn to_code(&self) -> Option<String> {
+		// Returns None when the board doesn't pack
+		let bytes = self.to_packed()?;
+		let mut code = String::with_capacity(CODE_LENGTH);
+		let mut bits: u32 = 0;
+		let mut count = 0;
+
+		for byte in bytes {
+			bits = (bits << 8) | byte as u32;
+			count += 8;
+			while count >= 6 {
+				count -= 6;
+				code.push(CODE_ALPHABET[((bits >> count) & 0b111111) as usize] as char);
+			}
+		}
+		if count > 0 {
+			code.push(CODE_ALPHABET[((bits << (6 - count)) & 0b111111) as usize] as char);
+		}
+
+		return Some(code);
+	}
+
+	pub fn from_code(code: &str) -> Option<Board> {
+		// Returns None for anything to_code can't have written
+		if code.len() != CODE_LENGTH {
+			return None;
+		}
+
+		let mut bytes = [0u8; PACKED_SIZE];
+		let mut filled = 0;
+		let mut bits: u32 = 0;
+		let mut count = 0;
+		for chr in code.bytes() {
+			bits = (bits << 6) | CODE_ALPHABET.iter().position(|letter| *letter == chr)? as u32;
+			count += 6;
+			if count >= 8 && filled < PACKED_SIZE {
+				count -= 8;
+				bytes[filled] = (bits >> count) as u8;
+				filled += 1;
+			}
+		}
+
+		// The bits left over pad the last character, they are always 0
+		if bits & ((1 << count) - 1) != 0 {
+			return None;
+		}
+		return Board::from_packed(&bytes);
+	}
+}
@@ -0,0 +1,233 @@
+use crate::chess::*;
+
+// The pawn structure of one side
+/*
+Islands    - groups of pawns on neighbouring files, with a file without own pawns in between
+Doubled    - pawns sharing their file with another own pawn, all of them are listed
+Isolated   - pawns with no own pawn on the files next to them
+Backward   - pawns behind the own pawns on the files next to them, that can't move up without
+             an enemy pawn taking them on the square in front, isolated pawns aside
+Hanging    - two pawns side by side on half-open files, the only pawns on their files and the
+             ones next to them (c and d pawns usually)
+Chains     - pawns protecting one another diagonally, from the base to the head
+Open       - files without pawns of either side
+Half-open  - files without own pawns, with enemy pawns on them
+
+Pawn lists are sorted by square number, a1 first, the structure is a snapshot of the board it
+was taken from
+*/
+// source: https://www.chessprogramming.org/Pawn_Structure
+
+// Squares for the pieces that the pawns make
+/*
+An outpost is a square on the fourth, fifth or sixth rank (the side's own way up the board)
+that an own pawn protects and no enemy pawn can ever attack: there are none left on the files
+next to it further up the board. Knights and bishops are the pieces that like them best
+*/
+// source: https://www.chessprogramming.org/Outposts
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct PawnStructure {
+	pub color: Color,
+	pub pawns: Vec<Coordinate>,
+	pub islands: Vec<Vec<File>>,
+	pub doubled: Vec<Coordinate>,
+	pub isolated: Vec<Coordinate>,
+	pub backward: Vec<Coordinate>,
+	pub hanging: Vec<Coordinate>,
+	pub chains: Vec<Vec<Coordinate>>,
+	pub open_files: Vec<File>,
+	pub half_open_files: Vec<File>,
+}
+
+impl PawnStructure {
+	pub fn is_open(&self, file: File) -> bool {
+		return self.open_files.contains(&file);
+	}
+
+	pub fn is_half_open(&self, file: File) -> bool {
+		return self.half_open_files.contains(&file);
+	}
+}
+
+fn forward(color: Color) -> i8 {
+	// Row step of a pawn move, rank 8 is row 0
+	return match color {
+		Color::White => -1,
+		Color::Black => 1,
+	};
+}
+
+impl Board {
+	fn pawns_of(&self, color: Color) -> Vec<Coordinate> {
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		let mut result: Vec<Coordinate> = pieces
+		.iter()
+		.filter(|(_, piece)| piece.breed == Pieces::Pawn)
+		.map(|(num, _)| Coordinate::from_number(*num))
+		.collect();
+		result.sort_by_key(|coord| coord.as_number());
+		return result;
+	}
+
+	pub fn pawn_structure(&self, color: Color) -> PawnStructure {
+		let opponent = match color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let pawns = self.pawns_of(color);
+		let enemies = self.pawns_of(opponent);
+		let on_file = |pawns: &[Coordinate], col: i8| pawns.iter().filter(|pawn| pawn.col == col).count();
+		let neighbours = |pawn: &Coordinate| pawns.iter().filter(|other| (other.col - pawn.col).abs() == 1).copied().collect::<Vec<Coordinate>>();
+
+		let mut islands: Vec<Vec<File>> = Vec::new();
+		let mut open_files = Vec::new();
+		let mut half_open_files = Vec::new();
+		for col in 0..8 {
+			let file = File::ALL[col as usize];
+			match (on_file(&pawns, col), on_file(&enemies, col)) {
+				(0, 0) => open_files.push(file),
+				(0, _) => half_open_files.push(file),
+				_ => {}
+			}
+			if on_file(&pawns, col) == 0 {
+				continue;
+			}
+			match islands.last_mut() {
+				Some(island) if on_file(&pawns, col - 1) > 0 => island.push(file),
+				_ => islands.push(vec![file]),
+			}
+		}
+
+		let doubled = pawns.iter().filter(|pawn| on_file(&pawns, pawn.col) > 1).copied().collect();
+		let isolated: Vec<Coordinate> = pawns.iter().filter(|pawn| neighbours(pawn).is_empty()).copied().collect();
+
+		let step = forward(color);
+		let backward = pawns
+		.iter()
+		.filter(|pawn| !isolated.contains(pawn))
+		.filter(|pawn| {
+			// Every own pawn next to it is further up the board
+			neighbours(pawn).iter().all(|other| (other.row - pawn.row) * step > 0)
+		})
+		.filter(|pawn| {
+			// An enemy pawn attacks the square in front
+			enemies.iter().any(|enemy| enemy.row == pawn.row + 2 * step && (enemy.col - pawn.col).abs() == 1)
+		})
+		.copied()
+		.collect();
+
+		// Alone on its file with one neighbour beside it, and no enemy pawn on either file
+		let alone = |pawn: &Coordinate| -> Option<Coordinate> {
+			let others = neighbours(pawn);
+			if others.len() != 1 || others[0].row != pawn.row || on_file(&pawns, pawn.col) != 1 || on_file(&enemies, pawn.col) != 0 {
+				return None;
+			}
+			return Some(others[0]);
+		};
+		let hanging = pawns
+		.iter()
+		.filter(|pawn| alone(pawn).and_then(|other| alone(&other)) == Some(**pawn))
+		.copied()
+		.collect();
+
+		PawnStructure {
+			color,
+			chains: pawn_chains(&pawns, step),
+			pawns,
+			islands,
+			doubled,
+			isolated,
+			backward,
+			hanging,
+			open_files,
+			half_open_files,
+		}
+	}
+}
+
+impl Board {
+	pub fn open_files(&self) -> Vec<File> {
+		return self.pawn_structure(Color::White).open_files;
+	}
+
+	pub fn half_open_files(&self, color: Color) -> Vec<File> {
+		return self.pawn_structure(color).half_open_files;
+	}
+
+	pub fn is_outpost(&self, coord: Coordinate, color: Color) -> bool {
+		// Whether the square is an outpost for `color`, whatever stands on it
+		let step = forward(color);
+		let rank = match color {
+			Color::White => 8 - coord.row,
+			Color::Black => coord.row + 1,
+		};
+		if !coord.is_valid() || !(4..=6).contains(&rank) {
+			return false;
+		}
+		let is_pawn = |coord: Coordinate, color: Color| self.get_piece(coord) == Some(Piece { breed: Pieces::Pawn, color });
+
+		let protected = [-1, 1].iter().any(|col_diff| is_pawn(coord!(coord.row - step, coord.col + col_diff), color));
+		let opponent = match color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let mut row = coord.row + step;
+		while (0..8).contains(&row) {
+			if [-1, 1].iter().any(|col_diff| is_pawn(coord!(row, coord.col + col_diff), opponent)) {
+				return false;
+			}
+			row += step;
+		}
+		return protected;
+	}
+
+	pub fn outposts(&self, color: Color) -> Vec<Coordinate> {
+		// Knights and bishops of `color` standing on outposts, sorted by square number
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		let mut result: Vec<Coordinate> = pieces
+		.iter()
+		.filter(|(_, piece)| matches!(piece.breed, Pieces::Knight | Pieces::Bishop))
+		.map(|(num, _)| Coordinate::from_number(*num))
+		.filter(|coord| self.is_outpost(*coord, color))
+		.collect();
+		result.sort_by_key(|coord| coord.as_number());
+		return result;
+	}
+}
+
+fn pawn_chains(pawns: &[Coordinate], step: i8) -> Vec<Vec<Coordinate>> {
+	// Groups of pawns joined by diagonal protection, at least two of them
+	let protects = |a: &Coordinate, b: &Coordinate| b.row == a.row + step && (b.col - a.col).abs() == 1;
+	let mut chains: Vec<Vec<Coordinate>> = Vec::new();
+	let mut seen: Vec<Coordinate> = Vec::new();
+	for pawn in pawns {
+		if seen.contains(pawn) {
+			continue;
+		}
+		let mut chain = vec![*pawn];
+		let mut index = 0;
+		while index < chain.len() {
+			let current = chain[index];
+			for other in pawns {
+				if !chain.contains(other) && (protects(&current, other) || protects(other, &current)) {
+					chain.push(*other);
+				}
+			}
+			index += 1;
+		}
+		seen.extend(chain.iter().copied());
+		if chain.len() > 1 {
+			// From the base to the head
+			chain.sort_by_key(|coord| (coord.row * step, coord.col));
+			chains.push(chain);
+		}
+	}
+	return chains;
+}
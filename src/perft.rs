@@ -0,0 +1,200 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chess::*;
+use crate::engine::{SearchLimits, SearchStats};
+use crate::eval::MaterialEvaluator;
+use crate::search::AlphaBeta;
+
+// Positions searched by the bench, picked to cover castling, en passant, promotions and endgames
+// source: https://www.chessprogramming.org/Perft_Results
+pub const BENCH_POSITIONS: [&str; 6] = [
+	"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+	"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+	"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+	"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+	"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+	"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+// Default depth of the bench searches
+pub const BENCH_DEPTH: u32 = 3;
+
+pub fn perft(board: &Board, depth: u32) -> u64 {
+	// Counts the leaf nodes of the legal move tree
+	if depth == 0 {
+		return 1;
+	}
+
+	let moves = board.legal_moves();
+	if depth == 1 {
+		return moves.len() as u64;
+	}
+
+	let mut nodes = 0;
+	for mv in moves {
+		let mut child = *board;
+		child.make_move(mv);
+		nodes += perft(&child, depth - 1);
+	}
+
+	return nodes;
+}
+
+pub fn perft_stats(board: &Board, depth: u32) -> SearchStats {
+	// Perft that also sorts the moves of the last ply by kind
+	let mut stats = SearchStats::default();
+	if depth == 0 {
+		stats.nodes = 1;
+		return stats;
+	}
+
+	for mv in board.legal_moves() {
+		let mut child = *board;
+		let captured = child.make_move(mv);
+
+		if depth > 1 {
+			stats += perft_stats(&child, depth - 1);
+			continue;
+		}
+
+		let piece = board.get_piece(mv.from).map(|piece| piece.breed);
+		stats.nodes += 1;
+		if captured.is_some() {
+			stats.captures += 1;
+			// Only en passant captures on an empty square
+			if board.get_piece(mv.to).is_none() {
+				stats.en_passants += 1;
+			}
+		}
+		if piece == Some(Pieces::King) && (mv.to.col - mv.from.col).abs() == 2 {
+			stats.castles += 1;
+		}
+		if mv.promotion.is_some() {
+			stats.promotions += 1;
+		}
+		if child.is_in_check(child.turn) {
+			stats.checks += 1;
+			if child.legal_moves().is_empty() {
+				stats.checkmates += 1;
+			}
+		}
+	}
+
+	return stats;
+}
+
+pub fn divide(board: &Board, depth: u32) -> Vec<(Move, u64)> {
+	// Perft split by the first move, the usual way to find which move the generator gets wrong
+	let mut result = Vec::new();
+	if depth == 0 {
+		return result;
+	}
+
+	for mv in board.legal_moves() {
+		let mut child = *board;
+		child.make_move(mv);
+		result.push((mv, perft(&child, depth - 1)));
+	}
+
+	return result;
+}
+
+pub fn divide_parallel(board: &Board, depth: u32, threads: usize) -> Vec<(Move, u64)> {
+	// Same as divide, but the work is shared by a pool of threads
+	// The tree is split one ply below the root (when it is deep enough) so a few heavy root
+	// moves don't leave the other threads idle, totals don't depend on the thread count
+	trace_span!(tracing::Level::DEBUG, "divide_parallel", depth, threads);
+	if depth == 0 {
+		return Vec::new();
+	}
+
+	let moves = board.legal_moves();
+
+	// Every task is a root move index, a position and the depth left to count from it
+	let mut tasks: Vec<(usize, Board, u32)> = Vec::new();
+	for (index, mv) in moves.iter().enumerate() {
+		let mut child = *board;
+		child.make_move(*mv);
+
+		if depth >= 3 {
+			for reply in child.legal_moves() {
+				let mut grandchild = child;
+				grandchild.make_move(reply);
+				tasks.push((index, grandchild, depth - 2));
+			}
+		} else {
+			tasks.push((index, child, depth - 1));
+		}
+	}
+
+	let next = AtomicUsize::new(0);
+	let mut counts = vec![0u64; moves.len()];
+
+	thread::scope(|scope| {
+		let workers: Vec<_> = (0..threads.max(1))
+		.map(|_| {
+			scope.spawn(|| {
+				let mut done: Vec<(usize, u64)> = Vec::new();
+				loop {
+					let task = next.fetch_add(1, Ordering::Relaxed);
+					if task >= tasks.len() {
+						break;
+					}
+					let (index, position, depth) = &tasks[task];
+					done.push((*index, perft(position, *depth)));
+				}
+				done
+			})
+		})
+		.collect();
+
+		for worker in workers {
+			for (index, nodes) in worker.join().unwrap() {
+				counts[index] += nodes;
+			}
+		}
+	});
+
+	return moves.into_iter().zip(counts).collect();
+}
+
+pub fn perft_parallel(board: &Board, depth: u32, threads: usize) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
+	return divide_parallel(board, depth, threads).iter().map(|(_, nodes)| nodes).sum();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+	pub stats: SearchStats,
+	pub elapsed: Duration,
+}
+
+impl BenchReport {
+	pub fn nps(&self) -> u64 {
+		let seconds = self.elapsed.as_secs_f64();
+		if seconds == 0.0 {
+			return 0;
+		}
+		return (self.stats.nodes as f64 / seconds) as u64;
+	}
+}
+
+pub fn bench(depth: u32) -> BenchReport {
+	// Searches every bench position to a fixed depth, the node count only changes when the search does
+	let start = Instant::now();
+	let mut stats = SearchStats::default();
+
+	for fen in BENCH_POSITIONS {
+		let board = Board::from_fen(fen).unwrap();
+		stats += AlphaBeta::new(MaterialEvaluator).search(&board, &SearchLimits::depth(depth)).stats;
+	}
+
+	return BenchReport {
+		stats,
+		elapsed: start.elapsed(),
+	};
+}
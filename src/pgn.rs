@@ -0,0 +1,704 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::game::{Game, Outcome};
+use crate::notation::{Notation, ParseMode};
+use crate::search::{MATE_SCORE, MAX_DEPTH};
+
+// Portable Game Notation export
+// source: http://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
+/*
+[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "1-0"]
+[Termination "normal"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+*/
+
+/*
+Consumers read different dialects, PgnOptions picks one:
+line_width - export format keeps the movetext lines below 80 characters, None writes one line
+charset    - export format is Latin-1, characters it doesn't have become '?'
+tag_order  - the Seven Tag Roster always comes first, export format sorts the other tags
+comments, variations, nags, evals, clocks - which annotations make it into the movetext
+*/
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// Export format keeps the movetext lines below 80 characters
+const LINE_WIDTH: usize = 79;
+/*
+Import reads the tags and the main line, comments, variations and NAGs are skipped (read_pgn_tree
+keeps them, for studies and repertoires). Games that don't start from the usual position carry
+[SetUp "1"] and [FEN "..."]. Castling only knows the corner rooks, so a [Variant] tag other than
+standard chess is refused instead of read with the wrong rules
+*/
+
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum PgnCharset {
+	#[default]
+	Utf8,
+	Latin1,
+}
+
+impl PgnCharset {
+	pub fn encode(&self, pgn: &str) -> Vec<u8> {
+		// Bytes to write to a file, Latin-1 text has to be made representable first
+		return match self {
+			PgnCharset::Utf8 => pgn.as_bytes().to_vec(),
+			PgnCharset::Latin1 => pgn.chars().map(|chr| u8::try_from(u32::from(chr)).unwrap_or(b'?')).collect(),
+		};
+	}
+
+	fn represent(&self, text: &str) -> String {
+		return match self {
+			PgnCharset::Utf8 => text.to_string(),
+			PgnCharset::Latin1 => text.chars().map(|chr| if u32::from(chr) <= 0xff { chr } else { '?' }).collect(),
+		};
+	}
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum TagOrder {
+	// The roster, then the other tags in the order they were added
+	#[default]
+	Given,
+	// The roster, then the other tags sorted by name
+	Export,
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct PgnOptions {
+	pub line_width: Option<usize>,
+	pub charset: PgnCharset,
+	pub tag_order: TagOrder,
+	pub comments: bool,
+	pub variations: bool,
+	pub nags: bool,
+	pub evals: bool,
+	pub clocks: bool,
+}
+
+impl Default for PgnOptions {
+	fn default() -> Self {
+		PgnOptions {
+			line_width: Some(LINE_WIDTH),
+			charset: PgnCharset::Utf8,
+			tag_order: TagOrder::Given,
+			comments: true,
+			variations: true,
+			nags: true,
+			evals: true,
+			clocks: true,
+		}
+	}
+}
+
+impl PgnOptions {
+	pub fn export() -> Self {
+		// The spec's export format, what archives like TWIC expect
+		PgnOptions {
+			charset: PgnCharset::Latin1,
+			tag_order: TagOrder::Export,
+			..PgnOptions::default()
+		}
+	}
+
+	pub fn bare() -> Self {
+		// Tags and moves only
+		PgnOptions {
+			comments: false,
+			variations: false,
+			nags: false,
+			evals: false,
+			clocks: false,
+			..PgnOptions::default()
+		}
+	}
+}
+
+fn is_standard_variant(value: &str) -> bool {
+	return matches!(value.to_lowercase().replace([' ', '-'], "").as_str(), "standard" | "chess");
+}
+
+// A game read from PGN together with its tags, in the order they were written
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+	pub tags: Vec<(String, String)>,
+	pub game: Game,
+}
+
+// What gets written after one move of the game
+#[derive(Eq, Hash, Clone, PartialEq, Debug, Default)]
+pub struct PgnAnnotation {
+	// Numeric annotation glyphs, $1 is "!", $2 is "?" and so on
+	pub nags: Vec<u8>,
+	pub comment: Option<String>,
+	// Centipawns from white's point of view, written as a [%eval] command
+	pub eval: Option<i32>,
+	// Time left on the clock of the player who moved, written as a [%clk] command
+	pub clock: Option<Duration>,
+	// Other moves that could have been played instead, each from the position before the move
+	pub variations: Vec<Vec<Move>>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PgnWriter {
+	pub options: PgnOptions,
+	tags: Vec<(String, String)>,
+	// Indexed by ply, the first move of the game is ply 0
+	annotations: HashMap<usize, PgnAnnotation>,
+}
+
+pub fn result_token(outcome: Option<Outcome>) -> &'static str {
+	return match outcome {
+		Some(Outcome::WhiteWins) => "1-0",
+		Some(Outcome::BlackWins) => "0-1",
+		Some(Outcome::Draw) => "1/2-1/2",
+		None => "*",
+	};
+}
+
+pub fn parse_result_token(token: &str) -> Option<Option<Outcome>> {
+	// The other way around, None for text that isn't a result, Some(None) for "*"
+	return match token {
+		"1-0" => Some(Some(Outcome::WhiteWins)),
+		"0-1" => Some(Some(Outcome::BlackWins)),
+		"1/2-1/2" => Some(Some(Outcome::Draw)),
+		"*" => Some(None),
+		_ => None,
+	};
+}
+
+fn escape(value: &str) -> String {
+	return value.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+pub(crate) fn eval_text(score: i32) -> String {
+	// Pawns with two decimals, mates as #N, negative when black mates
+	if score.abs() >= MATE_SCORE - MAX_DEPTH as i32 {
+		let moves = (MATE_SCORE - score.abs() + 1) / 2;
+		return format!("#{}", if score > 0 { moves } else { -moves });
+	}
+	return format!("{}{}.{:02}", if score < 0 { "-" } else { "" }, score.abs() / 100, score.abs() % 100);
+}
+
+fn clock_text(time: Duration) -> String {
+	// H:MM:SS the way Lichess writes it, with tenths under a second
+	let seconds = time.as_secs();
+	let text = format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+	let tenths = time.subsec_millis() / 100;
+	if tenths > 0 {
+		return format!("{}.{}", text, tenths);
+	}
+	return text;
+}
+
+fn move_number(board: &Board, force: bool) -> Option<String> {
+	// White moves always get a number, black ones only where the movetext was interrupted
+	if board.turn == Color::White {
+		return Some(format!("{}.", board.fullmove_number()));
+	}
+	if force {
+		return Some(format!("{}...", board.fullmove_number()));
+	}
+	return None;
+}
+
+pub fn write_pgn(game: &Game) -> String {
+	return PgnWriter::new(PgnOptions::default()).write(game);
+}
+
+impl PgnWriter {
+	pub fn new(options: PgnOptions) -> Self {
+		PgnWriter {
+			options,
+			tags: Vec::new(),
+			annotations: HashMap::new(),
+		}
+	}
+
+	pub fn tag(&mut self, name: &str, value: &str) -> &mut Self {
+		// Sets a tag, the roster and the generated tags included
+		match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+			Some((_, old)) => *old = value.to_string(),
+			None => self.tags.push((name.to_string(), value.to_string())),
+		}
+		return self;
+	}
+
+	pub fn annotate(&mut self, ply: usize, annotation: PgnAnnotation) -> &mut Self {
+		self.annotations.insert(ply, annotation);
+		return self;
+	}
+
+	fn tag_pairs(&self, game: &Game, result: &str) -> Vec<(String, String)> {
+		let termination = game.termination();
+		let mut tags: Vec<(String, String)> = vec![
+			("Event".to_string(), "?".to_string()),
+			("Site".to_string(), "?".to_string()),
+			("Date".to_string(), "????.??.??".to_string()),
+			("Round".to_string(), "?".to_string()),
+			("White".to_string(), "?".to_string()),
+			("Black".to_string(), "?".to_string()),
+			("Result".to_string(), result.to_string()),
+		];
+
+		let start_fen = game.start().get_fen();
+		if start_fen != START_FEN {
+			tags.push(("SetUp".to_string(), "1".to_string()));
+			tags.push(("FEN".to_string(), start_fen));
+		}
+
+		tags.push((
+			"Termination".to_string(),
+			match termination {
+				Some((_, reason)) => reason.pgn_termination().to_string(),
+				None => "unterminated".to_string(),
+			},
+		));
+
+		// The result always comes from the game, it has to match the one after the moves
+		for (name, value) in self.tags.iter().filter(|(name, _)| name != "Result") {
+			match tags.iter_mut().find(|(tag, _)| tag == name) {
+				Some((_, old)) => *old = value.clone(),
+				None => tags.push((name.clone(), value.clone())),
+			}
+		}
+
+		if self.options.tag_order == TagOrder::Export {
+			tags[SEVEN_TAG_ROSTER.len()..].sort_by(|(left, _), (right, _)| left.cmp(right));
+		}
+		return tags;
+	}
+
+	fn annotation_tokens(&self, annotation: &PgnAnnotation, board: &Board, tokens: &mut Vec<String>) -> bool {
+		// function to add what follows a move, true when the movetext got interrupted
+		let mut interrupted = false;
+		if self.options.nags {
+			tokens.extend(annotation.nags.iter().map(|nag| format!("${}", nag)));
+		}
+
+		let mut comment: Vec<String> = Vec::new();
+		if let Some(eval) = annotation.eval.filter(|_| self.options.evals) {
+			comment.push(format!("[%eval {}]", eval_text(eval)));
+		}
+		if let Some(clock) = annotation.clock.filter(|_| self.options.clocks) {
+			comment.push(format!("[%clk {}]", clock_text(clock)));
+		}
+		if let Some(text) = annotation.comment.as_ref().filter(|_| self.options.comments) {
+			// A comment ends at the first closing brace, it can't contain one
+			comment.push(self.options.charset.represent(&text.replace('}', "")));
+		}
+		if !comment.is_empty() {
+			tokens.push(format!("{{{}}}", comment.join(" ")));
+			interrupted = true;
+		}
+
+		if self.options.variations {
+			for variation in &annotation.variations {
+				let mut line = *board;
+				let mut moves: Vec<String> = Vec::new();
+				for mv in variation {
+					if !line.legal_moves().contains(mv) {
+						break;
+					}
+					moves.extend(move_number(&line, moves.is_empty()));
+					moves.push(Notation::San.format(&line, *mv));
+					line.make_move(*mv);
+				}
+
+				if let Some(first) = moves.first_mut() {
+					first.insert(0, '(');
+					moves.last_mut().unwrap().push(')');
+					tokens.extend(moves);
+					interrupted = true;
+				}
+			}
+		}
+		return interrupted;
+	}
+
+	pub fn write(&self, game: &Game) -> String {
+		let result = result_token(game.termination().map(|(outcome, _)| outcome));
+
+		let mut pgn = String::new();
+		for (name, value) in self.tag_pairs(game, result) {
+			let value = self.options.charset.represent(&value);
+			pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(&value)));
+		}
+		pgn.push('\n');
+
+		// Movetext, black's first move gets a "1..." style number when the game starts with black
+		let mut tokens: Vec<String> = Vec::new();
+		let mut board = *game.start();
+		let mut interrupted = true;
+		for (ply, mv) in game.moves().iter().enumerate() {
+			tokens.extend(move_number(&board, interrupted));
+			tokens.push(Notation::San.format(&board, *mv));
+
+			let before = board;
+			board.make_move(*mv);
+			interrupted = match self.annotations.get(&ply) {
+				Some(annotation) => self.annotation_tokens(annotation, &before, &mut tokens),
+				None => false,
+			};
+		}
+		tokens.push(result.to_string());
+
+		let mut line = String::new();
+		for token in tokens {
+			let too_long = self.options.line_width.is_some_and(|width| line.len() + 1 + token.len() > width);
+			if !line.is_empty() && too_long {
+				pgn.push_str(&line);
+				pgn.push('\n');
+				line.clear();
+			}
+			if !line.is_empty() {
+				line.push(' ');
+			}
+			line.push_str(&token);
+		}
+		pgn.push_str(&line);
+		pgn.push('\n');
+
+		return pgn;
+	}
+}
+
+impl PgnGame {
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		return self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+	}
+
+	pub fn writer(&self, options: PgnOptions) -> PgnWriter {
+		// A writer that gives the tags back, SetUp, FEN and Termination come from the game
+		let mut writer = PgnWriter::new(options);
+		for (name, value) in self.tags.iter().filter(|(name, _)| !matches!(name.as_str(), "SetUp" | "FEN")) {
+			writer.tag(name, value);
+		}
+		return writer;
+	}
+}
+
+fn read_tag(line: &str) -> Result<(String, String), ChessError> {
+	// [Name "value"], the value may escape quotes and backslashes
+	let invalid = || ChessError::InvalidPgn(format!("invalid tag {}", line));
+	let inner = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')).ok_or_else(invalid)?;
+	let (name, quoted) = inner.split_once(char::is_whitespace).ok_or_else(invalid)?;
+	let quoted = quoted.trim().strip_prefix('"').and_then(|rest| rest.strip_suffix('"')).ok_or_else(invalid)?;
+
+	let mut value = String::new();
+	let mut chars = quoted.chars();
+	while let Some(chr) = chars.next() {
+		value.push(if chr == '\\' { chars.next().ok_or_else(invalid)? } else { chr });
+	}
+	return Ok((name.to_string(), value));
+}
+
+fn movetext_tokens(text: &str) -> Result<Vec<String>, ChessError> {
+	// The moves of the main line and the result, everything else taken out
+	let mut tokens: Vec<String> = Vec::new();
+	let mut token = String::new();
+	let mut depth = 0;
+	let mut chars = text.chars();
+	while let Some(chr) = chars.next() {
+		match chr {
+			'{' => {
+				chars.by_ref().find(|chr| *chr == '}').ok_or_else(|| ChessError::InvalidPgn("unterminated comment".to_string()))?;
+			}
+			';' => {
+				chars.by_ref().find(|chr| *chr == '\n');
+			}
+			'(' => depth += 1,
+			')' if depth == 0 => return Err(ChessError::InvalidPgn("unbalanced variation".to_string())),
+			')' => depth -= 1,
+			_ if depth > 0 => continue,
+			_ if chr.is_whitespace() => {}
+			_ => {
+				token.push(chr);
+				continue;
+			}
+		}
+		if !token.is_empty() {
+			tokens.push(std::mem::take(&mut token));
+		}
+	}
+	if depth > 0 {
+		return Err(ChessError::InvalidPgn("unbalanced variation".to_string()));
+	}
+	if !token.is_empty() {
+		tokens.push(token);
+	}
+
+	// Move numbers may stick to the move after them, "1.e4" or "12...Nf6"
+	return Ok(tokens
+	.into_iter()
+	.map(|token| {
+		let number = token.trim_start_matches(|chr: char| chr.is_ascii_digit());
+		if number.starts_with('.') {
+			return number.trim_start_matches('.').to_string();
+		}
+		return token;
+	})
+	.filter(|token| !token.is_empty() && !token.starts_with('$'))
+	.collect());
+}
+
+fn read_header(text: &str) -> Result<(PgnGame, String), ChessError> {
+	// One game at its start position with its tags, and its movetext
+	let mut tags: Vec<(String, String)> = Vec::new();
+	let mut movetext = String::new();
+	for line in text.lines() {
+		let line = line.trim();
+		if line.starts_with('[') && movetext.trim().is_empty() {
+			tags.push(read_tag(line)?);
+		} else if !line.starts_with('%') {
+			movetext.push_str(line);
+			movetext.push('\n');
+		}
+	}
+
+	let tag = |name: &str| tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+	if let Some(value) = tag("Variant").filter(|value| !is_standard_variant(value)) {
+		return Err(ChessError::InvalidPgn(format!("unsupported variant {}", value)));
+	}
+
+	let game = match tag("FEN") {
+		Some(fen) => Game::from_fen(fen)?,
+		None => Game::default(),
+	};
+	return Ok((PgnGame { tags, game }, movetext));
+}
+
+pub fn read_pgn(text: &str, mode: ParseMode) -> Result<PgnGame, ChessError> {
+	// function to read one game, moves the notation can't place are errors
+	let (PgnGame { tags, mut game }, movetext) = read_header(text)?;
+	let tag = |name: &str| tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+
+	let mut result: Option<Option<Outcome>> = None;
+	let tokens = movetext_tokens(&movetext)?;
+	for token in &tokens {
+		if let Some(outcome) = parse_result_token(token) {
+			result = Some(outcome);
+			break;
+		}
+		let mv = Notation::San
+		.parse_with(game.board(), token, mode)
+		.ok_or_else(|| ChessError::InvalidPgn(format!("can't read move {}", token)))?;
+		game.make_move(mv)?;
+	}
+
+	// A result the board doesn't show was agreed on or conceded
+	if game.termination().is_none() {
+		match result.or_else(|| tag("Result").and_then(parse_result_token)).flatten() {
+			Some(Outcome::WhiteWins) => game.resign(Color::Black)?,
+			Some(Outcome::BlackWins) => game.resign(Color::White)?,
+			Some(Outcome::Draw) => {
+				game.offer_draw(game.board().turn)?;
+				game.accept_draw(opponent(game.board().turn))?;
+			}
+			_ => {}
+		}
+	}
+
+	return Ok(PgnGame { tags, game });
+}
+
+fn opponent(color: Color) -> Color {
+	return match color {
+		Color::White => Color::Black,
+		Color::Black => Color::White,
+	};
+}
+
+// Limits of read_pgn_tree, so no text can take the stack: the plies of a line from the start
+// of the tree, and the variations inside one another
+pub const MAX_TREE_PLIES: usize = 2048;
+pub const MAX_VARIATION_NESTING: usize = 64;
+
+// A move of a game read with its variations, see read_pgn_tree
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct PgnNode {
+	pub mv: Move,
+	pub comment: Option<String>,
+	pub nags: Vec<u8>,
+	// The moves that can follow, the one of the line first
+	pub children: Vec<PgnNode>,
+}
+
+// A game read with its variations, comments and NAGs, the way studies and repertoires are written
+#[derive(Clone, Debug)]
+pub struct PgnTree {
+	pub tags: Vec<(String, String)>,
+	pub start: Board,
+	// The first moves, the one of the main line first
+	pub moves: Vec<PgnNode>,
+}
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+enum TreeToken {
+	Move(String),
+	Comment(String),
+	Nag(u8),
+	Open,
+	Close,
+}
+
+fn push_tree_word(word: &mut String, tokens: &mut Vec<TreeToken>) -> Result<(), ChessError> {
+	// A move with its number taken off or a NAG, results are left out
+	let text = std::mem::take(word);
+	let text = match text.trim_start_matches(|chr: char| chr.is_ascii_digit()) {
+		rest if rest.starts_with('.') => rest.trim_start_matches('.').to_string(),
+		_ => text,
+	};
+	if text.is_empty() || parse_result_token(&text).is_some() {
+		return Ok(());
+	}
+	match text.strip_prefix('$') {
+		Some(nag) => tokens.push(TreeToken::Nag(nag.parse().map_err(|_| ChessError::InvalidPgn(format!("invalid NAG {}", text)))?)),
+		None => tokens.push(TreeToken::Move(text)),
+	}
+	return Ok(());
+}
+
+fn tree_tokens(text: &str) -> Result<Vec<TreeToken>, ChessError> {
+	// Moves, comments, NAGs and parentheses
+	let mut tokens = Vec::new();
+	let mut word = String::new();
+	let mut chars = text.chars();
+	while let Some(chr) = chars.next() {
+		if matches!(chr, '{' | ';' | '(' | ')') || chr.is_whitespace() {
+			push_tree_word(&mut word, &mut tokens)?;
+		}
+		match chr {
+			'{' => {
+				let mut comment = String::new();
+				loop {
+					match chars.next() {
+						Some('}') => break,
+						Some(chr) => comment.push(chr),
+						None => return Err(ChessError::InvalidPgn("unterminated comment".to_string())),
+					}
+				}
+				tokens.push(TreeToken::Comment(comment.split_whitespace().collect::<Vec<&str>>().join(" ")));
+			}
+			';' => {
+				let comment: String = chars.by_ref().take_while(|chr| *chr != '\n').collect();
+				tokens.push(TreeToken::Comment(comment.trim().to_string()));
+			}
+			'(' => tokens.push(TreeToken::Open),
+			')' => tokens.push(TreeToken::Close),
+			_ if chr.is_whitespace() => {}
+			_ => word.push(chr),
+		}
+	}
+	push_tree_word(&mut word, &mut tokens)?;
+	return Ok(tokens);
+}
+
+fn tree_line(tokens: &[TreeToken], index: &mut usize, board: &Board, mode: ParseMode, ply: usize, nesting: usize) -> Result<Vec<PgnNode>, ChessError> {
+	// The moves played in `board` from `index` on, the one of the line first and the
+	// variations to it after it, with everything that follows them. `ply` is the one of the
+	// board in the tree, `nesting` the number of variations it is in
+	if nesting > MAX_VARIATION_NESTING {
+		return Err(ChessError::InvalidPgn("variations nested too deep".to_string()));
+	}
+	while matches!(tokens.get(*index), Some(TreeToken::Comment(_) | TreeToken::Nag(_))) {
+		*index += 1;
+	}
+
+	// Every move of the line with its variations, put together from the end
+	let mut board = *board;
+	let mut moves: Vec<(PgnNode, Vec<PgnNode>)> = Vec::new();
+	while let Some(TreeToken::Move(text)) = tokens.get(*index) {
+		if ply + moves.len() >= MAX_TREE_PLIES {
+			return Err(ChessError::InvalidPgn("line too long".to_string()));
+		}
+		let mv = Notation::San
+		.parse_with(&board, text, mode)
+		.ok_or_else(|| ChessError::InvalidPgn(format!("can't read move {}", text)))?;
+		*index += 1;
+
+		let mut node = PgnNode { mv, comment: None, nags: Vec::new(), children: Vec::new() };
+		let mut variations = Vec::new();
+		loop {
+			match tokens.get(*index) {
+				Some(TreeToken::Comment(text)) => {
+					node.comment = Some(match node.comment.take() {
+						Some(comment) => format!("{} {}", comment, text),
+						None => text.clone(),
+					});
+				}
+				Some(TreeToken::Nag(nag)) => node.nags.push(*nag),
+				Some(TreeToken::Open) => {
+					*index += 1;
+					variations.extend(tree_line(tokens, index, &board, mode, ply + moves.len(), nesting + 1)?);
+					if tokens.get(*index) != Some(&TreeToken::Close) {
+						return Err(ChessError::InvalidPgn("unbalanced variation".to_string()));
+					}
+				}
+				_ => break,
+			}
+			*index += 1;
+		}
+		board.make_move(mv);
+		moves.push((node, variations));
+	}
+
+	let mut line = Vec::new();
+	for (mut node, variations) in moves.into_iter().rev() {
+		node.children = line;
+		line = vec![node];
+		line.extend(variations);
+	}
+	return Ok(line);
+}
+
+pub fn read_pgn_tree(text: &str, mode: ParseMode) -> Result<PgnTree, ChessError> {
+	// One game with its variations, a variation starts from the position before the move it
+	// replaces
+	let (PgnGame { tags, game, .. }, movetext) = read_header(text)?;
+	let tokens = tree_tokens(&movetext)?;
+	let mut index = 0;
+	let moves = tree_line(&tokens, &mut index, game.start(), mode, 0, 0)?;
+	while matches!(tokens.get(index), Some(TreeToken::Comment(_) | TreeToken::Nag(_))) {
+		index += 1;
+	}
+	if index < tokens.len() {
+		return Err(ChessError::InvalidPgn("unbalanced variation".to_string()));
+	}
+	return Ok(PgnTree { tags, start: *game.start(), moves });
+}
+
+pub fn split_pgn(text: &str) -> Vec<String> {
+	// The games of a file (or the chapters of a study), a game starts with the tags after the
+	// movetext of the one before
+	let mut games: Vec<String> = Vec::new();
+	let mut current = String::new();
+	let mut in_movetext = false;
+	for line in text.lines() {
+		let trimmed = line.trim();
+		if trimmed.starts_with('[') && in_movetext {
+			games.push(std::mem::take(&mut current));
+			in_movetext = false;
+		}
+		if !trimmed.is_empty() && !trimmed.starts_with('[') {
+			in_movetext = true;
+		}
+		current.push_str(line);
+		current.push('\n');
+	}
+	if !current.trim().is_empty() {
+		games.push(current);
+	}
+	return games;
+}
@@ -0,0 +1,92 @@
+use crate::chess::*;
+
+// How far the game has gone, by the material left on the board and the development
+/*
+Every piece but the pawns and the kings counts towards the phase: knight 1, bishop 1, rook 2,
+queen 4, 24 with all of them on the board. The phase value goes from 0 with all of that
+material still there to 256 when it is all gone, a tapered evaluation mixes its opening and
+endgame scores by it (see taper)
+
+Opening    - (almost) all the pieces are there and they are still being developed
+Endgame    - a third or less of the piece material is left
+Middlegame - everything in between
+*/
+// source: https://www.chessprogramming.org/Tapered_Eval
+
+pub const PHASE_MAX: i32 = 256;
+// Sum of the phase weights of the starting position
+pub const PHASE_TOTAL: i32 = 24;
+// Phase weights that may be gone while the game is still an opening, a pair of minor pieces
+pub const OPENING_TRADES: i32 = 2;
+// Phase weights left at most in an endgame
+pub const ENDGAME_MATERIAL: i32 = 8;
+// The opening is over by this move even with pieces still at home
+pub const OPENING_MOVES: u32 = 10;
+// Minor pieces (both sides) still on their starting squares that keep the opening going after that
+pub const UNDEVELOPED_MINORS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamePhase {
+	Opening,
+	Middlegame,
+	Endgame,
+}
+
+pub fn phase_weight(breed: Pieces) -> i32 {
+	use Pieces::*;
+	return match breed {
+		Queen => 4,
+		Rook => 2,
+		Bishop | Knight => 1,
+		Pawn | King => 0,
+	};
+}
+
+pub fn taper(opening: i32, endgame: i32, phase: i32) -> i32 {
+	// Opening score at phase 0, endgame score at PHASE_MAX, in proportion in between
+	let phase = phase.clamp(0, PHASE_MAX);
+	return (opening * (PHASE_MAX - phase) + endgame * phase) / PHASE_MAX;
+}
+
+impl Board {
+	pub fn phase_material(&self) -> i32 {
+		// Phase weights of the pieces on the board, more than PHASE_TOTAL after promotions
+		return self
+		.white_pieces
+		.values()
+		.chain(self.black_pieces.values())
+		.map(|piece| phase_weight(piece.breed))
+		.sum();
+	}
+
+	pub fn phase_value(&self) -> i32 {
+		let gone = (PHASE_TOTAL - self.phase_material()).max(0);
+		return (gone * PHASE_MAX + PHASE_TOTAL / 2) / PHASE_TOTAL;
+	}
+
+	pub fn undeveloped_minors(&self) -> usize {
+		// Knights and bishops of both sides still on their starting squares
+		let mut count = 0;
+		for (row, color) in [(7, Color::White), (0, Color::Black)] {
+			for (col, breed) in [(1, Pieces::Knight), (2, Pieces::Bishop), (5, Pieces::Bishop), (6, Pieces::Knight)] {
+				if self.get_piece(coord!(row, col)) == Some(Piece { breed, color }) {
+					count += 1;
+				}
+			}
+		}
+		return count;
+	}
+
+	pub fn phase(&self) -> GamePhase {
+		let material = self.phase_material();
+		if material <= ENDGAME_MATERIAL {
+			return GamePhase::Endgame;
+		}
+
+		let developing = self.fullmove_number() <= OPENING_MOVES || self.undeveloped_minors() >= UNDEVELOPED_MINORS;
+		if material >= PHASE_TOTAL - OPENING_TRADES && developing {
+			return GamePhase::Opening;
+		}
+		return GamePhase::Middlegame;
+	}
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use crate::chess::*;
+use crate::engine::{GreedyMover, RandomMover, SearchControl, SearchLimits};
+use crate::error::ChessError;
+use crate::eval::MaterialEvaluator;
+use crate::game::Game;
+use crate::mcts::Mcts;
+use crate::search::AlphaBeta;
+
+// Immutable positions to share between threads
+/*
+Board and Game own all their data, so they are Send and Sync and a Board is Copy. Engines are
+Send and Sync when their evaluator is, they are moved into the thread that searches with them
+and stopped from elsewhere through the SearchControl they were given, which is shared on purpose.
+
+A Position is the thing to hand to a pool of workers: a snapshot that is cheap to clone (one
+reference count), with the hash and the legal moves worked out once for everybody. Playing a
+move gives a new Position and leaves the old one alone
+*/
+
+#[derive(Debug)]
+struct Snapshot {
+	board: Board,
+	hash: u64,
+	legal_moves: Vec<Move>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Position {
+	snapshot: Arc<Snapshot>,
+}
+
+// The build fails here if one of the types stops being safe to share
+const _: () = {
+	const fn shared<T: Send + Sync>() {}
+	shared::<Board>();
+	shared::<Game>();
+	shared::<Position>();
+	shared::<SearchLimits>();
+	shared::<SearchControl>();
+	shared::<AlphaBeta<MaterialEvaluator>>();
+	shared::<Mcts<MaterialEvaluator>>();
+	shared::<RandomMover>();
+	shared::<GreedyMover>();
+};
+
+impl Position {
+	pub fn new(board: Board) -> Self {
+		Position {
+			snapshot: Arc::new(Snapshot {
+				board,
+				hash: board.zobrist(),
+				legal_moves: board.legal_moves(),
+			}),
+		}
+	}
+
+	pub fn board(&self) -> &Board {
+		return &self.snapshot.board;
+	}
+
+	pub fn hash(&self) -> u64 {
+		return self.snapshot.hash;
+	}
+
+	pub fn legal_moves(&self) -> &[Move] {
+		return &self.snapshot.legal_moves;
+	}
+
+	pub fn fen(&self) -> String {
+		return self.snapshot.board.get_fen();
+	}
+
+	pub fn play(&self, mv: Move) -> Result<Position, ChessError> {
+		if !self.legal_moves().contains(&mv) {
+			return Err(ChessError::IllegalMove(mv));
+		}
+		let mut board = self.snapshot.board;
+		board.make_move(mv);
+		return Ok(Position::new(board));
+	}
+}
+
+impl Default for Position {
+	fn default() -> Self {
+		Position::new(Board::default())
+	}
+}
+
+impl PartialEq for Position {
+	fn eq(&self, other: &Self) -> bool {
+		return self.hash() == other.hash() && self.fen() == other.fen();
+	}
+}
+
+impl From<Board> for Position {
+	fn from(board: Board) -> Self {
+		Position::new(board)
+	}
+}
+
+impl From<&Game> for Position {
+	fn from(game: &Game) -> Self {
+		Position::new(*game.board())
+	}
+}
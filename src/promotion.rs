@@ -0,0 +1,83 @@
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::game::Game;
+
+// What a pawn turns into when the move doesn't say, for UCI moves like "e7e8" and quick-play UIs
+/*
+Strict         - the move has to name the piece, try_apply_move refuses it otherwise
+Queen          - always a queen
+AvoidStalemate - a queen, unless the opponent would be stalemated: then the first of rook,
+                 bishop and knight that doesn't stalemate (a queen when they all do)
+Ask            - the callback picks, GUIs pop a picker there. It gets the board and the move
+                 and is only asked about moves that are legal with some piece, None takes the
+                 move back and it is refused like with Strict
+
+Moves that name their piece, and moves that aren't promotions, are left alone
+*/
+
+// The piece for the move, None to take it back
+pub type PromotionPicker<'a> = Box<dyn FnMut(&Board, Move) -> Option<Pieces> + 'a>;
+
+#[derive(Default)]
+pub enum PromotionPolicy<'a> {
+	Strict,
+	#[default]
+	Queen,
+	AvoidStalemate,
+	Ask(PromotionPicker<'a>),
+}
+
+impl<'a> PromotionPolicy<'a> {
+	pub fn ask(callback: impl FnMut(&Board, Move) -> Option<Pieces> + 'a) -> Self {
+		return PromotionPolicy::Ask(Box::new(callback));
+	}
+}
+
+const PROMOTIONS: [Pieces; 4] = [Pieces::Queen, Pieces::Rook, Pieces::Bishop, Pieces::Knight];
+
+impl Board {
+	pub fn resolve_promotion(&self, mv: Move, policy: &mut PromotionPolicy) -> Move {
+		// The move with the piece the policy picks, when it is a pawn reaching the last rank
+		// without one
+		let is_pawn = self.get_piece(mv.from).is_some_and(|piece| piece.breed == Pieces::Pawn);
+		if mv.promotion.is_some() || !is_pawn || !(mv.to.row == 0 || mv.to.row == 7) {
+			return mv;
+		}
+		let with = |breed: Pieces| Move { promotion: Some(breed), ..mv };
+		if self.check_move(with(Pieces::Queen)).is_err() {
+			// Illegal whatever it promotes to, check_move tells why
+			return mv;
+		}
+
+		let breed = match policy {
+			PromotionPolicy::Strict => None,
+			PromotionPolicy::Queen => Some(Pieces::Queen),
+			PromotionPolicy::AvoidStalemate => {
+				let stalemates = |breed: Pieces| {
+					let mut board = *self;
+					board.make_move(with(breed));
+					return board.legal_moves().is_empty() && !board.is_in_check(board.turn);
+				};
+				Some(PROMOTIONS.into_iter().find(|breed| !stalemates(*breed)).unwrap_or(Pieces::Queen))
+			}
+			PromotionPolicy::Ask(callback) => callback(self, mv).filter(|breed| PROMOTIONS.contains(breed)),
+		};
+		return match breed {
+			Some(breed) => with(breed),
+			None => mv,
+		};
+	}
+
+	pub fn try_apply_move_with(&mut self, mv: Move, policy: &mut PromotionPolicy) -> Result<MoveOutcome, IllegalMove> {
+		// try_apply_move, with the promotion piece filled in by the policy
+		let mv = self.resolve_promotion(mv, policy);
+		return self.try_apply_move(mv);
+	}
+}
+
+impl Game {
+	pub fn make_move_with(&mut self, mv: Move, policy: &mut PromotionPolicy) -> Result<MoveOutcome, ChessError> {
+		let mv = self.board().resolve_promotion(mv, policy);
+		return self.make_move(mv);
+	}
+}
@@ -0,0 +1,154 @@
+use std::io::BufRead;
+
+use crate::chess::*;
+use crate::engine::{score_after_move, Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::search::{MATE_SCORE, MAX_DEPTH};
+
+// Tactics puzzles and the Lichess puzzle database
+// source: https://database.lichess.org/#puzzles
+/*
+PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,mate mateIn2 middlegame short,https://lichess.org/yyznGmXs/black#34,
+
+The FEN is the position before the opponent's last move, the first of the moves. A Puzzle
+starts after that move, so its FEN is the one the solver sees and the solution alternates
+between the solver and the forced replies, the solver's moves first
+*/
+/*
+A solution is verified one solver move at a time, by the engine's score of the position after
+each move. The solver move has to win (WINNING_SCORE or a mate) and every other move has to do
+worse by WINNING_SCORE at least, when it wins at all, or mate slower when the solution mates. A mate in one on the last move is always
+accepted, like Lichess does. Engines that don't score can't tell a winning move apart, every
+puzzle fails with them
+*/
+
+// Centipawns a move has to keep for the side that played it to count as winning
+pub const WINNING_SCORE: i32 = 200;
+const LICHESS_HEADER: &str = "PuzzleId";
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Puzzle {
+	pub id: String,
+	pub fen: String,
+	pub solution: Vec<Move>,
+	pub rating: u32,
+	pub rating_deviation: u32,
+	pub popularity: i32,
+	pub plays: u32,
+	pub themes: Vec<String>,
+	pub game_url: String,
+	pub openings: Vec<String>,
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum PuzzleCheck {
+	// Every solver move is the only one that wins
+	Unique,
+	// The solver move at this ply of the solution doesn't win
+	NotWinning(usize),
+	// Another move wins at this ply of the solution as well
+	Ambiguous(usize, Move),
+}
+
+fn uci_move(board: &Board, text: &str) -> Option<Move> {
+	return board.legal_moves().into_iter().find(|mv| mv.to_string() == text);
+}
+
+impl Puzzle {
+	pub fn from_lichess_csv(line: &str) -> Result<Puzzle, ChessError> {
+		let invalid = |reason: &str| ChessError::InvalidPuzzle(format!("{}: {}", reason, line));
+		let fields: Vec<&str> = line.trim_end().split(',').collect();
+		if fields.len() < 8 {
+			return Err(invalid("expected at least 8 fields"));
+		}
+		let number = |index: usize| fields[index].parse().map_err(|_| invalid("invalid number"));
+
+		// The opponent's move comes first, the puzzle starts after it
+		let mut board = Board::from_fen(fields[1])?;
+		let mut moves = fields[2].split_whitespace();
+		let setup = moves.next().and_then(|text| uci_move(&board, text)).ok_or_else(|| invalid("invalid first move"))?;
+		board.make_move(setup);
+		let start = board;
+
+		let mut solution: Vec<Move> = Vec::new();
+		for text in moves {
+			let mv = uci_move(&board, text).ok_or_else(|| invalid("illegal solution move"))?;
+			board.make_move(mv);
+			solution.push(mv);
+		}
+		if solution.is_empty() {
+			return Err(invalid("no solution"));
+		}
+
+		let words = |index: usize| -> Vec<String> {
+			return fields.get(index).map_or(Vec::new(), |field| field.split_whitespace().map(str::to_string).collect());
+		};
+		return Ok(Puzzle {
+			id: fields[0].to_string(),
+			fen: start.get_fen(),
+			solution,
+			rating: number(3)?,
+			rating_deviation: number(4)?,
+			popularity: fields[5].parse().map_err(|_| invalid("invalid number"))?,
+			plays: number(6)?,
+			themes: words(7),
+			game_url: fields.get(8).unwrap_or(&"").to_string(),
+			openings: words(9),
+		});
+	}
+
+	pub fn board(&self) -> Result<Board, ChessError> {
+		// The fields are public, so the FEN may not be the one from_lichess_csv checked
+		return Board::from_fen(&self.fen);
+	}
+
+	pub fn has_theme(&self, theme: &str) -> bool {
+		return self.themes.iter().any(|known| known == theme);
+	}
+
+	pub fn verify(&self, engine: &mut impl Engine, limits: &SearchLimits) -> Result<PuzzleCheck, ChessError> {
+		let mut board = self.board()?;
+		for (ply, mv) in self.solution.iter().enumerate() {
+			if ply % 2 == 1 {
+				board.try_apply_move(*mv)?;
+				continue;
+			}
+
+			let score = score_after_move(engine, &board, *mv, limits)?;
+			if score < WINNING_SCORE {
+				return Ok(PuzzleCheck::NotWinning(ply));
+			}
+
+			let last = ply + 1 == self.solution.len();
+			for candidate in board.legal_moves().into_iter().filter(|candidate| candidate != mv) {
+				let mut other = board;
+				other.make_move(candidate);
+				let mates = other.legal_moves().is_empty() && other.is_in_check(other.turn);
+				let other_score = score_after_move(engine, &board, candidate, limits)?;
+
+				let as_good = if score >= MATE_SCORE - MAX_DEPTH as i32 {
+					other_score >= score
+				} else {
+					other_score > score - WINNING_SCORE
+				};
+				if other_score >= WINNING_SCORE && as_good && !(last && mates) {
+					return Ok(PuzzleCheck::Ambiguous(ply, candidate));
+				}
+			}
+			board.make_move(*mv);
+		}
+		return Ok(PuzzleCheck::Unique);
+	}
+}
+
+pub fn read_lichess_csv(reader: impl BufRead) -> impl Iterator<Item = Result<Puzzle, ChessError>> {
+	// One puzzle per line, the header and empty lines are skipped
+	return reader
+	.lines()
+	.filter(|line| !matches!(line, Ok(line) if line.trim().is_empty() || line.starts_with(LICHESS_HEADER)))
+	.map(|line| match line {
+		Ok(line) => Puzzle::from_lichess_csv(&line),
+		Err(error) => Err(ChessError::InvalidPuzzle(error.to_string())),
+	});
+}
@@ -0,0 +1,212 @@
+use std::fmt::Write;
+
+use crate::chess::*;
+use crate::crosstable::FinishedGame;
+use crate::score::Score;
+
+// Rating changes of an event, the way FIDE and US Chess work them out
+/*
+Expected score - 1 / (1 + 10^(-D / 400)) for a rating difference D, FIDE counts differences of
+                 more than 400 as 400
+FIDE change    - K * (score - expected score), K is 40 for players with fewer than 30 rated
+                 games, 20 under 2400 and 10 from there on
+US Chess       - K = 800 / (N + m), with m the games of the event and N the player's games up to
+                 what the rating makes them worth: 50 / sqrt(0.662 + 0.00000739 * (2569 - R)^2).
+                 Three games or more earn a bonus on top, for the change beyond
+                 BONUS_THRESHOLD * sqrt(max(m, 4))
+Performance    - the rating the score would keep unchanged: the average opponent rating plus
+                 the difference the percentage stands for, at most 800 either way
+
+Games against unrated opponents aren't rated, for either player
+*/
+// source: https://handbook.fide.com/chapter/B022024
+// source: https://new.uschess.org/sites/default/files/media/documents/the-us-chess-rating-system-revised-september-2020.pdf
+/*
+Norms need NORM_GAMES games or more, a performance of the norm's level and opponents with an
+average rating high enough. Titles and federations of the opponents aren't looked at, the
+result is what the numbers allow, the arbiter has the last word
+*/
+// source: https://handbook.fide.com/chapter/B012024
+
+pub const FIDE_MAX_DIFFERENCE: f64 = 400.0;
+pub const MAX_PERFORMANCE_DIFFERENCE: f64 = 800.0;
+pub const BONUS_THRESHOLD: f64 = 14.0;
+pub const NORM_GAMES: usize = 9;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum RatingSystem {
+	#[default]
+	Fide,
+	Uscf,
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub enum Norm {
+	Grandmaster,
+	InternationalMaster,
+	WomanGrandmaster,
+	WomanInternationalMaster,
+}
+
+impl Norm {
+	pub const ALL: [Norm; 4] = [Norm::Grandmaster, Norm::InternationalMaster, Norm::WomanGrandmaster, Norm::WomanInternationalMaster];
+
+	pub fn performance(&self) -> i32 {
+		return match self {
+			Norm::Grandmaster => 2600,
+			Norm::InternationalMaster => 2450,
+			Norm::WomanGrandmaster => 2400,
+			Norm::WomanInternationalMaster => 2250,
+		};
+	}
+
+	pub fn average_opponent(&self) -> i32 {
+		// The lowest average rating of the opponents
+		return match self {
+			Norm::Grandmaster => 2380,
+			Norm::InternationalMaster => 2230,
+			Norm::WomanGrandmaster => 2180,
+			Norm::WomanInternationalMaster => 2030,
+		};
+	}
+}
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct RatedPlayer {
+	pub name: String,
+	pub rating: i32,
+	// Rated games before the event
+	pub games: u32,
+}
+
+impl RatedPlayer {
+	pub fn new(name: &str, rating: i32, games: u32) -> Self {
+		RatedPlayer { name: name.to_string(), rating, games }
+	}
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RatingChange {
+	pub player: String,
+	pub rating: i32,
+	// Rated games of the event
+	pub games: usize,
+	pub score: Score,
+	pub expected: f64,
+	pub change: f64,
+	pub new_rating: i32,
+	pub performance: i32,
+	pub average_opponent: i32,
+	pub norms: Vec<Norm>,
+}
+
+pub fn expected_score(rating: i32, opponent: i32, system: RatingSystem) -> f64 {
+	let mut difference = (rating - opponent) as f64;
+	if system == RatingSystem::Fide {
+		difference = difference.clamp(-FIDE_MAX_DIFFERENCE, FIDE_MAX_DIFFERENCE);
+	}
+	return 1.0 / (1.0 + 10f64.powf(-difference / 400.0));
+}
+
+pub fn performance_rating(average_opponent: f64, percentage: f64) -> i32 {
+	// `percentage` between 0 and 1
+	let difference = match percentage {
+		p if p <= 0.0 => -MAX_PERFORMANCE_DIFFERENCE,
+		p if p >= 1.0 => MAX_PERFORMANCE_DIFFERENCE,
+		p => (400.0 * (p / (1.0 - p)).log10()).clamp(-MAX_PERFORMANCE_DIFFERENCE, MAX_PERFORMANCE_DIFFERENCE),
+	};
+	return (average_opponent + difference).round() as i32;
+}
+
+pub fn k_factor(player: &RatedPlayer, event_games: usize, system: RatingSystem) -> f64 {
+	return match system {
+		RatingSystem::Fide if player.games < 30 => 40.0,
+		RatingSystem::Fide if player.rating < 2400 => 20.0,
+		RatingSystem::Fide => 10.0,
+		RatingSystem::Uscf => {
+			let worth = 50.0 / (0.662 + 0.00000739 * (2569.0 - player.rating as f64).powi(2)).sqrt();
+			800.0 / (worth.min(player.games as f64) + event_games as f64)
+		}
+	};
+}
+
+pub fn rating_changes(games: &[FinishedGame], players: &[RatedPlayer], system: RatingSystem) -> Vec<RatingChange> {
+	// One entry per player of the list, in its order, from the ratings before the event
+	let rating = |name: &str| players.iter().find(|player| player.name == name).map(|player| player.rating);
+
+	let mut changes = Vec::new();
+	for player in players {
+		// (opponent rating, score) of the player's rated games
+		let mut rated: Vec<(i32, Score)> = Vec::new();
+		for game in games {
+			let (opponent, color) = match (&game.white, &game.black) {
+				(white, black) if *white == player.name => (black, Color::White),
+				(white, black) if *black == player.name => (white, Color::Black),
+				_ => continue,
+			};
+			if let Some(opponent) = rating(opponent) {
+				rated.push((opponent, game.outcome.score(color)));
+			}
+		}
+
+		let score: Score = rated.iter().map(|(_, score)| *score).sum();
+		let expected: f64 = rated.iter().map(|(opponent, _)| expected_score(player.rating, *opponent, system)).sum();
+		let k = k_factor(player, rated.len(), system);
+		let mut change = k * (score.points() - expected);
+		if system == RatingSystem::Uscf && rated.len() >= 3 {
+			change += (change - BONUS_THRESHOLD * (rated.len().max(4) as f64).sqrt()).max(0.0);
+		}
+
+		let average = match rated.len() {
+			0 => 0.0,
+			count => rated.iter().map(|(opponent, _)| *opponent as f64).sum::<f64>() / count as f64,
+		};
+		let performance = match rated.len() {
+			0 => 0,
+			count => performance_rating(average, score.points() / count as f64),
+		};
+		let average_opponent = average.round() as i32;
+		let norms = Norm::ALL
+		.into_iter()
+		.filter(|norm| rated.len() >= NORM_GAMES && performance >= norm.performance() && average_opponent >= norm.average_opponent())
+		.collect();
+
+		changes.push(RatingChange {
+			player: player.name.clone(),
+			rating: player.rating,
+			games: rated.len(),
+			score,
+			expected,
+			change,
+			new_rating: (player.rating as f64 + change).round() as i32,
+			performance,
+			average_opponent,
+			norms,
+		});
+	}
+	return changes;
+}
+
+pub fn rating_report(changes: &[RatingChange]) -> String {
+	// One line per player, for the organizer's report
+	let width = changes.iter().map(|change| change.player.chars().count()).max().unwrap_or(0).max(6);
+	let mut out = String::new();
+	let _ = writeln!(out, "{:<width$}  Rating  Games  Score  Expected  Change    New   Perf   Avg", "Player", width = width);
+	for change in changes {
+		let _ = writeln!(
+			out,
+			"{:<width$}  {:>6}  {:>5}  {:>5}  {:>8.2}  {:>+6.1}  {:>5}  {:>5}  {:>4}",
+			change.player,
+			change.rating,
+			change.games,
+			change.score,
+			change.expected,
+			change.change,
+			change.new_rating,
+			change.performance,
+			change.average_opponent,
+			width = width
+		);
+	}
+	return out;
+}
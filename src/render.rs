@@ -0,0 +1,214 @@
+use std::fmt::Write;
+
+use crate::chess::*;
+use crate::game::Game;
+
+// Drawing the board in a terminal
+/*
+The piece set decides what a piece looks like, the color scheme what the squares look like:
+
+Unicode - chess figurines, outlined for white and filled for black, they need a font that has
+          them and a lot of Windows consoles don't
+Ascii   - the FEN letters, upper case for white, readable everywhere
+Dec     - the FEN letters on DEC Special Graphics checkerboard squares, for VT100 style
+          terminals (the Windows console included) that can't show the figurines
+
+Plain leaves the colors to the terminal, the other schemes paint the squares with the
+256-color palette and the pieces white and black on top of them
+
+The squares of the last move and the king in check are painted over, yellow and red. Without
+colors they get a mark instead of the space after them, '*' for the last move and '+' for check
+
+diagram() is the text for tests and bug reports, the same for the same position every time:
+the FEN letters and '.' split by single spaces, no labels, marks or colors, and the side to
+move on a last line. Board::from_diagram reads it back
+*/
+// source: https://en.wikipedia.org/wiki/DEC_Special_Graphics
+
+// Indexed by color and then piece, in the order of Pieces
+pub(crate) const FIGURINES: [[&str; 6]; 2] = [["♔", "♕", "♖", "♗", "♘", "♙"], ["♚", "♛", "♜", "♝", "♞", "♟"]];
+// The checkerboard character of the DEC Special Graphics set, switched in and out again
+const DEC_CHECKERBOARD: &str = "\x1b(0a\x1b(B";
+// 256-color palette indices of the piece colors
+const WHITE_INK: u8 = 231;
+const BLACK_INK: u8 = 16;
+// Light and dark squares of the last move, the square of a king in check
+const LAST_MOVE_SQUARES: (u8, u8) = (186, 143);
+const CHECK_SQUARE: u8 = 196;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum PieceSet {
+	#[default]
+	Unicode,
+	Ascii,
+	Dec,
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub enum ColorScheme {
+	#[default]
+	Plain,
+	Brown,
+	Blue,
+	Green,
+}
+
+impl ColorScheme {
+	pub fn squares(&self) -> Option<(u8, u8)> {
+		// 256-color palette indices of the light and the dark squares, None when not painted
+		return match self {
+			ColorScheme::Plain => None,
+			ColorScheme::Brown => Some((180, 130)),
+			ColorScheme::Blue => Some((153, 67)),
+			ColorScheme::Green => Some((187, 65)),
+		};
+	}
+}
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default)]
+pub struct RenderTheme {
+	pub pieces: PieceSet,
+	pub colors: ColorScheme,
+	// Rank numbers on the left, file letters below
+	pub labels: bool,
+}
+
+impl RenderTheme {
+	pub fn new(pieces: PieceSet, colors: ColorScheme) -> Self {
+		RenderTheme {
+			pieces,
+			colors,
+			labels: false,
+		}
+	}
+
+	pub fn labeled(self) -> Self {
+		RenderTheme { labels: true, ..self }
+	}
+
+	pub fn glyph(&self, piece: Piece) -> String {
+		return match self.pieces {
+			PieceSet::Unicode => FIGURINES[piece.color as usize][piece.breed as usize].to_string(),
+			PieceSet::Ascii | PieceSet::Dec => piece.to_fen_char().to_string(),
+		};
+	}
+
+	fn empty(&self, light: bool) -> &'static str {
+		// Painted squares speak for themselves
+		if self.colors != ColorScheme::Plain {
+			return " ";
+		}
+		return match self.pieces {
+			PieceSet::Dec if !light => DEC_CHECKERBOARD,
+			PieceSet::Dec => " ",
+			_ => ".",
+		};
+	}
+}
+
+impl Board {
+	pub fn render(&self, theme: &RenderTheme) -> String {
+		return self.render_move(theme, None);
+	}
+
+	pub fn render_move(&self, theme: &RenderTheme, last_move: Option<Move>) -> String {
+		// Rank 8 first, every square is the piece (or the empty square) and a space or a mark
+		/*
+		8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+		7 ♟ ♟ ♟ ♟ .*♟ ♟ ♟
+		6 . . . . . . . .
+		5 . . . . ♟*. . .
+		4 . . . . ♙ . . .
+		3 . . . . . . . .
+		2 ♙ ♙ ♙ ♙ . ♙ ♙ ♙
+		1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+		  a b c d e f g h
+		*/
+		let checked_king = self.get_king_coord(self.turn).filter(|_| self.is_in_check(self.turn));
+		let moved = |coord: Coordinate| last_move.is_some_and(|mv| mv.from == coord || mv.to == coord);
+
+		let mut out = String::new();
+		for row in 0..8 {
+			if theme.labels {
+				let _ = write!(out, "{} ", 8 - row);
+			}
+			for col in 0..8 {
+				let coord = coord!(row, col);
+				let piece = self.get_piece(coord);
+				let text = match piece {
+					Some(piece) => theme.glyph(piece),
+					None => theme.empty(coord.is_light()).to_string(),
+				};
+
+				match theme.colors.squares() {
+					Some((light, dark)) => {
+						let (light, dark) = if moved(coord) { LAST_MOVE_SQUARES } else { (light, dark) };
+						let mut background = if coord.is_light() { light } else { dark };
+						if checked_king == Some(coord) {
+							background = CHECK_SQUARE;
+						}
+						let ink = match piece.map(|piece| piece.color) {
+							Some(Color::Black) => BLACK_INK,
+							_ => WHITE_INK,
+						};
+						let _ = write!(out, "\x1b[48;5;{}m\x1b[38;5;{}m{} \x1b[0m", background, ink, text);
+					}
+					None => {
+						let mark = if checked_king == Some(coord) {
+							'+'
+						} else if moved(coord) {
+							'*'
+						} else {
+							' '
+						};
+						let _ = write!(out, "{}{}", text, mark);
+					}
+				}
+			}
+			out.push('\n');
+		}
+		if theme.labels {
+			out.push_str("  a b c d e f g h\n");
+		}
+		return out;
+	}
+}
+
+impl Board {
+	pub fn diagram(&self) -> String {
+		/*
+		r . b q k b n r
+		p p p p . p p p
+		. . n . . . . .
+		. . . . p . . .
+		. . . . P . . .
+		. . . . . N . .
+		P P P P . P P P
+		R N B Q K B . R
+		w
+		*/
+		let mut out = String::new();
+		for row in 0..8 {
+			let squares: Vec<String> = (0..8)
+			.map(|col| match self.get_piece(coord!(row, col)) {
+				Some(piece) => piece.to_fen_char().to_string(),
+				None => ".".to_string(),
+			})
+			.collect();
+			out.push_str(&squares.join(" "));
+			out.push('\n');
+		}
+		out.push_str(match self.turn {
+			Color::White => "w\n",
+			Color::Black => "b\n",
+		});
+		return out;
+	}
+}
+
+impl Game {
+	pub fn render(&self, theme: &RenderTheme) -> String {
+		// The current position with the last move of the game marked
+		return self.board().render_move(theme, self.moves().last().copied());
+	}
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use crate::chess::*;
+use crate::error::ChessError;
+use crate::notation::ParseMode;
+use crate::pgn::{read_pgn_tree, split_pgn, PgnNode};
+
+// An opening repertoire, the moves a player has prepared, and drilling it
+/*
+The repertoire is a tree of positions from its start position, the way the moves were added.
+In the positions where the player is to move the moves are the player's chosen responses (the
+first one is the one drilled first, usually there is only one), in the others they are the
+opponent's moves the player has prepared for. Every node can carry a comment
+
+PGN studies are imported chapter by chapter, with their variations and comments. Chapters have
+to start from the start position of the repertoire
+
+Positions are told apart by their zobrist hash, so move orders that transpose share their moves:
+the first node to reach a position holds them, the later ones are leaves pointing to it, and
+moves added after them go to it. Comments given to the later nodes go to the position too,
+unless the repertoire keeps them on the move order they were given for. A position repeating
+one of its own ancestors isn't merged
+
+Drilling works by spaced repetition, the SM-2 way, one card per position where the player is
+to move and has a response: a card is due on a day (days are any count the caller keeps, days
+since 1970 for example), a right answer pushes the next one further away (1 day, 6 days, then
+the last interval times the ease), a wrong one brings the card back the next day and makes it
+easier to come back. Due cards are drilled most overdue first, then in the order of the moves
+*/
+// source: https://super-memory.com/english/ol/sm2.htm
+
+pub const START_EASE: f64 = 2.5;
+pub const MIN_EASE: f64 = 1.3;
+// Ease lost with a wrong answer, a right one keeps it
+pub const WRONG_EASE: f64 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Card {
+	// Right answers in a row
+	pub repetitions: u32,
+	// Days
+	pub interval: u64,
+	pub ease: f64,
+	pub due: u64,
+}
+
+impl Default for Card {
+	fn default() -> Self {
+		Card {
+			repetitions: 0,
+			interval: 0,
+			ease: START_EASE,
+			due: 0,
+		}
+	}
+}
+
+impl Card {
+	pub fn review(&mut self, right: bool, today: u64) {
+		if right {
+			self.interval = match self.repetitions {
+				0 => 1,
+				1 => 6,
+				_ => (self.interval as f64 * self.ease).round() as u64,
+			};
+			self.repetitions += 1;
+		} else {
+			self.repetitions = 0;
+			self.interval = 1;
+			self.ease = (self.ease - WRONG_EASE).max(MIN_EASE);
+		}
+		self.due = today + self.interval;
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct RepertoireNode {
+	pub board: Board,
+	// The move from the parent, None for the root
+	pub mv: Option<Move>,
+	pub parent: Option<usize>,
+	pub children: Vec<usize>,
+	pub comment: Option<String>,
+	pub card: Card,
+	// The node holding the moves of the position, when this one reached it by another move order
+	pub transposition: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Repertoire {
+	// The side the repertoire is for
+	pub color: Color,
+	// Comments of transposing move orders stay on their own nodes
+	pub keep_move_order_comments: bool,
+	nodes: Vec<RepertoireNode>,
+	// Zobrist hash to the node holding the position
+	positions: HashMap<u64, usize>,
+}
+
+// A position to find the prepared move in
+#[derive(Debug, Clone)]
+pub struct DrillQuestion {
+	pub node: usize,
+	pub board: Board,
+	// From the start of the repertoire
+	pub moves: Vec<Move>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DrillAnswer {
+	pub right: bool,
+	// The prepared responses, the first one first
+	pub expected: Vec<Move>,
+	pub comment: Option<String>,
+	pub next_due: u64,
+}
+
+impl Repertoire {
+	pub const ROOT: usize = 0;
+
+	pub fn new(color: Color) -> Self {
+		return Repertoire::from_board(color, Board::default());
+	}
+
+	pub fn from_board(color: Color, board: Board) -> Self {
+		Repertoire {
+			color,
+			keep_move_order_comments: false,
+			nodes: vec![RepertoireNode {
+				board,
+				mv: None,
+				parent: None,
+				children: Vec::new(),
+				comment: None,
+				card: Card::default(),
+				transposition: None,
+			}],
+			positions: HashMap::from([(board.zobrist(), Repertoire::ROOT)]),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		return self.nodes.len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		// Only the root
+		return self.nodes.len() == 1;
+	}
+
+	pub fn node(&self, node: usize) -> Option<&RepertoireNode> {
+		return self.nodes.get(node);
+	}
+
+	pub fn position(&self, node: usize) -> usize {
+		// The node holding the moves of the position of `node`
+		return self.nodes.get(node).and_then(|current| current.transposition).unwrap_or(node);
+	}
+
+	pub fn find(&self, board: &Board) -> Option<usize> {
+		return self.positions.get(&board.zobrist()).copied();
+	}
+
+	pub fn transpositions(&self) -> Vec<(usize, usize)> {
+		// Every node reaching a position by another move order, with the node holding it
+		return self.nodes.iter().enumerate().filter_map(|(node, current)| Some((node, current.transposition?))).collect();
+	}
+
+	pub fn child(&self, node: usize, mv: Move) -> Option<usize> {
+		let node = self.position(node);
+		return self.nodes.get(node)?.children.iter().copied().find(|child| self.nodes[*child].mv == Some(mv));
+	}
+
+	pub fn add_move(&mut self, node: usize, mv: Move) -> Result<usize, ChessError> {
+		// The child `mv` leads to, added when it isn't there yet
+		let node = self.position(node);
+		if let Some(child) = self.child(node, mv) {
+			return Ok(child);
+		}
+		let mut board = self.nodes.get(node).ok_or(ChessError::PlyOutOfRange(node))?.board;
+		board.try_apply_move(mv)?;
+		let child = self.nodes.len();
+		let transposition = self.find(&board).filter(|position| !self.is_ancestor(*position, node));
+		self.nodes.push(RepertoireNode {
+			board,
+			mv: Some(mv),
+			parent: Some(node),
+			children: Vec::new(),
+			comment: None,
+			card: Card::default(),
+			transposition,
+		});
+		self.positions.entry(board.zobrist()).or_insert(child);
+		self.nodes[node].children.push(child);
+		return Ok(child);
+	}
+
+	pub fn add_line(&mut self, moves: &[Move]) -> Result<usize, ChessError> {
+		// The node at the end of the line from the root
+		let mut node = Repertoire::ROOT;
+		for mv in moves {
+			node = self.add_move(node, *mv)?;
+		}
+		return Ok(node);
+	}
+
+	fn is_ancestor(&self, ancestor: usize, node: usize) -> bool {
+		// `node` itself included
+		let mut current = Some(node);
+		while let Some(node) = current {
+			if node == ancestor {
+				return true;
+			}
+			current = self.nodes[node].parent;
+		}
+		return false;
+	}
+
+	fn comment_node(&self, node: usize) -> usize {
+		// Where a comment given to `node` goes, see above
+		return match self.keep_move_order_comments {
+			true => node,
+			false => self.position(node),
+		};
+	}
+
+	pub fn set_comment(&mut self, node: usize, comment: &str) -> bool {
+		let node = self.comment_node(node);
+		return match self.nodes.get_mut(node) {
+			Some(node) => {
+				node.comment = Some(comment.to_string());
+				true
+			}
+			None => false,
+		};
+	}
+
+	pub fn moves_to(&self, node: usize) -> Vec<Move> {
+		let mut moves = Vec::new();
+		let mut current = node;
+		while let Some(parent) = self.nodes[current].parent {
+			moves.push(self.nodes[current].mv.unwrap());
+			current = parent;
+		}
+		moves.reverse();
+		return moves;
+	}
+
+	pub fn responses(&self, node: usize) -> Vec<Move> {
+		// The prepared moves of the node, nothing when the opponent is to move there
+		return match self.nodes.get(self.position(node)) {
+			Some(current) if current.board.turn == self.color => current.children.iter().filter_map(|child| self.nodes[*child].mv).collect(),
+			_ => Vec::new(),
+		};
+	}
+
+	fn import_nodes(&mut self, node: usize, moves: &[PgnNode]) -> Result<usize, ChessError> {
+		// Nodes added, every move with everything after it
+		let mut added = 0;
+		for pgn in moves {
+			let before = self.nodes.len();
+			let child = self.add_move(node, pgn.mv)?;
+			added += self.nodes.len() - before;
+			if let Some(comment) = &pgn.comment {
+				// Move orders meeting with different comments keep both
+				let target = self.comment_node(child);
+				self.nodes[target].comment = match self.nodes[target].comment.take() {
+					Some(old) if old != *comment => Some(format!("{} {}", old, comment)),
+					_ => Some(comment.clone()),
+				};
+			}
+			added += self.import_nodes(child, &pgn.children)?;
+		}
+		return Ok(added);
+	}
+
+	pub fn import_pgn(&mut self, text: &str, mode: ParseMode) -> Result<usize, ChessError> {
+		// Every chapter of the study with its variations, the number of nodes added comes back
+		let mut added = 0;
+		for chapter in split_pgn(text) {
+			let tree = read_pgn_tree(&chapter, mode)?;
+			if tree.start.zobrist() != self.nodes[Repertoire::ROOT].board.zobrist() {
+				return Err(ChessError::InvalidPgn("the chapter starts from another position than the repertoire".to_string()));
+			}
+			added += self.import_nodes(Repertoire::ROOT, &tree.moves)?;
+		}
+		return Ok(added);
+	}
+
+	pub fn due(&self, today: u64) -> Vec<usize> {
+		// The nodes to drill today, most overdue first, once per position
+		let mut due: Vec<usize> = (0..self.nodes.len())
+		.filter(|node| self.nodes[*node].transposition.is_none() && !self.responses(*node).is_empty() && self.nodes[*node].card.due <= today)
+		.collect();
+		due.sort_by_key(|node| self.nodes[*node].card.due);
+		return due;
+	}
+
+	pub fn next_question(&self, today: u64) -> Option<DrillQuestion> {
+		let node = *self.due(today).first()?;
+		return Some(DrillQuestion {
+			node,
+			board: self.nodes[node].board,
+			moves: self.moves_to(node),
+		});
+	}
+
+	pub fn answer(&mut self, node: usize, mv: Move, today: u64) -> Result<DrillAnswer, ChessError> {
+		// Any prepared response is right, the card of the position is reviewed with the answer
+		let node = self.position(node);
+		let expected = self.responses(node);
+		if expected.is_empty() {
+			return Err(ChessError::InvalidPosition("nothing is prepared in this position".to_string()));
+		}
+		let right = expected.contains(&mv);
+		let card = &mut self.nodes[node].card;
+		card.review(right, today);
+		let next_due = card.due;
+		let comment = self.child(node, expected[0]).and_then(|child| self.nodes[child].comment.clone());
+		return Ok(DrillAnswer { right, expected, comment, next_due });
+	}
+}
@@ -0,0 +1,322 @@
+use std::fmt::Write;
+
+use crate::accuracy::{game_accuracy, GameAccuracy, WinModel};
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::game::{Game, Outcome};
+use crate::moments::{key_moments, KeyMoment, MomentKind};
+use crate::notation::Notation;
+use crate::opening::{identify_opening, Opening};
+use crate::pgn::{eval_text, result_token, PgnAnnotation, PgnWriter};
+
+// A report of a finished game: the opening, how well both sides played, the mistakes and the
+// key moments
+/*
+Every position of the game gets an evaluation (from an engine or given), from which come the
+accuracy and average centipawn loss of both players (see accuracy.rs) and the judgement of
+every move, by the winning chances in percent it gave away (the thresholds Lichess uses):
+Inaccuracy - INACCURACY or more, "?!"
+Mistake    - MISTAKE or more, "?"
+Blunder    - BLUNDER or more, "??"
+
+The key moments of the game come with it, see moments.rs
+*/
+// source: https://github.com/lichess-org/lila/blob/master/modules/analyse/src/main/Advice.scala
+
+pub const INACCURACY: f64 = 5.0;
+pub const MISTAKE: f64 = 10.0;
+pub const BLUNDER: f64 = 15.0;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, PartialOrd, Ord)]
+pub enum MoveJudgment {
+	Inaccuracy,
+	Mistake,
+	Blunder,
+}
+
+impl MoveJudgment {
+	pub fn judge(before: f64, after: f64) -> Option<MoveJudgment> {
+		// Winning chances in percent of the player who moved, before and after the move
+		let lost = before - after;
+		return match lost {
+			lost if lost >= BLUNDER => Some(MoveJudgment::Blunder),
+			lost if lost >= MISTAKE => Some(MoveJudgment::Mistake),
+			lost if lost >= INACCURACY => Some(MoveJudgment::Inaccuracy),
+			_ => None,
+		};
+	}
+
+	pub fn nag(&self) -> u8 {
+		return match self {
+			MoveJudgment::Inaccuracy => 6,
+			MoveJudgment::Mistake => 2,
+			MoveJudgment::Blunder => 4,
+		};
+	}
+
+	pub fn symbol(&self) -> &'static str {
+		return match self {
+			MoveJudgment::Inaccuracy => "?!",
+			MoveJudgment::Mistake => "?",
+			MoveJudgment::Blunder => "??",
+		};
+	}
+
+	pub fn name(&self) -> &'static str {
+		return match self {
+			MoveJudgment::Inaccuracy => "inaccuracy",
+			MoveJudgment::Mistake => "mistake",
+			MoveJudgment::Blunder => "blunder",
+		};
+	}
+}
+
+// A move of the game, with what the evaluations say about it
+#[derive(Debug, Clone)]
+pub struct ReportedMove {
+	pub ply: usize,
+	pub color: Color,
+	pub mv: Move,
+	pub san: String,
+	// Centipawns from white's point of view, of the positions before and after the move
+	pub before: i32,
+	pub after: i32,
+	pub judgment: Option<MoveJudgment>,
+	// The engine's choice in the position before the move, if it was another one
+	pub best: Option<Move>,
+	pub best_san: Option<String>,
+	// The position before the move
+	pub board: Board,
+}
+
+impl ReportedMove {
+	pub fn move_text(&self) -> String {
+		// "12. Nxe5" or "12... Nxe5"
+		let dots = match self.color {
+			Color::White => ".",
+			Color::Black => "...",
+		};
+		return format!("{}{} {}", self.board.fullmove_number(), dots, self.san);
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct GameReport {
+	pub white: String,
+	pub black: String,
+	pub result: Option<Outcome>,
+	pub opening: Option<Opening>,
+	// Centipawns from white's point of view, of every position from the start
+	pub evals: Vec<i32>,
+	pub accuracy: GameAccuracy,
+	// Every move of the game
+	pub moves: Vec<ReportedMove>,
+	pub key_moments: Vec<KeyMoment>,
+}
+
+impl GameReport {
+	pub fn new(game: &Game, engine: &mut impl Engine, limits: &SearchLimits, model: &WinModel) -> Result<Self, ChessError> {
+		// The engine evaluates every position of the game, from_evals can't refuse its evaluations
+		return Ok(GameReport::from_evals(game, &game.evaluate(engine, limits)?, model).unwrap());
+	}
+
+	pub fn from_evals(game: &Game, evals: &[(i32, Option<Move>)], model: &WinModel) -> Option<Self> {
+		// One (score for the side to move, best move) per position of the game, None otherwise
+		if evals.len() != game.moves().len() + 1 {
+			return None;
+		}
+
+		let boards: Vec<Board> = game.positions().collect();
+		let white_evals: Vec<i32> = boards
+		.iter()
+		.zip(evals)
+		.map(|(board, (score, _))| match board.turn {
+			Color::White => *score,
+			Color::Black => -*score,
+		})
+		.collect();
+		let percents: Vec<f64> = white_evals.iter().map(|eval| model.win_percent(*eval)).collect();
+
+		let mut moves = Vec::new();
+		for (ply, mv) in game.moves().iter().enumerate() {
+			let board = boards[ply];
+			let (before, after) = match board.turn {
+				Color::White => (percents[ply], percents[ply + 1]),
+				Color::Black => (100.0 - percents[ply], 100.0 - percents[ply + 1]),
+			};
+			let best = evals[ply].1.filter(|best| best != mv);
+			moves.push(ReportedMove {
+				ply,
+				color: board.turn,
+				mv: *mv,
+				san: Notation::San.format(&board, *mv),
+				before: white_evals[ply],
+				after: white_evals[ply + 1],
+				judgment: MoveJudgment::judge(before, after),
+				best,
+				best_san: best.map(|best| Notation::San.format(&board, best)),
+				board,
+			});
+		}
+
+		let result = game.termination().map(|(outcome, _)| outcome).or(game.game_result().map(|result| result.outcome()));
+		return Some(GameReport {
+			white: "White".to_string(),
+			black: "Black".to_string(),
+			result,
+			opening: identify_opening(game).map(|(opening, _)| opening),
+			accuracy: game_accuracy(game.start().turn, &white_evals, model),
+			evals: white_evals,
+			moves,
+			key_moments: key_moments(game, evals, model),
+		});
+	}
+
+	pub fn mistakes(&self) -> Vec<&ReportedMove> {
+		// The judged moves, in the order they were played
+		return self.moves.iter().filter(|mv| mv.judgment.is_some()).collect();
+	}
+
+	pub fn count(&self, color: Color, judgment: MoveJudgment) -> usize {
+		return self.moves.iter().filter(|mv| mv.color == color && mv.judgment == Some(judgment)).count();
+	}
+
+	pub fn annotate(&self, writer: &mut PgnWriter) {
+		// Evals of every move, glyphs of the judged ones and the better move as a variation
+		for mv in &self.moves {
+			let mut annotation = PgnAnnotation {
+				eval: Some(mv.after),
+				..PgnAnnotation::default()
+			};
+			if let Some(judgment) = mv.judgment {
+				annotation.nags.push(judgment.nag());
+				if let (Some(best), Some(best_san)) = (mv.best, &mv.best_san) {
+					annotation.comment = Some(format!("{}, {} was best", capitalize(judgment.name()), best_san));
+					annotation.variations.push(vec![best]);
+				}
+			}
+			writer.annotate(mv.ply, annotation);
+		}
+	}
+
+	fn header(&self) -> (String, String) {
+		// Title and opening lines
+		let title = format!("{} - {} {}", self.white, self.black, result_token(self.result));
+		let opening = match &self.opening {
+			Some(opening) => format!("{} {}", opening.eco, opening.name),
+			None => "unknown".to_string(),
+		};
+		return (title, opening);
+	}
+
+	fn player_rows(&self) -> Vec<[String; 6]> {
+		// Name, accuracy, ACPL, inaccuracies, mistakes and blunders of both players
+		let mut rows = Vec::new();
+		for (color, name) in [(Color::White, &self.white), (Color::Black, &self.black)] {
+			let number = |value: Option<f64>, decimals: usize| value.map_or("-".to_string(), |value| format!("{:.*}", decimals, value));
+			rows.push([
+				name.clone(),
+				number(self.accuracy.of(color), 1),
+				number(self.accuracy.centipawn_loss[color as usize], 0),
+				self.count(color, MoveJudgment::Inaccuracy).to_string(),
+				self.count(color, MoveJudgment::Mistake).to_string(),
+				self.count(color, MoveJudgment::Blunder).to_string(),
+			]);
+		}
+		return rows;
+	}
+
+	fn mistake_line(mv: &ReportedMove) -> String {
+		// "12. Nxe5?? (0.35 -> -3.20), d3 was best"
+		let mut line = format!("{}{} ({} -> {})", mv.move_text(), mv.judgment.map_or("", |judgment| judgment.symbol()), eval_text(mv.before), eval_text(mv.after));
+		if let Some(best) = &mv.best_san {
+			line.push_str(&format!(", {} was best", best));
+		}
+		return line;
+	}
+
+	fn moment_line(&self, moment: &KeyMoment) -> String {
+		// "12. Nxe5: the decisive mistake (0.35 -> -3.20), d3 was best"
+		let mv = &self.moves[moment.ply];
+		let mut line = format!("{}: {} ({} -> {})", mv.move_text(), moment.description(), eval_text(moment.before), eval_text(moment.after));
+		if let (MomentKind::DecisiveMistake | MomentKind::MissedWin, Some(best)) = (moment.kind, &mv.best_san) {
+			line.push_str(&format!(", {} was best", best));
+		}
+		return line;
+	}
+
+	pub fn to_text(&self) -> String {
+		let (title, opening) = self.header();
+		let mut out = String::new();
+		let _ = writeln!(out, "{}", title);
+		let _ = writeln!(out, "Opening: {}", opening);
+		let _ = writeln!(out);
+
+		let width = self.white.chars().count().max(self.black.chars().count()).max(6);
+		let _ = writeln!(out, "{:<width$}  Accuracy  ACPL  Inaccuracies  Mistakes  Blunders", "Player", width = width);
+		for row in self.player_rows() {
+			let _ = writeln!(out, "{:<width$}  {:>8}  {:>4}  {:>12}  {:>8}  {:>8}", row[0], row[1], row[2], row[3], row[4], row[5], width = width);
+		}
+
+		let _ = writeln!(out);
+		let _ = writeln!(out, "Mistakes");
+		for mv in self.mistakes() {
+			let _ = writeln!(out, "{}", GameReport::mistake_line(mv));
+			if mv.judgment == Some(MoveJudgment::Blunder) {
+				let _ = write!(out, "{}", mv.board.diagram());
+			}
+		}
+
+		let _ = writeln!(out);
+		let _ = writeln!(out, "Key moments");
+		for moment in &self.key_moments {
+			let _ = writeln!(out, "{}", self.moment_line(moment));
+		}
+		return out;
+	}
+
+	pub fn to_markdown(&self) -> String {
+		let (title, opening) = self.header();
+		let mut out = String::new();
+		let _ = writeln!(out, "# {}", title);
+		let _ = writeln!(out);
+		let _ = writeln!(out, "**Opening:** {}", opening);
+		let _ = writeln!(out);
+		let _ = writeln!(out, "| Player | Accuracy | ACPL | Inaccuracies | Mistakes | Blunders |");
+		let _ = writeln!(out, "| :--- | ---: | ---: | ---: | ---: | ---: |");
+		for row in self.player_rows() {
+			let _ = writeln!(out, "| {} |", row.join(" | "));
+		}
+
+		let _ = writeln!(out);
+		let _ = writeln!(out, "## Mistakes");
+		let _ = writeln!(out);
+		for mv in self.mistakes() {
+			let _ = writeln!(out, "- {}", GameReport::mistake_line(mv));
+			if mv.judgment == Some(MoveJudgment::Blunder) {
+				let _ = writeln!(out);
+				let _ = writeln!(out, "```text");
+				let _ = write!(out, "{}", mv.board.diagram());
+				let _ = writeln!(out, "```");
+				let _ = writeln!(out);
+			}
+		}
+
+		let _ = writeln!(out);
+		let _ = writeln!(out, "## Key moments");
+		let _ = writeln!(out);
+		for moment in &self.key_moments {
+			let _ = writeln!(out, "- {}", self.moment_line(moment));
+		}
+		return out;
+	}
+}
+
+fn capitalize(text: &str) -> String {
+	let mut chars = text.chars();
+	return match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	};
+}
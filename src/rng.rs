@@ -0,0 +1,60 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Small xorshift64* generator, good enough for picking moves and playouts
+// source: https://en.wikipedia.org/wiki/Xorshift#xorshift*
+#[derive(Debug, Clone)]
+pub struct Rng {
+	state: u64,
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		// Zero is a fixed point of xorshift, so it is swapped for a constant
+		Rng {
+			state: if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed },
+		}
+	}
+	
+	pub fn from_time() -> Self {
+		let nanos = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|time| time.as_nanos() as u64)
+		.unwrap_or(0);
+		return Rng::new(nanos);
+	}
+	
+	pub fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		return self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+	}
+	
+	pub fn below(&mut self, n: usize) -> usize {
+		// Random index in 0..n, n must not be zero
+		return (self.next_u64() % n as u64) as usize;
+	}
+	
+	pub fn next_f64(&mut self) -> f64 {
+		// Random number in [0, 1), built from the top 53 bits
+		return (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+	}
+}
+
+// Whatever plays or picks at random takes its generator from the outside
+/*
+Everything random starts with a generator seeded from the clock, seeding it again (or giving it
+another generator) makes its runs the same every time. Components holding others seed them from
+their own generator, so one seed covers them all
+*/
+pub trait Seeded {
+	fn rng(&mut self) -> &mut Rng;
+
+	fn set_rng(&mut self, rng: Rng) {
+		*self.rng() = rng;
+	}
+
+	fn seed(&mut self, seed: u64) {
+		self.set_rng(Rng::new(seed));
+	}
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+use crate::chess::*;
+use crate::game::Outcome;
+
+// Points of a player or a team over games, a win is one point and a draw a half
+/*
+Kept in half points, so 2.5 + 1.5 adds up to exactly 4. Scores are written the way crosstables
+and match reports have them: "2.5", "4", "0.5", and two of them as "2.5-1.5"
+*/
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug, Default, PartialOrd, Ord)]
+pub struct Score {
+	half_points: u32,
+}
+
+impl Score {
+	pub const ZERO: Score = Score { half_points: 0 };
+	pub const DRAW: Score = Score { half_points: 1 };
+	pub const WIN: Score = Score { half_points: 2 };
+
+	pub fn from_half_points(half_points: u32) -> Self {
+		Score { half_points }
+	}
+
+	pub fn half_points(&self) -> u32 {
+		return self.half_points;
+	}
+
+	pub fn points(&self) -> f64 {
+		return self.half_points as f64 / 2.0;
+	}
+
+	pub fn percentage(&self, games: usize) -> f64 {
+		// Share of the points that were there to win, 0 without games
+		if games == 0 {
+			return 0.0;
+		}
+		return self.half_points as f64 * 50.0 / games as f64;
+	}
+
+	pub fn parse(text: &str) -> Option<Score> {
+		// "2.5", "2½", "4" and "1/2" the way they are written in tables
+		let text = text.trim();
+		if text == "1/2" || text == "½" {
+			return Some(Score::DRAW);
+		}
+		let (whole, half) = match text.strip_suffix('½').or_else(|| text.strip_suffix(".5")) {
+			Some(whole) => (whole, 1),
+			None => (text.strip_suffix(".0").unwrap_or(text), 0),
+		};
+		let whole: u32 = if whole.is_empty() && half == 1 { 0 } else { whole.parse().ok()? };
+		// Too many points to count in half points is no score either
+		return Some(Score { half_points: whole.checked_mul(2)?.checked_add(half)? });
+	}
+}
+
+impl Add for Score {
+	type Output = Score;
+
+	fn add(self, other: Score) -> Score {
+		return Score { half_points: self.half_points + other.half_points };
+	}
+}
+
+impl AddAssign for Score {
+	fn add_assign(&mut self, other: Score) {
+		self.half_points += other.half_points;
+	}
+}
+
+impl Sum for Score {
+	fn sum<I: Iterator<Item = Score>>(iter: I) -> Score {
+		return iter.fold(Score::ZERO, Add::add);
+	}
+}
+
+impl fmt::Display for Score {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Padded like a string, so tables can line scores up with {:>5}
+		let text = match self.half_points % 2 {
+			0 => (self.half_points / 2).to_string(),
+			_ => format!("{}.5", self.half_points / 2),
+		};
+		return f.pad(&text);
+	}
+}
+
+impl Outcome {
+	pub fn score(&self, color: Color) -> Score {
+		// What the game is worth to the player of `color`
+		return match (self, color) {
+			(Outcome::Draw, _) => Score::DRAW,
+			(Outcome::WhiteWins, Color::White) | (Outcome::BlackWins, Color::Black) => Score::WIN,
+			_ => Score::ZERO,
+		};
+	}
+}
+
+pub fn match_score(first: Score, second: Score) -> String {
+	// "2.5-1.5"
+	return format!("{}-{}", first, second);
+}
@@ -0,0 +1,629 @@
+use std::time::Instant;
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchLimits, SearchStats};
+use crate::error::ChessError;
+use crate::eval::{piece_value, EvalCache, Evaluator};
+use crate::game::FIFTY_MOVE_PLIES;
+use crate::notation::Notation;
+use crate::options::{EngineOptions, UciOption};
+use crate::rng::{Rng, Seeded};
+use crate::skill::SkillLevel;
+use crate::time::TimeBudget;
+use crate::tt::{Bound, TranspositionTable, TtEntry};
+
+// Mate scores are MATE_SCORE minus the number of plies to the mate
+pub const MATE_SCORE: i32 = 100_000;
+// Depth used when the limits don't say anything
+pub const DEFAULT_DEPTH: u32 = 3;
+// Deepest iteration when only time or nodes limit the search
+pub const MAX_DEPTH: u32 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+	pub best_move: Option<Move>,
+	pub score: i32,
+	pub depth: u32,
+	// Principal variation, the line both sides are expected to play, starting with best_move
+	pub pv: Vec<Move>,
+	pub stats: SearchStats,
+}
+
+impl SearchResult {
+	pub fn pv_san(&self, board: &Board) -> String {
+		// The PV written like PGN movetext from the searched position: "12... Nf6 13. Bg5 Be7"
+		let mut tokens: Vec<String> = Vec::new();
+		let mut board = *board;
+		for (i, mv) in self.pv.iter().enumerate() {
+			if board.turn == Color::White {
+				tokens.push(format!("{}.", board.fullmove_number()));
+			} else if i == 0 {
+				tokens.push(format!("{}...", board.fullmove_number()));
+			}
+
+			tokens.push(Notation::San.format(&board, *mv));
+			board.make_move(*mv);
+		}
+		return tokens.join(" ");
+	}
+}
+
+// Iterative deepening negamax with alpha-beta pruning
+#[derive(Debug, Clone)]
+pub struct AlphaBeta<E: Evaluator> {
+	pub evaluator: E,
+	pub options: EngineOptions,
+	eval_cache: EvalCache,
+	// Kept between searches like the evaluation cache, sized by the Hash option
+	tt: TranspositionTable,
+	limits: SearchLimits,
+	start: Instant,
+	stats: SearchStats,
+	stopped: bool,
+	// Still searching the opponent's time, the limits don't apply yet
+	pondering: bool,
+	budget: Option<TimeBudget>,
+	// None at full strength
+	skill: Option<SkillLevel>,
+	rng: Rng,
+	last_score: Option<i32>,
+	last_pv: Vec<Move>,
+	// Hashes of the game history and of the line being searched, without the current node
+	path: Vec<u64>,
+	// Draws by repetition and the 50-move rule found so far, they depend on the way to the
+	// position and not on the position, and stalemates scored with contempt, which depend on
+	// the color at the root. A score with one of them under it isn't stored
+	path_draws: u64,
+}
+
+impl<E: Evaluator> AlphaBeta<E> {
+	pub fn new(evaluator: E) -> Self {
+		AlphaBeta {
+			evaluator,
+			options: EngineOptions::default(),
+			eval_cache: EvalCache::new(EngineOptions::default().eval_cache_mb),
+			tt: TranspositionTable::new(EngineOptions::default().hash_mb),
+			limits: SearchLimits::default(),
+			start: Instant::now(),
+			stats: SearchStats::default(),
+			stopped: false,
+			pondering: false,
+			budget: None,
+			skill: None,
+			rng: Rng::from_time(),
+			last_score: None,
+			last_pv: Vec::new(),
+			path: Vec::new(),
+			path_draws: 0,
+		}
+	}
+
+	pub fn eval_cache(&self) -> &EvalCache {
+		// Kept between searches, its statistics included
+		return &self.eval_cache;
+	}
+
+	pub fn transposition_table(&self) -> &TranspositionTable {
+		return &self.tt;
+	}
+
+	pub fn set_transposition_table(&mut self, table: TranspositionTable) {
+		// A table saved by an earlier session picks the analysis up where it stopped
+		// NOTE: Changing the options replaces it with an empty one
+		self.tt = table;
+	}
+
+	pub fn search(&mut self, board: &Board, limits: &SearchLimits) -> SearchResult {
+		return self.search_with(board, limits, |_| {});
+	}
+
+	fn start(&mut self, board: &Board, limits: &SearchLimits) {
+		// Resets everything a new search needs
+		self.limits = limits.clone();
+		self.start = Instant::now();
+		self.stats = SearchStats::default();
+		self.stopped = false;
+		self.pondering = limits.control.is_pondering();
+		self.budget = TimeBudget::new(limits, board.turn, self.options.move_overhead);
+		self.skill = SkillLevel::from_options(&self.options);
+		self.path = limits.history.clone();
+	}
+
+	pub fn search_with(&mut self, board: &Board, limits: &SearchLimits, mut on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+		// `on_iteration` gets the result of every finished iteration, for GUIs and analysis screens
+		trace_span!(
+			tracing::Level::DEBUG,
+			"search",
+			depth = ?limits.depth,
+			nodes = ?limits.nodes,
+			movetime = ?limits.movetime
+		);
+		self.start(board, limits);
+
+		let mut result = SearchResult {
+			best_move: None,
+			score: 0,
+			depth: 0,
+			pv: Vec::new(),
+			stats: SearchStats::default(),
+		};
+		// Every legal move is excluded, there is nothing to search
+		if limits.root_moves(board).is_empty() && !board.legal_moves().is_empty() {
+			return result;
+		}
+
+		// Iterations in a row that kept the best move
+		let mut stable_iterations = 0;
+		let mut depth = 1;
+		while depth <= self.max_depth() {
+			let mut pv: Vec<Move> = Vec::new();
+			let score = self.search_root(board, depth, result.best_move, &mut pv);
+			let best_move = pv.first().copied();
+
+			// An interrupted iteration is only trusted when there is nothing better
+			if self.stopped && result.best_move.is_some() {
+				break;
+			}
+
+			if best_move == result.best_move {
+				stable_iterations += 1;
+			} else {
+				stable_iterations = 0;
+			}
+			result.best_move = best_move;
+			result.score = score;
+			result.depth = depth;
+			result.pv = pv;
+			trace_event!(
+				tracing::Level::DEBUG,
+				depth,
+				score,
+				nodes = self.stats.nodes,
+				best_move = ?best_move.map(|mv| mv.to_string()),
+				"iteration finished"
+			);
+			result.stats = self.stats;
+			on_iteration(&result);
+
+			if self.stopped || score.abs() >= MATE_SCORE - MAX_DEPTH as i32 {
+				break;
+			}
+
+			// The next iteration takes longer than all the previous ones, don't start it late
+			if let Some(budget) = self.budget {
+				if !self.pondering && self.start.elapsed() >= budget.soft_limit(stable_iterations) {
+					break;
+				}
+			}
+			depth += 1;
+		}
+
+		if let Some(skill) = self.skill {
+			if !self.stopped && result.depth > 0 {
+				if let Some((mv, score, pv)) = self.weaker_move(board, result.depth, skill) {
+					result.best_move = Some(mv);
+					result.score = score;
+					result.pv = pv;
+				}
+			}
+		}
+
+		if result.best_move.is_none() {
+			// Out of limits before the first move was searched, any legal move beats none
+			result.best_move = limits.root_moves(board).first().copied();
+			result.pv = result.best_move.into_iter().collect();
+		}
+
+		result.stats = self.stats;
+		return result;
+	}
+
+	fn max_depth(&self) -> u32 {
+		// Asked every iteration, a ponder search without a depth goes on until the ponderhit
+		let depth = match self.limits.depth {
+			Some(depth) => depth.max(1),
+			None if self.limits.nodes.is_some() || self.budget.is_some() => MAX_DEPTH,
+			None if self.pondering => MAX_DEPTH,
+			None => DEFAULT_DEPTH,
+		};
+		return match self.skill {
+			Some(skill) => depth.min(skill.max_depth()),
+			None => depth,
+		};
+	}
+
+	fn weaker_move(&mut self, board: &Board, depth: u32, skill: SkillLevel) -> Option<(Move, i32, Vec<Move>)> {
+		// The skill level chooses among the good root moves, None when the search runs out of limits first
+		let lines = self.score_root_moves(board, depth)?;
+		let scored: Vec<(Move, i32)> = lines.iter().map(|(mv, score, _)| (*mv, *score)).collect();
+
+		let mv = skill.pick(&scored, &mut self.rng)?;
+		return lines.into_iter().find(|(scored_mv, _, _)| *scored_mv == mv);
+	}
+
+	fn score_root_moves(&mut self, board: &Board, depth: u32) -> Option<Vec<(Move, i32, Vec<Move>)>> {
+		// Every root move with an exact score and its line, None when the search runs out of limits first
+		let mut result: Vec<(Move, i32, Vec<Move>)> = Vec::new();
+		let hash = board.zobrist();
+		for mv in self.limits.root_moves(board) {
+			let mut child = *board;
+			child.make_move(mv);
+			let mut child_pv: Vec<Move> = Vec::new();
+			self.path.push(hash);
+			let score = -self.negamax(&child, depth - 1, 1, -MATE_SCORE - 1, MATE_SCORE + 1, &mut child_pv);
+			self.path.pop();
+
+			if self.stopped {
+				return None;
+			}
+			let mut pv = vec![mv];
+			pv.extend_from_slice(&child_pv);
+			result.push((mv, score, pv));
+		}
+		return Some(result);
+	}
+
+	pub fn search_lines(
+		&mut self,
+		board: &Board,
+		limits: &SearchLimits,
+		lines: usize,
+		mut on_iteration: impl FnMut(&[SearchResult]),
+	) -> Vec<SearchResult> {
+		// The best `lines` moves with their own scores and lines, best first (MultiPV)
+		// Every root move gets an exact score, so it costs more than a search for the best move alone
+		self.start(board, limits);
+		let mut results: Vec<SearchResult> = Vec::new();
+
+		let mut depth = 1;
+		while depth <= self.max_depth() {
+			let mut scored = match self.score_root_moves(board, depth) {
+				Some(scored) => scored,
+				None => break,
+			};
+			// Stable, so equal scores keep the move ordering
+			scored.sort_by_key(|(_, score, _)| -score);
+
+			results = scored
+			.into_iter()
+			.take(lines.max(1))
+			.map(|(mv, score, pv)| SearchResult {
+				best_move: Some(mv),
+				score,
+				depth,
+				pv,
+				stats: self.stats,
+			})
+			.collect();
+			on_iteration(&results);
+
+			if results.is_empty() || results[0].score.abs() >= MATE_SCORE - MAX_DEPTH as i32 {
+				break;
+			}
+			if let Some(budget) = self.budget {
+				if !self.pondering && self.start.elapsed() >= budget.soft {
+					break;
+				}
+			}
+			depth += 1;
+		}
+
+		return results;
+	}
+
+	fn search_root(&mut self, board: &Board, depth: u32, previous_best: Option<Move>, pv: &mut Vec<Move>) -> i32 {
+		// Fills `pv` with the best line found, empty when no move was finished
+		let allowed = self.limits.root_moves(board);
+		let mut moves: Vec<Move> = self.ordered_moves(board).into_iter().filter(|mv| allowed.contains(mv)).collect();
+
+		// The best move of the previous iteration is searched first
+		if let Some(previous_best) = previous_best {
+			if let Some(index) = moves.iter().position(|mv| *mv == previous_best) {
+				let mv = moves.remove(index);
+				moves.insert(0, mv);
+			}
+		}
+
+		let mut alpha = -MATE_SCORE - 1;
+		let beta = MATE_SCORE + 1;
+
+		if moves.is_empty() {
+			return self.terminal_score(board, 0);
+		}
+
+		let hash = board.zobrist();
+		let path_draws = self.path_draws;
+		let mut child_pv: Vec<Move> = Vec::new();
+		for mv in moves {
+			let mut child = *board;
+			child.make_move(mv);
+			self.path.push(hash);
+			let score = -self.negamax(&child, depth - 1, 1, -beta, -alpha, &mut child_pv);
+			self.path.pop();
+
+			if self.stopped {
+				break;
+			}
+
+			if score > alpha || pv.is_empty() {
+				alpha = score;
+				update_pv(pv, mv, &child_pv);
+			}
+		}
+
+		// A score among some of the moves only isn't the position's
+		let restricted = !self.limits.searchmoves.is_empty() || !self.limits.excluded.is_empty();
+		if !self.stopped && !restricted && self.path_draws == path_draws {
+			self.store(hash, depth, 0, alpha, Bound::Exact, pv.first().copied());
+		}
+		return alpha;
+	}
+
+	fn negamax(&mut self, board: &Board, depth: u32, ply: u32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+		// `pv` gets the best line from this node, it stays empty unless a move raised alpha
+		pv.clear();
+		self.stats.nodes += 1;
+		if self.out_of_limits() {
+			self.stopped = true;
+			return 0;
+		}
+
+		// Draws by the rules end the line whatever the material, except a mate on the last move
+		let hash = board.zobrist();
+		if self.is_repetition(board, hash) {
+			self.path_draws += 1;
+			return self.draw_score(ply);
+		}
+		if board.halfmove_clock() >= FIFTY_MOVE_PLIES && !(board.is_in_check(board.turn) && board.legal_moves().is_empty()) {
+			self.path_draws += 1;
+			return self.draw_score(ply);
+		}
+
+		if depth == 0 {
+			let score = self.eval_cache.evaluate(&self.evaluator, board);
+			return match self.skill {
+				Some(skill) => skill.perturb(score, &mut self.rng),
+				None => score,
+			};
+		}
+
+		// Weaker play scores the leaves at random, those scores are not worth keeping
+		let entry = if self.skill.is_none() { self.tt.probe(hash) } else { None };
+		if let Some(entry) = entry {
+			self.stats.tt_hits += 1;
+			let score = score_from_tt(entry.score, ply);
+			let usable = match entry.bound {
+				Bound::Exact => true,
+				Bound::Lower => score >= beta,
+				Bound::Upper => score <= alpha,
+			};
+			if entry.depth == depth && usable {
+				if score > alpha && score < beta {
+					// The line goes on with the stored move, when it is one (hashes may collide)
+					if let Some(mv) = entry.best_move.filter(|mv| board.legal_moves().contains(mv)) {
+						pv.push(mv);
+					}
+				}
+				return score.clamp(alpha, beta);
+			}
+		}
+
+		let mut moves = self.ordered_moves(board);
+		if moves.is_empty() {
+			return self.terminal_score(board, ply);
+		}
+		if let Some(index) = entry.and_then(|entry| moves.iter().position(|mv| Some(*mv) == entry.best_move)) {
+			let mv = moves.remove(index);
+			moves.insert(0, mv);
+		}
+
+		let original_alpha = alpha;
+		let path_draws = self.path_draws;
+		let mut child_pv: Vec<Move> = Vec::new();
+		for mv in moves {
+			let mut child = *board;
+			child.make_move(mv);
+			self.path.push(hash);
+			let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+			self.path.pop();
+
+			if self.stopped {
+				return 0;
+			}
+
+			if score >= beta {
+				self.stats.beta_cutoffs += 1;
+				if self.path_draws == path_draws {
+					self.store(hash, depth, ply, beta, Bound::Lower, Some(mv));
+				}
+				return beta;
+			}
+			if score > alpha {
+				alpha = score;
+				update_pv(pv, mv, &child_pv);
+			}
+		}
+
+		if self.path_draws != path_draws {
+			return alpha;
+		}
+		if alpha > original_alpha {
+			self.store(hash, depth, ply, alpha, Bound::Exact, pv.first().copied());
+		} else {
+			self.store(hash, depth, ply, alpha, Bound::Upper, None);
+		}
+		return alpha;
+	}
+
+	fn store(&mut self, key: u64, depth: u32, ply: u32, score: i32, bound: Bound, best_move: Option<Move>) {
+		if self.skill.is_some() {
+			return;
+		}
+		self.tt.store(TtEntry {
+			key,
+			depth,
+			score: score_to_tt(score, ply),
+			bound,
+			best_move,
+		});
+	}
+
+	fn is_repetition(&self, board: &Board, hash: u64) -> bool {
+		// Once is enough inside the search, the side that can repeat can repeat again
+		// Only the positions since the last capture or pawn move can be the same
+		return self
+		.path
+		.iter()
+		.rev()
+		.take(board.halfmove_clock() as usize)
+		.any(|seen| *seen == hash);
+	}
+
+	fn terminal_score(&mut self, board: &Board, ply: u32) -> i32 {
+		// No moves: mated if in check, stalemate otherwise
+		if board.is_in_check(board.turn) {
+			return -MATE_SCORE + ply as i32;
+		}
+		if self.options.contempt != 0 {
+			self.path_draws += 1;
+		}
+		return self.draw_score(ply);
+	}
+
+	fn draw_score(&self, ply: u32) -> i32 {
+		// A draw is worth -contempt to the side to move at the root, +contempt to its opponent,
+		// with a positive contempt the engine steers clear of draws and seeks them when negative
+		if ply.is_multiple_of(2) {
+			return -self.options.contempt;
+		}
+		return self.options.contempt;
+	}
+
+	fn ordered_moves(&self, board: &Board) -> Vec<Move> {
+		// Captures of valuable pieces by cheap ones go first, it makes the pruning a lot better
+		let mut moves = board.legal_moves();
+		moves.sort_by_key(|mv| {
+			let victim = board.get_piece(mv.to).map_or(0, |piece| piece_value(piece.breed));
+			let attacker = board.get_piece(mv.from).map_or(0, |piece| piece_value(piece.breed));
+			if victim > 0 {
+				-(victim * 10 - attacker)
+			} else {
+				0
+			}
+		});
+		return moves;
+	}
+
+	fn out_of_limits(&mut self) -> bool {
+		if self.limits.control.is_stopped() {
+			return true;
+		}
+
+		if self.pondering {
+			if self.limits.control.is_pondering() {
+				return false;
+			}
+			// Ponderhit, the limits count from now on
+			self.pondering = false;
+			self.start = Instant::now();
+		}
+
+		if let Some(nodes) = self.limits.nodes {
+			if self.stats.nodes >= nodes {
+				return true;
+			}
+		}
+
+		if let Some(budget) = self.budget {
+			// Checking the clock is not free, so it is done every so often
+			if self.stats.nodes.is_multiple_of(256) && self.start.elapsed() >= budget.hard {
+				return true;
+			}
+		}
+
+		return false;
+	}
+}
+
+fn score_to_tt(score: i32, ply: u32) -> i32 {
+	// Mate scores count from the stored position instead of the root
+	if score >= MATE_SCORE - MAX_DEPTH as i32 {
+		return score + ply as i32;
+	}
+	if score <= -MATE_SCORE + MAX_DEPTH as i32 {
+		return score - ply as i32;
+	}
+	return score;
+}
+
+fn score_from_tt(score: i32, ply: u32) -> i32 {
+	if score >= MATE_SCORE - MAX_DEPTH as i32 {
+		return score - ply as i32;
+	}
+	if score <= -MATE_SCORE + MAX_DEPTH as i32 {
+		return score + ply as i32;
+	}
+	return score;
+}
+
+fn update_pv(pv: &mut Vec<Move>, mv: Move, child_pv: &[Move]) {
+	// A new best move, the line continues with whatever the child found
+	pv.clear();
+	pv.push(mv);
+	pv.extend_from_slice(child_pv);
+}
+
+impl<E: Evaluator> Seeded for AlphaBeta<E> {
+	fn rng(&mut self) -> &mut Rng {
+		return &mut self.rng;
+	}
+}
+
+impl<E: Evaluator> Engine for AlphaBeta<E> {
+	fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+		let result = self.search(board, limits);
+		self.last_score = Some(result.score);
+		self.last_pv = result.pv;
+
+		return result.best_move.ok_or(ChessError::NoLegalMoves);
+	}
+
+	fn score(&self) -> Option<i32> {
+		return self.last_score;
+	}
+
+	fn pv(&self) -> Vec<Move> {
+		return self.last_pv.clone();
+	}
+
+	fn multi_pv(&mut self, board: &Board, limits: &SearchLimits, lines: usize) -> Vec<SearchResult> {
+		let results = self.search_lines(board, limits, lines, |_| {});
+		// The best line is the chosen move, for score and pv
+		self.last_score = results.first().map(|result| result.score);
+		self.last_pv = results.first().map(|result| result.pv.clone()).unwrap_or_default();
+		return results;
+	}
+
+	fn options(&self) -> Vec<UciOption> {
+		return self.evaluator.options();
+	}
+
+	fn set_options(&mut self, options: &EngineOptions) {
+		// The tables are only made again when their size changes, and emptied when the scores
+		// they hold change, with an evaluator knob or the contempt
+		if options.hash_mb != self.options.hash_mb {
+			self.tt = TranspositionTable::new(options.hash_mb);
+		}
+		if options.eval_cache_mb != self.options.eval_cache_mb {
+			self.eval_cache = EvalCache::new(options.eval_cache_mb);
+		}
+		let rescored = options.contempt != self.options.contempt
+		|| self.evaluator.options().iter().any(|option| options.value(&option.name) != self.options.value(&option.name));
+		if rescored {
+			self.tt.clear();
+			self.eval_cache.clear();
+		}
+		self.options = options.clone();
+		self.evaluator.set_options(options);
+	}
+}
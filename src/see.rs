@@ -0,0 +1,61 @@
+use crate::chess::*;
+use crate::eval::piece_value;
+
+// Static exchange evaluation, the material a capture wins once every recapture on the
+// square has been played, each side using its cheapest piece first and stopping when
+// going on would lose more
+// source: https://www.chessprogramming.org/Static_Exchange_Evaluation
+/*
+Only legal moves take part, so pinned pieces stay out of the exchange and sliders hidden
+behind the first attacker join in once it has captured. Kings only capture undefended
+pieces, which is why they come last
+*/
+
+fn capture_value(board: &Board, mv: Move) -> i32 {
+	// Material a move wins straight away, en passant and promotions included
+	let mut value = match board.get_piece(mv.to) {
+		Some(piece) => piece_value(piece.breed),
+		None if board.get_piece(mv.from).is_some_and(|piece| piece.breed == Pieces::Pawn) && mv.from.col != mv.to.col => {
+			piece_value(Pieces::Pawn)
+		}
+		None => 0,
+	};
+
+	if let Some(breed) = mv.promotion {
+		value += piece_value(breed) - piece_value(Pieces::Pawn);
+	}
+
+	return value;
+}
+
+fn attacker_order(board: &Board, mv: Move) -> i32 {
+	return match board.get_piece(mv.from) {
+		Some(piece) if piece.breed == Pieces::King => i32::MAX,
+		Some(piece) => piece_value(piece.breed),
+		None => i32::MAX,
+	};
+}
+
+impl Board {
+	pub fn see(&self, mv: Move) -> i32 {
+		// Net material the move wins for the side to move, negative when the piece gets lost
+		let mut after = *self;
+		let gain = capture_value(self, mv);
+		after.make_move(mv);
+		return gain - after.see_square(mv.to);
+	}
+
+	pub fn see_square(&self, coord: Coordinate) -> i32 {
+		// What the side to move wins by starting an exchange on `coord`, 0 when it better not
+		let cheapest = self
+		.capture_moves()
+		.into_iter()
+		.filter(|mv| mv.to == coord && matches!(mv.promotion, None | Some(Pieces::Queen)))
+		.min_by_key(|mv| attacker_order(self, *mv));
+
+		return match cheapest {
+			Some(mv) => self.see(mv).max(0),
+			None => 0,
+		};
+	}
+}
@@ -0,0 +1,196 @@
+use std::io::{self, Read, Write};
+
+use crate::chess::*;
+use crate::engine::SearchLimits;
+use crate::eval::Evaluator;
+use crate::game::{Game, Outcome};
+use crate::packed::PACKED_SIZE;
+use crate::rng::{Rng, Seeded};
+use crate::search::AlphaBeta;
+
+// File layout
+/*
+header - "CHSP" and a version byte
+record - packed position (see packed.rs), score as little endian i16, result as i8
+*/
+pub const MAGIC: &[u8; 4] = b"CHSP";
+pub const VERSION: u8 = 1;
+pub const RECORD_SIZE: usize = PACKED_SIZE + 3;
+
+// One training position, score and result are for the side to move
+#[derive(Debug, Clone)]
+pub struct Sample {
+	pub board: Board,
+	// Search score in centipawns, mate scores are clamped to fit
+	pub score: i16,
+	// 1 win, 0 draw, -1 loss
+	pub result: i8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfPlayConfig {
+	pub games: usize,
+	// Random plies played before recording starts, so games don't all look the same
+	pub opening_plies: u32,
+	// Games still going after this many recorded plies are counted as draws
+	pub max_plies: u32,
+	pub limits: SearchLimits,
+}
+
+impl Default for SelfPlayConfig {
+	fn default() -> Self {
+		SelfPlayConfig {
+			games: 1,
+			opening_plies: 8,
+			max_plies: 200,
+			limits: SearchLimits::depth(2),
+		}
+	}
+}
+
+// Plays the alpha-beta engine against itself and collects samples
+pub struct SelfPlay<E: Evaluator> {
+	pub config: SelfPlayConfig,
+	engine: AlphaBeta<E>,
+	rng: Rng,
+}
+
+impl<E: Evaluator> SelfPlay<E> {
+	pub fn new(evaluator: E, config: SelfPlayConfig) -> Self {
+		SelfPlay {
+			config,
+			engine: AlphaBeta::new(evaluator),
+			rng: Rng::from_time(),
+		}
+	}
+
+	pub fn play_game(&mut self) -> Vec<Sample> {
+		let mut board = Board::default();
+
+		for _ in 0..self.config.opening_plies {
+			let moves = board.legal_moves();
+			if moves.is_empty() {
+				break;
+			}
+			board.make_move(moves[self.rng.below(moves.len())]);
+		}
+
+		let mut positions: Vec<(Board, i32)> = Vec::new();
+		// Result from white's point of view, a draw unless somebody gets mated
+		let mut white_result: i8 = 0;
+		// Played on a game for the repetitions, the search gets them in its limits
+		let mut game = Game::new(board);
+
+		for _ in 0..self.config.max_plies {
+			if let Some((outcome, _)) = game.termination() {
+				white_result = match outcome {
+					Outcome::WhiteWins => 1,
+					Outcome::BlackWins => -1,
+					Outcome::Draw => 0,
+				};
+				break;
+			}
+
+			let mut limits = self.config.limits.clone();
+			limits.history = game.search_history();
+			let search = self.engine.search(game.board(), &limits);
+			let mv = match search.best_move {
+				Some(mv) => mv,
+				None => break,
+			};
+
+			positions.push((*game.board(), search.score));
+			if game.make_move(mv).is_err() {
+				break;
+			}
+		}
+
+		return positions
+		.into_iter()
+		.map(|(board, score)| Sample {
+			result: if board.turn == Color::White { white_result } else { -white_result },
+			score: score.clamp(i16::MIN as i32 + 1, i16::MAX as i32) as i16,
+			board,
+		})
+		.collect();
+	}
+
+	pub fn run(&mut self, writer: &mut impl Write) -> io::Result<usize> {
+		// Plays all the configured games, returns the number of samples written
+		write_header(writer)?;
+
+		let mut count = 0;
+		for _ in 0..self.config.games {
+			for sample in self.play_game() {
+				write_sample(writer, &sample)?;
+				count += 1;
+			}
+		}
+
+		return Ok(count);
+	}
+}
+
+impl<E: Evaluator> Seeded for SelfPlay<E> {
+	fn rng(&mut self) -> &mut Rng {
+		return &mut self.rng;
+	}
+
+	fn set_rng(&mut self, mut rng: Rng) {
+		// The engine only rolls dice when it plays weaker on purpose
+		self.engine.set_rng(Rng::new(rng.next_u64()));
+		self.rng = rng;
+	}
+}
+
+pub fn write_header(writer: &mut impl Write) -> io::Result<()> {
+	writer.write_all(MAGIC)?;
+	return writer.write_all(&[VERSION]);
+}
+
+pub fn write_sample(writer: &mut impl Write, sample: &Sample) -> io::Result<()> {
+	let packed = match sample.board.to_packed() {
+		Some(packed) => packed,
+		None => return Err(io::Error::new(io::ErrorKind::InvalidData, "board does not fit the packed format")),
+	};
+
+	writer.write_all(&packed)?;
+	writer.write_all(&sample.score.to_le_bytes())?;
+	return writer.write_all(&[sample.result as u8]);
+}
+
+pub fn read_samples(reader: &mut impl Read) -> io::Result<Vec<Sample>> {
+	let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+	let mut header = [0u8; 5];
+	reader.read_exact(&mut header)?;
+	if &header[..4] != MAGIC {
+		return Err(invalid("not a self-play file"));
+	}
+	if header[4] != VERSION {
+		return Err(invalid("unsupported self-play file version"));
+	}
+
+	let mut data = Vec::new();
+	reader.read_to_end(&mut data)?;
+	if data.len() % RECORD_SIZE != 0 {
+		return Err(invalid("truncated self-play record"));
+	}
+
+	let mut result = Vec::new();
+	for record in data.chunks(RECORD_SIZE) {
+		let packed: [u8; PACKED_SIZE] = record[..PACKED_SIZE].try_into().unwrap();
+		let board = match Board::from_packed(&packed) {
+			Some(board) => board,
+			None => return Err(invalid("invalid packed position")),
+		};
+
+		result.push(Sample {
+			board,
+			score: i16::from_le_bytes([record[PACKED_SIZE], record[PACKED_SIZE + 1]]),
+			result: record[PACKED_SIZE + 2] as i8,
+		});
+	}
+
+	return Ok(result);
+}
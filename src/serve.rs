@@ -0,0 +1,230 @@
+use std::io;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::chess::*;
+use crate::endgame::EndgameEvaluator;
+use crate::engine::SearchLimits;
+use crate::error::ChessError;
+use crate::eval::MaterialEvaluator;
+use crate::game::MaterialRule;
+use crate::notation::{Notation, ParseMode};
+use crate::pgn::result_token;
+use crate::search::AlphaBeta;
+
+// JSON over HTTP, for web front-ends (the `serve` feature)
+/*
+POST /legal-moves   {"fen": "..."}
+                    {"moves": [{"uci": "e2e4", "san": "e4"}, ...]}
+POST /apply-move    {"fen": "...", "move": "e2e4"}
+                    {"fen": "...", "uci": "e2e4", "san": "e4", "check": false, "result": null}
+POST /best-move     {"fen": "...", "depth": 6, "movetime_ms": 1000}
+                    {"uci": "g1f3", "san": "Nf3", "score": 25, "depth": 6, "pv": ["g1f3", ...]}
+POST /validate-fen  {"fen": "..."}
+                    {"valid": true, "fen": "...", "error": null}
+
+Moves may be written the UCI way or in SAN. Scores are centipawns for the side to move and
+`result` is the PGN result once the move ends the game ("1-0", "0-1", "1/2-1/2"). Bad input
+gets a 400 with {"error": "..."}, a body that isn't the right JSON the usual axum 4xx
+
+Every request gets its own engine, searches run on tokio's blocking threads and their depth and
+time are capped so one request can't keep the server busy
+*/
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+// Depth of /best-move when the request doesn't say
+pub const SERVE_DEPTH: u32 = 4;
+pub const MAX_SERVE_DEPTH: u32 = 8;
+pub const MAX_SERVE_MOVETIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FenRequest {
+	pub fen: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyMoveRequest {
+	pub fen: String,
+	#[serde(rename = "move")]
+	pub mv: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BestMoveRequest {
+	pub fen: String,
+	#[serde(default)]
+	pub depth: Option<u32>,
+	#[serde(default)]
+	pub movetime_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveText {
+	pub uci: String,
+	pub san: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegalMovesResponse {
+	pub moves: Vec<MoveText>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyMoveResponse {
+	pub fen: String,
+	pub uci: String,
+	pub san: String,
+	pub check: bool,
+	pub result: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BestMoveResponse {
+	pub uci: String,
+	pub san: String,
+	pub score: i32,
+	pub depth: u32,
+	pub pv: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidateFenResponse {
+	pub valid: bool,
+	pub fen: Option<String>,
+	pub error: Option<String>,
+}
+
+// What a request did wrong, sent back as {"error": "..."}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+	pub status: StatusCode,
+	pub message: String,
+}
+
+impl From<ChessError> for ApiError {
+	fn from(error: ChessError) -> Self {
+		ApiError {
+			status: StatusCode::BAD_REQUEST,
+			message: error.to_string(),
+		}
+	}
+}
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		return (self.status, Json(serde_json::json!({ "error": self.message }))).into_response();
+	}
+}
+
+fn parse_move(board: &Board, text: &str) -> Option<Move> {
+	// UCI first, "e2e4" is never SAN
+	let text = text.trim();
+	return match board.legal_moves().into_iter().find(|mv| mv.to_string() == text) {
+		Some(mv) => Some(mv),
+		None => Notation::San.parse_with(board, text, ParseMode::Lenient),
+	};
+}
+
+pub fn legal_moves(request: &FenRequest) -> Result<LegalMovesResponse, ApiError> {
+	let board = Board::from_fen(&request.fen)?;
+	let moves = board
+	.legal_moves()
+	.into_iter()
+	.map(|mv| MoveText {
+		uci: mv.to_string(),
+		san: Notation::San.format(&board, mv),
+	})
+	.collect();
+	return Ok(LegalMovesResponse { moves });
+}
+
+pub fn apply_move(request: &ApplyMoveRequest) -> Result<ApplyMoveResponse, ApiError> {
+	let mut board = Board::from_fen(&request.fen)?;
+	let mv = parse_move(&board, &request.mv).ok_or_else(|| ApiError {
+		status: StatusCode::UNPROCESSABLE_ENTITY,
+		message: format!("illegal move \"{}\"", request.mv),
+	})?;
+	let san = Notation::San.format(&board, mv);
+	board.make_move(mv);
+
+	let result = board.game_result(MaterialRule::default()).map(|result| result_token(Some(result.outcome())).to_string());
+	return Ok(ApplyMoveResponse {
+		fen: board.get_fen(),
+		uci: mv.to_string(),
+		san,
+		check: board.is_in_check(board.turn),
+		result,
+	});
+}
+
+pub fn best_move(request: &BestMoveRequest) -> Result<BestMoveResponse, ApiError> {
+	let board = Board::from_fen(&request.fen)?;
+	if board.legal_moves().is_empty() {
+		return Err(ChessError::NoLegalMoves.into());
+	}
+
+	let mut limits = SearchLimits::depth(request.depth.unwrap_or(SERVE_DEPTH).clamp(1, MAX_SERVE_DEPTH));
+	limits.movetime = Some(request.movetime_ms.map_or(MAX_SERVE_MOVETIME, Duration::from_millis).min(MAX_SERVE_MOVETIME));
+	let mut engine = AlphaBeta::new(EndgameEvaluator::new(MaterialEvaluator));
+	let result = engine.search(&board, &limits);
+
+	let mv = result.best_move.ok_or(ChessError::NoLegalMoves)?;
+	return Ok(BestMoveResponse {
+		uci: mv.to_string(),
+		san: Notation::San.format(&board, mv),
+		score: result.score,
+		depth: result.depth,
+		pv: result.pv.iter().map(|mv| mv.to_string()).collect(),
+	});
+}
+
+pub fn validate_fen(request: &FenRequest) -> ValidateFenResponse {
+	// A FEN that parses can still be a position no game reaches, both count as invalid
+	let checked = Board::from_fen(&request.fen).and_then(|board| board.validate().map(|_| board));
+	return match checked {
+		Ok(board) => ValidateFenResponse {
+			valid: true,
+			fen: Some(board.get_fen()),
+			error: None,
+		},
+		Err(error) => ValidateFenResponse {
+			valid: false,
+			fen: None,
+			error: Some(error.to_string()),
+		},
+	};
+}
+
+pub fn router() -> Router {
+	return Router::new()
+	.route("/legal-moves", post(|Json(request): Json<FenRequest>| async move { legal_moves(&request).map(Json) }))
+	.route("/apply-move", post(|Json(request): Json<ApplyMoveRequest>| async move { apply_move(&request).map(Json) }))
+	.route(
+		"/best-move",
+		post(|Json(request): Json<BestMoveRequest>| async move {
+			// The search would hold up the other requests on this thread
+			return match tokio::task::spawn_blocking(move || best_move(&request)).await {
+				Ok(response) => response.map(Json),
+				Err(error) => Err(ApiError {
+					status: StatusCode::INTERNAL_SERVER_ERROR,
+					message: error.to_string(),
+				}),
+			};
+		}),
+	)
+	.route("/validate-fen", post(|Json(request): Json<FenRequest>| async move { Json(validate_fen(&request)) }));
+}
+
+pub fn serve(address: &str) -> io::Result<()> {
+	// Blocks and answers requests until the process ends
+	let runtime = tokio::runtime::Runtime::new()?;
+	return runtime.block_on(async {
+		let listener = tokio::net::TcpListener::bind(address).await?;
+		return axum::serve(listener, router()).await;
+	});
+}
@@ -0,0 +1,94 @@
+use crate::chess::Move;
+use crate::options::EngineOptions;
+use crate::rng::Rng;
+
+// Making the engine play worse on purpose, for opponents people can beat
+/*
+Level 20 is full strength. Lower levels search less deep, add noise to the evaluation and
+pick any root move whose score is close enough to the best one, the better moves being more
+likely. An Elo limit is turned into the level that plays about that strong
+*/
+
+pub const MAX_SKILL_LEVEL: u32 = 20;
+// Elo range of the levels, a rough guess rather than a measurement
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkillLevel {
+	level: u32,
+}
+
+impl SkillLevel {
+	pub fn new(level: u32) -> Self {
+		SkillLevel {
+			level: level.min(MAX_SKILL_LEVEL),
+		}
+	}
+
+	pub fn from_elo(elo: u32) -> Self {
+		let elo = elo.clamp(MIN_ELO, MAX_ELO);
+		return SkillLevel::new((elo - MIN_ELO) * MAX_SKILL_LEVEL / (MAX_ELO - MIN_ELO));
+	}
+
+	pub fn from_options(options: &EngineOptions) -> Option<SkillLevel> {
+		// None when the engine plays at full strength, the Elo limit wins over the level
+		let skill = if options.limit_strength {
+			SkillLevel::from_elo(options.elo)
+		} else {
+			SkillLevel::new(options.skill_level)
+		};
+
+		if skill.level == MAX_SKILL_LEVEL {
+			return None;
+		}
+		return Some(skill);
+	}
+
+	pub fn level(&self) -> u32 {
+		return self.level;
+	}
+
+	pub fn elo(&self) -> u32 {
+		// The Elo from_elo takes the level for, a starting point until the level is calibrated
+		return MIN_ELO + self.level * (MAX_ELO - MIN_ELO) / MAX_SKILL_LEVEL;
+	}
+
+	pub fn max_depth(&self) -> u32 {
+		return 1 + self.level / 4;
+	}
+
+	pub fn margin(&self) -> i32 {
+		// Centipawns a move can be worse than the best and still be played
+		return ((MAX_SKILL_LEVEL - self.level) * 20) as i32;
+	}
+
+	pub fn noise(&self) -> i32 {
+		// Largest error added to an evaluation, either way
+		return ((MAX_SKILL_LEVEL - self.level) * 5) as i32;
+	}
+
+	pub fn perturb(&self, score: i32, rng: &mut Rng) -> i32 {
+		let noise = self.noise();
+		return score + rng.below(2 * noise as usize + 1) as i32 - noise;
+	}
+
+	pub fn pick(&self, scored: &[(Move, i32)], rng: &mut Rng) -> Option<Move> {
+		// function to choose among the moves close to the best, weighted by how close they are
+		let best = scored.iter().map(|(_, score)| *score).max()?;
+		let weights: Vec<(Move, usize)> = scored
+		.iter()
+		.filter(|(_, score)| *score >= best - self.margin())
+		.map(|(mv, score)| (*mv, (self.margin() - (best - score) + 1) as usize))
+		.collect();
+
+		let mut roll = rng.below(weights.iter().map(|(_, weight)| weight).sum());
+		for (mv, weight) in weights {
+			if roll < weight {
+				return Some(mv);
+			}
+			roll -= weight;
+		}
+		return None;
+	}
+}
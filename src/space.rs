@@ -0,0 +1,75 @@
+use crate::chess::*;
+
+// Space and control of the center, the positional terms behind "White has more space"
+/*
+Space  - the squares of the c to f files on a side's second, third and fourth rank that no
+         enemy pawn attacks and no own pawn stands on. The ones up to three squares behind an
+         own pawn count twice, the pawns shelter them and the pieces can use them
+Center - every attack on d4, e4, d5 or e5 and every own piece standing on one of them
+
+Both are counts, eval turns them into centipawns (see EvalBreakdown)
+*/
+// source: https://www.chessprogramming.org/Space
+
+// c2-f4, a1 is bit 0
+const WHITE_SPACE: u64 = 0x0000_0000_3c3c_3c00;
+// c7-f5
+const BLACK_SPACE: u64 = 0x003c_3c3c_0000_0000;
+// d4, e4, d5, e5
+pub const CENTER: [Coordinate; 4] = [
+	Coordinate::from_rank_file(Rank::Fourth, File::D),
+	Coordinate::from_rank_file(Rank::Fourth, File::E),
+	Coordinate::from_rank_file(Rank::Fifth, File::D),
+	Coordinate::from_rank_file(Rank::Fifth, File::E),
+];
+
+impl Board {
+	pub(crate) fn pawn_bits(&self, color: Color) -> u64 {
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		return pieces
+		.iter()
+		.filter(|(_, piece)| piece.breed == Pieces::Pawn)
+		.fold(0, |bits, (num, _)| bits | 1 << num);
+	}
+
+	pub(crate) fn pawn_attack_bits(&self, color: Color) -> u64 {
+		// Squares the pawns of `color` attack, whatever is on them
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		return pieces
+		.iter()
+		.filter(|(_, piece)| piece.breed == Pieces::Pawn)
+		.fold(0, |bits, (num, piece)| bits | self.piece_attacks(Coordinate::from_number(*num), *piece));
+	}
+
+	pub fn space(&self, color: Color) -> i32 {
+		let opponent = match color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let pawns = self.pawn_bits(color);
+		let (area, behind) = match color {
+			Color::White => (WHITE_SPACE, pawns >> 8 | pawns >> 16 | pawns >> 24),
+			Color::Black => (BLACK_SPACE, pawns << 8 | pawns << 16 | pawns << 24),
+		};
+
+		let safe = area & !pawns & !self.pawn_attack_bits(opponent);
+		return (safe.count_ones() + (safe & behind).count_ones()) as i32;
+	}
+
+	pub fn center_control(&self, color: Color) -> i32 {
+		let mut count = 0;
+		for coord in CENTER {
+			count += self.attackers_of(coord, color).len() as i32;
+			if self.get_piece(coord).is_some_and(|piece| piece.color == color) {
+				count += 1;
+			}
+		}
+		return count;
+	}
+}
@@ -0,0 +1,235 @@
+use crate::chess::*;
+use crate::eval::piece_value;
+
+// Tactical patterns on the board, for trainers, annotations and puzzle tags
+/*
+Fork             - one piece attacks two or more targets at once
+Pin              - a piece can't (absolute, the king is behind) or shouldn't (relative) leave
+                   the line between a slider and a more valuable piece
+Skewer           - the more valuable piece is in front and has to get out of the way
+DiscoveredAttack - moving an own piece off a slider's line uncovers an attack
+
+A target is something worth going after: the king, a piece worth more than the attacker
+or a piece nobody defends
+*/
+/*
+Threats are the opponent's captures that win material once the exchange is over (by SEE),
+hanging pieces are the ones attacked with no defender at all
+*/
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub enum TacticMotif {
+	Fork { attacker: Coordinate, targets: Vec<Coordinate> },
+	Pin { pinner: Coordinate, pinned: Coordinate, behind: Coordinate, absolute: bool },
+	Skewer { attacker: Coordinate, front: Coordinate, behind: Coordinate },
+	DiscoveredAttack { slider: Coordinate, blocker: Coordinate, target: Coordinate },
+}
+
+// An opponent capture on `target` that wins `gain` centipawns
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct Threat {
+	pub target: Coordinate,
+	pub attackers: Vec<Coordinate>,
+	pub defenders: Vec<Coordinate>,
+	pub gain: i32,
+}
+
+fn worth(piece: Piece) -> i32 {
+	// Kings outweigh everything here
+	if piece.breed == Pieces::King {
+		return i32::MAX;
+	}
+	return piece_value(piece.breed);
+}
+
+fn slider_steps(breed: Pieces) -> Vec<(i8, i8)> {
+	return match breed {
+		Pieces::Bishop => DIAGONAL_STEPS.to_vec(),
+		Pieces::Rook => LINEAR_STEPS.to_vec(),
+		Pieces::Queen => DIAGONAL_STEPS.iter().chain(LINEAR_STEPS.iter()).copied().collect(),
+		_ => Vec::new(),
+	};
+}
+
+impl Board {
+	pub fn detect_tactics(&self, color: Color) -> Vec<TacticMotif> {
+		// Motifs `color` can use right now, whoever is to move
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		let mut squares: Vec<i8> = pieces.keys().copied().collect();
+		squares.sort();
+
+		let mut result: Vec<TacticMotif> = Vec::new();
+		for num in squares {
+			let coord = Coordinate::from_number(num);
+			let piece = pieces[&num];
+
+			if let Some(fork) = self.fork(coord, piece) {
+				result.push(fork);
+			}
+			for step in slider_steps(piece.breed) {
+				if let Some(motif) = self.line_motif(coord, piece, step) {
+					result.push(motif);
+				}
+			}
+		}
+
+		return result;
+	}
+
+	fn is_target(&self, attacker: Piece, coord: Coordinate) -> bool {
+		return match self.get_piece(coord) {
+			Some(target) if target.color != attacker.color => {
+				let defender = target.color;
+				target.breed == Pieces::King || worth(target) > worth(attacker) || !self.is_square_attacked(coord, defender)
+			}
+			_ => false,
+		};
+	}
+
+	fn fork(&self, coord: Coordinate, piece: Piece) -> Option<TacticMotif> {
+		let attacks = self.piece_attacks(coord, piece);
+		let targets: Vec<Coordinate> = (0..64)
+		.filter(|num| attacks & (1 << num) != 0)
+		.map(Coordinate::from_number)
+		.filter(|target| self.is_target(piece, *target))
+		.collect();
+
+		if targets.len() < 2 {
+			return None;
+		}
+		return Some(TacticMotif::Fork { attacker: coord, targets });
+	}
+
+	fn line_motif(&self, coord: Coordinate, piece: Piece, (row_diff, col_diff): (i8, i8)) -> Option<TacticMotif> {
+		// function to look at the first two pieces along one direction of a slider
+		let mut found: Vec<Coordinate> = Vec::new();
+		let mut target = coord!(coord.row + row_diff, coord.col + col_diff);
+		while target.is_valid() && found.len() < 2 {
+			if self.get_piece(target).is_some() {
+				found.push(target);
+			}
+			target = coord!(target.row + row_diff, target.col + col_diff);
+		}
+
+		let (front, behind) = match found[..] {
+			[front, behind] => (front, behind),
+			_ => return None,
+		};
+		let front_piece = self.get_piece(front)?;
+		let behind_piece = self.get_piece(behind)?;
+
+		if behind_piece.color == piece.color || !self.is_target(piece, behind) {
+			return None;
+		}
+
+		if front_piece.color == piece.color {
+			// Any move off the line uncovers the attack, capturing the target is just a capture
+			let mut line: Vec<Coordinate> = vec![behind];
+			let mut square = coord!(coord.row + row_diff, coord.col + col_diff);
+			while square != behind {
+				line.push(square);
+				square = coord!(square.row + row_diff, square.col + col_diff);
+			}
+
+			let uncovers = self
+			.pseudo_legal_moves_from(front)
+			.unwrap_or_default()
+			.iter()
+			.any(|mv| !line.contains(&mv.to));
+
+			if uncovers {
+				return Some(TacticMotif::DiscoveredAttack { slider: coord, blocker: front, target: behind });
+			}
+			return None;
+		}
+
+		if worth(front_piece) < worth(behind_piece) {
+			return Some(TacticMotif::Pin {
+				pinner: coord,
+				pinned: front,
+				behind,
+				absolute: behind_piece.breed == Pieces::King,
+			});
+		}
+		if worth(front_piece) > worth(behind_piece) {
+			return Some(TacticMotif::Skewer { attacker: coord, front, behind });
+		}
+		return None;
+	}
+	pub fn attackers_of(&self, coord: Coordinate, by: Color) -> Vec<Coordinate> {
+		// Pieces of `by` attacking the square directly, pieces behind them don't count
+		let pieces = match by {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+		let mut result: Vec<Coordinate> = pieces
+		.iter()
+		.filter(|(num, piece)| self.piece_attacks(Coordinate::from_number(**num), **piece) & (1 << coord.as_number()) != 0)
+		.map(|(num, _)| Coordinate::from_number(*num))
+		.collect();
+		result.sort_by_key(|attacker| attacker.as_number());
+		return result;
+	}
+
+	pub fn hanging_pieces(&self, color: Color) -> Vec<Coordinate> {
+		// Pieces of `color` the opponent attacks and nobody defends, the king aside
+		let opponent = match color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+
+		let mut result: Vec<Coordinate> = pieces
+		.iter()
+		.filter(|(_, piece)| piece.breed != Pieces::King)
+		.map(|(num, _)| Coordinate::from_number(*num))
+		.filter(|coord| self.is_square_attacked(*coord, opponent) && !self.is_square_attacked(*coord, color))
+		.collect();
+		result.sort_by_key(|coord| coord.as_number());
+		return result;
+	}
+
+	pub fn threats(&self) -> Vec<Threat> {
+		// What the opponent threatens to win against the side to move, biggest first
+		let color = self.turn;
+		let opponent = match color {
+			Color::White => Color::Black,
+			Color::Black => Color::White,
+		};
+		let pieces = match color {
+			Color::White => &self.white_pieces,
+			Color::Black => &self.black_pieces,
+		};
+
+		// The opponent gets the move to see what its captures would win
+		let mut passed = *self;
+		passed.make_null_move();
+
+		let mut result: Vec<Threat> = Vec::new();
+		for (num, piece) in pieces.iter() {
+			let target = Coordinate::from_number(*num);
+			if piece.breed == Pieces::King {
+				continue;
+			}
+
+			let gain = passed.see_square(target);
+			if gain > 0 {
+				result.push(Threat {
+					target,
+					attackers: self.attackers_of(target, opponent),
+					defenders: self.attackers_of(target, color),
+					gain,
+				});
+			}
+		}
+
+		result.sort_by_key(|threat| (-threat.gain, threat.target.as_number()));
+		return result;
+	}
+}
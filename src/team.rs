@@ -0,0 +1,222 @@
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+use crate::chess::*;
+use crate::crosstable::FinishedGame;
+use crate::error::ChessError;
+use crate::game::Outcome;
+use crate::score::{match_score, Score};
+
+// Team events, matches played on a fixed number of boards
+/*
+A team lists its players in board order, board 1 (the strongest) first, and plays them in
+that order: the first players of the list take the boards and the rest are the reserves. The
+first-named team of a match has white on the odd boards (1, 3...) and black on the others
+
+Every game counts a game point (a half for a draw), the team with more game points wins the
+match and gets 2 match points, 1 each for a drawn match. Standings go by match points, ties are
+broken the way the Olympiad does:
+Sonneborn-Berger - the match points of every opponent times the game points scored against
+                   them, with the opponent that scored the fewest match points left out
+Game points      - all of them
+and by name when all of them are equal
+*/
+// source: https://handbook.fide.com/chapter/OlympiadPairingRules
+
+pub const MATCH_WIN: u32 = 2;
+pub const MATCH_DRAW: u32 = 1;
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct Team {
+	pub name: String,
+	// Board order
+	pub players: Vec<String>,
+}
+
+impl Team {
+	pub fn new(name: &str, players: &[&str]) -> Self {
+		Team {
+			name: name.to_string(),
+			players: players.iter().map(|player| player.to_string()).collect(),
+		}
+	}
+}
+
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct TeamMatch {
+	pub home: String,
+	pub away: String,
+	// Board 1 first, the outcome of the game as it was played on the board
+	pub boards: Vec<Outcome>,
+}
+
+impl TeamMatch {
+	pub fn new(home: &str, away: &str, boards: &[Outcome]) -> Self {
+		TeamMatch {
+			home: home.to_string(),
+			away: away.to_string(),
+			boards: boards.to_vec(),
+		}
+	}
+
+	pub fn home_color(board: usize) -> Color {
+		// Boards count from 0 here, board 1 is white for the first-named team
+		return match board % 2 {
+			0 => Color::White,
+			_ => Color::Black,
+		};
+	}
+
+	pub fn board_scores(&self, team: &str) -> Vec<Score> {
+		// What `team` scored on every board, nothing when it didn't play the match
+		let home = match team {
+			team if team == self.home => true,
+			team if team == self.away => false,
+			_ => return Vec::new(),
+		};
+		return self
+		.boards
+		.iter()
+		.enumerate()
+		.map(|(board, outcome)| {
+			let color = TeamMatch::home_color(board);
+			let color = if home { color } else { opponent(color) };
+			return outcome.score(color);
+		})
+		.collect();
+	}
+
+	pub fn game_points(&self, team: &str) -> Score {
+		return self.board_scores(team).into_iter().sum();
+	}
+
+	pub fn match_points(&self, team: &str) -> u32 {
+		let other = if team == self.home { &self.away } else { &self.home };
+		return match self.game_points(team).cmp(&self.game_points(other)) {
+			Ordering::Greater => MATCH_WIN,
+			Ordering::Equal => MATCH_DRAW,
+			Ordering::Less => 0,
+		};
+	}
+
+	pub fn result_text(&self) -> String {
+		// "Home - Away 2.5-1.5"
+		return format!("{} - {} {}", self.home, self.away, match_score(self.game_points(&self.home), self.game_points(&self.away)));
+	}
+
+	pub fn games(&self, teams: &[Team]) -> Result<Vec<FinishedGame>, ChessError> {
+		// The games of the players, for their own crosstable, the teams need enough players
+		let lineup = |name: &str| -> Result<&[String], ChessError> {
+			let team = teams.iter().find(|team| team.name == name).ok_or_else(|| ChessError::InvalidTeam(format!("unknown team \"{}\"", name)))?;
+			if team.players.len() < self.boards.len() {
+				return Err(ChessError::InvalidTeam(format!("\"{}\" has {} players for {} boards", name, team.players.len(), self.boards.len())));
+			}
+			return Ok(&team.players);
+		};
+		let (home, away) = (lineup(&self.home)?, lineup(&self.away)?);
+
+		let mut games = Vec::new();
+		for (board, outcome) in self.boards.iter().enumerate() {
+			let game = match TeamMatch::home_color(board) {
+				Color::White => FinishedGame::new(&home[board], &away[board], *outcome),
+				Color::Black => FinishedGame::new(&away[board], &home[board], *outcome),
+			};
+			games.push(game);
+		}
+		return Ok(games);
+	}
+}
+
+fn opponent(color: Color) -> Color {
+	return match color {
+		Color::White => Color::Black,
+		Color::Black => Color::White,
+	};
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TeamStanding {
+	pub team: String,
+	pub matches: usize,
+	pub match_points: u32,
+	pub game_points: Score,
+	pub sonneborn_berger: f64,
+	// Game points and games played on every board, board 1 first
+	pub boards: Vec<(Score, usize)>,
+}
+
+fn played<'a>(matches: &'a [TeamMatch], team: &str) -> Vec<&'a TeamMatch> {
+	return matches.iter().filter(|game| game.home == team || game.away == team).collect();
+}
+
+pub fn team_standings(teams: &[Team], matches: &[TeamMatch]) -> Vec<TeamStanding> {
+	// Best first, every team of the list is there, with or without matches
+	let match_points = |team: &str| played(matches, team).iter().map(|game| game.match_points(team)).sum::<u32>();
+
+	let mut standings: Vec<TeamStanding> = teams
+	.iter()
+	.map(|team| {
+		let name = team.name.as_str();
+		let mut boards: Vec<(Score, usize)> = Vec::new();
+		for game in played(matches, name) {
+			for (board, score) in game.board_scores(name).into_iter().enumerate() {
+				if boards.len() <= board {
+					boards.resize(board + 1, (Score::ZERO, 0));
+				}
+				boards[board].0 += score;
+				boards[board].1 += 1;
+			}
+		}
+
+		// (match points of the opponent, game points against them) of every match
+		let mut opponents: Vec<(u32, Score)> = played(matches, name)
+		.iter()
+		.map(|game| {
+			let other = if game.home == name { &game.away } else { &game.home };
+			return (match_points(other), game.game_points(name));
+		})
+		.collect();
+		opponents.sort_by_key(|(points, _)| *points);
+		let sonneborn_berger = opponents.iter().skip(1).map(|(points, score)| *points as f64 * score.points()).sum();
+
+		TeamStanding {
+			team: team.name.clone(),
+			matches: played(matches, name).len(),
+			match_points: match_points(name),
+			game_points: played(matches, name).iter().map(|game| game.game_points(name)).sum(),
+			sonneborn_berger,
+			boards,
+		}
+	})
+	.collect();
+
+	standings.sort_by(|a, b| {
+		return b
+		.match_points
+		.cmp(&a.match_points)
+		.then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+		.then(b.game_points.cmp(&a.game_points))
+		.then(a.team.cmp(&b.team));
+	});
+	return standings;
+}
+
+pub fn standings_text(standings: &[TeamStanding]) -> String {
+	// One line per team: rank, name, match points, game points and Sonneborn-Berger
+	let width = standings.iter().map(|standing| standing.team.chars().count()).max().unwrap_or(0).max(4);
+	let mut out = String::new();
+	let _ = writeln!(out, "#  {:<width$}  MP     GP     SB", "Team", width = width);
+	for (index, standing) in standings.iter().enumerate() {
+		let _ = writeln!(
+			out,
+			"{:<2} {:<width$}  {:>2}  {:>5}  {:>5.1}",
+			index + 1,
+			standing.team,
+			standing.match_points,
+			standing.game_points,
+			standing.sonneborn_berger,
+			width = width
+		);
+	}
+	return out;
+}
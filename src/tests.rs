@@ -1,21 +1,42 @@
-use crate::chess::*;
-
-// Macro to expand coord!(x, y) to Coordinate { row: x, col: y }
-macro_rules! coord {
-    ($x:expr, $y:expr) => {
-        Coordinate { row: $x, col: $y }
-    };
-}
+#![allow(clippy::bool_assert_comparison, clippy::module_inception)]
 
-// Macro to expand piece!(piece, color) to Piece { breed: Pieces::piece, color: Color::color }
-macro_rules! piece {
-    ($piece:ident, $color:ident) => {
-        Piece {
-            breed: Pieces::$piece,
-            color: Color::$color,
-        }
-    };
-}
+use crate::chess::*;
+use crate::notation::*;
+use crate::engine::*;
+use crate::eval::*;
+use crate::search::*;
+use crate::mcts::*;
+use crate::selfplay::*;
+use crate::match_runner::*;
+use crate::perft::*;
+use crate::error::*;
+use crate::game::*;
+use crate::pgn::*;
+use crate::options::*;
+use crate::uci::*;
+use crate::time::*;
+use crate::skill::*;
+use crate::rng::{Rng, Seeded};
+use crate::hint::*;
+use crate::ics::*;
+use crate::correspondence::*;
+use crate::tactics::*;
+use crate::position::*;
+use crate::bulk::*;
+use crate::packed::*;
+use crate::odds::*;
+use crate::puzzle::*;
+use crate::training::*;
+use crate::phase::*;
+use crate::endgame::*;
+use crate::decision::*;
+use crate::book::*;
+use crate::render::*;
+use crate::tui::*;
+use crate::drops::*;
+use crate::bughouse::*;
+use crate::fog::*;
+use crate::score::*;
 
 #[cfg(test)]
 mod tests {
@@ -45,7 +66,7 @@ mod tests {
         false
         */
 
-        board.place_piece(king, coord!(3, 3));
+        board.place_piece(king, coord!(3, 3)).unwrap();
         assert_eq!(board.is_in_check(Color::White), false);
         board.clear();
 
@@ -60,8 +81,8 @@ mod tests {
         true
         */
 
-        board.place_piece(piece!(Queen, Black), coord!(0, 0));
-        board.place_piece(king, coord!(3, 3));
+        board.place_piece(piece!(Queen, Black), coord!(0, 0)).unwrap();
+        board.place_piece(king, coord!(3, 3)).unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
 
@@ -76,8 +97,8 @@ mod tests {
         false
         */
 
-        board.place_piece(piece!(Queen, White), coord!(0, 0));
-        board.place_piece(king, coord!(3, 3));
+        board.place_piece(piece!(Queen, White), coord!(0, 0)).unwrap();
+        board.place_piece(king, coord!(3, 3)).unwrap();
         assert_eq!(board.is_in_check(Color::White), false);
         board.clear();
 
@@ -92,8 +113,8 @@ mod tests {
         true
         */
 
-        board.place_piece(king, coord!(3, 3));
-        board.place_piece(piece!(Rook, Black), coord!(0, 3));
+        board.place_piece(king, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(0, 3)).unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
 
@@ -108,8 +129,8 @@ mod tests {
         true
         */
 
-        board.place_piece(king, coord!(3, 3));
-        board.place_piece(piece!(Knight, Black), coord!(5, 4));
+        board.place_piece(king, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Knight, Black), coord!(5, 4)).unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
 
@@ -124,8 +145,8 @@ mod tests {
         true
         */
 
-        board.place_piece(king, coord!(3, 3));
-        board.place_piece(piece!(Pawn, Black), coord!(2, 4));
+        board.place_piece(king, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(2, 4)).unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
 
@@ -140,11 +161,11 @@ mod tests {
         false
         */
 
-        board.place_piece(king, coord!(3, 3));
-        board.place_piece(piece!(Rook, Black), coord!(0, 2));
-        board.place_piece(piece!(Rook, Black), coord!(0, 4));
-        board.place_piece(piece!(Rook, Black), coord!(2, 7));
-        board.place_piece(piece!(Rook, Black), coord!(4, 4));
+        board.place_piece(king, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(0, 2)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(0, 4)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(2, 7)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(4, 4)).unwrap();
         assert_eq!(board.is_in_check(Color::White), false);
         board.clear();
 
@@ -159,8 +180,8 @@ mod tests {
         true
         */
 
-        board.place_piece(king, coord!(3, 3));
-        board.place_piece(piece!(Bishop, Black), coord!(5, 5));
+        board.place_piece(king, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Bishop, Black), coord!(5, 5)).unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
 
@@ -175,9 +196,9 @@ mod tests {
         false
         */
 
-        board.place_piece(king, coord!(4, 3));
-        board.place_piece(piece!(Pawn, White), coord!(3, 4));
-        board.place_piece(piece!(Queen, Black), coord!(0, 7));
+        board.place_piece(king, coord!(4, 3)).unwrap();
+        board.place_piece(piece!(Pawn, White), coord!(3, 4)).unwrap();
+        board.place_piece(piece!(Queen, Black), coord!(0, 7)).unwrap();
         assert_eq!(board.is_in_check(Color::White), false);
         board.clear();
 
@@ -192,13 +213,13 @@ mod tests {
         true
         */
 
-        board.place_piece(king, coord!(3, 3));
-        board.place_piece(piece!(Rook, Black), coord!(3, 7));
+        board.place_piece(king, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(3, 7)).unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
 
         // Case 11
-        board.load_fen("4r3/8/8/4K2r/8/8/8/8 w - - 0 1");
+        board.load_fen("4r3/8/8/4K2r/8/8/8/8 w - - 0 1").unwrap();
         assert_eq!(board.is_in_check(Color::White), true);
         board.clear();
     }
@@ -211,6 +232,35 @@ mod tests {
         assert_eq!(coord!(7, 7).to_string(), "h1".to_string());
     }
 
+    #[test]
+    fn coordinate_conversions() {
+        // Case 1: The corners, written every way there is
+        assert_eq!(Coordinate::from_algebraic("a8"), Ok(coord!(0, 0)));
+        assert_eq!(Coordinate::from_algebraic("h1"), Ok(coord!(7, 7)));
+        assert_eq!(Coordinate::from_algebraic("E4"), Ok(coord!(4, 4)));
+        assert_eq!(Coordinate::from_rank_file(Rank::First, File::A), coord!(7, 0));
+        assert_eq!(Coordinate::from_rank_file(Rank::Eighth, File::H), coord!(0, 7));
+        assert_eq!(Coordinate::from_rank_file(Rank::First, File::A).as_number(), 0);
+        assert_eq!(Coordinate::from_rank_file(Rank::Eighth, File::H).as_number(), 63);
+
+        // Case 2: Every square survives a round trip through each form
+        for rank in Rank::ALL {
+            for file in File::ALL {
+                let coord = Coordinate::from_rank_file(rank, file);
+                assert!(coord.is_valid());
+                assert_eq!((coord.rank(), coord.file()), (rank, file));
+                assert_eq!(Coordinate::from_algebraic(&coord.to_string()), Ok(coord));
+                assert_eq!(Coordinate::from_number(coord.as_number()), coord);
+                assert_eq!(coord.as_number(), rank as i8 * 8 + file as i8);
+            }
+        }
+
+        // Case 3: Not squares
+        for text in ["", "e", "e9", "i1", "e44", "4e"] {
+            assert_eq!(Coordinate::from_algebraic(text), Err(ChessError::InvalidSquare(text.to_string())));
+        }
+    }
+
     #[test]
     fn king_moves() {
         let mut board = Board::new();
@@ -223,7 +273,7 @@ mod tests {
           * * .
           . . .
         */
-        board.place_piece(king, coord!(0, 0));
+        board.place_piece(king, coord!(0, 0)).unwrap();
         moves = board.get_moves(coord!(0, 0));
         assert_eq!(moves.len(), 3);
         board.clear();
@@ -234,7 +284,7 @@ mod tests {
         // . . .
         // 3 valid moves
 
-        board.place_piece(king, coord!(0, 7));
+        board.place_piece(king, coord!(0, 7)).unwrap();
         moves = board.get_moves(coord!(0, 7));
         assert_eq!(moves.len(), 3);
         board.clear();
@@ -245,7 +295,7 @@ mod tests {
         // K * .
         // 3 valid moves
 
-        board.place_piece(king, coord!(7, 0));
+        board.place_piece(king, coord!(7, 0)).unwrap();
         moves = board.get_moves(coord!(7, 0));
         assert_eq!(moves.len(), 3);
         board.clear();
@@ -256,7 +306,7 @@ mod tests {
         // . * K
         // 3 valid moves
 
-        board.place_piece(king, coord!(7, 7));
+        board.place_piece(king, coord!(7, 7)).unwrap();
         moves = board.get_moves(coord!(7, 7));
         assert_eq!(moves.len(), 3);
         board.clear();
@@ -267,7 +317,7 @@ mod tests {
         // * * *
         // 8 valid moves
 
-        board.place_piece(king, coord!(4, 4));
+        board.place_piece(king, coord!(4, 4)).unwrap();
         moves = board.get_moves(coord!(4, 4));
         assert_eq!(moves.len(), 8);
         board.clear();
@@ -278,8 +328,8 @@ mod tests {
         // * * *
         // 7 valid moves
 
-        board.place_piece(king, coord!(4, 4));
-        board.place_piece(piece!(Queen, White), coord!(3, 4));
+        board.place_piece(king, coord!(4, 4)).unwrap();
+        board.place_piece(piece!(Queen, White), coord!(3, 4)).unwrap();
         moves = board.get_moves(coord!(4, 4));
         assert_eq!(moves.len(), 7);
         board.clear();
@@ -290,8 +340,8 @@ mod tests {
         // * . .
         // 3 valid moves
 
-        board.place_piece(king, coord!(4, 4));
-        board.place_piece(piece!(Queen, Black), coord!(4, 5));
+        board.place_piece(king, coord!(4, 4)).unwrap();
+        board.place_piece(piece!(Queen, Black), coord!(4, 5)).unwrap();
         moves = board.get_moves(coord!(4, 4));
         assert_eq!(moves.len(), 3);
         board.clear();
@@ -307,7 +357,7 @@ mod tests {
         0 valid moves
         */
 
-        board.load_fen("2r1r3/8/7r/3K4/7r/8/8/8 w - - 0 1");
+        board.load_fen("2r1r3/8/7r/3K4/7r/8/8/8 w - - 0 1").unwrap();
         moves = board.get_moves(coord!(3, 3));
         assert_eq!(moves.len(), 0);
         board.clear();
@@ -322,8 +372,8 @@ mod tests {
         4 valid moves
         */
 
-        board.place_piece(king, coord!(4, 4));
-        board.place_piece(piece!(Rook, Black), coord!(3, 3));
+        board.place_piece(king, coord!(4, 4)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(3, 3)).unwrap();
         moves = board.get_moves(coord!(4, 4));
         assert_eq!(moves.len(), 4);
         board.clear();
@@ -404,12 +454,12 @@ mod tests {
         10 valid moves
         */
 
-        board.place_piece(piece!(Pawn, White), coord!(6, 6));
+        board.place_piece(piece!(Pawn, White), coord!(6, 6)).unwrap();
         moves = board.diagonal_moves(coord!(5, 5), Color::White);
         assert_eq!(moves.len(), 9);
 
         // Case 7: Enemy piece on the way
-        board.place_piece(piece!(Pawn, Black), coord!(6, 6));
+        board.place_piece(piece!(Pawn, Black), coord!(6, 6)).unwrap();
         moves = board.diagonal_moves(coord!(5, 5), Color::White);
         assert_eq!(moves.len(), 10);
     }
@@ -490,7 +540,7 @@ mod tests {
         12 valid moves
         */
 
-        board.place_piece(piece!(Pawn, White), coord!(6, 6));
+        board.place_piece(piece!(Pawn, White), coord!(6, 6)).unwrap();
         moves = board.linear_moves(coord!(6, 5), Color::White);
         assert_eq!(moves.len(), 12);
 
@@ -504,7 +554,7 @@ mod tests {
         13 valid moves
         */
 
-        board.place_piece(piece!(Pawn, Black), coord!(6, 6));
+        board.place_piece(piece!(Pawn, Black), coord!(6, 6)).unwrap();
         moves = board.linear_moves(coord!(6, 5), Color::White);
         assert_eq!(moves.len(), 13);
     }
@@ -526,7 +576,7 @@ mod tests {
         2 valid moves
         */
 
-        board.place_piece(knight, coord!(0, 0));
+        board.place_piece(knight, coord!(0, 0)).unwrap();
         moves = board.get_moves(coord!(0, 0));
         assert_eq!(moves.len(), 2);
         board.clear();
@@ -542,7 +592,7 @@ mod tests {
         2 valid moves
         */
 
-        board.place_piece(knight, coord!(0, 7));
+        board.place_piece(knight, coord!(0, 7)).unwrap();
         moves = board.get_moves(coord!(0, 7));
         assert_eq!(moves.len(), 2);
         board.clear();
@@ -558,7 +608,7 @@ mod tests {
         2 valid moves
         */
 
-        board.place_piece(knight, coord!(7, 0));
+        board.place_piece(knight, coord!(7, 0)).unwrap();
         moves = board.get_moves(coord!(7, 0));
         assert_eq!(moves.len(), 2);
         board.clear();
@@ -574,7 +624,7 @@ mod tests {
         2 valid moves
         */
 
-        board.place_piece(knight, coord!(7, 7));
+        board.place_piece(knight, coord!(7, 7)).unwrap();
         moves = board.get_moves(coord!(7, 7));
         assert_eq!(moves.len(), 2);
         board.clear();
@@ -590,7 +640,7 @@ mod tests {
         8 valid moves
         */
 
-        board.place_piece(knight, coord!(2, 2));
+        board.place_piece(knight, coord!(2, 2)).unwrap();
         moves = board.get_moves(coord!(2, 2));
         assert_eq!(moves.len(), 8);
         board.clear();
@@ -606,8 +656,8 @@ mod tests {
         7 valid moves
         */
 
-        board.place_piece(knight, coord!(2, 2));
-        board.place_piece(piece!(Pawn, White), coord!(4, 1));
+        board.place_piece(knight, coord!(2, 2)).unwrap();
+        board.place_piece(piece!(Pawn, White), coord!(4, 1)).unwrap();
         moves = board.get_moves(coord!(2, 2));
         assert_eq!(moves.len(), 7);
         board.clear();
@@ -623,9 +673,9 @@ mod tests {
         6 valid moves
         */
 
-        board.place_piece(knight, coord!(2, 2));
-        board.place_piece(piece!(Pawn, White), coord!(4, 1));
-        board.place_piece(piece!(Pawn, White), coord!(3, 4));
+        board.place_piece(knight, coord!(2, 2)).unwrap();
+        board.place_piece(piece!(Pawn, White), coord!(4, 1)).unwrap();
+        board.place_piece(piece!(Pawn, White), coord!(3, 4)).unwrap();
         moves = board.get_moves(coord!(2, 2));
         assert_eq!(moves.len(), 6);
         board.clear();
@@ -641,8 +691,8 @@ mod tests {
         8 valid moves
         */
 
-        board.place_piece(knight, coord!(2, 2));
-        board.place_piece(piece!(Pawn, Black), coord!(4, 3));
+        board.place_piece(knight, coord!(2, 2)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(4, 3)).unwrap();
         moves = board.get_moves(coord!(2, 2));
         assert_eq!(moves.len(), 8);
         board.clear();
@@ -663,7 +713,7 @@ mod tests {
         1 valid move
         */
 
-        board.place_piece(pawn, coord!(4, 3));
+        board.place_piece(pawn, coord!(4, 3)).unwrap();
         moves = board.get_moves(coord!(4, 3));
         assert_eq!(moves.len(), 1);
         board.clear();
@@ -677,8 +727,8 @@ mod tests {
         0 valid moves
         */
 
-        board.place_piece(pawn, coord!(4, 3));
-        board.place_piece(piece!(King, White), coord!(3, 3));
+        board.place_piece(pawn, coord!(4, 3)).unwrap();
+        board.place_piece(piece!(King, White), coord!(3, 3)).unwrap();
         moves = board.get_moves(coord!(4, 3));
         assert_eq!(moves.len(), 0);
         board.clear();
@@ -692,8 +742,8 @@ mod tests {
         0 valid moves
         */
 
-        board.place_piece(pawn, coord!(4, 3));
-        board.place_piece(piece!(Pawn, Black), coord!(3, 3));
+        board.place_piece(pawn, coord!(4, 3)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(3, 3)).unwrap();
         moves = board.get_moves(coord!(4, 3));
         assert_eq!(moves.len(), 0);
         board.clear();
@@ -707,8 +757,8 @@ mod tests {
         2 valid move
         */
 
-        board.place_piece(pawn, coord!(4, 3));
-        board.place_piece(piece!(Pawn, Black), coord!(3, 4));
+        board.place_piece(pawn, coord!(4, 3)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(3, 4)).unwrap();
         moves = board.get_moves(coord!(4, 3));
         assert_eq!(moves.len(), 2);
         board.clear();
@@ -723,9 +773,9 @@ mod tests {
         3 valid moves
         */
 
-        board.place_piece(pawn, coord!(4, 3));
-        board.place_piece(piece!(Pawn, Black), coord!(3, 4));
-        board.place_piece(piece!(Pawn, Black), coord!(3, 2));
+        board.place_piece(pawn, coord!(4, 3)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(3, 4)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(3, 2)).unwrap();
         moves = board.get_moves(coord!(4, 3));
         assert_eq!(moves.len(), 3);
         board.clear();
@@ -733,7 +783,7 @@ mod tests {
         // Case 6: Pawn in the starting row
         // 2 valid moves
 
-        board.place_piece(pawn, coord!(6, 1));
+        board.place_piece(pawn, coord!(6, 1)).unwrap();
         moves = board.get_moves(coord!(6, 1));
         assert_eq!(moves.len(), 2);
         board.clear();
@@ -741,8 +791,8 @@ mod tests {
         // Case 7: Pawn in the starting row with a friendly piece 2 steps away
         // 1 valid move
 
-        board.place_piece(pawn, coord!(6, 1));
-        board.place_piece(piece!(Pawn, White), coord!(4, 1));
+        board.place_piece(pawn, coord!(6, 1)).unwrap();
+        board.place_piece(piece!(Pawn, White), coord!(4, 1)).unwrap();
         moves = board.get_moves(coord!(6, 1));
         assert_eq!(moves.len(), 1);
         board.clear();
@@ -750,8 +800,8 @@ mod tests {
         // Case 8: Pawn in the starting row with an enemy piece 2 steps away
         // 1 valid moves
 
-        board.place_piece(pawn, coord!(6, 1));
-        board.place_piece(piece!(Pawn, Black), coord!(4, 1));
+        board.place_piece(pawn, coord!(6, 1)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(4, 1)).unwrap();
         moves = board.get_moves(coord!(6, 1));
         assert_eq!(moves.len(), 1);
         board.clear();
@@ -767,8 +817,8 @@ mod tests {
         2 valid moves
         */
 
-        board.place_piece(pawn, coord!(3, 3));
-        board.place_piece(piece!(Pawn, Black), coord!(3, 4));
+        board.place_piece(pawn, coord!(3, 3)).unwrap();
+        board.place_piece(piece!(Pawn, Black), coord!(3, 4)).unwrap();
         board.en_passant_target_sq = Some(coord!(2, 4).as_number());
         moves = board.get_moves(coord!(3, 3));
         assert_eq!(moves.len(), 2);
@@ -785,7 +835,7 @@ mod tests {
         2 valid moves
         */
 
-        board.load_fen("8/8/2pK4/3Pp3/8/8/8/8 w - e6 0 1");
+        board.load_fen("8/8/2pK4/3Pp3/8/8/8/8 w - e6 0 1").unwrap();
         assert_eq!(moves.len(), 2);
         board.clear();
     }
@@ -802,7 +852,7 @@ mod tests {
          For pawn it is 0 valid moves
       */
 
-      board.load_fen("8/8/8/8/1KP3r1/8/8/8 w - - 0 1");
+      board.load_fen("8/8/8/8/1KP3r1/8/8/8 w - - 0 1").unwrap();
       assert_eq!(board.get_moves(coord!(4, 2)).len(), 0);
 
       // Case 2
@@ -814,7 +864,7 @@ mod tests {
          For rook it is 1 valid move
        */
 
-      board.load_fen("8/8/8/8/1KRr4/8/8/8 w - - 0 1");
+      board.load_fen("8/8/8/8/1KRr4/8/8/8 w - - 0 1").unwrap();
       assert_eq!(board.get_moves(coord!(4, 2)).len(), 1);
     }
 
@@ -833,7 +883,7 @@ mod tests {
       */
 
       println!("Double rook mate");
-      board.load_fen("K6r/7r/8/8/8/8/8/8 w - - 0 1");
+      board.load_fen("K6r/7r/8/8/8/8/8/8 w - - 0 1").unwrap();
       assert_eq!(board.is_in_checkmate(Color::White), true);
       board.clear();
 
@@ -845,23 +895,23 @@ mod tests {
        */
 
       println!("Anderssen's mate");
-      // board.load_fen("6kR/6P1/5K2/8/8/8/8/8 w - - 0 1");
-      board.place_piece(piece!(King, Black), coord!(0, 6));
-      board.place_piece(piece!(Rook, White), coord!(0, 7));
-      board.place_piece(piece!(Pawn, White), coord!(1, 6));
-      board.place_piece(piece!(King, White), coord!(2, 5));
+      // board.load_fen("6kR/6P1/5K2/8/8/8/8/8 w - - 0 1").unwrap();
+      board.place_piece(piece!(King, Black), coord!(0, 6)).unwrap();
+      board.place_piece(piece!(Rook, White), coord!(0, 7)).unwrap();
+      board.place_piece(piece!(Pawn, White), coord!(1, 6)).unwrap();
+      board.place_piece(piece!(King, White), coord!(2, 5)).unwrap();
       assert_eq!(board.is_in_checkmate(Color::Black), true);
       board.clear();
 
       // Case 3: Arabian mate
 
       println!("Arabian mate");
-      board.load_fen("7k/7R/5N2/8/8/8/8/8 w - - 0 1");
+      board.load_fen("7k/7R/5N2/8/8/8/8/8 w - - 0 1").unwrap();
       board.clear();
 
       // Case N: Not a mate
       println!("Not a mate");
-      board.load_fen("K6r/7r/6N1/8/8/8/8/8 w - - 0 1");
+      board.load_fen("K6r/7r/6N1/8/8/8/8/8 w - - 0 1").unwrap();
       assert_eq!(board.is_in_checkmate(Color::White), false);
     }
 
@@ -872,7 +922,7 @@ mod tests {
 
         macro_rules! test {
             ($x: expr, $y: expr) => {
-                board.place_piece(king, coord!($x, $y));
+                board.place_piece(king, coord!($x, $y)).unwrap();
                 assert_eq!(board.get_king_coord(Color::White), Some(coord!($x, $y)));
                 board.clear();
             };
@@ -886,4 +936,4269 @@ mod tests {
         test!(6, 6);
         test!(6, 3);
     }
+
+    #[test]
+    fn move_generation_fixes() {
+        let mut board = Board::new();
+
+        // Case 1: Pawns on the edge don't capture around the board
+        board.load_fen("4k3/8/8/8/p7/6p1/7P/4K3 w - - 0 1").unwrap();
+        let mut targets: Vec<String> = board.get_moves(coord!(6, 7)).iter().map(|num| Coordinate::from_number(*num).to_string()).collect();
+        targets.sort();
+        assert_eq!(targets, vec!["g3", "h3", "h4"]);
+        board.clear();
+
+        // Case 2: A capture takes the piece out of the map of its own color
+        board.load_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        board.apply_move(coord!(4, 4), coord!(3, 3));
+        assert_eq!(board.black_pieces.len(), 1);
+        assert_eq!(board.white_pieces.len(), 2);
+        board.clear();
+
+        // Case 3: A pinned piece still gives check
+        board.load_fen("4k3/4n3/8/3K4/8/8/8/4R3 w - - 0 1").unwrap();
+        assert_eq!(board.is_in_check(Color::White), true);
+        board.clear();
+
+        // Case 4: No moves without a check is a stalemate, not a mate
+        board.load_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(board.get_moves(coord!(0, 7)).is_empty());
+        assert_eq!(board.is_in_checkmate(Color::Black), false);
+    }
+
+    #[test]
+    fn notation() {
+        let mut board = Board::default();
+        let knight_move = Move { from: coord!(7, 6), to: coord!(5, 5), promotion: None };
+
+        // Case 1: Knight from g1 to f3, written in every notation and parsed back
+        assert_eq!(Notation::San.format(&board, knight_move), "Nf3");
+        assert_eq!(Notation::Lan.format(&board, knight_move), "Ng1-f3");
+        assert_eq!(Notation::Iccf.format(&board, knight_move), "7163");
+        for notation in [Notation::San, Notation::Lan, Notation::Iccf] {
+            let text = notation.format(&board, knight_move);
+            assert_eq!(notation.parse(&board, &text), Some(knight_move));
+        }
+        assert_eq!(Notation::San.parse(&board, "Nf4"), None);
+        board.clear();
+
+        // Case 2: Promotion with a check
+        /*
+        . . . . . . . k
+        P . . . . . . .
+
+        a8=Q+
+        */
+
+        board.load_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let promotion = Move { from: coord!(1, 0), to: coord!(0, 0), promotion: Some(Pieces::Queen) };
+        assert_eq!(Notation::San.format(&board, promotion), "a8=Q+");
+        assert_eq!(Notation::Lan.format(&board, promotion), "a7-a8=Q+");
+        assert_eq!(Notation::Iccf.format(&board, promotion), "17181");
+        assert_eq!(
+            Notation::Iccf.parse(&board, "17184"),
+            Some(Move { promotion: Some(Pieces::Knight), ..promotion })
+        );
+        board.clear();
+
+        // Case 3: Two rooks on the first row, the file tells them apart
+        board.load_fen("6k1/8/8/8/8/8/K7/R6R w - - 0 1").unwrap();
+        let rook_move = Move { from: coord!(7, 0), to: coord!(7, 3), promotion: None };
+        assert_eq!(Notation::San.format(&board, rook_move), "Rad1");
+        assert_eq!(Notation::San.parse(&board, "Rad1"), Some(rook_move));
+        board.clear();
+
+        // Case 4: Two rooks on the a file, the rank tells them apart
+        board.load_fen("7k/8/8/8/R7/8/8/R5K1 w - - 0 1").unwrap();
+        let rook_move = Move { from: coord!(7, 0), to: coord!(5, 0), promotion: None };
+        assert_eq!(Notation::San.format(&board, rook_move), "R1a3");
+        board.clear();
+
+        // Case 5: Back rank mate
+        board.load_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mate = Move { from: coord!(7, 0), to: coord!(0, 0), promotion: None };
+        assert_eq!(Notation::San.format(&board, mate), "Ra8#");
+        assert_eq!(Notation::Lan.format(&board, mate), "Ra1-a8#");
+        assert_eq!(Notation::Lan.parse(&board, "Ra1-a8"), Some(mate));
+    }
+
+    #[test]
+    fn engines() {
+        let mut board = Board::default();
+        let limits = SearchLimits::depth(2);
+        let mut engines: Vec<Box<dyn Engine>> = vec![
+            Box::new(RandomMover::new()),
+            Box::new(GreedyMover::new()),
+            Box::new(AlphaBeta::new(MaterialEvaluator)),
+        ];
+
+        // Case 1: Every engine plays a legal move from the starting position
+        let legal = board.legal_moves();
+        for engine in engines.iter_mut() {
+            assert!(legal.contains(&engine.choose_move(&board, &limits).unwrap()));
+        }
+
+        // Case 2: Free queen
+        /*
+        . . . q . . . .
+        . . . . . . . .
+        . . . R . . . .
+
+        Greedy takes the queen
+        */
+
+        board.load_fen("3q3k/8/3R4/8/8/8/8/K7 w - - 0 1").unwrap();
+        let capture = Move { from: coord!(2, 3), to: coord!(0, 3), promotion: None };
+        assert_eq!(GreedyMover::new().choose_move(&board, &limits).unwrap(), capture);
+        assert_eq!(AlphaBeta::new(MaterialEvaluator).choose_move(&board, &limits).unwrap(), capture);
+
+        // Case 3: Back rank mate in one
+        board.load_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = AlphaBeta::new(MaterialEvaluator).search(&board, &limits);
+        assert_eq!(result.best_move, Some(Move { from: coord!(7, 0), to: coord!(0, 0), promotion: None }));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn mcts() {
+        let mut board = Board::default();
+
+        // Case 1: Both playout kinds play legal moves
+        let legal = board.legal_moves();
+        let limits = SearchLimits { nodes: Some(20), ..SearchLimits::default() };
+        for playout in [Playout::Random, Playout::Evaluation] {
+            let mut engine = Mcts::new(MaterialEvaluator, playout);
+            assert!(legal.contains(&engine.choose_move(&board, &limits).unwrap()));
+        }
+
+        // Case 2: Back rank mate in one with equal material
+        /*
+        . . . . . . k .
+        . . . . . p p p
+        . . . . . . . r
+        . . . . . P P P
+        R . . . . . K .
+
+        Ra8#
+        */
+
+        board.load_fen("6k1/5ppp/8/8/8/7r/5PPP/R5K1 w - - 0 1").unwrap();
+        let limits = SearchLimits { nodes: Some(300), ..SearchLimits::default() };
+        let mut engine = Mcts::new(MaterialEvaluator, Playout::Evaluation);
+        assert_eq!(
+            engine.choose_move(&board, &limits).unwrap(),
+            Move { from: coord!(7, 0), to: coord!(0, 0), promotion: None }
+        );
+
+        // Case 3: Nothing to play
+        board.load_fen("K6r/7r/8/8/8/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(engine.search(&board, &limits), None);
+        assert!(win_probability(0) == 0.5 && win_probability(400) > 0.9);
+    }
+
+    #[test]
+    fn packed_positions() {
+        let mut board = Board::default();
+
+        // Case 1: Starting position
+        let packed = board.to_packed().unwrap();
+        assert_eq!(Board::from_packed(&packed).unwrap().get_fen(), board.get_fen());
+
+        // Case 2: Black to move with an en passant square and partial castling rights
+        board.load_fen("r3k2r/8/8/8/4Pp2/8/8/R3K2R b Kq - 0 1").unwrap();
+        board.en_passant_target_sq = Some(coord!(5, 4).as_number());
+        let unpacked = Board::from_packed(&board.to_packed().unwrap()).unwrap();
+        assert_eq!(unpacked.get_fen(), board.get_fen());
+        assert_eq!(unpacked.en_passant_target_sq, board.en_passant_target_sq);
+
+        // Case 3: Garbage en passant square
+        let mut packed = board.to_packed().unwrap();
+        packed[25] = 100;
+        assert!(Board::from_packed(&packed).is_none());
+    }
+
+    #[test]
+    fn selfplay() {
+        let config = SelfPlayConfig {
+            games: 2,
+            opening_plies: 4,
+            max_plies: 6,
+            limits: SearchLimits::depth(1),
+        };
+        let mut selfplay = SelfPlay::new(MaterialEvaluator, config);
+
+        let mut data: Vec<u8> = Vec::new();
+        let count = selfplay.run(&mut data).unwrap();
+        assert_eq!(count, 12);
+        assert_eq!(data.len(), 5 + count * RECORD_SIZE);
+
+        let samples = read_samples(&mut data.as_slice()).unwrap();
+        assert_eq!(samples.len(), count);
+        assert!(samples.iter().all(|sample| sample.result == 0));
+
+        // Truncated files are rejected
+        assert!(read_samples(&mut &data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn adjudication() {
+        let mut first = AlphaBeta::new(MaterialEvaluator);
+        let mut second = AlphaBeta::new(MaterialEvaluator);
+
+        // Case 1: Nothing happens in the opening, the draw rule kicks in after 2 moves each
+        let mut config = MatchConfig {
+            games: 2,
+            limits: SearchLimits::depth(1),
+            ..MatchConfig::default()
+        };
+        config.adjudication.draw = Some(DrawAdjudication { score: 50, moves: 2, from_move: 1 });
+        let report = run_match(&mut first, &mut second, &config).unwrap();
+        assert_eq!(report.draws, 2);
+        for game in report.games.iter() {
+            assert_eq!(game.reason, Reason::DrawAdjudication);
+            assert_eq!(game.moves.len(), 4);
+        }
+
+        // Case 2: Black is a queen down and gives up after 2 moves
+        let mut config = MatchConfig {
+            games: 1,
+            limits: SearchLimits::depth(1),
+            start_fen: "4k3/8/8/8/8/8/8/3QK3 w - - 0 1".to_string(),
+            ..MatchConfig::default()
+        };
+        config.adjudication.resign = Some(ResignAdjudication { score: 500, moves: 2 });
+        let game = play_game(&mut first, &mut second, &config).unwrap();
+        assert_eq!((game.outcome, game.reason), (Outcome::WhiteWins, Reason::ResignAdjudication));
+        assert_eq!(game.moves.len(), 4);
+
+        // Case 3: Rook endings are looked up in the tablebase, bare kings end the game on their own
+        struct RookEndings;
+        impl Tablebase for RookEndings {
+            fn max_pieces(&self) -> usize {
+                3
+            }
+            fn probe_wdl(&self, _board: &Board) -> Option<Wdl> {
+                Some(Wdl::Loss)
+            }
+        }
+
+        let mut config = MatchConfig {
+            games: 1,
+            limits: SearchLimits::depth(1),
+            start_fen: "4k3/8/8/8/8/8/3q4/R3K3 w - - 0 1".to_string(),
+            ..MatchConfig::default()
+        };
+        config.adjudication.tablebase = Some(Box::new(RookEndings));
+        let game = play_game(&mut GreedyMover::new(), &mut second, &config).unwrap();
+        assert_eq!((game.outcome, game.reason), (Outcome::WhiteWins, Reason::TablebaseAdjudication));
+        assert_eq!(game.moves.len(), 1);
+
+        // Case 4: The automatic draws need no rule, bare kings and a locked pawn wall
+        let mut config = MatchConfig { games: 1, limits: SearchLimits::depth(1), ..MatchConfig::default() };
+        config.start_fen = "4k3/8/8/8/8/8/3q4/4K3 w - - 0 1".to_string();
+        let game = play_game(&mut GreedyMover::new(), &mut second, &config).unwrap();
+        assert_eq!((game.outcome, game.reason, game.moves.len()), (Outcome::Draw, Reason::InsufficientMaterial, 1));
+        config.start_fen = "8/8/4k3/1p1p1p1p/1P1P1P1P/4K3/8/8 w - - 0 1".to_string();
+        let game = play_game(&mut first, &mut second, &config).unwrap();
+        assert_eq!((game.outcome, game.reason, game.moves.len()), (Outcome::Draw, Reason::DeadPosition, 0));
+
+        // Case 5: Rules over 0 moves are off
+        let mut config = MatchConfig { games: 1, limits: SearchLimits::depth(1), max_plies: 4, ..MatchConfig::default() };
+        config.adjudication.draw = Some(DrawAdjudication { score: 50, moves: 0, from_move: 1 });
+        config.adjudication.resign = Some(ResignAdjudication { score: -1000, moves: 0 });
+        let game = play_game(&mut first, &mut second, &config).unwrap();
+        assert_eq!((game.outcome, game.reason, game.moves.len()), (Outcome::Draw, Reason::MoveLimit, 4));
+    }
+
+    #[test]
+    fn perft_counts() {
+        // Reference numbers from https://www.chessprogramming.org/Perft_Results
+        let cases = [
+            (BENCH_POSITIONS[0], 3, 8902),
+            (BENCH_POSITIONS[1], 2, 2039),
+            (BENCH_POSITIONS[2], 3, 2812),
+            (BENCH_POSITIONS[3], 2, 264),
+            (BENCH_POSITIONS[4], 2, 1486),
+            (BENCH_POSITIONS[5], 2, 2079),
+        ];
+
+        let mut board = Board::new();
+        for (fen, depth, nodes) in cases {
+            board.load_fen(fen).unwrap();
+            assert_eq!(perft(&board, depth), nodes, "{}", fen);
+        }
+
+        // Divide adds up to the same total
+        board.load_fen(BENCH_POSITIONS[1]).unwrap();
+        let split = divide(&board, 2);
+        assert_eq!(split.len(), 48);
+        assert_eq!(split.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+        assert!(split.iter().any(|(mv, _)| mv.to_string() == "e1g1"));
+
+        assert!(bench(1).stats.nodes > 0);
+    }
+
+    #[test]
+    fn parallel_perft() {
+        let mut board = Board::default();
+
+        // The totals don't depend on the number of threads, and the split keeps the move order
+        for threads in [1, 3, 8] {
+            assert_eq!(perft_parallel(&board, 3, threads), 8902);
+        }
+        assert_eq!(divide_parallel(&board, 3, 4), divide(&board, 3));
+
+        board.load_fen(BENCH_POSITIONS[2]).unwrap();
+        assert_eq!(perft_parallel(&board, 3, 2), 2812);
+        assert_eq!(perft_parallel(&board, 1, 2), 14);
+        assert_eq!(perft_parallel(&board, 0, 2), 1);
+    }
+
+    #[test]
+    fn search_stats() {
+        let mut board = Board::default();
+
+        // Perft breakdowns from https://www.chessprogramming.org/Perft_Results
+        let stats = perft_stats(&board, 3);
+        assert_eq!((stats.nodes, stats.captures, stats.checks, stats.checkmates), (8902, 34, 12, 0));
+
+        board.load_fen(BENCH_POSITIONS[1]).unwrap();
+        let stats = perft_stats(&board, 2);
+        assert_eq!(
+            (stats.nodes, stats.captures, stats.en_passants, stats.castles, stats.checks),
+            (2039, 351, 1, 91, 3)
+        );
+
+        board.load_fen(BENCH_POSITIONS[3]).unwrap();
+        let stats = perft_stats(&board, 2);
+        assert_eq!((stats.nodes, stats.captures, stats.castles, stats.promotions, stats.checks), (264, 87, 6, 48, 10));
+
+        // Searches count their nodes and cutoffs
+        board = Board::default();
+        let result = AlphaBeta::new(MaterialEvaluator).search(&board, &SearchLimits::depth(3));
+        assert!(result.stats.nodes > 0 && result.stats.beta_cutoffs > 0);
+        assert_eq!(result.stats.captures, 0);
+
+        let mut total = SearchStats::default();
+        total += result.stats;
+        total += result.stats;
+        assert_eq!(total.nodes, result.stats.nodes * 2);
+    }
+
+    #[test]
+    fn moves_from() {
+        let board = Board::default();
+
+        // Case 1: Knight on g1
+        let moves = board.moves_from(coord!(7, 6)).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert!(moves.iter().all(|mv| mv.from == coord!(7, 6) && mv.promotion.is_none()));
+
+        // Case 2: Empty square and squares outside of the board
+        assert_eq!(board.moves_from(coord!(4, 4)), Err(MoveError::EmptySquare(coord!(4, 4))));
+        assert_eq!(board.moves_from(coord!(8, 0)), Err(MoveError::OutOfBounds(coord!(8, 0))));
+        assert_eq!(board.moves_from(coord!(0, -1)), Err(MoveError::OutOfBounds(coord!(0, -1))));
+        assert_eq!(MoveError::EmptySquare(coord!(4, 4)).to_string(), "there is no piece on e4");
+
+        // Case 3: Pawn promotions come in fours
+        let mut board = Board::new();
+        board.load_fen("7k/1P6/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(board.moves_from(coord!(1, 1)).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn errors() {
+        let mut board = Board::default();
+
+        // Case 1: Broken FEN strings are rejected and the board keeps its position
+        for fen in [
+            "",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppXppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1",
+        ] {
+            assert!(matches!(board.load_fen(fen), Err(ChessError::InvalidFen(..))), "{}", fen);
+        }
+        assert_eq!(board.get_fen(), Board::default().get_fen());
+
+        // Case 2: The clocks may be left out
+        assert!(Board::from_fen("8/8/8/8/8/8/8/K6k w - -").is_ok());
+
+        // Case 3: Squares outside of the board
+        assert_eq!(board.place_piece(piece!(Queen, White), coord!(8, 0)), Err(ChessError::OutOfBounds(coord!(8, 0))));
+        assert_eq!(board.get_piece(coord!(-1, 3)), None);
+        assert_eq!(board.apply_move(coord!(6, 4), coord!(6, 8)), None);
+        assert_eq!(ChessError::from(MoveError::EmptySquare(coord!(4, 4))), ChessError::EmptySquare(coord!(4, 4)));
+
+        // Case 4: Engines report positions without moves
+        board.load_fen("K6r/7r/8/8/8/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(RandomMover::new().choose_move(&board, &SearchLimits::default()), Err(ChessError::NoLegalMoves));
+    }
+
+    #[test]
+    fn pseudo_legal_moves() {
+        let mut board = Board::new();
+
+        /*
+        Case 1: Pinned bishop
+        8 . . . . k . . .
+        7 . . . . r . . .
+        6 . . . . . . . .
+        5 . . . . . . . .
+        4 . . . . . . . .
+        3 . . . . . . . .
+        2 . . . . B . . .
+        1 . . . . K . . .
+          a b c d e f g h
+        */
+
+        board.load_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.moves_from(coord!(6, 4)).unwrap().len(), 0);
+        assert_eq!(board.pseudo_legal_moves_from(coord!(6, 4)).unwrap().len(), 9);
+
+        // Case 2: The king may walk into the rook
+        let king = board.pseudo_legal_moves_from(coord!(7, 4)).unwrap();
+        assert!(king.contains(&Move { from: coord!(7, 4), to: coord!(7, 3), promotion: None }));
+        assert_eq!(board.pseudo_legal_moves_from(coord!(3, 3)), Err(MoveError::EmptySquare(coord!(3, 3))));
+
+        // Case 3: Promotions are expanded the same way as for legal moves
+        board.load_fen("7k/1P6/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(board.pseudo_legal_moves_from(coord!(1, 1)).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn capture_and_check_moves() {
+        let mut board = Board::new();
+
+        /*
+        Case 1: Kiwipete, every capture and check is also a legal move
+        8 r . . . k . . r
+        7 p . p p q p b .
+        6 b n . . p n p .
+        5 . . . P N . . .
+        4 . p . . P . . .
+        3 . . N . . Q . p
+        2 P P P B B P P P
+        1 R . . . K . . R
+          a b c d e f g h
+        */
+
+        board.load_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let legal = board.legal_moves();
+        let captures = board.capture_moves();
+        let checks = board.checking_moves();
+
+        let expected: Vec<Move> = legal.iter().copied().filter(|mv| board.get_piece(mv.to).is_some()).collect();
+        assert_eq!(captures, expected);
+        assert_eq!(captures.len(), 8);
+        assert!(checks.is_empty());
+
+        // Case 2: En passant and a discovered check
+        board.load_fen("4k3/8/8/3pP3/8/8/8/4R1K1 w - d6 0 1").unwrap();
+        assert_eq!(board.capture_moves(), vec![Move { from: coord!(3, 4), to: coord!(2, 3), promotion: None }]);
+        let checks = board.checking_moves();
+        assert!(checks.contains(&Move { from: coord!(3, 4), to: coord!(2, 3), promotion: None }));
+        assert!(!checks.contains(&Move { from: coord!(3, 4), to: coord!(2, 4), promotion: None }));
+        let legal = board.legal_moves();
+        assert!(checks.iter().all(|mv| board.gives_check(*mv) && legal.contains(mv)));
+
+        // Case 3: Only the knight promotion checks
+        board.load_fen("8/3P4/4k3/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(board.checking_moves(), vec![Move { from: coord!(1, 3), to: coord!(0, 3), promotion: Some(Pieces::Knight) }]);
+    }
+
+    #[test]
+    fn attack_state() {
+        let mut board = Board::new();
+
+        /*
+        Case 1: Pinned knight and a check from the bishop
+        8 . . . . r . . k
+        7 . . . . . . . .
+        6 . . . . . . . .
+        5 b . . . . . . .
+        4 . . . . . . . .
+        3 . . . . N . . .
+        2 . . . . . . . .
+        1 . . . . K . . .
+          a b c d e f g h
+        */
+
+        board.load_fen("4r2k/8/8/b7/8/4N3/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::White), vec![coord!(3, 0)]);
+        assert!(board.checkers(Color::Black).is_empty());
+
+        // The knight is pinned to the e-file, everything between the king and the rook
+        let file: u64 = (0..7).map(|row| 1u64 << coord!(row, 4).as_number()).sum();
+        assert_eq!(board.pin_mask(coord!(5, 4)), file);
+        assert_eq!(board.pin_mask(coord!(3, 0)), u64::MAX);
+        assert!(board.moves_from(coord!(5, 4)).unwrap().is_empty());
+
+        // The rook sees the knight but not past it
+        assert!(board.is_square_attacked(coord!(5, 4), Color::Black));
+        assert!(!board.is_square_attacked(coord!(6, 4), Color::Black));
+        assert!(board.attacked_squares(Color::Black) & (1 << coord!(6, 3).as_number()) != 0);
+        assert!(board.is_square_attacked(coord!(6, 3), Color::White));
+
+        // Case 2: The state follows the moves
+        board.make_move(Move { from: coord!(7, 4), to: coord!(7, 3), promotion: None });
+        assert!(!board.is_in_check(Color::White));
+        assert_eq!(board.pin_mask(coord!(5, 4)), u64::MAX);
+
+        // Case 3: Only the king moves in a double check
+        board.load_fen("4r2k/8/8/8/8/3n4/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(Color::White).len(), 2);
+        assert!(board.legal_moves().iter().all(|mv| mv.from == coord!(7, 4)));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let mut board = Board::new();
+
+        // Case 1: Positions that are drawn under both rules, and ones that are not
+        for (fen, fide, no_forced_mate) in [
+            ("8/8/8/4k3/8/8/8/4K3 w - - 0 1", true, true),
+            ("8/8/8/4k3/8/8/8/2B1K3 w - - 0 1", true, true),
+            ("8/8/8/4k3/8/8/8/1N2K3 w - - 0 1", true, true),
+            // Bishops on dark squares only
+            ("5b2/8/8/4k3/8/8/8/2B1K3 w - - 0 1", true, true),
+            ("2b5/8/8/4k3/8/8/8/2B1K3 w - - 0 1", false, true),
+            ("8/8/8/4k3/8/8/8/1NN1K3 w - - 0 1", false, true),
+            ("1n6/8/8/4k3/8/8/8/1N2K3 w - - 0 1", false, true),
+            ("8/8/8/4k3/8/8/8/1NB1K3 w - - 0 1", false, false),
+            ("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1", false, false),
+            ("8/8/8/4k3/8/8/8/R3K3 w - - 0 1", false, false),
+        ] {
+            board.load_fen(fen).unwrap();
+            assert_eq!(board.is_insufficient_material(MaterialRule::Fide), fide, "{}", fen);
+            assert_eq!(board.is_insufficient_material(MaterialRule::NoForcedMate), no_forced_mate, "{}", fen);
+        }
+
+        // Case 2: Game results
+        assert_eq!(Board::default().game_result(MaterialRule::Fide), None);
+        board.load_fen("8/8/8/4k3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.game_result(MaterialRule::Fide), Some(GameResult::InsufficientMaterial));
+        board.load_fen("6k1/5ppp/8/8/8/8/8/K5R1 b - - 0 1").unwrap();
+        assert_eq!(board.game_result(MaterialRule::Fide), None);
+        board.load_fen("R5k1/5ppp/8/8/8/8/8/K7 b - - 0 1").unwrap();
+        let result = board.game_result(MaterialRule::Fide).unwrap();
+        assert_eq!(result, GameResult::Checkmate { winner: Color::White });
+        assert_eq!(result.outcome(), Outcome::WhiteWins);
+        board.load_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.game_result(MaterialRule::Fide), Some(GameResult::Stalemate));
+
+        // Case 3: The match adjudicator stops the game once the material is gone
+        let config = MatchConfig {
+            start_fen: "8/8/8/4k3/8/8/3q4/2B1K3 w - - 0 1".to_string(),
+            adjudication: Adjudication { insufficient_material: Some(MaterialRule::Fide), ..Adjudication::default() },
+            ..MatchConfig::default()
+        };
+        let game = play_game(&mut GreedyMover::new(), &mut GreedyMover::new(), &config).unwrap();
+        assert_eq!((game.outcome, game.reason), (Outcome::Draw, Reason::InsufficientMaterial));
+        assert_eq!(game.moves.len(), 1);
+    }
+
+    #[test]
+    fn dead_position() {
+        let mut board = Board::new();
+
+        /*
+        Case 1: Locked pawn wall
+        8 . . . . . . . .
+        7 . . . . . . . .
+        6 . . . . k . . .
+        5 . p . p . p . p
+        4 . P . P . P . P
+        3 . . . . K . . .
+        2 . . . . . . . .
+        1 . . . . . . . .
+          a b c d e f g h
+        */
+
+        board.load_fen("8/8/4k3/1p1p1p1p/1P1P1P1P/4K3/8/8 w - - 0 1").unwrap();
+        assert!(board.is_dead_position());
+        assert_eq!(board.game_result(MaterialRule::Fide), Some(GameResult::DeadPosition));
+
+        // Case 2: A bishop that only ever sees its own pawns' squares, bishops of both colors
+        board.load_fen("8/8/4k3/1p1p1p1p/1P1P1P1P/4K3/8/2B5 w - - 0 1").unwrap();
+        assert!(board.is_dead_position());
+        board.load_fen("8/8/4k3/1p1p1p1p/1P1P1P1P/4K3/8/2B2b2 w - - 0 1").unwrap();
+        assert_eq!(board.is_dead_position(), false);
+        board.load_fen("8/8/4k3/1p1p1p1p/1P1P1P1P/4K3/8/5B2 w - - 0 1").unwrap();
+        assert_eq!(board.is_dead_position(), false);
+
+        // Case 3: A gap in the wall lets the king through to an unprotected pawn
+        board.load_fen("8/8/4k3/1p1p1p2/1P1P1P2/4K3/8/8 w - - 0 1").unwrap();
+        assert_eq!(board.is_dead_position(), false);
+
+        // Case 4: A pawn can still take something, a rook, an ordinary position
+        board.load_fen("8/8/4k3/1p1p1p1p/1P1PPP1P/4K3/8/8 w - - 0 1").unwrap();
+        assert_eq!(board.is_dead_position(), false);
+        board.load_fen("8/8/4k3/1p1p1p1p/1P1P1P1P/4K3/8/R7 w - - 0 1").unwrap();
+        assert_eq!(board.is_dead_position(), false);
+        assert_eq!(Board::default().is_dead_position(), false);
+        assert_eq!(Board::default().game_result(MaterialRule::Fide), None);
+    }
+
+    #[test]
+    fn move_rules() {
+        let mut board = Board::new();
+
+        // Case 1: Clocks come from the FEN and follow the moves
+        board.load_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 98 60").unwrap();
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (98, 60));
+        board.make_move(Move { from: coord!(7, 0), to: coord!(6, 0), promotion: None });
+        board.make_move(Move { from: coord!(0, 4), to: coord!(0, 3), promotion: None });
+        assert_eq!((board.halfmove_clock(), board.fullmove_number()), (100, 61));
+        assert!(board.can_claim_fifty_move_rule());
+        assert_eq!(board.game_result(MaterialRule::Fide), None);
+        assert_eq!(board.get_fen(), "3k4/8/8/8/8/8/R3P3/4K3 w - - 100 61");
+
+        // A pawn move starts over
+        board.make_move(Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None });
+        assert_eq!(board.halfmove_clock(), 0);
+        assert!(!board.can_claim_fifty_move_rule());
+
+        // Case 2: The 75 move rule ends the game on its own, unless the last move mates
+        board.load_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 150 80").unwrap();
+        assert_eq!(board.game_result(MaterialRule::Fide), Some(GameResult::SeventyFiveMoveRule));
+        board.load_fen("R5k1/5ppp/8/8/8/8/8/K7 b - - 150 80").unwrap();
+        assert_eq!(board.game_result(MaterialRule::Fide), Some(GameResult::Checkmate { winner: Color::White }));
+
+        let config = MatchConfig {
+            start_fen: "4k3/8/8/8/8/8/8/R3K3 w - - 149 80".to_string(),
+            ..MatchConfig::default()
+        };
+        let game = play_game(&mut GreedyMover::new(), &mut GreedyMover::new(), &config).unwrap();
+        assert_eq!((game.outcome, game.reason), (Outcome::Draw, Reason::SeventyFiveMoveRule));
+        assert_eq!(game.moves.len(), 1);
+
+        // Case 3: Broken clocks
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").is_err());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0").is_err());
+    }
+
+    #[test]
+    fn repetitions() {
+        let mut game = Game::default();
+        let shuffle = [
+            Move { from: coord!(7, 6), to: coord!(5, 5), promotion: None },
+            Move { from: coord!(0, 6), to: coord!(2, 5), promotion: None },
+            Move { from: coord!(5, 5), to: coord!(7, 6), promotion: None },
+            Move { from: coord!(2, 5), to: coord!(0, 6), promotion: None },
+        ];
+
+        // Case 1: Knights going back and forth, the start position comes up every 4 plies
+        for cycle in 1..=4 {
+            for mv in shuffle {
+                assert_eq!(game.game_result(), None);
+                game.make_move(mv).unwrap();
+            }
+            assert_eq!(game.repetition_count(), cycle + 1);
+            assert_eq!(game.can_claim_threefold(), cycle >= 2);
+        }
+        assert_eq!(game.game_result(), Some(GameResult::FivefoldRepetition));
+        assert_eq!(game.moves().len(), 16);
+
+        // Case 2: Illegal moves are refused
+        let mut game = Game::default();
+        let illegal = Move { from: coord!(7, 6), to: coord!(4, 6), promotion: None };
+        assert_eq!(game.make_move(illegal), Err(ChessError::IllegalMove(illegal)));
+
+        // Case 3: An en passant square only counts when the capture can be played
+        game.make_move(Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None }).unwrap();
+        let without = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(game.board().zobrist(), without.zobrist());
+
+        let with = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let without = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert!(with.zobrist() != without.zobrist());
+        assert!(Board::default().zobrist() != without.zobrist());
+    }
+
+    #[test]
+    fn game_actions() {
+        let e4 = Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None };
+        let e5 = Move { from: coord!(1, 4), to: coord!(3, 4), promotion: None };
+
+        // Case 1: Draw offers stand until the opponent answers with a move
+        let mut game = Game::default();
+        game.make_move(e4).unwrap();
+        game.offer_draw(Color::White).unwrap();
+        assert_eq!(game.accept_draw(Color::White), Err(ChessError::NoDrawOffer));
+        game.make_move(e5).unwrap();
+        assert_eq!(game.draw_offer(), None);
+        assert_eq!(game.accept_draw(Color::White), Err(ChessError::NoDrawOffer));
+
+        game.offer_draw(Color::Black).unwrap();
+        game.accept_draw(Color::White).unwrap();
+        assert_eq!(game.termination(), Some((Outcome::Draw, TerminationReason::Agreement)));
+        assert_eq!(game.make_move(e4), Err(ChessError::GameOver));
+        assert_eq!(game.resign(Color::White), Err(ChessError::GameOver));
+
+        // Case 2: Resigning, abandoning and breaking the rules lose
+        let mut game = Game::default();
+        game.resign(Color::White).unwrap();
+        assert_eq!(game.termination(), Some((Outcome::BlackWins, TerminationReason::Resignation)));
+        let mut game = Game::default();
+        game.abandon(Color::Black).unwrap();
+        assert_eq!(game.termination(), Some((Outcome::WhiteWins, TerminationReason::Abandonment)));
+        assert!(write_pgn(&game).contains("[Termination \"abandoned\"]"));
+        let mut game = Game::default();
+        game.forfeit(Color::Black).unwrap();
+        assert!(write_pgn(&game).contains("[Termination \"rules infraction\"]"));
+
+        // Case 3: Running out of time against a lone king is a draw
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/Q3K3 b - - 0 1").unwrap();
+        game.flag(Color::White).unwrap();
+        assert_eq!(game.termination(), Some((Outcome::Draw, TerminationReason::Timeout)));
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/Q3K3 b - - 0 1").unwrap();
+        game.flag(Color::Black).unwrap();
+        assert_eq!(game.termination(), Some((Outcome::WhiteWins, TerminationReason::Timeout)));
+        assert!(write_pgn(&game).contains("[Termination \"time forfeit\"]"));
+
+        // A lone minor piece still mates with an enemy piece in the way, a lone king never does
+        for (fen, color, outcome) in [
+            ("4k3/4p3/8/8/8/8/8/1N2K3 w - - 0 1", Color::Black, Outcome::WhiteWins),
+            ("4k3/4p3/8/8/8/8/8/1N2K3 w - - 0 1", Color::White, Outcome::BlackWins),
+            ("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1", Color::White, Outcome::Draw),
+            ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::Black, Outcome::WhiteWins),
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            game.flag(color).unwrap();
+            assert_eq!(game.termination(), Some((outcome, TerminationReason::Timeout)), "{}", fen);
+        }
+
+        // Case 4: Results that come from the board
+        let mut game = Game::default();
+        for (from, to) in [((6, 5), (5, 5)), ((1, 4), (3, 4)), ((6, 6), (4, 6)), ((0, 3), (4, 7))] {
+            game.make_move(Move { from: coord!(from.0, from.1), to: coord!(to.0, to.1), promotion: None }).unwrap();
+        }
+        assert_eq!(game.termination(), Some((Outcome::BlackWins, TerminationReason::Checkmate)));
+        assert_eq!(
+            write_pgn(&game),
+            concat!(
+                "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n",
+                "[White \"?\"]\n[Black \"?\"]\n[Result \"0-1\"]\n[Termination \"normal\"]\n\n",
+                "1. f3 e5 2. g4 Qh4# 0-1\n",
+            )
+        );
+
+        let game = Game::from_fen("8/8/8/4k3/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(
+            game.termination(),
+            Some((Outcome::Draw, TerminationReason::DrawRule(GameResult::InsufficientMaterial)))
+        );
+        assert!(write_pgn(&game).contains("[FEN \"8/8/8/4k3/8/8/8/4K3 b - - 0 1\"]"));
+    }
+
+    #[test]
+    fn piece_helpers() {
+        // Case 1: Constructors
+        assert_eq!(Piece::new(Color::White, Pieces::Knight), piece!(Knight, White));
+        assert_eq!(Piece::white_knight(), piece!(Knight, White));
+        assert_eq!(Piece::black_queen(), piece!(Queen, Black));
+
+        // Case 2: Predicates and values
+        assert!(Piece::white_queen().is_slider() && Piece::black_bishop().is_slider());
+        assert!(!Piece::white_knight().is_slider() && !Piece::black_king().is_slider());
+        assert_eq!(Piece::black_rook().value(), 500);
+        assert_eq!(Piece::white_king().value(), 0);
+
+        // Case 3: FEN characters both ways
+        for chr in "KQRBNPkqrbnp".chars() {
+            assert_eq!(Piece::from_fen_char(chr).map(|piece| piece.to_fen_char()), Some(chr));
+        }
+        assert_eq!(Piece::from_fen_char('n'), Some(Piece::black_knight()));
+        assert_eq!(Piece::from_fen_char('x'), None);
+    }
+
+    #[test]
+    fn empty_squares() {
+        let mut board = Board::default();
+
+        // Case 1: Empty squares are None, so color filters only see real pieces
+        let white = board.board.iter().flatten().flatten().filter(|piece| piece.color == Color::White).count();
+        assert_eq!(white, 16);
+        assert_eq!(board.board[4][4], None);
+        assert_eq!(board.board[7][4], Some(Piece::white_king()));
+
+        // Case 2: Emptying a square by hand hands back what was there
+        assert_eq!(board.modify_sq(coord!(6, 0), None), Some(Piece::white_pawn()));
+        assert_eq!(board.modify_sq(coord!(6, 0), None), None);
+        assert_eq!(board.get_piece(coord!(6, 0)), None);
+    }
+
+    #[test]
+    fn try_apply_move() {
+        let mut board = Board::default();
+        let mv = |from: Coordinate, to: Coordinate| Move { from, to, promotion: None };
+
+        // Case 1: Everything that is wrong with a move gets its own error, the board stays as it was
+        let fen = board.get_fen();
+        assert_eq!(board.try_apply_move(mv(coord!(6, 4), coord!(8, 4))), Err(IllegalMove::OutOfBounds(mv(coord!(6, 4), coord!(8, 4)))));
+        assert_eq!(board.try_apply_move(mv(coord!(4, 4), coord!(3, 4))), Err(IllegalMove::EmptySquare(mv(coord!(4, 4), coord!(3, 4)))));
+        assert_eq!(board.try_apply_move(mv(coord!(1, 4), coord!(3, 4))), Err(IllegalMove::NotYourTurn(mv(coord!(1, 4), coord!(3, 4)))));
+        assert_eq!(board.try_apply_move(mv(coord!(6, 4), coord!(3, 4))), Err(IllegalMove::NotLegal(mv(coord!(6, 4), coord!(3, 4)))));
+        let queen_push = Move { from: coord!(6, 4), to: coord!(4, 4), promotion: Some(Pieces::Queen) };
+        assert_eq!(board.try_apply_move(queen_push), Err(IllegalMove::BadPromotion(queen_push)));
+        assert_eq!(board.get_fen(), fen);
+
+        // Case 2: Legal moves go through and report captures
+        assert_eq!(board.try_apply_move(mv(coord!(6, 4), coord!(4, 4))).unwrap().captured, None);
+        board.try_apply_move(mv(coord!(1, 3), coord!(3, 3))).unwrap();
+        assert_eq!(board.try_apply_move(mv(coord!(4, 4), coord!(3, 3))).unwrap().captured, Some(Piece::black_pawn()));
+
+        // Case 3: Pawns on the last row have to promote, kings can't be promoted to
+        board.load_fen("7k/1P6/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let plain = mv(coord!(1, 1), coord!(0, 1));
+        assert_eq!(board.try_apply_move(plain), Err(IllegalMove::BadPromotion(plain)));
+        let king = Move { promotion: Some(Pieces::King), ..plain };
+        assert_eq!(board.try_apply_move(king), Err(IllegalMove::BadPromotion(king)));
+        board.try_apply_move(Move { promotion: Some(Pieces::Knight), ..plain }).unwrap();
+        assert_eq!(board.get_piece(coord!(0, 1)), Some(Piece::white_knight()));
+        assert_eq!(ChessError::from(IllegalMove::NotLegal(plain)), ChessError::IllegalMove(plain));
+    }
+
+    #[test]
+    fn move_outcomes() {
+        let mut board = Board::new();
+
+        // Case 1: Castling, then en passant
+        board.load_fen("4k3/8/8/8/3p4/8/4P3/4K2R w K - 0 1").unwrap();
+        let castle = board.try_apply_move(Move { from: coord!(7, 4), to: coord!(7, 6), promotion: None }).unwrap();
+        assert!(castle.castling && !castle.en_passant && !castle.check);
+        board.load_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        board.try_apply_move(Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None }).unwrap();
+        let en_passant = board.try_apply_move(Move { from: coord!(4, 3), to: coord!(5, 4), promotion: None }).unwrap();
+        assert!(en_passant.en_passant && !en_passant.castling);
+        assert_eq!(en_passant.captured, Some(Piece::white_pawn()));
+        assert_eq!(en_passant.result, None);
+
+        // Case 2: Promotion with check, then a mate
+        board.load_fen("4k3/1P6/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let promotion = board.try_apply_move(Move { from: coord!(1, 1), to: coord!(0, 1), promotion: Some(Pieces::Queen) }).unwrap();
+        assert!(promotion.check && !promotion.checkmate);
+        assert_eq!(promotion.promotion, Some(Pieces::Queen));
+        assert_eq!(promotion.result, None);
+
+        board.load_fen("6k1/5ppp/8/8/8/8/8/K3R3 w - - 0 1").unwrap();
+        let mate = board.try_apply_move(Move { from: coord!(7, 4), to: coord!(0, 4), promotion: None }).unwrap();
+        assert!(mate.check && mate.checkmate);
+        assert_eq!(mate.result, Some(GameResult::Checkmate { winner: Color::White }));
+
+        // Case 3: The game adds the repetitions
+        let mut game = Game::default();
+        let shuffle = [((7, 6), (5, 5)), ((0, 6), (2, 5)), ((5, 5), (7, 6)), ((2, 5), (0, 6))];
+        let mut last = None;
+        for _ in 0..4 {
+            for (from, to) in shuffle {
+                last = Some(game.make_move(Move { from: coord!(from.0, from.1), to: coord!(to.0, to.1), promotion: None }).unwrap());
+            }
+        }
+        assert_eq!(last.unwrap().result, Some(GameResult::FivefoldRepetition));
+    }
+
+    #[test]
+    fn null_move() {
+        let mut board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 20").unwrap();
+        let fen = board.get_fen();
+        let hash = board.zobrist();
+
+        // Case 1: The turn passes and the en passant chance is gone
+        let undo = board.make_null_move();
+        assert_eq!(board.turn, Color::Black);
+        assert_eq!(board.en_passant_target_sq, None);
+        assert_eq!(board.get_fen(), "4k3/8/8/3pP3/8/8/8/4K3 b - - 4 20");
+        assert_eq!(board.zobrist(), Board::from_fen(&board.get_fen()).unwrap().zobrist());
+        assert!(board.zobrist() != hash);
+
+        // Case 2: Black passing starts the next move, white moving twice works
+        let second = board.make_null_move();
+        assert_eq!(board.fullmove_number(), 21);
+        assert!(board.legal_moves().iter().all(|mv| board.get_piece(mv.from).unwrap().color == Color::White));
+        board.undo_null_move(second);
+
+        // Case 3: Undo gives back the exact position
+        board.undo_null_move(undo);
+        assert_eq!(board.get_fen(), fen);
+        assert_eq!(board.zobrist(), hash);
+    }
+
+
+    #[test]
+    fn principal_variation() {
+        // Case 1: Mate in one, the line is just the mate
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = AlphaBeta::new(MaterialEvaluator).search(&board, &SearchLimits::depth(2));
+        assert_eq!(result.pv, vec![Move { from: coord!(7, 0), to: coord!(0, 0), promotion: None }]);
+        assert_eq!(result.pv_san(&board), "1. Ra8#");
+
+        // Case 2: The line starts with the best move and every move of it is legal
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let result = AlphaBeta::new(MaterialEvaluator).search(&board, &SearchLimits::depth(3));
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.pv.first().copied(), result.best_move);
+        let mut position = board;
+        for mv in &result.pv {
+            assert!(position.try_apply_move(*mv).is_ok());
+        }
+
+        // Case 3: Lines starting with black get a "N..." number
+        let mut board = board;
+        board.make_move(result.best_move.unwrap());
+        let reply = AlphaBeta::new(MaterialEvaluator).search(&board, &SearchLimits::depth(2));
+        assert!(reply.pv_san(&board).starts_with("3... "));
+        assert_eq!(reply.pv_san(&board).split(' ').count(), 4);
+    }
+
+
+    #[test]
+    fn engine_options() {
+        let mut options = EngineOptions::new();
+
+        // Case 1: Standard options are typed and checked against their bounds
+        options.set("hash", "64").unwrap();
+        options.set("Move Overhead", "30").unwrap();
+        options.set("Ponder", "true").unwrap();
+        options.set("MultiPV", "4").unwrap();
+        assert_eq!(options.hash_mb, 64);
+        assert_eq!(options.move_overhead, std::time::Duration::from_millis(30));
+        assert_eq!(options.ponder, true);
+        assert_eq!(options.multi_pv, 4);
+        assert_eq!(options.set("MultiPV", "0"), Err(ChessError::InvalidOptionValue("MultiPV".to_string(), "0".to_string())));
+        assert_eq!(options.set("Ponder", "yes"), Err(ChessError::InvalidOptionValue("Ponder".to_string(), "yes".to_string())));
+        assert_eq!(options.set("Style", "10"), Err(ChessError::UnknownOption("Style".to_string())));
+        options.set("Threads", "2").unwrap();
+        options.set("SyzygyPath", "/tb").unwrap();
+        assert_eq!(options.threads, 2);
+        assert_eq!(options.syzygy_path, Some("/tb".to_string()));
+        assert_eq!(options.set("Threads", "0"), Err(ChessError::InvalidOptionValue("Threads".to_string(), "0".to_string())));
+        options.set("SyzygyPath", "<empty>").unwrap();
+        assert_eq!(options.syzygy_path, None);
+
+        // Case 2: Custom knobs
+        options.register(UciOption::spin("PawnValue", 100, 50, 200));
+        assert_eq!(options.spin("PawnValue"), Some(100));
+        options.set("pawnvalue", "120").unwrap();
+        assert_eq!(options.spin("PawnValue"), Some(120));
+        assert_eq!(options.value("MultiPV"), Some(OptionValue::Spin(4)));
+        options.register(UciOption::string("BookFile", "book.bin"));
+        options.set("BookFile", "<empty>").unwrap();
+        assert_eq!(options.value("BookFile"), Some(OptionValue::String(String::new())));
+        assert_eq!(options.check("PawnValue"), None);
+
+        // Case 3: The lines announced to the GUI
+        assert_eq!(UciOption::spin("Hash", 16, 1, 65536).to_string(), "option name Hash type spin default 16 min 1 max 65536");
+        assert_eq!(UciOption::string("BookFile", "").to_string(), "option name BookFile type string default <empty>");
+        assert_eq!(options.options().last().unwrap().name, "BookFile");
+    }
+
+    #[test]
+    fn uci_protocol() {
+        // An evaluator with a knob, to see it reach the engine
+        #[derive(Default)]
+        struct PawnCounter {
+            pawn_value: i32,
+        }
+
+        impl Evaluator for PawnCounter {
+            fn evaluate(&self, board: &Board) -> i32 {
+                let pawns = |pieces: &PieceMap| pieces.values().filter(|piece| piece.breed == Pieces::Pawn).count() as i32;
+                let score = (pawns(&board.white_pieces) - pawns(&board.black_pieces)) * self.pawn_value;
+                return if board.turn == Color::White { score } else { -score };
+            }
+
+            fn options(&self) -> Vec<UciOption> {
+                return vec![UciOption::spin("PawnValue", 100, 0, 1000)];
+            }
+
+            fn set_options(&mut self, options: &EngineOptions) {
+                self.pawn_value = options.spin("PawnValue").unwrap() as i32;
+            }
+        }
+
+        let mut uci = Uci::new(AlphaBeta::new(PawnCounter::default()));
+        assert_eq!(uci.engine().evaluator.pawn_value, 100);
+
+        // Case 1: Handshake lists the standard options and the knob
+        let lines = uci.handle("uci").unwrap();
+        assert_eq!(lines.first().unwrap(), "id name chyes");
+        assert!(lines.contains(&"option name Ponder type check default false".to_string()));
+        assert!(lines.contains(&"option name PawnValue type spin default 100 min 0 max 1000".to_string()));
+        assert!(lines.contains(&"option name Threads type spin default 1 min 1 max 256".to_string()));
+        assert!(lines.contains(&"option name SyzygyPath type string default <empty>".to_string()));
+        assert_eq!(lines.last().unwrap(), "uciok");
+        assert_eq!(uci.handle("isready").unwrap(), vec!["readyok"]);
+
+        // Case 2: setoption reaches the evaluator, bad values are reported
+        assert!(uci.handle("setoption name PawnValue value 250").unwrap().is_empty());
+        assert_eq!(uci.engine().evaluator.pawn_value, 250);
+        assert_eq!(uci.engine().options.spin("PawnValue"), Some(250));
+        assert!(uci.handle("setoption name Move Overhead value 100").unwrap().is_empty());
+        assert_eq!(uci.options().move_overhead, std::time::Duration::from_millis(100));
+        assert_eq!(uci.handle("setoption name Hash value lots").unwrap(), vec!["info string invalid value \"lots\" for option \"Hash\""]);
+
+        // Case 3: Positions and searches
+        uci.handle("position startpos moves e2e4 e7e5 g1f3").unwrap();
+        assert_eq!(uci.board().get_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(uci.handle("position startpos moves e2e5").unwrap(), vec!["info string illegal move e2e5"]);
+        assert_eq!(uci.board().turn, Color::Black);
+
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(uci.handle("go depth 2").unwrap().is_empty());
+        assert_eq!(uci.wait(), vec!["info score mate 1 pv a1a8", "bestmove a1a8"]);
+
+        uci.handle("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        uci.handle("go depth 1").unwrap();
+        assert_eq!(uci.wait(), vec!["bestmove 0000"]);
+
+        // MultiPV shows one line per move, best first
+        uci.handle("setoption name MultiPV value 3").unwrap();
+        uci.handle("go depth 1").unwrap();
+        assert_eq!(uci.wait(), vec!["bestmove 0000"]);
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        uci.handle("go depth 2").unwrap();
+        let lines = uci.wait();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "info depth 2 multipv 1 score mate 1 pv a1a8");
+        assert!(lines[1].starts_with("info depth 2 multipv 2 score cp "));
+        assert!(lines[2].starts_with("info depth 2 multipv 3 score cp "));
+        assert_eq!(lines[3], "bestmove a1a8");
+        uci.handle("setoption name MultiPV value 1").unwrap();
+
+        // The table outlives options that don't touch it, a new size or a new knob empties it
+        let filled = uci.engine().transposition_table().filled();
+        assert!(filled > 0);
+        uci.handle("setoption name Threads value 4").unwrap();
+        assert_eq!(uci.engine().transposition_table().filled(), filled);
+        uci.handle("setoption name PawnValue value 100").unwrap();
+        assert_eq!(uci.engine().transposition_table().filled(), 0);
+        uci.handle("go depth 2").unwrap();
+        uci.wait();
+        uci.handle("setoption name Hash value 2").unwrap();
+        assert_eq!(uci.engine().transposition_table().filled(), 0);
+
+        // Case 4: Unknown commands are ignored and quit stops the loop
+        assert!(uci.handle("xyzzy").unwrap().is_empty());
+        assert_eq!(uci.handle("quit"), None);
+
+        let mut output: Vec<u8> = Vec::new();
+        uci.run("isready\nquit\nisready\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "readyok\n");
+    }
+
+    #[test]
+    fn pondering() {
+        let mut uci = Uci::new(AlphaBeta::new(MaterialEvaluator));
+
+        // Case 1: The best move comes with the expected reply to ponder on
+        uci.handle("position startpos moves e2e4").unwrap();
+        uci.handle("go depth 3").unwrap();
+        let lines = uci.wait();
+        let words: Vec<&str> = lines.last().unwrap().split(' ').collect();
+        assert_eq!((words.len(), words[0], words[2]), (4, "bestmove", "ponder"));
+
+        // Case 2: A finished ponder search holds its move back until the ponderhit
+        uci.handle(&format!("position startpos moves e2e4 {} {}", words[1], words[3])).unwrap();
+        assert!(uci.handle("go ponder depth 1").unwrap().is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(uci.is_searching());
+        assert_eq!(uci.handle("isready").unwrap(), vec!["readyok"]);
+        uci.handle("ponderhit").unwrap();
+        assert!(uci.wait().last().unwrap().starts_with("bestmove "));
+        assert_eq!(uci.is_searching(), false);
+
+        // Case 3: Without a depth the ponder search goes on until stopped, the move is still legal
+        uci.handle("position startpos").unwrap();
+        uci.handle("go ponder").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let lines = uci.handle("stop").unwrap();
+        let best = lines.last().unwrap().split(' ').nth(1).unwrap().to_string();
+        assert!(Board::default().legal_moves().iter().any(|mv| mv.to_string() == best));
+
+        // Case 4: After a ponderhit the time limit counts from the ponderhit
+        uci.handle("go ponder movetime 50").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(uci.is_searching());
+        uci.handle("ponderhit").unwrap();
+        assert!(uci.wait().last().unwrap().starts_with("bestmove "));
+
+        // Case 5: A new position aborts the ponder search, a miss is just a stop and a new search
+        uci.handle("go ponder").unwrap();
+        let lines = uci.handle("position startpos moves d2d4").unwrap();
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+        assert_eq!(uci.is_searching(), false);
+
+        // Case 6: Searches with limits finish when the input ends, endless ones are stopped
+        let mut output: Vec<u8> = Vec::new();
+        uci.run("position startpos\ngo depth 2\n".as_bytes(), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("bestmove "));
+
+        let mut output: Vec<u8> = Vec::new();
+        uci.run("go infinite\n".as_bytes(), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("bestmove "));
+
+        // Case 7: An infinite search that is over early still waits for the stop
+        uci.handle("position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(uci.handle("go infinite").unwrap().is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(uci.is_searching());
+        assert_eq!(uci.handle("stop").unwrap(), vec!["bestmove 0000"]);
+    }
+
+
+    #[test]
+    fn time_management() {
+        use std::time::{Duration, Instant};
+        let ms = Duration::from_millis;
+        let overhead = ms(10);
+
+        // Case 1: Sudden death, a share of the remaining time after the overhead
+        let mut clock = Clock { time: [ms(60_010), ms(1000)], ..Clock::default() };
+        let budget = TimeBudget::new(&SearchLimits::clock(clock), Color::White, overhead).unwrap();
+        assert_eq!(budget, TimeBudget { soft: ms(2000), hard: ms(6000) });
+
+        // Case 2: Most of the increment is spent, moves to go split the time
+        clock.increment = [ms(1000), ms(0)];
+        clock.moves_to_go = Some(10);
+        let budget = TimeBudget::new(&SearchLimits::clock(clock), Color::White, overhead).unwrap();
+        assert_eq!(budget.soft, ms(6750));
+
+        // Case 3: The hard limit never takes all the remaining time
+        clock.moves_to_go = Some(1);
+        let budget = TimeBudget::new(&SearchLimits::clock(clock), Color::Black, overhead).unwrap();
+        assert_eq!(budget, TimeBudget { soft: ms(792), hard: ms(792) });
+
+        // Case 4: A clock below the overhead still gives a move some time, movetime pays the overhead too
+        clock.time[0] = ms(5);
+        let budget = TimeBudget::new(&SearchLimits::clock(clock), Color::White, overhead).unwrap();
+        assert_eq!(budget.hard, MIN_MOVE_TIME);
+        let budget = TimeBudget::new(&SearchLimits::movetime(ms(100)), Color::White, overhead).unwrap();
+        assert_eq!(budget, TimeBudget { soft: ms(90), hard: ms(90) });
+        assert_eq!(TimeBudget::new(&SearchLimits::depth(3), Color::White, overhead), None);
+
+        // Case 5: An unstable best move gets more time, up to the hard limit
+        let budget = TimeBudget { soft: ms(100), hard: ms(180) };
+        assert_eq!(budget.soft_limit(0), ms(180));
+        assert_eq!(budget.soft_limit(1), ms(150));
+        assert_eq!(budget.soft_limit(5), ms(100));
+
+        // Case 6: Searches keep to the clock
+        let clock = Clock { time: [ms(300), ms(300)], ..Clock::default() };
+        let start = Instant::now();
+        let result = AlphaBeta::new(MaterialEvaluator).search(&Board::default(), &SearchLimits::clock(clock));
+        assert!(start.elapsed() < ms(300));
+        assert!(result.best_move.is_some());
+
+        let mut uci = Uci::new(AlphaBeta::new(MaterialEvaluator));
+        uci.handle("position startpos").unwrap();
+        uci.handle("go wtime 500 btime 500 winc 0 binc 0").unwrap();
+        assert!(uci.wait().last().unwrap().starts_with("bestmove "));
+        assert!(start.elapsed() < ms(800));
+    }
+
+
+    #[test]
+    fn skill_level() {
+        // Case 1: Full strength unless asked otherwise, the Elo limit wins over the level
+        let mut options = EngineOptions::default();
+        assert_eq!(SkillLevel::from_options(&options), None);
+        options.set("Skill Level", "8").unwrap();
+        assert_eq!(SkillLevel::from_options(&options), Some(SkillLevel::new(8)));
+        options.set("UCI_LimitStrength", "true").unwrap();
+        options.set("UCI_Elo", "1600").unwrap();
+        assert_eq!(SkillLevel::from_options(&options), Some(SkillLevel::new(10)));
+        assert_eq!(SkillLevel::from_elo(100), SkillLevel::new(0));
+        assert_eq!(SkillLevel::new(35).level(), MAX_SKILL_LEVEL);
+
+        // Case 2: Only moves within the margin are picked, noise stays within its bounds
+        let skill = SkillLevel::new(15);
+        let mut rng = Rng::new(7);
+        let moves = Board::default().legal_moves();
+        let scored: Vec<(Move, i32)> = moves.iter().enumerate().map(|(i, mv)| (*mv, i as i32 * 40)).collect();
+        for _ in 0..50 {
+            let picked = skill.pick(&scored, &mut rng).unwrap();
+            assert!(scored.iter().any(|(mv, score)| *mv == picked && *score >= 19 * 40 - skill.margin()));
+            assert!((skill.perturb(0, &mut rng)).abs() <= skill.noise());
+        }
+
+        // Case 3: A weak engine searches shallow and doesn't always play the same move
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        engine.options.skill_level = 0;
+        let mut played = std::collections::HashSet::new();
+        for _ in 0..20 {
+            let result = engine.search(&Board::default(), &SearchLimits::depth(4));
+            assert_eq!(result.depth, 1);
+            played.insert(result.best_move.unwrap());
+        }
+        assert!(played.len() > 1);
+
+        // Case 4: It still takes a queen for free
+        let board = Board::from_fen("3q3k/8/3R4/8/8/8/8/K7 w - - 0 1").unwrap();
+        let capture = Move { from: coord!(2, 3), to: coord!(0, 3), promotion: None };
+        assert_eq!(engine.choose_move(&board, &SearchLimits::depth(2)).unwrap(), capture);
+    }
+
+
+    #[test]
+    fn static_exchange() {
+        let find = |board: &Board, text: &str| board.legal_moves().into_iter().find(|mv| mv.to_string() == text).unwrap();
+
+        // Case 1: An undefended pawn, a defended one and an even trade
+        let board = Board::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find(&board, "e1e5")), 100);
+        let board = Board::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find(&board, "d3e5")), -220);
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        assert_eq!(board.see(find(&board, "e4d5")), 0);
+
+        // Case 2: The recapture would cost more than it wins, so it doesn't happen
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/8/PPP2PPP/RNBQKBNR b KQkq - 0 3").unwrap();
+        assert_eq!(board.see(find(&board, "c6d4")), 100);
+
+        // Case 3: A pinned defender doesn't count, a quiet move into an attack loses the piece
+        let board = Board::from_fen("5k2/8/3p4/4n3/8/B7/8/4R2K w - - 0 1").unwrap();
+        assert_eq!(board.see_square(coord!(3, 4)), 320);
+        let board = Board::from_fen("5k2/8/3p4/4n3/8/8/8/4R2K w - - 0 1").unwrap();
+        assert_eq!(board.see(find(&board, "e1e5")), -180);
+        assert_eq!(board.see_square(coord!(3, 4)), 0);
+        assert_eq!(board.see(find(&board, "e1e4")), 0);
+        let board = Board::from_fen("4k3/8/2p5/8/3R4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find(&board, "d4d5")), -500);
+    }
+
+    #[test]
+    fn hints() {
+        let find = |board: &Board, text: &str| board.legal_moves().into_iter().find(|mv| mv.to_string() == text).unwrap();
+
+        // Case 1: Mate comes first and is what the engine suggests
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let hint = board.hint(&mut AlphaBeta::new(MaterialEvaluator), &SearchLimits::depth(2)).unwrap();
+        assert_eq!(hint.mv, find(&board, "a1a8"));
+        assert_eq!(hint.reasons, vec![HintReason::Checkmate]);
+        assert_eq!(hint.score, Some(MATE_SCORE - 1));
+
+        // Case 2: Winning material, with check and out of the attack in the last one
+        let board = Board::from_fen("4k3/8/8/8/8/8/3q4/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.explain_move(find(&board, "d1d2")).unwrap(), vec![HintReason::WinsMaterial(900)]);
+        let board = Board::from_fen("4k3/8/8/8/1q6/8/8/1R2K3 w - - 0 1").unwrap();
+        assert_eq!(board.explain_move(find(&board, "b1b4")).unwrap(), vec![HintReason::WinsMaterial(900)]);
+        let board = Board::from_fen("3r3k/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(board.explain_move(find(&board, "d1d8")).unwrap(), vec![HintReason::WinsMaterial(500), HintReason::Check, HintReason::EscapesAttack(Pieces::Queen)]);
+
+        // Case 3: Development, castling and promotion
+        let board = Board::default();
+        assert_eq!(board.explain_move(find(&board, "g1f3")).unwrap(), vec![HintReason::Develops(Pieces::Knight)]);
+        assert!(board.explain_move(find(&board, "e2e4")).unwrap().is_empty());
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.explain_move(find(&board, "e1g1")).unwrap(), vec![HintReason::Castles]);
+        let board = Board::from_fen("8/4P3/k7/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotion = Move { from: coord!(1, 4), to: coord!(0, 4), promotion: Some(Pieces::Queen) };
+        assert_eq!(board.explain_move(promotion).unwrap(), vec![HintReason::Promotes(Pieces::Queen)]);
+
+        // Case 4: Running from an attack, only to a safe square
+        let board = Board::from_fen("4k3/5p2/8/2p5/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.explain_move(find(&board, "d4f3")).unwrap(), vec![HintReason::EscapesAttack(Pieces::Knight)]);
+        assert!(board.explain_move(find(&board, "d4e6")).unwrap().is_empty());
+        assert_eq!(
+            board.explain_move(find(&board, "d4f5")).unwrap().first().unwrap().to_string(),
+            "moves the attacked knight to safety"
+        );
+
+        // Case 5: Illegal moves are errors
+        let board = Board::default();
+        assert!(board.explain_move(Move { from: coord!(6, 4), to: coord!(3, 4), promotion: None }).is_err());
+        assert_eq!(board.explain_move(Move { from: coord!(4, 4), to: coord!(3, 4), promotion: None }), Err(ChessError::EmptySquare(coord!(4, 4))));
+    }
+
+
+    #[test]
+    fn tactics() {
+        // Case 1: Knight fork of king and rook
+        let board = Board::from_fen("r3k3/2N5/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(board.detect_tactics(Color::White), vec![TacticMotif::Fork { attacker: coord!(1, 2), targets: vec![coord!(0, 0), coord!(0, 4)] }]);
+        assert_eq!(board.detect_tactics(Color::Black), vec![]);
+
+        // Case 2: Absolute and relative pins
+        let board = Board::from_fen("4k3/8/2n5/1B6/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.detect_tactics(Color::White),
+            vec![TacticMotif::Pin { pinner: coord!(3, 1), pinned: coord!(2, 2), behind: coord!(0, 4), absolute: true }]
+        );
+        let board = Board::from_fen("3qk3/8/5n2/6B1/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.detect_tactics(Color::White),
+            vec![TacticMotif::Pin { pinner: coord!(3, 6), pinned: coord!(2, 5), behind: coord!(0, 3), absolute: false }]
+        );
+
+        // Case 3: Skewer through the king
+        let board = Board::from_fen("q7/8/8/8/k7/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(board.detect_tactics(Color::White), vec![TacticMotif::Skewer { attacker: coord!(7, 0), front: coord!(4, 0), behind: coord!(0, 0) }]);
+
+        // Case 4: Discovered attacks need a blocker that can leave the line
+        let board = Board::from_fen("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(
+            board.detect_tactics(Color::White),
+            vec![TacticMotif::DiscoveredAttack { slider: coord!(7, 4), blocker: coord!(4, 4), target: coord!(0, 4) }]
+        );
+        let board = Board::from_fen("4k3/8/8/8/8/4P3/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(board.detect_tactics(Color::White), vec![]);
+
+        // Case 5: Defended pieces worth less than the attacker are not targets
+        let board = Board::from_fen("4k3/8/2p1p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(board.detect_tactics(Color::White), vec![]);
+        assert_eq!(Board::default().detect_tactics(Color::White), vec![]);
+    }
+
+
+    #[test]
+    fn threats() {
+        let board = Board::from_fen("4k3/8/8/3p4/2N1B3/8/8/4RK2 w - - 0 1").unwrap();
+
+        // Case 1: Hanging means attacked and not defended at all
+        assert_eq!(board.hanging_pieces(Color::White), vec![coord!(4, 2)]);
+        assert_eq!(board.hanging_pieces(Color::Black), vec![coord!(3, 3)]);
+        assert_eq!(board.attackers_of(coord!(4, 4), Color::White), vec![coord!(7, 4)]);
+
+        // Case 2: Threats are weighted by the exchange, the biggest comes first
+        assert_eq!(
+            board.threats(),
+            vec![
+                Threat { target: coord!(4, 2), attackers: vec![coord!(3, 3)], defenders: vec![], gain: 320 },
+                Threat { target: coord!(4, 4), attackers: vec![coord!(3, 3)], defenders: vec![coord!(7, 4)], gain: 230 },
+            ]
+        );
+
+        // Case 3: The same threats with black to move are black's to worry about
+        let board = Board::from_fen("4k3/8/8/3p4/2N1B3/8/8/4RK2 b - - 0 1").unwrap();
+        assert_eq!(board.threats(), vec![Threat { target: coord!(3, 3), attackers: vec![coord!(4, 4)], defenders: vec![], gain: 100 }]);
+
+        // Case 4: Enough defenders, or cheap enough ones, make no threat
+        let board = Board::from_fen("4k3/8/4p3/3n4/8/8/8/3RK3 b - - 0 1").unwrap();
+        assert_eq!(board.threats(), vec![]);
+        assert_eq!(Board::default().threats(), vec![]);
+        assert_eq!(Board::default().hanging_pieces(Color::White), vec![]);
+    }
+
+
+    #[test]
+    fn classified_moves() {
+        let count = |moves: &Vec<ClassifiedMove>, test: fn(&ClassifiedMove) -> bool| moves.iter().filter(|mv| test(mv)).count();
+
+        // Case 1: Kiwipete, the counts agree with its published perft breakdown
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let moves = board.classified_moves();
+        assert_eq!(moves.len(), 48);
+        assert_eq!(count(&moves, |mv| mv.is_capture()), 8);
+        assert_eq!(count(&moves, |mv| mv.castling), 2);
+        assert_eq!(count(&moves, |mv| mv.gives_check), 0);
+        assert_eq!(moves.iter().map(|mv| mv.mv).collect::<Vec<Move>>(), board.legal_moves());
+
+        // Case 2: Checks and quiet moves
+        let board = Board::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        let moves = board.classified_moves();
+        assert_eq!((count(&moves, |mv| mv.gives_check), count(&moves, |mv| mv.is_capture())), (2, 1));
+        // Rxf4+ is both
+        assert_eq!(count(&moves, |mv| mv.is_quiet()), 12);
+
+        // Case 3: En passant captures a pawn that is not on the target square, promotions capture too
+        let board = Board::from_fen("1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let moves = board.classified_moves();
+        let en_passant = moves.iter().find(|mv| mv.en_passant).unwrap();
+        assert_eq!((en_passant.mv.to, en_passant.captured_piece), (coord!(2, 3), Some(piece!(Pawn, Black))));
+        assert_eq!(count(&moves, |mv| mv.is_promotion()), 8);
+        assert_eq!(count(&moves, |mv| mv.is_promotion() && mv.is_capture()), 4);
+        assert!(moves.iter().any(|mv| mv.mv.promotion == Some(Pieces::Queen) && mv.mv.to == coord!(0, 1) && mv.gives_check));
+    }
+
+
+    #[test]
+    fn san_disambiguation() {
+        let san = |fen: &str, uci: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            let mv = board.legal_moves().into_iter().find(|mv| mv.to_string() == uci).unwrap();
+            return Notation::San.format(&board, mv);
+        };
+
+        // Case 1: File, rank and both, whatever tells the piece apart first
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "f1d2"), "Nfd2");
+        assert_eq!(san("3rk3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1d5"), "Rd5");
+        assert_eq!(san("4k3/3R4/8/8/8/8/8/3RK3 w - - 0 1", "d1d5"), "R1d5");
+        assert_eq!(san("4k3/3R4/8/8/8/8/8/3RK3 w - - 0 1", "d7d5"), "R7d5");
+        assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2"), "Qa1b2");
+        assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a3b2"), "Q3b2");
+        assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "c1b2"), "Qcb2");
+
+        // Case 2: Only legal alternatives count, a pinned knight doesn't make the other one ambiguous
+        assert_eq!(san("4k3/8/8/3b4/8/5N2/6K1/1N6 w - - 0 1", "b1d2"), "Nd2");
+        assert_eq!(san("4k3/8/8/8/8/5N2/6K1/1N6 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("4k3/4r3/8/8/8/8/4R3/R3K3 w - - 0 1", "a1a2"), "Ra2");
+
+        // Case 3: Black pieces, captures and checks keep the disambiguation
+        assert_eq!(san("1n2kn2/8/8/8/8/8/8/4K3 b - - 0 1", "b8d7"), "Nbd7");
+        assert_eq!(san("4k3/8/8/3p4/8/2N1N3/8/4K3 w - - 0 1", "c3d5"), "Ncxd5");
+        assert_eq!(san("3k4/8/8/8/8/8/4K3/R6R w - - 0 1", "a1d1"), "Rad1+");
+        assert_eq!(san("3k4/8/8/8/8/8/4K3/R6R w - - 0 1", "h1d1"), "Rhd1+");
+
+        // Case 4: Pawns and kings never need it, castling stays castling
+        assert_eq!(san("4k3/8/8/3p4/2P1P3/8/8/4K3 w - - 0 1", "c4d5"), "cxd5");
+        assert_eq!(san("4k3/8/8/3p4/2P1P3/8/8/4K3 w - - 0 1", "e4d5"), "exd5");
+        assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"), "O-O-O");
+
+        // Case 5: Every legal move gets its own text, which parses back to it
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+            "N3N3/8/4k3/N7/8/8/8/4K3 w - - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            let texts: Vec<String> = board.legal_moves().iter().map(|mv| Notation::San.format(&board, *mv)).collect();
+            let unique: std::collections::HashSet<&String> = texts.iter().collect();
+            assert_eq!(unique.len(), texts.len());
+            for (mv, text) in board.legal_moves().iter().zip(&texts) {
+                assert_eq!(Notation::San.parse(&board, text), Some(*mv));
+            }
+        }
+    }
+
+
+    #[test]
+    fn lenient_san() {
+        let parse = |fen: &str, text: &str, mode: ParseMode| {
+            let board = Board::from_fen(fen).unwrap();
+            return Notation::San.parse_with(&board, text, mode).map(|mv| mv.to_string());
+        };
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let castle = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        // Case 1: Strict only takes what the formatter writes, check marks may be missing
+        assert_eq!(parse(start, "Nf3", ParseMode::Strict), Some(String::from("g1f3")));
+        assert_eq!(parse("3k4/8/8/8/8/8/8/R3K3 w - - 0 1", "Rd1", ParseMode::Strict), Some(String::from("a1d1")));
+        assert_eq!(parse(castle, "0-0", ParseMode::Strict), None);
+        assert_eq!(parse(start, "Ng1f3", ParseMode::Strict), None);
+
+        // Case 2: Castling with zeros
+        assert_eq!(parse(castle, "0-0", ParseMode::Lenient), Some(String::from("e1g1")));
+        assert_eq!(parse(castle, "0-0-0+", ParseMode::Lenient), Some(String::from("e1c1")));
+        assert_eq!(parse(castle, "o-o", ParseMode::Lenient), Some(String::from("e1g1")));
+
+        // Case 3: En passant marks
+        let ep = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        assert_eq!(parse(ep, "exd6 e.p.", ParseMode::Lenient), Some(String::from("e5d6")));
+        assert_eq!(parse(ep, "exd6ep", ParseMode::Lenient), Some(String::from("e5d6")));
+        assert_eq!(parse(ep, "exd6 e.p.", ParseMode::Strict), None);
+
+        // Case 4: Promotion styles, a bare pawn push to the last rank is a queen
+        let promotion = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+        assert_eq!(parse(promotion, "e8Q", ParseMode::Lenient), Some(String::from("e7e8q")));
+        assert_eq!(parse(promotion, "e8(N)", ParseMode::Lenient), Some(String::from("e7e8n")));
+        assert_eq!(parse(promotion, "e8=r", ParseMode::Lenient), Some(String::from("e7e8r")));
+        assert_eq!(parse(promotion, "e8", ParseMode::Lenient), Some(String::from("e7e8q")));
+        assert_eq!(parse(promotion, "e8K", ParseMode::Lenient), None);
+
+        // Case 5: Capture marks and disambiguation that aren't needed, long algebraic
+        assert_eq!(parse(start, "Ng1f3", ParseMode::Lenient), Some(String::from("g1f3")));
+        assert_eq!(parse(start, "Ng1-f3", ParseMode::Lenient), Some(String::from("g1f3")));
+        assert_eq!(parse(start, "e2e4", ParseMode::Lenient), Some(String::from("e2e4")));
+        assert_eq!(parse("4k3/8/8/3p4/8/2N5/8/4K3 w - - 0 1", "Nd5", ParseMode::Lenient), Some(String::from("c3d5")));
+
+        // Case 6: A capital B is a b-pawn only when no bishop can make the move
+        assert_eq!(parse("4k3/8/8/2p5/1P6/8/8/4K3 w - - 0 1", "Bxc5", ParseMode::Lenient), Some(String::from("b4c5")));
+        assert_eq!(parse("4k3/8/8/2p5/1P6/4B3/8/4K3 w - - 0 1", "Bxc5", ParseMode::Lenient), Some(String::from("e3c5")));
+
+        // Case 7: Still nothing when the move is ambiguous or illegal
+        assert_eq!(parse("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "Nd2", ParseMode::Lenient), None);
+        assert_eq!(parse(start, "Nf4", ParseMode::Lenient), None);
+        assert_eq!(parse(start, "", ParseMode::Lenient), None);
+    }
+
+
+    #[test]
+    fn pgn_options() {
+        let mut game = Game::default();
+        for san in ["e4", "e5", "Nf3", "Nc6"] {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let c5 = Notation::San.parse(&board, "c5").unwrap();
+        let nf3 = Notation::San.parse(&board, "e5").map(|mv| {
+            let mut after = board;
+            after.make_move(mv);
+            return Notation::San.parse(&after, "Nf3").unwrap();
+        }).unwrap();
+
+        let mut writer = PgnWriter::new(PgnOptions::default());
+        writer
+        .tag("White", "Løvenskiöld")
+        .tag("Result", "1-0")
+        .tag("Annotator", "me")
+        .tag("ECO", "C44")
+        .annotate(1, PgnAnnotation { nags: vec![1], comment: Some(String::from("open} game")), eval: Some(-25), clock: None, variations: vec![vec![c5, nf3]] })
+        .annotate(2, PgnAnnotation { eval: Some(99_997), ..PgnAnnotation::default() });
+
+        // Case 1: Everything, tags in the order they came, the result can't be overridden
+        assert_eq!(
+            writer.write(&game),
+            concat!(
+                "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n",
+                "[White \"Løvenskiöld\"]\n[Black \"?\"]\n[Result \"*\"]\n[Termination \"unterminated\"]\n",
+                "[Annotator \"me\"]\n[ECO \"C44\"]\n\n",
+                "1. e4 e5 $1 {[%eval -0.25] open game} (1... c5 2. Nf3) 2. Nf3 {[%eval #2]} 2...\n",
+                "Nc6 *\n",
+            )
+        );
+
+        // Case 2: Export format, Latin-1 and sorted tags after the roster
+        writer.options = PgnOptions::export();
+        writer.tag("White", "Carlsen ♔");
+        let pgn = writer.write(&game);
+        assert!(pgn.contains("[White \"Carlsen ?\"]\n[Black \"?\"]\n[Result \"*\"]\n[Annotator \"me\"]\n[ECO \"C44\"]\n[Termination \"unterminated\"]\n"));
+        assert_eq!(PgnCharset::Latin1.encode("ö♔"), vec![0xf6, b'?']);
+        assert_eq!(PgnCharset::Utf8.encode("ö"), vec![0xc3, 0xb6]);
+
+        // Case 3: Annotations can be left out one by one
+        writer.options = PgnOptions { comments: false, variations: false, ..PgnOptions::default() };
+        assert!(writer.write(&game).ends_with("1. e4 e5 $1 {[%eval -0.25]} 2. Nf3 {[%eval #2]} 2... Nc6 *\n"));
+        writer.options = PgnOptions::bare();
+        assert!(writer.write(&game).ends_with("\n\n1. e4 e5 2. Nf3 Nc6 *\n"));
+
+        // Case 4: Line width, None keeps the movetext on one line
+        let mut game = Game::default();
+        let ruy_lopez = "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Nb8 d4 Nbd7";
+        for san in ruy_lopez.split(' ') {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        let movetext = |options: PgnOptions| {
+            let pgn = PgnWriter::new(options).write(&game);
+            return pgn.split("\n\n").nth(1).unwrap().to_string();
+        };
+        assert!(movetext(PgnOptions::default()).lines().count() > 1);
+        assert!(movetext(PgnOptions::default()).lines().all(|line| line.len() < 80));
+        assert_eq!(movetext(PgnOptions { line_width: None, ..PgnOptions::default() }).lines().count(), 1);
+    }
+
+
+    #[test]
+    fn pgn_import() {
+        // Case 1: Tags, comments, variations, NAGs and move numbers stuck to the moves
+        let text = concat!(
+            "[Event \"Club \\\"open\\\"\"]\n[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n\n",
+            "1.e4 e5 {main line} 2. Nf3 $1 (2. f4 exf4 (2... d5)) 2... Nc6 ; rest of the line\n",
+            "3. Bb5 a6!? 1-0\n",
+        );
+        let imported = read_pgn(text, ParseMode::Strict).unwrap();
+        assert_eq!(imported.tag("Event"), Some("Club \"open\""));
+        assert_eq!(imported.game.moves().len(), 6);
+        assert_eq!(imported.game.termination(), Some((Outcome::WhiteWins, TerminationReason::Resignation)));
+        let pgn = imported.writer(PgnOptions::bare()).write(&imported.game);
+        assert!(pgn.starts_with("[Event \"Club \\\"open\\\"\"]\n[Site \"?\"]"));
+        assert!(pgn.ends_with("[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n[Termination \"normal\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0\n"));
+
+        // Case 2: SetUp and FEN round-trip, black to move first
+        let text = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]\n\n7... Kd7 8. e4 *\n";
+        let imported = read_pgn(text, ParseMode::Strict).unwrap();
+        assert_eq!(imported.game.start().get_fen(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 7");
+        let pgn = imported.writer(PgnOptions::default()).write(&imported.game);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]\n"));
+        assert!(pgn.ends_with("7... Kd7 8. e4 *\n"));
+        assert_eq!(read_pgn(&pgn, ParseMode::Strict).unwrap().game.board().get_fen(), imported.game.board().get_fen());
+
+        // Case 3: Standard chess may say so, other variants aren't read with the wrong rules
+        let imported = read_pgn("[Variant \"Standard\"]\n\n1. e4 *\n", ParseMode::Strict).unwrap();
+        assert_eq!(imported.game.moves().len(), 1);
+        let chess960 = "[Variant \"Chess960\"]\n[FEN \"bnrbkrqn/pppppppp/8/8/8/8/PPPPPPPP/BNRBKRQN w - - 0 1\"]\n\n*\n";
+        assert!(matches!(read_pgn(chess960, ParseMode::Strict), Err(ChessError::InvalidPgn(_))));
+
+        // Case 4: What can't be read
+        assert!(matches!(read_pgn("[Variant \"Crazyhouse\"]\n\n*\n", ParseMode::Strict), Err(ChessError::InvalidPgn(_))));
+        assert!(matches!(read_pgn("1. e4 e5 2. Ke3 *", ParseMode::Strict), Err(ChessError::InvalidPgn(_))));
+        assert!(matches!(read_pgn("1. e4 (1. d4 *", ParseMode::Strict), Err(ChessError::InvalidPgn(_))));
+        assert_eq!(read_pgn("1. e4 e5 2. 0-0-0 *", ParseMode::Lenient).is_err(), true);
+        assert_eq!(read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. 0-0", ParseMode::Lenient).unwrap().game.moves().len(), 7);
+    }
+
+
+    #[test]
+    fn write_fen() {
+        // Case 1: Same text as get_fen, appended to what the writer already holds
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 1",
+            "8/8/8/8/8/8/8/k6K b - - 99 120",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut out = String::from("fen ");
+            board.write_fen(&mut out).unwrap();
+            assert_eq!(out, format!("fen {}", fen));
+            assert_eq!(board.get_fen(), fen);
+        }
+
+        // Case 2: Piece letters come from the same table both ways
+        for chr in "KQRBNPkqrbnp".chars() {
+            assert_eq!(Piece::from_fen_char(chr).unwrap().to_fen_char(), chr);
+        }
+    }
+
+
+    #[test]
+    fn board_copy() {
+        // Case 1: A copy is independent of the original
+        let board = Board::default();
+        let mut copy = board;
+        copy.make_move(Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None });
+        assert_eq!(board.get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(copy.white_pieces.get(&28), Some(&piece!(Pawn, White)));
+        assert_eq!(board.white_pieces.get(&28), None);
+
+        // Case 2: The piece maps work like the maps they replaced, in square order
+        let mut pieces = PieceMap::new();
+        assert_eq!(pieces.is_empty(), true);
+        assert_eq!(pieces.insert(12, piece!(Queen, Black)), None);
+        assert_eq!(pieces.insert(3, piece!(King, Black)), None);
+        assert_eq!(pieces.insert(12, piece!(Rook, Black)), Some(piece!(Queen, Black)));
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces.keys().copied().collect::<Vec<i8>>(), vec![3, 12]);
+        assert_eq!(pieces[&12], piece!(Rook, Black));
+        assert_eq!(pieces.remove(&3), Some(piece!(King, Black)));
+        assert_eq!(pieces.remove(&3), None);
+        assert_eq!(pieces.contains_key(&3), false);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(board.white_pieces.len() + board.black_pieces.len(), 32);
+    }
+
+
+    #[test]
+    fn shared_positions() {
+        // Case 1: Snapshots share one copy, playing a move leaves them alone
+        let start = Position::default();
+        let copy = start.clone();
+        assert_eq!(copy, start);
+        assert_eq!(start.legal_moves().len(), 20);
+        let e4 = Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None };
+        let after = start.play(e4).unwrap();
+        assert_eq!(after.board().turn, Color::Black);
+        assert_eq!(after.hash(), after.board().zobrist());
+        assert_eq!(start.fen(), Board::default().get_fen());
+        assert_eq!(after.play(e4), Err(ChessError::IllegalMove(e4)));
+        assert_eq!(Position::from(&Game::default()), start);
+
+        // Case 2: Workers on other threads read the same snapshot and agree
+        let counts: Vec<u64> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    let position = after.clone();
+                    scope.spawn(move || position.legal_moves().iter().map(|mv| perft(position.play(*mv).unwrap().board(), 1)).sum::<u64>())
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+        assert_eq!(counts, vec![perft(after.board(), 2); 4]);
+
+        // Case 3: Boards and engines go to other threads too
+        let board = *after.board();
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let mv = std::thread::spawn(move || engine.choose_move(&board, &SearchLimits::depth(1)).unwrap()).join().unwrap();
+        assert!(after.legal_moves().contains(&mv));
+    }
+
+
+    #[test]
+    fn bulk_analysis() {
+        let fens = vec![
+            String::from("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"),
+            String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            String::from("not a fen"),
+            String::from("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            String::from("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1"),
+        ];
+
+        // Case 1: Every position comes back once, whatever order the workers finish in
+        let receiver = analyze_positions(fens.clone(), || AlphaBeta::new(MaterialEvaluator), SearchLimits::depth(2), 3);
+        let mut results: Vec<Result<Analysis, ChessError>> = receiver.into_iter().collect();
+        assert_eq!(results.len(), 5);
+        let errors: Vec<ChessError> = results.iter().filter_map(|result| result.clone().err()).collect();
+        assert!(matches!(&errors[..], [ChessError::InvalidFen(fen, _)] if fen == "not a fen"));
+
+        results.retain(|result| result.is_ok());
+        let mut analyses: Vec<Analysis> = results.into_iter().map(|result| result.unwrap()).collect();
+        analyses.sort_by_key(|analysis| analysis.index);
+        assert_eq!(analyses.iter().map(|analysis| analysis.index).collect::<Vec<usize>>(), vec![0, 1, 3, 4]);
+        assert_eq!(analyses[0].fen, fens[0]);
+
+        // Case 2: Scores, moves and lines, stalemate has none of them
+        assert_eq!(analyses[0].best_move.map(|mv| mv.to_string()), Some(String::from("a1a8")));
+        assert_eq!(analyses[0].pv.first(), analyses[0].best_move.as_ref());
+        assert!(analyses[0].score.unwrap() > 10_000);
+        assert_eq!(analyses[2].best_move, None);
+        assert_eq!(analyses[2].score, None);
+        assert_eq!(analyses[3].best_move.map(|mv| mv.to_string()), Some(String::from("d2d5")));
+
+        // Case 3: Same answers with one thread, nothing at all from a stopped control
+        let receiver = analyze_positions(fens.clone(), || AlphaBeta::new(MaterialEvaluator), SearchLimits::depth(2), 1);
+        let single: Vec<Option<Move>> = receiver.into_iter().filter_map(|result| result.ok()).map(|analysis| analysis.best_move).collect();
+        assert_eq!(single, analyses.iter().map(|analysis| analysis.best_move).collect::<Vec<Option<Move>>>());
+
+        let limits = SearchLimits::depth(2);
+        limits.control.stop();
+        assert_eq!(analyze_positions(fens, || AlphaBeta::new(MaterialEvaluator), limits, 2).into_iter().count(), 0);
+    }
+
+
+    #[test]
+    fn eval_cache() {
+        // Case 1: Slots are a power of two that fits, 0 MB turns the cache off
+        let mut cache = EvalCache::new(1);
+        assert_eq!(cache.len(), 32768);
+        assert_eq!(EvalCache::new(0).is_empty(), true);
+
+        // Case 2: Hits, misses and slots shared by two positions
+        assert_eq!(cache.probe(42), None);
+        cache.store(42, 150);
+        assert_eq!(cache.probe(42), Some(150));
+        assert_eq!(cache.probe(42 + 32768), None);
+        cache.store(42 + 32768, -30);
+        assert_eq!(cache.probe(42), None);
+        assert_eq!(cache.stats(), EvalCacheStats { probes: 4, hits: 1 });
+        assert_eq!(cache.stats().hit_rate(), 0.25);
+        cache.clear();
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+
+        // Case 3: Same scores through the cache, the second time from the cache
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        assert_eq!(cache.evaluate(&MaterialEvaluator, &board), MaterialEvaluator.evaluate(&board));
+        assert_eq!(cache.evaluate(&MaterialEvaluator, &board), -400);
+        assert_eq!(cache.stats(), EvalCacheStats { probes: 2, hits: 1 });
+
+        // Case 4: Searches use it, sized by its own option
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let result = engine.search(&Board::default(), &SearchLimits::depth(3));
+        assert!(engine.eval_cache().stats().hits > 0);
+        let mut options = EngineOptions::default();
+        options.set("EvalCache", "0").unwrap();
+        assert_eq!(options.hash_mb, 16);
+        engine.set_options(&options);
+        assert_eq!(engine.eval_cache().is_empty(), true);
+        assert_eq!(engine.search(&Board::default(), &SearchLimits::depth(3)).score, result.score);
+        assert_eq!(engine.eval_cache().stats().probes, 0);
+    }
+
+
+    #[test]
+    fn game_history() {
+        // Case 1: Hashes of earlier positions come back across checkpoints
+        let mut game = Game::default();
+        let mut boards = vec![*game.board()];
+        let moves = "e4 e5 Nf3 Nc6 Ng1 Nb8 Nf3 Nc6 Ng1 Nb8 d4 d5 Nf3 Nc6 Ng1 Nb8 Nf3 Nc6 Ng1 Nb8 \
+                     c4 c5 Nf3 Nc6 Ng1 Nb8 Nf3 Nc6 Ng1 Nb8 a3 a6 Nf3 Nc6";
+        for san in moves.split_whitespace() {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+            boards.push(*game.board());
+        }
+        assert!(game.moves().len() > CHECKPOINT_INTERVAL);
+        for (ply, board) in boards.iter().enumerate() {
+            assert_eq!(game.hash_at(ply), Some(board.zobrist()));
+        }
+        assert_eq!(game.hash_at(boards.len()), None);
+
+        // Case 2: Repetitions only count since the last pawn move
+        assert_eq!(game.repetition_count(), 1);
+        for san in ["Ng1", "Nb8", "Nf3", "Nc6"] {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        assert_eq!(game.repetition_count(), 2);
+        for san in ["Ng1", "Nb8", "Nf3", "Nc6"] {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        assert_eq!(game.repetition_count(), 3);
+        assert_eq!(game.can_claim_threefold(), true);
+    }
+
+
+    #[test]
+    fn position_replay() {
+        let mut game = Game::default();
+        for san in "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7".split_whitespace() {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+
+        // Case 1: Random access and lazy iteration agree
+        assert_eq!(game.position_at(0).unwrap().get_fen(), Board::default().get_fen());
+        assert_eq!(game.position_at(10).unwrap().get_fen(), game.board().get_fen());
+        assert_eq!(game.position_at(11).is_none(), true);
+        assert_eq!(game.positions().len(), 11);
+        for (ply, board) in game.positions().enumerate() {
+            assert_eq!(board.get_fen(), game.position_at(ply).unwrap().get_fen());
+        }
+        assert_eq!(game.positions().nth(2).unwrap().get_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+
+        // Case 2: A range of moves onto another board
+        let board = game.position_at(2).unwrap();
+        assert_eq!(game.replay_range(2..6, &board).unwrap().get_fen(), game.position_at(6).unwrap().get_fen());
+        assert_eq!(game.replay_range(0..0, &board).unwrap().get_fen(), board.get_fen());
+        let other = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        assert_eq!(game.replay_range(4..6, &other).unwrap().get_fen(), game.position_at(6).unwrap().get_fen());
+        assert!(matches!(game.replay_range(4..6, &Board::default()), Err(ChessError::IllegalMove(_))));
+        assert_eq!(game.replay_range(8..12, &board).err(), Some(ChessError::PlyOutOfRange(12)));
+    }
+
+
+    #[test]
+    fn position_codes() {
+        // Case 1: Codes are URL-safe and give the position back, the clocks aside
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 1",
+            "8/8/8/8/8/8/8/k6K b - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let code = board.to_code().unwrap();
+            assert_eq!(code.len(), CODE_LENGTH);
+            assert!(code.chars().all(|chr| chr.is_ascii_alphanumeric() || chr == '-' || chr == '_'));
+            assert_eq!(Board::from_code(&code).unwrap().get_fen(), fen);
+        }
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 12 40").unwrap();
+        assert_eq!(Board::from_code(&board.to_code().unwrap()).unwrap().get_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        // Case 2: Anything else is refused
+        let code = Board::default().to_code().unwrap();
+        assert!(Board::from_code(&code[1..]).is_none());
+        assert!(Board::from_code(&format!("{}A", code)).is_none());
+        assert!(Board::from_code(&code.replacen(&code[..1], "/", 1)).is_none());
+        let mut padded = code.clone();
+        let last = padded.pop().unwrap();
+        padded.push(if last == 'B' { 'C' } else { 'B' });
+        assert!(Board::from_code(&padded).is_none());
+    }
+
+
+    #[test]
+    fn odds_games() {
+        // Case 1: The usual odds, from either side
+        assert_eq!(Board::odds(Odds::Knight, Color::White).get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1");
+        assert_eq!(Board::odds(Odds::Rook, Color::White).get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1");
+        assert_eq!(Board::odds(Odds::Queen, Color::Black).get_fen(), "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(Board::odds(Odds::Pawn, Color::Black).get_fen(), "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(Board::odds(Odds::PawnAndMove, Color::White).get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(Board::odds(Odds::PawnAndMove, Color::Black).turn, Color::White);
+        assert_eq!(Board::odds(Odds::Rook, Color::Black).legal_moves().len(), 20);
+
+        // Case 2: Any material handicap, the king and the opponent's pieces can't go
+        let board = Board::handicap(Color::White, &[coord!(7, 0), coord!(7, 7), coord!(7, 3)]).unwrap();
+        assert_eq!(board.get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NB1KBN1 w kq - 0 1");
+        assert_eq!(board.validate(), Ok(()));
+        assert!(matches!(Board::handicap(Color::White, &[coord!(7, 4)]), Err(ChessError::InvalidPosition(_))));
+        assert!(matches!(Board::handicap(Color::White, &[coord!(0, 3)]), Err(ChessError::InvalidPosition(_))));
+        assert_eq!(Board::handicap(Color::White, &[coord!(4, 4)]).err(), Some(ChessError::EmptySquare(coord!(4, 4))));
+
+        // Case 3: The validator on its own
+        let invalid = |fen: &str| matches!(Board::from_fen(fen).unwrap().validate(), Err(ChessError::InvalidPosition(_)));
+        assert_eq!(invalid("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), false);
+        assert_eq!(invalid("4k3/8/8/8/8/8/8/8 w - - 0 1"), true);
+        assert_eq!(invalid("4k3/8/8/8/8/8/8/4K2P w - - 0 1"), true);
+        assert_eq!(invalid("4k3/8/8/8/8/8/8/4K2R b - - 0 1"), false);
+        assert_eq!(invalid("4k2R/8/8/8/8/8/8/4K3 w - - 0 1"), true);
+        let mut rights = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        rights.castling_rights[0] = true;
+        assert!(matches!(rights.validate(), Err(ChessError::InvalidPosition(_))));
+        assert_eq!(invalid("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"), false);
+        let mut target = Board::from_fen("4k3/8/8/4P3/8/8/8/4K3 w - - 0 1").unwrap();
+        target.en_passant_target_sq = Some(coord!(2, 3).as_number());
+        assert!(matches!(target.validate(), Err(ChessError::InvalidPosition(_))));
+
+        // Case 4: A FEN only keeps an en passant square a pawn could have just moved past
+        for fen in ["4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1", "8/8/8/2Pk4/8/8/8/4K3 w - d6 0 1", "4k3/8/8/8/8/8/1P6/4K3 w - a3 0 1", "4k3/3p4/8/3pP3/8/8/8/4K3 w - d6 0 1"] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.get_fen().split(' ').nth(3), Some("-"), "{}", fen);
+            assert!(board.classified_moves().iter().all(|mv| !mv.en_passant), "{}", fen);
+        }
+        let mut king = Board::from_fen("8/8/8/2Pk4/8/8/8/4K3 w - - 0 1").unwrap();
+        king.en_passant_target_sq = Some(coord!(2, 3).as_number());
+        king.apply_move(coord!(3, 2), coord!(2, 3));
+        assert_eq!(king.get_piece(coord!(3, 3)), Some(piece!(King, Black)));
+
+        // Case 5: Taking pieces off
+        let mut board = Board::default();
+        assert_eq!(board.remove_piece(coord!(0, 3)), Ok(piece!(Queen, Black)));
+        assert_eq!(board.remove_piece(coord!(0, 3)), Err(ChessError::EmptySquare(coord!(0, 3))));
+    }
+
+
+    #[test]
+    fn puzzles() {
+        let csv = concat!(
+            "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags\n",
+            "00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,",
+            "mate mateIn2 middlegame short,https://lichess.org/yyznGmXs/black#34,\n",
+            "\n",
+            "bad,8/8/8/8/8/8/8/k6K w - - 0 1,a1a2 h1h2,1500,75,90,10,endgame,,\n",
+        );
+
+        // Case 1: Import, the puzzle starts after the opponent's move
+        let puzzles: Vec<Result<Puzzle, ChessError>> = read_lichess_csv(csv.as_bytes()).collect();
+        assert_eq!(puzzles.len(), 2);
+        let puzzle = puzzles[0].clone().unwrap();
+        assert_eq!(puzzle.id, "00sHx");
+        assert_eq!(puzzle.fen, "q5nr/1ppknQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 w - - 1 18");
+        assert_eq!(puzzle.solution.iter().map(|mv| mv.to_string()).collect::<Vec<String>>(), vec!["a2e6", "d7d8", "f7f8"]);
+        assert_eq!((puzzle.rating, puzzle.rating_deviation, puzzle.popularity, puzzle.plays), (1760, 80, 83, 72));
+        assert_eq!(puzzle.has_theme("mateIn2"), true);
+        assert_eq!(puzzle.game_url, "https://lichess.org/yyznGmXs/black#34");
+        assert_eq!(puzzle.openings.is_empty(), true);
+        assert!(matches!(puzzles[1], Err(ChessError::InvalidPuzzle(_))));
+        assert!(matches!(Puzzle::from_lichess_csv("x,not a fen,e2e4,1,1,1,1,"), Err(ChessError::InvalidFen(_, _))));
+
+        // Case 2: The solution is the only way to win
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        assert_eq!(puzzle.verify(&mut engine, &SearchLimits::depth(3)), Ok(PuzzleCheck::Unique));
+
+        // Case 3: A move that doesn't win, and a position where two moves win
+        let mut wrong = puzzle.clone();
+        wrong.solution = vec![puzzle.board().unwrap().legal_moves().into_iter().find(|mv| mv.to_string() == "a3b4").unwrap()];
+        assert_eq!(wrong.verify(&mut engine, &SearchLimits::depth(3)), Ok(PuzzleCheck::NotWinning(0)));
+
+        let two_rooks = Puzzle::from_lichess_csv("two,k7/8/1K6/8/8/8/8/R5R1 b - - 0 1,a8b8 g1g8,1000,75,90,10,mate,,").unwrap();
+        assert_eq!(two_rooks.verify(&mut engine, &SearchLimits::depth(3)), Ok(PuzzleCheck::Unique));
+        let hanging = Puzzle::from_lichess_csv("hang,6k1/8/8/3q4/8/8/3R4/4K3 b - - 0 1,g8h8 d2d5,1000,75,90,10,hangingPiece,,").unwrap();
+        assert_eq!(hanging.verify(&mut engine, &SearchLimits::depth(2)), Ok(PuzzleCheck::Unique));
+        let twice = Puzzle::from_lichess_csv("twice,6k1/8/8/3q4/8/5B2/3R4/4K3 b - - 0 1,g8h8 d2d5,1000,75,90,10,hangingPiece,,").unwrap();
+        assert!(matches!(twice.verify(&mut engine, &SearchLimits::depth(2)), Ok(PuzzleCheck::Ambiguous(0, _))));
+
+        // Case 4: A FEN changed by hand is an error, not a panic
+        let mut broken = puzzle.clone();
+        broken.fen = "not a fen".to_string();
+        assert!(matches!(broken.board(), Err(ChessError::InvalidFen(_, _))));
+        assert!(matches!(broken.verify(&mut engine, &SearchLimits::depth(1)), Err(ChessError::InvalidFen(_, _))));
+    }
+
+
+    #[test]
+    fn guess_the_move() {
+        let mut game = Game::default();
+        for san in "e4 e5 Qh5 Nc6 Bc4 Nf6 Qxf7#".split_whitespace() {
+            let mv = Notation::San.parse(game.board(), san).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let limits = SearchLimits::depth(2);
+        let mut session = GuessTheMove::new(game.clone(), Some(Color::White));
+        let guess = |session: &GuessTheMove, san: &str| Notation::San.parse(&session.position().unwrap(), san).unwrap();
+
+        // Case 1: The game move scores everything
+        let result = session.guess(&mut engine, guess(&session, "e4"), &limits).unwrap();
+        assert_eq!((result.ply, result.points, result.guess_score), (0, MAX_POINTS, None));
+
+        // Case 2: Black's moves are played in between, a guess as good as the game move loses one point
+        assert_eq!(session.position().unwrap().turn, Color::White);
+        let result = session.guess(&mut engine, guess(&session, "Nf3"), &limits).unwrap();
+        assert_eq!((result.ply, result.points), (2, MAX_POINTS - 1));
+        assert_eq!(result.actual.to_string(), "d1h5");
+
+        // Case 3: Missing the mate is worth nothing, illegal guesses don't count
+        session.guess(&mut engine, guess(&session, "Bc4"), &limits).unwrap();
+        let illegal = Move { from: coord!(7, 0), to: coord!(5, 0), promotion: None };
+        assert_eq!(session.guess(&mut engine, illegal, &limits), Err(ChessError::IllegalMove(illegal)));
+        let result = session.guess(&mut engine, guess(&session, "Qxe5+"), &limits).unwrap();
+        assert_eq!(result.points, 0);
+        assert!(result.actual_score.unwrap() > result.guess_score.unwrap());
+
+        // Case 4: The session is over with the game
+        assert_eq!(session.is_finished(), true);
+        assert_eq!(session.position().is_none(), true);
+        assert_eq!((session.score(), session.max_score()), (3 * MAX_POINTS - 1, 4 * MAX_POINTS));
+        assert_eq!(session.results().len(), 4);
+        assert_eq!(session.guess(&mut engine, illegal, &limits), Err(ChessError::GameOver));
+
+        // Case 5: Both sides, or only black
+        assert_eq!(GuessTheMove::new(game.clone(), None).position().unwrap().get_fen(), Board::default().get_fen());
+        assert_eq!(GuessTheMove::new(game, Some(Color::Black)).position().unwrap().turn, Color::Black);
+    }
+
+
+    #[test]
+    fn vision_training() {
+        let square = |text: &str| Coordinate::from_algebraic(text).unwrap();
+
+        // Case 1: Square questions carry their answers
+        let mut rng = Rng::new(7);
+        for _ in 0..20 {
+            let question = SquareQuestion::random(&mut rng);
+            assert_eq!(question.is_correct(&question.name), true);
+            assert_eq!(question.is_correct(&question.name.to_uppercase()), true);
+            assert_eq!(question.light, question.square.is_light());
+        }
+        assert_eq!(square("a1").is_light(), false);
+        assert_eq!(square("h1").is_light(), true);
+        assert_eq!(square("d1").is_light(), true);
+        assert_eq!(SquareQuestion { square: square("e4"), name: String::from("e4"), light: true }.is_correct("e5"), false);
+
+        // Case 2: Knight paths, corner to corner takes six moves
+        let path = shortest_knight_path(square("a1"), square("h8"));
+        assert_eq!(path.len(), 7);
+        assert_eq!((path[0], path[6]), (square("a1"), square("h8")));
+        for pair in path.windows(2) {
+            assert_eq!(can_piece_reach(piece!(Knight, White), pair[0], pair[1], &[]), true);
+        }
+        assert_eq!(shortest_knight_path(square("a1"), square("b2")).len(), 5);
+        assert_eq!(shortest_knight_path(square("e4"), square("e4")), vec![square("e4")]);
+        assert_eq!(shortest_knight_path(square("g1"), square("f3")), vec![square("g1"), square("f3")]);
+
+        // Case 3: One move with obstacles in the way
+        assert_eq!(can_piece_reach(piece!(Bishop, White), square("c1"), square("h6"), &[]), true);
+        assert_eq!(can_piece_reach(piece!(Bishop, White), square("c1"), square("h6"), &[square("e3")]), false);
+        assert_eq!(can_piece_reach(piece!(Bishop, White), square("c1"), square("h6"), &[square("h6")]), true);
+        assert_eq!(can_piece_reach(piece!(Rook, Black), square("a8"), square("a1"), &[square("b2")]), true);
+        assert_eq!(can_piece_reach(piece!(Queen, White), square("d1"), square("e3"), &[]), false);
+        assert_eq!(can_piece_reach(piece!(King, White), square("e1"), square("g1"), &[]), false);
+        assert_eq!(can_piece_reach(piece!(Knight, White), square("b1"), square("c3"), &[square("b2"), square("c2")]), true);
+
+        // Case 4: Pawns push onto empty squares and capture obstacles
+        assert_eq!(can_piece_reach(piece!(Pawn, White), square("e2"), square("e4"), &[]), true);
+        assert_eq!(can_piece_reach(piece!(Pawn, White), square("e2"), square("e4"), &[square("e3")]), false);
+        assert_eq!(can_piece_reach(piece!(Pawn, White), square("e3"), square("e5"), &[]), false);
+        assert_eq!(can_piece_reach(piece!(Pawn, Black), square("e7"), square("d6"), &[]), false);
+        assert_eq!(can_piece_reach(piece!(Pawn, Black), square("e7"), square("d6"), &[square("d6")]), true);
+        assert_eq!(can_piece_reach(piece!(Pawn, Black), square("e7"), square("e8"), &[]), false);
+    }
+
+
+    #[test]
+    fn mobility() {
+        // Case 1: The starting position, either side
+        let board = Board::default();
+        let white = board.mobility(Color::White);
+        assert_eq!(white.moves, 20);
+        assert_eq!((white.moves_of(Pieces::Pawn), white.moves_of(Pieces::Knight), white.moves_of(Pieces::Queen)), (16, 4, 0));
+        assert_eq!((white.attacked_squares, white.defended_pieces), (22, 14));
+        assert_eq!(board.mobility(Color::Black), white);
+
+        // Case 2: Pinned and blocked pieces have nothing to add, en passant only for the side to move
+        let board = Board::from_fen("4k3/8/2p5/b2pP3/8/8/3N4/4K3 w - d6 0 1").unwrap();
+        let white = board.mobility(Color::White);
+        assert_eq!(white.moves_of(Pieces::Knight), 0);
+        assert_eq!(white.moves_of(Pieces::Pawn), 2);
+        assert_eq!(white.moves, 2 + white.moves_of(Pieces::King));
+        let black = board.mobility(Color::Black);
+        assert_eq!(black.moves_of(Pieces::Pawn), 2);
+        assert_eq!(black.defended_pieces, 1);
+    }
+
+
+    #[test]
+    fn game_phase() {
+        // Case 1: The starting position and a developed one
+        let board = Board::default();
+        assert_eq!((board.phase(), board.phase_value(), board.undeveloped_minors()), (GamePhase::Opening, 0, 8));
+        let board = Board::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R2Q1RK1 w - - 2 14").unwrap();
+        assert_eq!(board.phase(), GamePhase::Middlegame);
+
+        // Case 2: Rook and minor piece each, a pawn ending
+        let board = Board::from_fen("2r3k1/5ppp/4n3/8/8/4B3/5PPP/3R2K1 w - - 0 30").unwrap();
+        assert_eq!((board.phase(), board.phase_value()), (GamePhase::Endgame, 192));
+        let board = Board::from_fen("8/5kpp/8/8/8/8/5PPP/6K1 w - - 0 40").unwrap();
+        assert_eq!(board.phase_value(), PHASE_MAX);
+
+        // Case 3: Tapering
+        assert_eq!(taper(100, 300, 0), 100);
+        assert_eq!(taper(100, 300, PHASE_MAX / 2), 200);
+        assert_eq!(taper(100, 300, PHASE_MAX * 2), 300);
+    }
+
+
+    #[test]
+    fn basic_mates() {
+        // Case 1: Recognizing the material
+        let board = Board::from_fen("8/8/3k4/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.basic_mate(), Some((Color::White, BasicMate::Rook)));
+        let board = Board::from_fen("8/8/3k4/8/8/8/8/1n2K1b1 b - - 0 1").unwrap();
+        assert_eq!(board.basic_mate(), Some((Color::Black, BasicMate::BishopKnight)));
+        assert!(Board::from_fen("8/8/3k4/8/8/8/8/1B2K1B1 b - - 0 1").unwrap().basic_mate().is_none());
+        assert!(Board::default().mating_bonus().is_none());
+
+        // Case 2: The defending king in the right corner is worth more than in the wrong one
+        let right = Board::from_fen("k7/8/1K6/8/8/8/8/5BN1 w - - 0 1").unwrap();
+        let wrong = Board::from_fen("7k/8/6K1/8/8/8/8/5BN1 w - - 0 1").unwrap();
+        assert!(right.mating_bonus() > wrong.mating_bonus());
+        let mut defending = right;
+        defending.turn = Color::Black;
+        assert_eq!(defending.mating_bonus(), right.mating_bonus().map(|bonus| -bonus));
+
+        // Case 3: The engine mates with the rook well within the 50-move rule
+        let mut board = Board::from_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut engine = AlphaBeta::new(EndgameEvaluator::new(MaterialEvaluator));
+        let mut plies = 0;
+        while !board.legal_moves().is_empty() && plies < 100 {
+            let mv = engine.choose_move(&board, &SearchLimits::depth(4)).unwrap();
+            board.make_move(mv);
+            plies += 1;
+        }
+        assert!(board.is_in_check(board.turn) && board.legal_moves().is_empty(), "no mate after {} plies: {}", plies, board.get_fen());
+    }
+
+
+    #[test]
+    fn search_draw_rules() {
+        // Case 1: The 50-move rule, unless the last move mates
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let board = Board::from_fen("8/8/8/3k4/8/8/8/Q3K3 w - - 99 80").unwrap();
+        assert_eq!(engine.search(&board, &SearchLimits::depth(2)).score, 0);
+        let board = Board::from_fen("7k/8/6K1/8/8/8/8/Q7 w - - 99 80").unwrap();
+        assert_eq!(engine.search(&board, &SearchLimits::depth(2)).score, MATE_SCORE - 1);
+
+        // Case 2: The losing side goes back to a position of the game
+        let board = Board::from_fen("8/8/8/3k4/8/8/8/Q3K3 b - - 10 40").unwrap();
+        let escape = Notation::San.parse(&board, "Kc5").unwrap();
+        let mut repeated = board;
+        repeated.make_move(escape);
+        let mut limits = SearchLimits::depth(2);
+        assert!(engine.search(&board, &limits).score < -500);
+        limits.history = vec![repeated.zobrist()];
+        let result = engine.search(&board, &limits);
+        assert_eq!((result.best_move, result.score), (Some(escape), 0));
+
+        // Case 3: Only the positions since the last pawn move or capture are kept
+        let mut game = Game::new(Board::default());
+        for text in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+            game.make_move(Notation::San.parse(game.board(), text).unwrap()).unwrap();
+        }
+        assert_eq!(game.search_history().len(), 4);
+        assert_eq!(game.search_history()[0], Board::default().zobrist());
+        game.make_move(Notation::San.parse(game.board(), "e4").unwrap()).unwrap();
+        assert!(game.search_history().is_empty());
+
+        // Case 4: Engines playing a match get the history of the game
+        struct Recorder {
+            moves: Vec<&'static str>,
+            histories: Vec<Vec<u64>>,
+        }
+        impl Engine for Recorder {
+            fn choose_move(&mut self, board: &Board, limits: &SearchLimits) -> Result<Move, ChessError> {
+                let text = self.moves[self.histories.len() % self.moves.len()];
+                self.histories.push(limits.history.clone());
+                return Notation::Coordinate.parse(board, text).ok_or(ChessError::NoLegalMoves);
+            }
+        }
+        let config = MatchConfig { max_plies: 6, ..MatchConfig::default() };
+        let mut white = Recorder { moves: vec!["g1f3", "f3g1"], histories: Vec::new() };
+        let mut black = Recorder { moves: vec!["b8c6", "c6b8"], histories: Vec::new() };
+        let record = play_game(&mut white, &mut black, &config).unwrap();
+        let mut game = Game::new(Board::default());
+        for (ply, mv) in record.moves.iter().enumerate() {
+            let engine = if ply % 2 == 0 { &white } else { &black };
+            assert_eq!(engine.histories[ply / 2], game.search_history());
+            game.make_move(*mv).unwrap();
+        }
+        assert_eq!(white.histories[2].len(), 4);
+    }
+
+
+    #[test]
+    fn resign_and_draw_decisions() {
+        // Case 1: The settings come from the options, nothing happens by default
+        let mut options = EngineOptions::default();
+        let mut tracker = DecisionTracker::new(DecisionPolicy::from_options(&options));
+        for _ in 0..10 {
+            tracker.record(Some(-5000), 50);
+        }
+        assert_eq!(tracker.decide(false), Decision::Play);
+        options.set("ResignMoves", "3").unwrap();
+        options.set("DrawMoves", "2").unwrap();
+        options.set("Contempt", "50").unwrap();
+        assert_eq!(options.value("ResignScore"), Some(OptionValue::Spin(1000)));
+
+        // Case 2: Resigning needs the score to stay bad, a better move starts over
+        let mut tracker = DecisionTracker::new(DecisionPolicy::from_options(&options));
+        for score in [-1200, -1500, -300, -1100, -1000] {
+            tracker.record(Some(score), 30);
+            assert_eq!(tracker.should_resign(), false);
+        }
+        tracker.record(Some(-2000), 31);
+        assert_eq!(tracker.decide(true), Decision::Resign);
+
+        // Case 3: Draws are offered late in level positions, accepted below the contempt
+        tracker.reset();
+        tracker.record(Some(5), 12);
+        tracker.record(Some(0), 13);
+        assert_eq!(tracker.decide(false), Decision::Play);
+        tracker.record(Some(-80), 41);
+        assert_eq!(tracker.decide(true), Decision::AcceptDraw);
+        tracker.record(Some(-30), 42);
+        assert_eq!(tracker.decide(true), Decision::Play);
+        tracker.record(Some(8), 43);
+        tracker.record(Some(-3), 44);
+        assert_eq!(tracker.decide(false), Decision::OfferDraw);
+
+        // Case 4: Carried out in a game
+        let mut game = Game::new(Board::default());
+        game.make_move(Notation::San.parse(game.board(), "e4").unwrap()).unwrap();
+        game.offer_draw(Color::White).unwrap();
+        assert_eq!(tracker.act(&mut game, Color::Black, Some(-100)).unwrap(), Decision::AcceptDraw);
+        assert_eq!(game.termination().map(|(outcome, _)| outcome), Some(Outcome::Draw));
+    }
+
+
+    #[test]
+    fn contempt() {
+        // The only draw on offer is repeating the position after 1. Nf3, every other move scores 0
+        let mut game = Game::new(Board::default());
+        for text in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+            game.make_move(Notation::San.parse(game.board(), text).unwrap()).unwrap();
+        }
+        let board = *game.board();
+        let knight = Notation::San.parse(&board, "Nf3").unwrap();
+        let mut limits = SearchLimits::depth(2);
+        limits.history = game.search_history();
+
+        // Case 1: Seeking draws against a stronger opponent
+        let mut options = EngineOptions::default();
+        options.set("Contempt", "-100").unwrap();
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        engine.set_options(&options);
+        let result = engine.search(&board, &limits);
+        assert_eq!((result.best_move, result.score), (Some(knight), 100));
+
+        // Case 2: Avoiding them against a weaker one
+        options.set("Contempt", "100").unwrap();
+        engine.set_options(&options);
+        let result = engine.search(&board, &limits);
+        assert_ne!(result.best_move, Some(knight));
+        assert_eq!(result.score, 0);
+
+        // Case 3: Stalemate is a draw like any other
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(engine.search(&board, &SearchLimits::depth(1)).score, -100);
+
+        // Case 4: A stalemate scored with contempt depends on the color at the root, it stays out
+        // of the table that is kept for the next searches (the first iteration doesn't see it)
+        let board = Board::from_fen("7k/8/6K1/5Q2/8/8/8/8 w - - 0 1").unwrap();
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        engine.set_options(&options);
+        engine.search(&board, &SearchLimits::depth(2));
+        let depth = |engine: &AlphaBeta<MaterialEvaluator>| engine.transposition_table().probe(board.zobrist()).map(|entry| entry.depth);
+        assert_eq!(depth(&engine), Some(1));
+        options.set("Contempt", "0").unwrap();
+        engine.set_options(&options);
+        engine.search(&board, &SearchLimits::depth(2));
+        assert_eq!(depth(&engine), Some(2));
+    }
+
+
+    #[test]
+    fn match_pgn_annotations() {
+        use std::time::Duration;
+
+        // Case 1: Every move gets the engine's eval and what is left on its clock
+        let clock = Clock {
+            time: [Duration::from_secs(5); 2],
+            increment: [Duration::from_millis(100); 2],
+            moves_to_go: None,
+        };
+        let config = MatchConfig {
+            games: 1,
+            limits: SearchLimits::clock(clock),
+            max_plies: 4,
+            ..MatchConfig::default()
+        };
+        let mut white = AlphaBeta::new(MaterialEvaluator);
+        let mut black = AlphaBeta::new(MaterialEvaluator);
+        let record = play_game(&mut white, &mut black, &config).unwrap();
+        assert_eq!(record.clocks.len(), 4);
+        assert!(record.clocks.iter().all(|left| left.is_some_and(|left| left <= Duration::from_millis(5100))));
+
+        let pgn = record.to_pgn(PgnOptions::default()).unwrap();
+        assert!(pgn.contains("[Result \"1/2-1/2\"]"));
+        assert_eq!(pgn.matches("[%eval ").count(), 4);
+        assert_eq!(pgn.matches("] [%clk 0:00:0").count(), 4);
+        assert_eq!(record.to_pgn(PgnOptions::bare()).unwrap().contains('{'), false);
+        assert_eq!(record.game().unwrap().termination(), Some((Outcome::Draw, TerminationReason::Adjudication)));
+        assert!(pgn.contains("[Termination \"adjudication\"]"));
+
+        // Case 2: Black's scores are turned around, clocks get tenths under a second, white's
+        // flag against a lone king is a draw
+        let record = GameRecord {
+            start_fen: "4k3/8/8/8/8/8/8/3QK3 b - - 0 1".to_string(),
+            moves: vec![Notation::San.parse(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap(), "Kf7").unwrap()],
+            scores: vec![Some(-900)],
+            clocks: vec![Some(Duration::from_millis(83_400))],
+            outcome: Outcome::Draw,
+            reason: Reason::TimeForfeit,
+        };
+        let pgn = record.to_pgn(PgnOptions::default()).unwrap();
+        assert!(pgn.contains("1... Kf7 {[%eval 9.00] [%clk 0:01:23.4]} 1/2-1/2"));
+        assert!(pgn.contains("[Termination \"time forfeit\"]"));
+
+        // Case 3: No time left is a loss on time
+        let config = MatchConfig {
+            games: 1,
+            limits: SearchLimits::clock(Clock { time: [Duration::ZERO, Duration::from_secs(5)], ..clock }),
+            ..MatchConfig::default()
+        };
+        let record = play_game(&mut white, &mut black, &config).unwrap();
+        assert_eq!((record.outcome, record.reason, record.moves.len()), (Outcome::BlackWins, Reason::TimeForfeit, 0));
+        assert_eq!(record.game().unwrap().termination(), Some((Outcome::BlackWins, TerminationReason::Timeout)));
+
+        // Case 4: Losing on time against a lone king is a draw, the record and its replay agree
+        let config = MatchConfig { start_fen: "4k3/8/8/8/8/8/8/QQQQK3 w - - 0 1".to_string(), ..config };
+        let record = play_game(&mut white, &mut black, &config).unwrap();
+        assert_eq!((record.outcome, record.reason), (Outcome::Draw, Reason::TimeForfeit));
+        assert_eq!(record.game().unwrap().termination(), Some((Outcome::Draw, TerminationReason::Timeout)));
+    }
+
+
+    #[test]
+    fn book_learning() {
+        let start = Board::default();
+        let line = |texts: &[&str]| -> Vec<Move> {
+            let mut board = start;
+            let mut moves = Vec::new();
+            for text in texts {
+                let mv = Notation::San.parse(&board, text).unwrap();
+                board.make_move(mv);
+                moves.push(mv);
+            }
+            moves
+        };
+
+        // Case 1: Building from games, only the first plies
+        let mut book = OpeningBook::new(3);
+        book.add_game(&start, &line(&["e4", "e5", "Nf3", "Nc6"])).unwrap();
+        book.add_game(&start, &line(&["d4", "d5"])).unwrap();
+        assert_eq!(book.len(), 4);
+        assert_eq!(book.moves(&start).len(), 2);
+        assert!(book.add_game(&start, &[Move { from: coord!(0, 0), to: coord!(4, 4), promotion: None }]).is_err());
+
+        // Case 2: Results go back into the book moves of the game
+        let record = GameRecord {
+            start_fen: start.get_fen(),
+            moves: line(&["e4", "e5", "Nf3", "Nc6", "Bb5"]),
+            scores: Vec::new(),
+            clocks: Vec::new(),
+            outcome: Outcome::WhiteWins,
+            reason: Reason::ResignAdjudication,
+        };
+        let report = MatchReport { games: vec![record.clone(); 5], wins: 5, ..MatchReport::default() };
+        assert_eq!(book.learn_match(&report).unwrap(), 15);
+        let e4 = book.moves(&start).iter().find(|book_move| book_move.mv == line(&["e4"])[0]).copied().unwrap();
+        assert_eq!((e4.weight, e4.wins, e4.losses), (INITIAL_WEIGHT + 5 * LEARN_STEP, 5, 0));
+        let mut after = start;
+        after.make_move(e4.mv);
+        assert_eq!(book.moves(&after)[0].weight, 0);
+
+        // Case 3: Lost moves aren't played, the engine takes over out of the book
+        let mut rng = Rng::new(7);
+        assert_eq!(book.pick(&after, &mut rng), None);
+        let mut engine = BookEngine::new(book.clone(), GreedyMover::new(), Rng::new(7));
+        let mv = engine.choose_move(&after, &SearchLimits::depth(1)).unwrap();
+        assert!(after.legal_moves().contains(&mv));
+
+        // Case 4: Saved and loaded back
+        let mut file: Vec<u8> = Vec::new();
+        book.write(&mut file).unwrap();
+        assert_eq!(String::from_utf8_lossy(&file).lines().count(), 5);
+        assert_eq!(OpeningBook::read(&mut &file[..], 3).unwrap(), book);
+        assert!(OpeningBook::read(&mut "00ff e2e4 10".as_bytes(), 3).is_err());
+    }
+
+
+    #[test]
+    fn render_themes() {
+        let board = Board::default();
+
+        // Case 1: White gets the outlined figurines, black the filled ones
+        let unicode = board.render(&RenderTheme::default());
+        let lines: Vec<&str> = unicode.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜ ");
+        assert_eq!(lines[3], ". . . . . . . . ");
+        assert_eq!(lines[7], "♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖ ");
+
+        // Case 2: Letters, on DEC checkerboard squares for VT100 terminals
+        let ascii = board.render(&RenderTheme::new(PieceSet::Ascii, ColorScheme::Plain));
+        assert_eq!(ascii.lines().next(), Some("r n b q k b n r "));
+        let dec = board.render(&RenderTheme::new(PieceSet::Dec, ColorScheme::Plain));
+        assert_eq!(dec.lines().nth(2), Some("  \x1b(0a\x1b(B   \x1b(0a\x1b(B   \x1b(0a\x1b(B   \x1b(0a\x1b(B "));
+
+        // Case 3: Painted squares
+        let brown = board.render(&RenderTheme::new(PieceSet::Ascii, ColorScheme::Brown));
+        assert!(brown.starts_with("\x1b[48;5;180m\x1b[38;5;16mr \x1b[0m\x1b[48;5;130m\x1b[38;5;16mn \x1b[0m"));
+        assert_eq!(brown.matches("\x1b[0m").count(), 64);
+    }
+
+
+    #[test]
+    fn render_markers() {
+        // Case 1: Labels and the last move
+        let mut game = Game::new(Board::default());
+        for text in ["e4", "e5"] {
+            game.make_move(Notation::San.parse(game.board(), text).unwrap()).unwrap();
+        }
+        let theme = RenderTheme::new(PieceSet::Ascii, ColorScheme::Plain).labeled();
+        let lines: Vec<String> = game.render(&theme).lines().map(String::from).collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[1], "7 p p p p .*p p p ");
+        assert_eq!(lines[3], "5 . . . . p*. . . ");
+        assert_eq!(lines[4], "4 . . . . P . . . ");
+        assert_eq!(lines[8], "  a b c d e f g h");
+
+        // Case 2: The king in check
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert_eq!(board.render(&theme).lines().next(), Some("8 . . . . k+. . . "));
+        let painted = board.render(&RenderTheme::new(PieceSet::Ascii, ColorScheme::Green));
+        assert!(painted.contains("\x1b[48;5;196m\x1b[38;5;16mk \x1b[0m"));
+        let mv = Move { from: coord!(7, 5), to: coord!(7, 4), promotion: None };
+        assert!(board.render_move(&RenderTheme::new(PieceSet::Ascii, ColorScheme::Green), Some(mv)).contains("\x1b[48;5;143m\x1b[38;5;231mR \x1b[0m"));
+    }
+
+
+    #[test]
+    fn analysis_screen() {
+        // Case 1: The eval bar fills up from the bottom with white's chances
+        assert_eq!(eval_bar(None, 4), vec!["░░", "░░", "██", "██"]);
+        assert_eq!(eval_bar(Some(2000), 4), vec!["██"; 4]);
+        assert_eq!(eval_bar(Some(-MATE_SCORE + 3), 4), vec!["░░"; 4]);
+
+        // Case 2: Top lines best first, every finished iteration reported
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let mut iterations = 0;
+        let lines = engine.search_lines(&board, &SearchLimits::depth(2), LINES, |_| iterations += 1);
+        assert_eq!(iterations, 2);
+        assert_eq!(lines.len(), LINES);
+        assert_eq!(lines[0].best_move, Some(Move { from: coord!(6, 3), to: coord!(3, 3), promotion: None }));
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+
+        // Case 3: Stepping through a game
+        let pgn = read_pgn("1. e4 e5 2. Nf3 *", ParseMode::Strict).unwrap();
+        let mut cursor = GameCursor::new(pgn.game.clone());
+        assert!(!cursor.back());
+        assert!(cursor.forward() && cursor.forward());
+        assert_eq!(cursor.last_move().map(|mv| mv.to_string()), Some(String::from("e7e5")));
+        cursor.to_end();
+        assert!(!cursor.forward());
+        assert!(!cursor.goto(4));
+        let theme = RenderTheme::new(PieceSet::Ascii, ColorScheme::Plain);
+        let screen = frame(&cursor, &[], &theme);
+        assert_eq!(screen.lines().nth(5), Some("3 . . . . . N*. .   ██"));
+        assert_eq!(screen.lines().nth(9), Some("ply 3/3  2. Nf3  thinking"));
+
+        // Case 4: Commands from the input, the engine comes back at the end
+        let mut analysis = AnalysisScreen::new(pgn.game, AlphaBeta::new(MaterialEvaluator), theme);
+        let mut output: Vec<u8> = Vec::new();
+        analysis.run(std::io::Cursor::new("n\nl\nx\nq\n"), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("ply 1/3  1. e4"));
+        assert!(output.contains("ply 3/3  2. Nf3"));
+        assert_eq!(analysis.cursor.ply(), 3);
+        assert!(analysis.finish().is_none());
+    }
+
+
+    #[cfg(feature = "serve")]
+    #[test]
+    fn serve_endpoints() {
+        use crate::serve::*;
+
+        // Case 1: Moves and positions
+        let request = FenRequest { fen: String::from("4k3/8/8/8/8/8/8/4K2R w K - 0 1") };
+        let moves = legal_moves(&request).unwrap().moves;
+        assert_eq!(moves.len(), 15);
+        assert!(moves.contains(&MoveText { uci: String::from("e1g1"), san: String::from("O-O") }));
+        assert!(validate_fen(&request).valid);
+        assert_eq!(validate_fen(&FenRequest { fen: String::from("8/8/8/8/8/8/8/8 w - - 0 1") }).error, Some(String::from("invalid position: each side needs exactly one king")));
+
+        // Case 2: Applying a move in either notation
+        let mate = |mv: &str| ApplyMoveRequest { fen: String::from("7k/8/6K1/8/8/8/8/R7 w - - 0 1"), mv: String::from(mv) };
+        let applied = apply_move(&mate("Ra8")).unwrap();
+        assert_eq!(applied, apply_move(&mate("a1a8")).unwrap());
+        assert_eq!((applied.san.as_str(), applied.check, applied.result.as_deref()), ("Ra8#", true, Some("1-0")));
+        assert_eq!(apply_move(&mate("Rh2")).unwrap_err().status.as_u16(), 422);
+        assert_eq!(legal_moves(&FenRequest { fen: String::from("bad") }).unwrap_err().status.as_u16(), 400);
+
+        // Case 3: The engine's move, and the JSON it goes out as
+        let best = best_move(&BestMoveRequest { fen: String::from("7k/8/6K1/8/8/8/8/R7 w - - 0 1"), depth: Some(2), movetime_ms: None }).unwrap();
+        assert_eq!(best.uci, "a1a8");
+        let json = serde_json::to_string(&mate("Ra8")).unwrap();
+        assert_eq!(json, r#"{"fen":"7k/8/6K1/8/8/8/8/R7 w - - 0 1","move":"Ra8"}"#);
+    }
+
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn grpc_service() {
+        use crate::grpc::proto::chess_server::Chess;
+        use crate::grpc::proto::*;
+        use crate::grpc::ChessService;
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let position = |fen: &str| Position { fen: String::from(fen) };
+
+        // Case 1: Moves and positions
+        let moves = runtime.block_on(ChessService.legal_moves(tonic::Request::new(position("4k3/8/8/8/8/8/8/4K2R w K - 0 1")))).unwrap().into_inner().moves;
+        assert_eq!(moves.len(), 15);
+        assert!(moves.contains(&Move { uci: String::from("e1g1"), san: String::from("O-O") }));
+        let checked = runtime.block_on(ChessService.validate_fen(tonic::Request::new(position("8/8/8/8/8/8/8/8 w - - 0 1")))).unwrap().into_inner();
+        assert!(!checked.valid);
+
+        // Case 2: A mating move, and an illegal one
+        let request = |mv: &str| ApplyMoveRequest { position: Some(position("7k/8/6K1/8/8/8/8/R7 w - - 0 1")), r#move: String::from(mv) };
+        let applied = runtime.block_on(ChessService.apply_move(tonic::Request::new(request("Ra8")))).unwrap().into_inner();
+        assert_eq!((applied.r#move.unwrap().uci.as_str(), applied.check, applied.result.as_str()), ("a1a8", true, "1-0"));
+        let error = runtime.block_on(ChessService.apply_move(tonic::Request::new(request("Rh2")))).unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        // Case 3: Analysis with several lines, best first
+        let analysis = AnalysisRequest { position: Some(position("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1")), depth: 2, movetime_ms: 0, lines: 3 };
+        let lines = runtime.block_on(ChessService.analyze(tonic::Request::new(analysis))).unwrap().into_inner().lines;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].pv[0].san, "Rxd5");
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+
+    #[test]
+    fn bughouse() {
+        let minute = std::time::Duration::from_secs(60);
+        let play = |game: &mut BughouseGame, board: usize, text: &str| {
+            let mv = game.board(board).parse_move(text).unwrap();
+            return game.make_move(board, mv);
+        };
+
+        // Case 1: A capture on one board goes to the partner on the other
+        let mut game = BughouseGame::new(minute, std::time::Duration::ZERO);
+        assert_eq!(game.board(0).legal_moves().len(), 20);
+        for text in ["e4", "d5", "exd5"] {
+            play(&mut game, 0, text).unwrap();
+        }
+        assert_eq!(game.board(1).pockets[Color::Black as usize].count(Pieces::Pawn), 1);
+        play(&mut game, 1, "e4").unwrap();
+        assert_eq!(game.board(1).fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR[p] b KQkq e3 0 1");
+        assert_eq!(play(&mut game, 1, "P@e1"), Err(ChessError::IllegalDrop(Drop { breed: Pieces::Pawn, to: coord!(7, 4) })));
+        assert!(play(&mut game, 1, "N@d6").is_err());
+        play(&mut game, 1, "P@d6").unwrap();
+        assert!(game.board(1).pockets[Color::Black as usize].is_empty());
+        assert_eq!(game.moves().len(), 5);
+
+        // Case 2: A promoted piece goes over as a pawn
+        let promotion = Board::from_fen("2r1k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut game = BughouseGame::from_boards([promotion, Board::default()], minute, std::time::Duration::ZERO);
+        play(&mut game, 0, "b8=Q").unwrap();
+        assert!(game.board(0).is_promoted(coord!(0, 1)));
+        play(&mut game, 0, "Rxb8").unwrap();
+        assert_eq!(game.board(1).pockets[Color::White as usize].to_fen(Color::White), "P");
+        assert_eq!(game.board(1).pockets[Color::Black as usize].to_fen(Color::Black), "");
+
+        // Case 3: A drop that blocks saves the king
+        let mut mated = BughouseBoard::new(Board::from_fen("4k3/8/8/8/8/8/3PPP2/r3K3 w - - 0 1").unwrap());
+        assert!(mated.is_checkmate());
+        mated.pockets[Color::White as usize].add(Pieces::Knight);
+        assert!(!mated.is_checkmate());
+        assert_eq!(mated.legal_moves().len(), 3);
+        assert_eq!(mated.san(BughouseMove::Drop(Drop::parse("N@d1").unwrap())), "N@d1");
+
+        // Case 4: Clocks run on both boards, the first flag decides
+        let mut game = BughouseGame::new(minute, std::time::Duration::from_secs(2));
+        play(&mut game, 1, "d4").unwrap();
+        assert_eq!(game.run_clocks(std::time::Duration::from_secs(30)), None);
+        assert_eq!(game.time_left(1, Color::White), std::time::Duration::from_secs(62));
+        assert_eq!(game.time_left(1, Color::Black), std::time::Duration::from_secs(30));
+        play(&mut game, 0, "e4").unwrap();
+        let result = Some(BughouseResult::Win { team: Team::B, board: 1, reason: TerminationReason::Timeout });
+        assert_eq!(game.run_clocks(std::time::Duration::from_secs(30)), result);
+        assert_eq!(game.time_left(0, Color::White), std::time::Duration::from_secs(32));
+        assert_eq!(play(&mut game, 0, "e5"), Err(ChessError::GameOver));
+    }
+
+
+    #[test]
+    fn fog_of_war() {
+        // Case 1: The start, own pieces and the squares in front of them
+        let board = Board::default();
+        assert_eq!(board.visible_squares(Color::White).count_ones(), 32);
+        assert_eq!(board.visible_squares(Color::Black).count_ones(), 32);
+        let view = board.fog_view(Color::White);
+        assert_eq!(view.square(coord!(4, 4)), FogSquare::Empty);
+        assert_eq!(view.square(coord!(1, 4)), FogSquare::Hidden);
+
+        // Case 2: A pawn sees a diagonal square only with something to take on it
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        let view = board.fog_view(Color::White);
+        assert_eq!(view.square(coord!(3, 3)), FogSquare::Piece(piece!(Pawn, Black)));
+        assert_eq!(view.square(coord!(5, 4)), FogSquare::Hidden);
+        let theme = RenderTheme::new(PieceSet::Ascii, ColorScheme::Plain).labeled();
+        assert_eq!(view.render(&theme).lines().nth(3), Some("5 ? . ? p . ? ? . "));
+
+        // Case 3: Sliders stop at the first piece, the masked board only has what is seen
+        let board = Board::from_fen("4k3/8/8/p7/8/8/8/R3K3 w - - 0 1").unwrap();
+        let view = board.fog_view(Color::White);
+        assert!(view.is_visible(coord!(3, 0)) && !view.is_visible(coord!(2, 0)));
+        assert!(!view.is_visible(coord!(0, 4)));
+        let masked = view.masked_board();
+        assert_eq!(masked.black_pieces.len(), 1);
+        assert_eq!(masked.get_piece(coord!(3, 0)), Some(piece!(Pawn, Black)));
+    }
+
+
+    #[test]
+    fn castling_rights_inference() {
+        // Case 1: Rights the pieces don't allow are dropped when loading
+        let board = Board::from_fen("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.castling_rights, [true, false, false, true]);
+        assert_eq!(board.get_fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+        assert_eq!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w KQkq - 0 1").unwrap().castling_rights, [false, false, true, true]);
+
+        // Case 2: Rights inferred from the pieces alone
+        let board = Board::from_placement("r3k2r/8/8/8/8/8/8/R3K3", Color::Black).unwrap();
+        assert_eq!(board.get_fen(), "r3k2r/8/8/8/8/8/8/R3K3 b Qkq - 0 1");
+        assert_eq!(board.validate(), Ok(()));
+        assert_eq!(Board::from_placement("8/8/8/8/8/8/8/8", Color::White).unwrap().castling_rights, [false; 4]);
+        assert!(Board::from_placement("4k3/8/8/8/8/8/8/4K3 w", Color::White).is_err());
+
+        // Case 3: Inferring on a board set up by hand
+        let mut board = Board::new();
+        board.place_piece(piece!(King, White), coord!(7, 4)).unwrap();
+        board.place_piece(piece!(Rook, White), coord!(7, 0)).unwrap();
+        board.place_piece(piece!(King, Black), coord!(0, 5)).unwrap();
+        board.place_piece(piece!(Rook, Black), coord!(0, 7)).unwrap();
+        board.infer_castling_rights();
+        assert_eq!(board.castling_rights, [false, true, false, false]);
+    }
+
+
+    #[test]
+    fn draw_claims() {
+        let san = |game: &Game, text: &str| Notation::San.parse(game.board(), text).unwrap();
+
+        // Case 1: A third repetition on the board, only the player to move can claim it
+        let mut game = Game::default();
+        for text in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"] {
+            game.make_move(san(&game, text)).unwrap();
+        }
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::ThreefoldRepetition));
+        assert!(matches!(game.claim_draw(Color::Black, None), Err(ChessError::InvalidClaim(_))));
+        assert_eq!(game.claim_draw(Color::White, None), Ok(Some(DrawClaim::ThreefoldRepetition)));
+        assert_eq!(game.termination(), Some((Outcome::Draw, TerminationReason::DrawRule(GameResult::ThreefoldRepetition))));
+        assert_eq!(game.claim_draw(Color::White, None), Err(ChessError::GameOver));
+
+        // Case 2: Claiming with the move that repeats, which is then not played
+        let mut game = Game::default();
+        for text in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"] {
+            game.make_move(san(&game, text)).unwrap();
+        }
+        assert_eq!(game.can_claim_draw(), None);
+        let repeat = san(&game, "Ng8");
+        assert_eq!(game.claim_draw(Color::Black, Some(repeat)), Ok(Some(DrawClaim::ThreefoldRepetition)));
+        assert_eq!(game.moves().len(), 7);
+
+        // Case 3: A wrong claim, the intended move has to be played
+        let mut game = Game::default();
+        for text in ["Nf3", "Nf6", "Ng1"] {
+            game.make_move(san(&game, text)).unwrap();
+        }
+        let back = san(&game, "Ng8");
+        assert_eq!(game.claim_draw(Color::Black, Some(back)), Ok(None));
+        assert_eq!((game.moves().len(), game.termination()), (4, None));
+
+        // Case 4: The fifty move rule, claimed and automatic draws told apart
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 60").unwrap();
+        assert!(!game.board().can_claim_draw());
+        let pawn = san(&game, "e4");
+        assert_eq!(game.claim_draw(Color::White, Some(pawn)), Ok(None));
+        assert_eq!(game.board().halfmove_clock(), 0);
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 60").unwrap();
+        let rook = san(&game, "Ra2");
+        assert_eq!(game.claim_draw(Color::White, Some(rook)), Ok(Some(DrawClaim::FiftyMoveRule)));
+        assert!(Board::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 100 60").unwrap().can_claim_draw());
+        assert!(!GameResult::FiftyMoveRule.is_automatic() && GameResult::FivefoldRepetition.is_automatic());
+    }
+
+
+    #[test]
+    fn illegal_move_reasons() {
+        let uci = |text: &str| Move {
+            from: Coordinate::from_algebraic(&text[0..2]).unwrap(),
+            to: Coordinate::from_algebraic(&text[2..4]).unwrap(),
+            promotion: None,
+        };
+        let reason = |fen: &str, text: &str| Board::from_fen(fen).unwrap().check_move(uci(text)).err();
+
+        // Case 1: Pieces in the way and on the square
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(reason(start, "a1a3"), Some(IllegalMove::Blocked(uci("a1a3"))));
+        assert_eq!(reason(start, "d1d2"), Some(IllegalMove::OwnPiece(uci("d1d2"))));
+        assert_eq!(reason(start, "g1g3"), Some(IllegalMove::NotLegal(uci("g1g3"))));
+        assert_eq!(reason("4k3/8/8/8/8/4p3/4P3/4K3 w - - 0 1", "e2e4"), Some(IllegalMove::Blocked(uci("e2e4"))));
+        assert_eq!(reason(start, "e2e4"), None);
+
+        // Case 2: The king's safety
+        let pinned = "4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1";
+        assert_eq!(reason(pinned, "e2d3"), Some(IllegalMove::Pinned(uci("e2d3"))));
+        assert_eq!(reason(pinned, "e1e2"), Some(IllegalMove::OwnPiece(uci("e1e2"))));
+        assert_eq!(reason("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", "e1e2"), Some(IllegalMove::IntoCheck(uci("e1e2"))));
+        assert_eq!(reason("4k3/4r3/8/8/8/8/B7/4K3 w - - 0 1", "a2b3"), Some(IllegalMove::KingInCheck(uci("a2b3"))));
+
+        // Case 3: Castling
+        let castle = "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1";
+        assert_eq!(reason(castle, "e1c1"), Some(IllegalMove::NoCastlingRights(uci("e1c1"))));
+        assert_eq!(reason("4k3/8/8/8/8/8/8/R3K1NR w KQ - 0 1", "e1g1"), Some(IllegalMove::CastlingBlocked(uci("e1g1"))));
+        assert_eq!(reason("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1", "e1g1"), Some(IllegalMove::CastlingInCheck(uci("e1g1"))));
+        assert_eq!(reason("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1", "e1g1"), Some(IllegalMove::CastlingThroughCheck(uci("e1g1"))));
+        assert_eq!(reason("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1", "e1c1"), None);
+
+        // Case 4: The reason comes back from try_apply_move and reads well
+        let mut board = Board::from_fen(pinned).unwrap();
+        let error = board.try_apply_move(uci("e2d3")).unwrap_err();
+        assert_eq!(error.to_string(), "the piece on e2 is pinned to its king");
+        assert_eq!(error.mv(), uci("e2d3"));
+    }
+
+
+    #[test]
+    fn square_control() {
+        let board = Board::from_fen("4k3/8/3p4/8/3N4/8/4P3/3RK3 w - - 0 1").unwrap();
+
+        // Case 1: One square
+        let control = board.square_control(coord!(3, 4));
+        assert_eq!(control.white_attackers, vec![]);
+        assert_eq!(control.black_attackers, vec![coord!(2, 3)]);
+        assert_eq!(control.controlled_by(), Some(Color::Black));
+
+        let control = board.square_control(coord!(4, 3));
+        assert_eq!(control.white_attackers, vec![coord!(7, 3)]);
+        assert_eq!(control.attackers(Color::Black), &[]);
+        assert_eq!(control.balance(), 1);
+
+        // Defending the own pieces counts, e2 is guarded by the king and the knight
+        let control = board.square_control(coord!(6, 4));
+        assert_eq!(control.white_attackers, vec![coord!(7, 4), coord!(4, 3)]);
+
+        // Case 2: The map agrees with the squares one by one
+        let map = board.control_map();
+        for num in 0..64 {
+            let coord = Coordinate::from_number(num);
+            assert_eq!(map.get(coord), &board.square_control(coord));
+        }
+        assert_eq!(map.heatmap()[6][4], 2);
+        assert_eq!(map.controlled_squares(Color::White) & (1 << coord!(5, 3).as_number()), 1 << coord!(5, 3).as_number());
+        assert_eq!(map.controlled_squares(Color::White) & map.controlled_squares(Color::Black), 0);
+    }
+
+
+    #[test]
+    fn space_and_center() {
+        // Case 1: The start position is even
+        let board = Board::default();
+        assert_eq!((board.space(Color::White), board.space(Color::Black)), (8, 8));
+        assert_eq!(board.center_control(Color::White), 0);
+        assert_eq!(board.eval_breakdown().total(), 0);
+        assert_eq!(board.eval_breakdown().biggest(Color::White), None);
+
+        // Case 2: The pawns on c4, d4 and e4 gain space and the center
+        let board = Board::from_fen("rnbqkbnr/ppp2ppp/3pp3/8/2PPP3/8/PP3PPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!((board.space(Color::White), board.space(Color::Black)), (14, 6));
+        assert_eq!((board.center_control(Color::White), board.center_control(Color::Black)), (6, 2));
+        let breakdown = board.eval_breakdown();
+        assert_eq!(breakdown.get(EvalTerm::Material), 0);
+        assert_eq!(breakdown.get(EvalTerm::Space), 8 * SPACE_WEIGHT);
+        assert_eq!(breakdown.get(EvalTerm::Center), 4 * CENTER_WEIGHT);
+        assert_eq!(breakdown.biggest(Color::White), Some((EvalTerm::Space, 32)));
+        assert_eq!(breakdown.biggest(Color::Black), None);
+        assert_eq!(EvalTerm::Center.to_string(), "center control");
+
+        // The evaluator scores it for the side to move
+        let mut black = board;
+        black.turn = Color::Black;
+        assert_eq!(PositionalEvaluator.evaluate(&board), 64);
+        assert_eq!(PositionalEvaluator.evaluate(&black), -64);
+
+        // Case 3: Without pieces the positional terms are gone
+        let board = Board::from_fen("4k3/8/8/8/3PP3/8/8/4K3 w - - 0 1").unwrap();
+        let breakdown = board.eval_breakdown();
+        assert_eq!(breakdown.get(EvalTerm::Material), 200);
+        assert_eq!((breakdown.get(EvalTerm::Space), breakdown.get(EvalTerm::Center)), (0, 0));
+    }
+
+
+    #[test]
+    fn passed_pawns() {
+        // Case 1: Passed pawns and how far they have to go
+        let board = Board::from_fen("4k3/p7/8/1P6/8/7p/3P4/4K3 w - - 0 1").unwrap();
+        assert!(!board.is_passed(coord!(3, 1)));
+        assert!(!board.is_passed(coord!(1, 0)));
+        assert!(board.is_passed(coord!(6, 3)));
+        assert!(board.is_passed(coord!(5, 7)));
+        assert!(!board.is_passed(coord!(7, 4)));
+        assert_eq!(board.promotion_distance(coord!(6, 3)), Some(5));
+        assert_eq!(board.promotion_distance(coord!(3, 1)), Some(3));
+        assert_eq!(board.promotion_distance(coord!(5, 7)), Some(2));
+        assert_eq!(board.promotion_distance(coord!(7, 4)), None);
+
+        // Case 2: The rule of the square, the pawn on a5 has a5-d8 as its square
+        let board = Board::from_fen("8/8/8/P2k4/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(board.king_in_square_of_pawn(coord!(3, 3), coord!(3, 0), Color::White));
+        assert!(!board.king_in_square_of_pawn(coord!(3, 4), coord!(3, 0), Color::White));
+        assert!(board.king_in_square_of_pawn(coord!(3, 4), coord!(3, 0), Color::Black));
+        assert!(!board.king_in_square_of_pawn(coord!(3, 5), coord!(3, 0), Color::Black));
+
+        // Case 3: Unstoppable pawns
+        let board = Board::from_fen("8/8/8/P3k3/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(board.unstoppable_pawns(Color::White), vec![coord!(3, 0)]);
+        let mut black = board;
+        black.turn = Color::Black;
+        assert_eq!(black.unstoppable_pawns(Color::White), vec![]);
+        // A rook stops it, so does an own piece in the way
+        assert_eq!(Board::from_fen("7r/8/8/P3k3/8/8/8/K7 w - - 0 1").unwrap().unstoppable_pawns(Color::White), vec![]);
+        assert_eq!(Board::from_fen("B7/8/8/P3k3/8/8/8/K7 w - - 0 1").unwrap().unstoppable_pawns(Color::White), vec![]);
+
+        // Case 4: The race goes to the pawn that queens first
+        let board = Board::from_fen("k7/8/7P/8/8/p7/8/7K w - - 0 1").unwrap();
+        assert_eq!(board.pawn_race(), Some((Color::White, coord!(2, 7))));
+        let board = Board::from_fen("k7/8/8/7P/8/p7/8/7K w - - 0 1").unwrap();
+        assert_eq!(board.pawn_race(), Some((Color::Black, coord!(5, 0))));
+        assert!(board.eval_breakdown().get(EvalTerm::PassedPawns) <= -RACE_BONUS);
+        assert_eq!(Board::default().pawn_race(), None);
+    }
+
+
+    #[test]
+    fn pawn_structure() {
+        // Case 1: Islands, doubled and isolated pawns, chains and files
+        let board = Board::from_fen("4k3/7p/3p4/8/2P1P3/1P2P3/P5P1/4K3 w - - 0 1").unwrap();
+        let structure = board.pawn_structure(Color::White);
+        assert_eq!(structure.islands, vec![vec![File::A, File::B, File::C], vec![File::E], vec![File::G]]);
+        assert_eq!(structure.doubled, vec![coord!(5, 4), coord!(4, 4)]);
+        assert_eq!(structure.isolated, vec![coord!(6, 6), coord!(5, 4), coord!(4, 4)]);
+        assert_eq!(structure.chains, vec![vec![coord!(6, 0), coord!(5, 1), coord!(4, 2)]]);
+        assert_eq!(structure.open_files, vec![File::F]);
+        assert_eq!(structure.half_open_files, vec![File::D, File::H]);
+        assert!(structure.is_open(File::F) && structure.is_half_open(File::D) && !structure.is_half_open(File::E));
+        assert_eq!(structure.backward, vec![]);
+
+        let structure = board.pawn_structure(Color::Black);
+        assert_eq!(structure.isolated, vec![coord!(2, 3), coord!(1, 7)]);
+        assert_eq!(structure.half_open_files, vec![File::A, File::B, File::C, File::E, File::G]);
+
+        // Case 2: A backward pawn, c5 covers d4
+        let board = Board::from_fen("4k3/8/8/2p5/2P1P3/3P4/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pawn_structure(Color::White).backward, vec![coord!(5, 3)]);
+
+        // Case 3: Hanging pawns on c4 and d4
+        let board = Board::from_fen("4k3/pp3ppp/4p3/8/2PP4/8/P4PPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pawn_structure(Color::White).hanging, vec![coord!(4, 2), coord!(4, 3)]);
+        assert_eq!(board.pawn_structure(Color::Black).hanging, vec![]);
+        let board = Board::from_fen("4k3/pp3ppp/4p3/8/2PP4/8/PP3PPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pawn_structure(Color::White).hanging, vec![]);
+
+        // Case 4: The evaluation charges for the weaknesses
+        assert_eq!(Board::default().eval_breakdown().get(EvalTerm::PawnStructure), 0);
+        let board = Board::from_fen("4k3/pppppppp/8/8/8/2P5/P1P1PPPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.eval_breakdown().get(EvalTerm::PawnStructure), -(2 * DOUBLED_PENALTY + 3 * ISOLATED_PENALTY + 2 * ISLAND_PENALTY));
+    }
+
+
+    #[test]
+    fn open_files_and_outposts() {
+        // Case 1: Files, the d-file is open and the e-file half-open for white
+        let board = Board::from_fen("r3k2r/pp3ppp/2p5/3Np3/2P5/8/PP3PPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.open_files(), vec![File::D]);
+        assert_eq!(board.half_open_files(Color::White), vec![File::E]);
+        assert_eq!(board.half_open_files(Color::Black), vec![]);
+
+        // Case 2: Outposts, d5 is protected by c4 and no black pawn can chase the knight away
+        assert!(!board.is_outpost(coord!(3, 3), Color::White));
+        let board = Board::from_fen("r3k2r/pp3ppp/8/3Np3/2P5/8/PP3PPP/R3K2R w KQkq - 0 1").unwrap();
+        assert!(board.is_outpost(coord!(3, 3), Color::White));
+        assert_eq!(board.outposts(Color::White), vec![coord!(3, 3)]);
+        assert_eq!(board.outposts(Color::Black), vec![]);
+        assert!(!board.is_outpost(coord!(6, 0), Color::White));
+
+        // Case 3: The evaluator likes both
+        let breakdown = board.eval_breakdown();
+        assert_eq!(breakdown.get(EvalTerm::Outposts), KNIGHT_OUTPOST);
+        assert_eq!(breakdown.get(EvalTerm::RookFiles), 0);
+        let board = Board::from_fen("3rk2r/pp3ppp/8/4p3/2P5/8/PP2RPPP/4K2R w Kk - 0 1").unwrap();
+        assert_eq!(board.eval_breakdown().get(EvalTerm::RookFiles), ROOK_HALF_OPEN_FILE - ROOK_OPEN_FILE);
+
+        // Case 4: And so does the annotation text
+        let board = Board::from_fen("4k2r/pp3ppp/8/4p3/2P5/8/PP3PPP/R3K2R w KQk - 0 1").unwrap();
+        let reasons = board.explain_move(Move { from: coord!(7, 0), to: coord!(7, 3), promotion: None }).unwrap();
+        assert!(reasons.contains(&HintReason::OpenFile));
+        assert_eq!(HintReason::OpenFile.to_string(), "puts the rook on the open file");
+        let board = Board::from_fen("r3k2r/pp3ppp/8/4p3/2P5/2N5/PP3PPP/R3K2R w KQkq - 0 1").unwrap();
+        let reasons = board.explain_move(Move { from: coord!(5, 2), to: coord!(3, 3), promotion: None }).unwrap();
+        assert!(reasons.contains(&HintReason::Outpost(Pieces::Knight)));
+    }
+
+
+    #[test]
+    fn searchmoves() {
+        // White can take the queen on d5, but only the moves on the list may be played
+        let board = Board::from_fen("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let uci_move = |text: &str| board.legal_moves().into_iter().find(|mv| mv.to_string() == text).unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            searchmoves: vec![uci_move("e1e2"), uci_move("e1f2")],
+            ..SearchLimits::default()
+        };
+
+        // Case 1: Every engine keeps to the list
+        let root = limits.root_moves(&board);
+        assert!(root.len() == 2 && limits.searchmoves.iter().all(|mv| root.contains(mv)));
+        let best = AlphaBeta::new(MaterialEvaluator).search(&board, &limits).best_move.unwrap();
+        assert!(limits.searchmoves.contains(&best));
+        assert!(limits.searchmoves.contains(&RandomMover::new().choose_move(&board, &limits).unwrap()));
+        assert!(limits.searchmoves.contains(&GreedyMover::new().choose_move(&board, &limits).unwrap()));
+        let mcts_limits = SearchLimits { nodes: Some(50), ..limits.clone() };
+        assert!(limits.searchmoves.contains(&Mcts::new(MaterialEvaluator, Playout::Evaluation).choose_move(&board, &mcts_limits).unwrap()));
+        assert_eq!(AlphaBeta::new(MaterialEvaluator).search(&board, &SearchLimits::depth(2)).best_move, Some(uci_move("e4d5")));
+
+        // A list without a legal move restricts nothing
+        let illegal = SearchLimits { searchmoves: vec![Move { from: coord!(7, 4), to: coord!(5, 4), promotion: None }], ..SearchLimits::default() };
+        assert_eq!(illegal.root_moves(&board), board.legal_moves());
+
+        // Case 2: "go searchmoves" takes the moves up to the next word that isn't a legal move
+        let mut uci = Uci::new(AlphaBeta::new(MaterialEvaluator));
+        uci.handle("position fen 4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        uci.handle("go searchmoves e1e2 e1f2 depth 2").unwrap();
+        let best = uci.wait().last().unwrap().split(' ').nth(1).unwrap().to_string();
+        assert!(best == "e1e2" || best == "e1f2");
+        uci.handle("go searchmoves e1e2 e2e4 e4d5 depth 2").unwrap();
+        assert!(uci.wait().last().unwrap().starts_with("bestmove e1e2"));
+        uci.handle("go searchmoves e4e5 depth 2").unwrap();
+        assert!(uci.wait().last().unwrap().starts_with("bestmove e4e5"));
+    }
+
+
+    #[test]
+    fn excluded_moves() {
+        // Only exd5 wins the queen
+        let board = Board::from_fen("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let uci_move = |board: &Board, text: &str| board.legal_moves().into_iter().find(|mv| mv.to_string() == text).unwrap();
+        let capture = uci_move(&board, "e4d5");
+
+        // Case 1: The search leaves the excluded move out
+        let limits = SearchLimits { depth: Some(2), excluded: vec![capture], ..SearchLimits::default() };
+        assert_eq!(limits.root_moves(&board).len(), board.legal_moves().len() - 1);
+        let result = AlphaBeta::new(MaterialEvaluator).search(&board, &limits);
+        assert!(result.best_move.is_some() && result.best_move != Some(capture));
+
+        // Case 2: How much worse the second best move is
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let best = engine.search(&board, &SearchLimits::depth(2)).score;
+        let (alternative, score) = best_alternative(&mut engine, &board, capture, &SearchLimits::depth(2)).unwrap().unwrap();
+        assert_ne!(alternative, capture);
+        assert!(best - score >= 800);
+
+        // Case 3: With a single legal move there is no alternative
+        let board = Board::from_fen("k7/8/8/8/8/8/8/1R4K1 b - - 0 1").unwrap();
+        let only = board.legal_moves();
+        assert_eq!(only.len(), 1);
+        assert_eq!(best_alternative(&mut engine, &board, only[0], &SearchLimits::depth(2)).unwrap(), None);
+        let limits = SearchLimits { depth: Some(2), excluded: only.clone(), ..SearchLimits::default() };
+        assert_eq!(engine.search(&board, &limits).best_move, None);
+        assert!(engine.choose_move(&board, &limits).is_err());
+    }
+
+
+    #[test]
+    fn transposition_table_files() {
+        use crate::tt::*;
+
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let first = engine.search(&board, &SearchLimits::depth(4));
+        let table = engine.transposition_table();
+        assert!(table.filled() > 0);
+        assert_eq!(table.probe(board.zobrist()).map(|entry| (entry.depth, entry.best_move)), Some((4, first.best_move)));
+
+        // Case 1: Saved and loaded the entries are the same, whatever the size of the table
+        let mut file: Vec<u8> = Vec::new();
+        table.write(&mut file).unwrap();
+        assert_eq!(&file[0..7], TT_MAGIC);
+        assert_eq!(file[7], TT_VERSION);
+        let loaded = TranspositionTable::read(&mut file.as_slice(), 16).unwrap();
+        assert_eq!(loaded.filled(), table.filled());
+        assert_eq!(loaded.probe(board.zobrist()), table.probe(board.zobrist()));
+        let small = TranspositionTable::read(&mut file.as_slice(), 1).unwrap();
+        assert!(small.filled() > 0 && small.filled() <= table.filled());
+
+        // Case 2: A new session picks the analysis up, with the same result and a lot less work
+        let mut resumed = AlphaBeta::new(MaterialEvaluator);
+        resumed.set_transposition_table(loaded);
+        let second = resumed.search(&board, &SearchLimits::depth(4));
+        assert_eq!((second.best_move, second.score), (first.best_move, first.score));
+        assert!(second.stats.nodes * 2 < first.stats.nodes);
+        assert!(second.stats.tt_hits > 0);
+
+        // Case 3: Other files and other versions are refused
+        let error = TranspositionTable::read(&mut &b"CHYESBOOK0000000"[..], 1).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let mut newer = file.clone();
+        newer[7] = TT_VERSION + 1;
+        let error = TranspositionTable::read(&mut newer.as_slice(), 1).unwrap_err();
+        assert_eq!(error.to_string(), format!("unsupported transposition table version {}", TT_VERSION + 1));
+        assert!(TranspositionTable::read(&mut &file[..file.len() - 1], 1).is_err());
+    }
+
+
+    #[test]
+    fn analysis_session() {
+        use crate::analysis::*;
+
+        let game = Game::from_fen("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let mut analysis = Analysis::from_game(&game);
+        let uci_move = |board: &Board, text: &str| board.legal_moves().into_iter().find(|mv| mv.to_string() == text).unwrap();
+
+        // Case 1: Playing moves grows the tree, playing one again follows the node it made
+        let exd5 = analysis.play(uci_move(analysis.board(), "e4d5")).unwrap();
+        assert!(analysis.back());
+        let ke2 = analysis.play(uci_move(analysis.board(), "e1e2")).unwrap();
+        assert_eq!(analysis.len(), 3);
+        assert!(analysis.back());
+        assert_eq!(analysis.play(uci_move(analysis.board(), "e4d5")).unwrap(), exd5);
+        assert_eq!(analysis.len(), 3);
+        assert_eq!(analysis.moves_to(exd5), vec![uci_move(game.board(), "e4d5")]);
+        assert!(analysis.play(Move { from: coord!(0, 0), to: coord!(7, 7), promotion: None }).is_err());
+
+        // The first move tried is the main line until another one is promoted
+        assert_eq!(analysis.main_line(Analysis::ROOT), vec![uci_move(game.board(), "e4d5")]);
+        assert!(analysis.promote(ke2));
+        assert!(!analysis.promote(Analysis::ROOT));
+        assert_eq!(analysis.main_line(Analysis::ROOT), vec![uci_move(game.board(), "e1e2")]);
+        analysis.go_to(Analysis::ROOT);
+        assert!(analysis.forward());
+        assert_eq!(analysis.current(), ke2);
+
+        // Case 2: Engine output goes to the position, a shallower search doesn't replace it
+        analysis.go_to(Analysis::ROOT);
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let eval = analysis.analyze(&mut engine, &SearchLimits::depth(3), 2).unwrap().clone();
+        assert_eq!((eval.depth, eval.lines.len()), (3, 2));
+        assert_eq!(eval.best().unwrap().pv[0], uci_move(game.board(), "e4d5"));
+        let shallow = engine.search_lines(game.board(), &SearchLimits::depth(1), 1, |_| {});
+        assert!(!analysis.merge_current(&shallow));
+        assert!(analysis.merge(ke2, &engine.search_lines(&analysis.node(ke2).unwrap().board, &SearchLimits::depth(1), 1, |_| {})));
+        assert_eq!(analysis.current_node().eval.as_ref(), Some(&eval));
+
+        // Case 3: Saved and loaded the session is the same
+        analysis.go_to(exd5);
+        let mut file: Vec<u8> = Vec::new();
+        analysis.write(&mut file).unwrap();
+        let loaded = Analysis::read(&mut file.as_slice()).unwrap();
+        let mut again: Vec<u8> = Vec::new();
+        loaded.write(&mut again).unwrap();
+        assert_eq!(again, file);
+        assert_eq!((loaded.len(), loaded.current()), (3, exd5));
+        assert_eq!(loaded.node(Analysis::ROOT).unwrap().eval, Some(eval));
+
+        // Other versions and lines that don't fit the tree are refused
+        let text = String::from_utf8(file).unwrap();
+        let newer = text.replacen(&format!("analysis {}", ANALYSIS_VERSION), &format!("analysis {}", ANALYSIS_VERSION + 1), 1);
+        let error = Analysis::read(&mut newer.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), format!("unsupported analysis version {}", ANALYSIS_VERSION + 1));
+        assert!(Analysis::read(&mut format!("{}node 0 e1e3\n", text).as_bytes()).is_err());
+        assert!(Analysis::read(&mut format!("{}node 9 e1e2\n", text).as_bytes()).is_err());
+    }
+
+
+    #[test]
+    fn parse_position_formats() {
+        use crate::diagram::*;
+
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let expected = Board::from_fen(fen).unwrap();
+
+        // Case 1: FEN, with or without the clocks, and EPD with its operations
+        assert_eq!(PositionFormat::detect(fen), Some(PositionFormat::Fen));
+        assert_eq!(Board::parse_position(&format!("  {}\n", fen)).unwrap().get_fen(), fen);
+        let epd = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5; id \"Ruy Lopez\"; hmvc 2; fmvn 3;";
+        assert_eq!(PositionFormat::detect(epd), Some(PositionFormat::Epd));
+        assert_eq!(Board::parse_position(epd).unwrap().get_fen(), fen);
+        let no_clocks = Board::parse_position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5;").unwrap();
+        assert_eq!(no_clocks.get_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+
+        // Case 2: Diagrams, the way draw() writes them or the way books and forums do
+        assert_eq!(PositionFormat::detect(&expected.render(&RenderTheme::default())), Some(PositionFormat::Diagram));
+        let themes = [RenderTheme::default(), RenderTheme::new(PieceSet::Ascii, ColorScheme::Plain).labeled()];
+        for theme in themes {
+            let parsed = Board::parse_position(&expected.render(&theme)).unwrap();
+            assert_eq!(parsed.get_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+        }
+        let forum = "
+            +---+---+---+---+---+---+---+---+
+            | - | - | - | - | k | - | - | - |
+            | - | - | - | - | - | - | - | - |
+            | - | - | - | - | - | - | - | - |
+            | - | - | - | q | - | - | - | - |
+            | - | - | - | - | P | - | - | - |
+            | - | - | - | - | - | - | - | - |
+            | - | - | - | - | - | - | - | - |
+            | - | - | - | - | K | - | - | - |
+            +---+---+---+---+---+---+---+---+
+            Black to move.
+        ";
+        assert_eq!(Board::parse_position(forum).unwrap().get_fen(), "4k3/8/8/3q4/4P3/8/8/4K3 b - - 0 1");
+        // The marks of the last move and of check don't get in the way
+        let mut game = Game::new(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap());
+        game.make_move(Move { from: coord!(7, 0), to: coord!(0, 0), promotion: None }).unwrap();
+        let drawn = game.render(&RenderTheme::default());
+        assert!(drawn.contains('*') && drawn.contains('+'));
+        assert_eq!(Board::parse_position(&format!("{}b\n", drawn)).unwrap().get_fen(), "R3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        // Case 3: Anything else is refused with the reason
+        assert!(Board::parse_position("").is_err());
+        assert!(matches!(Board::parse_position("hello"), Err(ChessError::InvalidPosition(_))));
+        let short = expected.render(&RenderTheme::default()).lines().skip(1).collect::<Vec<&str>>().join("\n");
+        assert_eq!(Board::parse_position(&short).unwrap_err().to_string(), "invalid diagram: expected 8 ranks, found 7");
+        assert!(matches!(Board::parse_position("k . . .\n. . . K"), Err(ChessError::InvalidDiagram(_))));
+    }
+
+
+    #[test]
+    fn diagram_round_trip() {
+        // Case 1: The diagram of a position is always the same text, without labels or marks
+        let mut board = Board::default();
+        board.make_move(Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None });
+        let diagram = board.diagram();
+        assert_eq!(diagram, "\
+r n b q k b n r
+p p p p p p p p
+. . . . . . . .
+. . . . . . . .
+. . . . P . . .
+. . . . . . . .
+P P P P . P P P
+R N B Q K B N R
+b
+");
+        assert!(diagram.lines().all(|line| !line.ends_with(' ')));
+
+        // Case 2: Read back it is the same position, up to what a diagram can't tell
+        let parsed = Board::from_diagram(&diagram).unwrap();
+        assert_eq!(parsed.diagram(), diagram);
+        assert_eq!(parsed.get_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        let checked = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(Board::from_diagram(&checked.render(&RenderTheme::default())).unwrap().diagram().lines().next(), Some(". . . . k . . ."));
+
+        // Case 3: Tests can write positions the way they look
+        let position = Board::from_diagram("
+            . . . . k . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . q . . . .
+            . . . . P . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . K . . .
+        ").unwrap();
+        assert_eq!(position.get_fen(), "4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1");
+        assert!(Board::from_diagram(". . . . k . . .").is_err());
+    }
+
+
+    #[test]
+    fn promotion_policy() {
+        use crate::promotion::*;
+
+        // A queen on g8 stalemates the king in the corner, a rook doesn't
+        let board = Board::from_fen("8/6P1/8/8/8/8/2K5/k7 w - - 0 1").unwrap();
+        let short = Move { from: coord!(1, 6), to: coord!(0, 6), promotion: None };
+        let with = |breed: Pieces| Move { promotion: Some(breed), ..short };
+
+        // Case 1: try_apply_move still wants the piece, like the Strict policy
+        assert_eq!(board.clone().try_apply_move(short), Err(IllegalMove::BadPromotion(short)));
+        assert_eq!(board.clone().try_apply_move_with(short, &mut PromotionPolicy::Strict), Err(IllegalMove::BadPromotion(short)));
+
+        // Case 2: Always a queen, even into stalemate, or whatever keeps the game going
+        let mut queened = board;
+        let outcome = queened.try_apply_move_with(short, &mut PromotionPolicy::default()).unwrap();
+        assert_eq!((outcome.promotion, outcome.result), (Some(Pieces::Queen), Some(GameResult::Stalemate)));
+        assert_eq!(board.resolve_promotion(short, &mut PromotionPolicy::AvoidStalemate), with(Pieces::Rook));
+        assert_eq!(board.resolve_promotion(with(Pieces::Knight), &mut PromotionPolicy::AvoidStalemate), with(Pieces::Knight));
+
+        // Case 3: The callback is asked about legal promotions only, None takes the move back
+        let mut asked = Vec::new();
+        let mut policy = PromotionPolicy::ask(|_, mv| {
+            asked.push(mv);
+            return Some(Pieces::Knight);
+        });
+        let mut game = Game::new(board);
+        assert_eq!(game.make_move_with(short, &mut policy).unwrap().promotion, Some(Pieces::Knight));
+        let sideways = Move { from: coord!(1, 6), to: coord!(0, 7), promotion: None };
+        assert_eq!(board.clone().try_apply_move_with(sideways, &mut policy), Err(IllegalMove::BadPromotion(sideways)));
+        drop(policy);
+        assert_eq!(asked, vec![short]);
+        let mut declined = PromotionPolicy::ask(|_, _| None);
+        assert!(board.clone().try_apply_move_with(short, &mut declined).is_err());
+
+        // Case 4: UCI moves without the piece get a queen
+        let mut uci = Uci::new(AlphaBeta::new(MaterialEvaluator));
+        uci.handle("position fen 8/6P1/8/8/8/8/2K5/k7 w - - 0 1 moves g7g8").unwrap();
+        assert_eq!(uci.board().get_fen(), "6Q1/8/8/8/8/8/2K5/k7 b - - 0 1");
+    }
+
+
+    #[test]
+    fn scores_and_results() {
+        // Case 1: PGN result tokens both ways
+        for outcome in [Some(Outcome::WhiteWins), Some(Outcome::BlackWins), Some(Outcome::Draw), None] {
+            assert_eq!(parse_result_token(result_token(outcome)), Some(outcome));
+        }
+        assert_eq!(parse_result_token("1-1"), None);
+
+        // Case 2: Points add up exactly and are written the way tables have them
+        let total: Score = [Outcome::WhiteWins, Outcome::Draw, Outcome::BlackWins, Outcome::Draw, Outcome::WhiteWins]
+        .iter()
+        .map(|outcome| outcome.score(Color::White))
+        .sum();
+        assert_eq!((total.to_string(), total.points()), ("3".to_string(), 3.0));
+        assert_eq!(match_score(Score::WIN + Score::DRAW + Score::ZERO + Score::WIN, Score::from_half_points(3)), "2.5-1.5");
+        assert_eq!(format!("[{:>4}]", Score::DRAW), "[ 0.5]");
+        assert_eq!(Score::from_half_points(5).percentage(4), 62.5);
+        assert_eq!((Score::parse("2.5"), Score::parse("2½"), Score::parse("5/2")), (Some(Score::from_half_points(5)), Some(Score::from_half_points(5)), None));
+        assert_eq!((Score::parse("1/2"), Score::parse("4")), (Some(Score::DRAW), Some(Score::from_half_points(8))));
+        assert_eq!((Score::parse("4294967295"), Score::parse("2147483648½")), (None, None));
+        assert_eq!(Score::parse("2147483647"), Some(Score::from_half_points(u32::MAX - 1)));
+
+        // Case 3: Match reports total the games for both engines
+        let report = MatchReport { wins: 2, draws: 1, losses: 1, ..MatchReport::default() };
+        assert_eq!((report.score(), report.opponent_score()), (Score::from_half_points(5), Score::from_half_points(3)));
+        assert_eq!(report.to_string(), "2.5-1.5 (+2 =1 -1)");
+    }
+
+
+    #[test]
+    fn crosstables() {
+        use crate::crosstable::*;
+
+        // A round robin of four, B and D share second place and Sonneborn-Berger tells them apart
+        let games = [
+            FinishedGame::new("A", "B", Outcome::WhiteWins),
+            FinishedGame::new("C", "A", Outcome::BlackWins),
+            FinishedGame::new("D", "A", Outcome::WhiteWins),
+            FinishedGame::new("B", "C", Outcome::WhiteWins),
+            FinishedGame::new("D", "B", Outcome::Draw),
+            FinishedGame::new("C", "D", Outcome::WhiteWins),
+        ];
+        let table = Crosstable::new(&games);
+
+        // Case 1: Ranking, totals and tie-breaks
+        let players: Vec<&str> = table.rows.iter().map(|row| row.player.as_str()).collect();
+        assert_eq!(players, vec!["A", "D", "B", "C"]);
+        let (b, d) = (table.row("B").unwrap(), table.row("D").unwrap());
+        assert_eq!((b.score, d.score), (Score::from_half_points(3), Score::from_half_points(3)));
+        assert_eq!((b.sonneborn_berger, d.sonneborn_berger), (1.75, 2.75));
+        assert_eq!((table.rows[0].buchholz, table.rows[0].wins, table.rows[0].games), (Score::from_half_points(8), 2, 3));
+        assert_eq!((table.cell(0, 0), table.cell(0, 1), table.cell(1, 2)), ("*".to_string(), "0".to_string(), "=".to_string()));
+
+        // Case 2: Text and Markdown
+        assert_eq!(table.to_text(), "\
+#  Player  1  2  3  4  Score  Games    SB  Buchholz  Wins
+1  A       *  0  1  1      2      3  2.50         4     2
+2  D       1  *  =  0    1.5      3  2.75       4.5     1
+3  B       0  =  *  1    1.5      3  1.75       4.5     1
+4  C       0  1  0  *      1      3  1.50         5     1
+");
+        let markdown = table.to_markdown();
+        assert!(markdown.starts_with("| # | Player | 1 | 2 | 3 | 4 | Score | Games | SB | Buchholz | Wins |\n| ---: | :--- |"));
+        assert!(markdown.contains("| 2 | D | 1 | * | = | 0 | 1.5 | 3 | 2.75 | 4.5 | 1 |"));
+
+        // Case 3: Games from PGN, the ones still going are left out
+        let pgn = read_pgn("[White \"A\"]\n[Black \"B\"]\n[Result \"0-1\"]\n\n1. e4 e5 0-1\n", ParseMode::Strict).unwrap();
+        assert_eq!(FinishedGame::from_pgn(&pgn), Some(FinishedGame::new("A", "B", Outcome::BlackWins)));
+        let going = read_pgn("[White \"A\"]\n[Black \"B\"]\n[Result \"*\"]\n\n1. e4 *\n", ParseMode::Strict).unwrap();
+        assert_eq!(FinishedGame::from_pgn(&going), None);
+
+        // Repeated games share a cell
+        let rematch = Crosstable::new(&[games[0].clone(), FinishedGame::new("B", "A", Outcome::Draw)]);
+        assert_eq!(rematch.to_text().lines().nth(1), Some("1  A        *  1=    1.5      2  0.75         1     1"));
+    }
+
+
+    #[test]
+    fn team_matches() {
+        use crate::team::*;
+
+        let teams = [
+            Team::new("Lions", &["L1", "L2", "L3", "L4", "L5"]),
+            Team::new("Tigers", &["T1", "T2", "T3", "T4"]),
+            Team::new("Bears", &["B1", "B2", "B3", "B4"]),
+        ];
+        let matches = [
+            TeamMatch::new("Lions", "Tigers", &[Outcome::WhiteWins, Outcome::Draw, Outcome::WhiteWins, Outcome::BlackWins]),
+            TeamMatch::new("Tigers", "Bears", &[Outcome::Draw; 4]),
+            TeamMatch::new("Bears", "Lions", &[Outcome::WhiteWins, Outcome::WhiteWins, Outcome::Draw, Outcome::Draw]),
+        ];
+
+        // Case 1: Board colors alternate, game points decide the match points
+        let first = &matches[0];
+        assert_eq!(first.board_scores("Tigers"), vec![Score::ZERO, Score::DRAW, Score::ZERO, Score::ZERO]);
+        assert_eq!((first.match_points("Lions"), first.match_points("Tigers"), matches[1].match_points("Bears")), (2, 0, 1));
+        assert_eq!(first.result_text(), "Lions - Tigers 3.5-0.5");
+        assert!(first.board_scores("Bears").is_empty());
+
+        // Case 2: Standings by match points, then Olympiad Sonneborn-Berger, with per-board scores
+        let standings = team_standings(&teams, &matches);
+        let summary: Vec<(&str, u32, Score, f64)> = standings.iter().map(|standing| (standing.team.as_str(), standing.match_points, standing.game_points, standing.sonneborn_berger)).collect();
+        assert_eq!(summary, vec![
+            ("Lions", 3, Score::from_half_points(11), 4.0),
+            ("Bears", 2, Score::from_half_points(8), 6.0),
+            ("Tigers", 1, Score::from_half_points(5), 1.5),
+        ]);
+        assert_eq!(standings[0].boards[0], (Score::WIN, 2));
+        assert_eq!(standings[0].boards[1], (Score::from_half_points(3), 2));
+        assert_eq!(standings_text(&standings), "\
+#  Team    MP     GP     SB
+1  Lions    3    5.5    4.0
+2  Bears    2      4    6.0
+3  Tigers   1    2.5    1.5
+");
+
+        // Case 3: The players' games follow the board order, for their own crosstable
+        let games = matches[2].games(&teams).unwrap();
+        assert_eq!(games[0], crate::crosstable::FinishedGame::new("B1", "L1", Outcome::WhiteWins));
+        assert_eq!(games[1], crate::crosstable::FinishedGame::new("L2", "B2", Outcome::WhiteWins));
+        let short = [Team::new("Lions", &["L1"]), teams[1].clone()];
+        assert!(matches!(first.games(&short), Err(ChessError::InvalidTeam(_))));
+    }
+
+
+    #[test]
+    fn arena_tournaments() {
+        use crate::arena::*;
+
+        let mut arena = Arena::new(&["A", "B", "C", "D"]);
+
+        // Case 1: The waiting players are paired by rank, a berserk win is worth a point more
+        assert_eq!(arena.pair(), vec![(0, 1), (2, 3)]);
+        assert!(arena.pair().is_empty());
+        assert_eq!(arena.record(0, 1, Outcome::WhiteWins, [true, false], 10), [3, 0]);
+        assert_eq!(arena.record(2, 3, Outcome::Draw, [false, false], 30), [1, 1]);
+        assert_eq!(arena.ranking(), vec![0, 2, 3, 1]);
+
+        // Case 2: No rematch in a row, white for whoever had black last
+        assert_eq!(arena.pair(), vec![(2, 0), (3, 1)]);
+        assert_eq!(arena.record(2, 0, Outcome::BlackWins, [false, true], 5), [0, 2]);
+        assert!(arena.players[0].on_fire());
+
+        // On fire wins count double, until a game that isn't a win
+        assert_eq!(arena.record(3, 1, Outcome::Draw, [false, false], 40), [1, 1]);
+        arena.pause(2, true);
+        assert_eq!(arena.pair(), vec![(0, 3)]);
+        assert_eq!(arena.record(0, 3, Outcome::WhiteWins, [false, false], 40), [4, 0]);
+        assert_eq!(arena.players[0].points, 9);
+        assert_eq!(arena.record(1, 0, Outcome::Draw, [false, false], 40), [1, 2]);
+        assert!(!arena.players[0].on_fire());
+        assert_eq!(arena.players[0].score(), Score::from_half_points(7));
+
+        // Case 3: Bot arenas through the match runner, berserk bots play on half the clock
+        let (mut first, mut second, mut third) = (GreedyMover::new(), GreedyMover::new(), GreedyMover::new());
+        let mut entries = [
+            ArenaEntry { name: "first".to_string(), engine: &mut first, berserk: false },
+            ArenaEntry { name: "second".to_string(), engine: &mut second, berserk: true },
+            ArenaEntry { name: "third".to_string(), engine: &mut third, berserk: false },
+        ];
+        let config = MatchConfig {
+            limits: SearchLimits::clock(Clock { time: [std::time::Duration::from_secs(60); 2], ..Clock::default() }),
+            max_plies: 20,
+            ..MatchConfig::default()
+        };
+        let (arena, records) = run_arena(&mut entries, &config, 3).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(arena.players.iter().map(|player| player.results.len()).sum::<usize>(), 6);
+        let (first_game, berserk) = (&arena.players[0].results[0], &arena.players[1].results[0]);
+        assert_eq!((first_game.opponent, first_game.color, berserk.berserk), (1, Color::White, true));
+        assert!(records[0].clocks[1].unwrap() <= std::time::Duration::from_secs(30));
+        assert!(records[0].clocks[0].unwrap() > std::time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn rating_report() {
+        use crate::crosstable::FinishedGame;
+        use crate::rating::*;
+
+        let close = |a: f64, b: f64| (a - b).abs() < 0.01;
+
+        // Case 1: Expected scores, FIDE counts a difference of more than 400 as 400
+        assert!(close(expected_score(2000, 2200, RatingSystem::Fide), 0.24));
+        assert!(close(expected_score(2000, 2000, RatingSystem::Uscf), 0.5));
+        assert!(close(expected_score(2000, 2600, RatingSystem::Fide), 0.0909));
+        assert!(close(expected_score(2000, 2600, RatingSystem::Uscf), 0.0307));
+
+        // Case 2: Performance ratings, at most 800 from the average opponent
+        assert_eq!(performance_rating(2000.0, 0.5), 2000);
+        assert_eq!(performance_rating(2000.0, 0.75), 2191);
+        assert_eq!(performance_rating(2000.0, 1.0), 2800);
+        assert_eq!(performance_rating(2000.0, 0.0), 1200);
+
+        // Case 3: K factors, FIDE by games and rating, US Chess by games of the player and of the event
+        assert_eq!(k_factor(&RatedPlayer::new("New", 1800, 10), 5, RatingSystem::Fide), 40.0);
+        assert_eq!(k_factor(&RatedPlayer::new("Club", 2300, 100), 5, RatingSystem::Fide), 20.0);
+        assert_eq!(k_factor(&RatedPlayer::new("Master", 2450, 100), 5, RatingSystem::Fide), 10.0);
+        assert!(close(k_factor(&RatedPlayer::new("Club", 1500, 100), 4, RatingSystem::Uscf), 800.0 / (16.57 + 4.0)));
+        assert!(close(k_factor(&RatedPlayer::new("New", 1500, 2), 4, RatingSystem::Uscf), 800.0 / 6.0));
+
+        // Case 4: FIDE changes of an event, the game against an unrated player doesn't count
+        let players = [RatedPlayer::new("Anna", 2000, 50), RatedPlayer::new("Boris", 2200, 50), RatedPlayer::new("Carl", 1800, 10)];
+        let games = [
+            FinishedGame::new("Anna", "Boris", Outcome::WhiteWins),
+            FinishedGame::new("Carl", "Anna", Outcome::Draw),
+            FinishedGame::new("Boris", "Carl", Outcome::WhiteWins),
+            FinishedGame::new("Xavier", "Anna", Outcome::BlackWins),
+        ];
+        let changes = rating_changes(&games, &players, RatingSystem::Fide);
+        let summary: Vec<(&str, usize, Score, i32, i32, i32)> = changes
+            .iter()
+            .map(|change| (change.player.as_str(), change.games, change.score, change.new_rating, change.performance, change.average_opponent))
+            .collect();
+        assert_eq!(summary, vec![
+            ("Anna", 2, Score::from_half_points(3), 2010, 2191, 2000),
+            ("Boris", 2, Score::WIN, 2187, 1900, 1900),
+            ("Carl", 2, Score::DRAW, 1807, 1909, 2100),
+        ]);
+        assert!(close(changes[0].expected, 1.0) && close(changes[0].change, 10.0));
+        assert!(changes.iter().all(|change| change.norms.is_empty()));
+        assert_eq!(rating_report(&changes), "\
+Player  Rating  Games  Score  Expected  Change    New   Perf   Avg
+Anna      2000      2    1.5      1.00   +10.0   2010   2191  2000
+Boris     2200      2      1      1.67   -13.4   2187   1900  1900
+Carl      1800      2    0.5      0.33    +6.8   1807   1909  2100
+");
+
+        // Case 5: US Chess adds a bonus for a big gain over three games or more
+        let players = [RatedPlayer::new("Hero", 1500, 100), RatedPlayer::new("A", 1500, 100), RatedPlayer::new("B", 1500, 100), RatedPlayer::new("C", 1500, 100), RatedPlayer::new("D", 1500, 100)];
+        let games: Vec<FinishedGame> = ["A", "B", "C", "D"].iter().map(|name| FinishedGame::new("Hero", name, Outcome::WhiteWins)).collect();
+        let changes = rating_changes(&games, &players, RatingSystem::Uscf);
+        let k = k_factor(&players[0], 4, RatingSystem::Uscf);
+        assert!(close(changes[0].change, k * 2.0 + (k * 2.0 - 28.0)));
+        assert_eq!(changes[1].games, 1);
+
+        // Case 6: Norms need nine games, the performance and the average opponent rating
+        let mut players = vec![RatedPlayer::new("Candidate", 2400, 200)];
+        let mut games = Vec::new();
+        for (index, outcome) in [Outcome::WhiteWins, Outcome::WhiteWins, Outcome::WhiteWins, Outcome::WhiteWins, Outcome::Draw, Outcome::Draw, Outcome::Draw, Outcome::WhiteWins, Outcome::BlackWins].into_iter().enumerate() {
+            let name = format!("Opponent {}", index);
+            players.push(RatedPlayer::new(&name, 2400, 200));
+            games.push(FinishedGame::new("Candidate", &name, outcome));
+        }
+        let changes = rating_changes(&games, &players, RatingSystem::Fide);
+        assert_eq!((changes[0].score, changes[0].performance), (Score::from_half_points(13), 2566));
+        assert_eq!(changes[0].norms, vec![Norm::InternationalMaster, Norm::WomanGrandmaster, Norm::WomanInternationalMaster]);
+        assert!(rating_changes(&games[..8], &players, RatingSystem::Fide)[0].norms.is_empty());
+    }
+
+    #[test]
+    fn strength_calibration() {
+        use crate::calibration::*;
+        use crate::skill::SkillLevel;
+
+        // Case 1: The Elo that expects the score, within 800 of the references
+        assert!((estimate_elo(&[(1500.0, 10)], 5.0) - 1500.0).abs() < 0.01);
+        assert!((estimate_elo(&[(1400.0, 1), (1600.0, 1)], 1.0) - 1500.0).abs() < 0.01);
+        assert!((estimate_elo(&[(1500.0, 4)], 3.0) - 1691.0).abs() < 1.0);
+        assert!((estimate_elo(&[(1400.0, 1), (1600.0, 1)], 0.0) - 600.0).abs() < 0.01);
+        assert!((estimate_elo(&[(1400.0, 1), (1600.0, 1)], 2.0) - 2400.0).abs() < 0.01);
+        assert_eq!(SkillLevel::new(10).elo(), 1600);
+        assert_eq!(SkillLevel::from_elo(SkillLevel::new(7).elo()).level(), 7);
+
+        // Case 2: Against a copy of itself the engine scores half and gets the reference's Elo
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let mut copy = AlphaBeta::new(MaterialEvaluator);
+        let mut random = RandomMover::new();
+        let mut references = [
+            Reference { name: "Copy".to_string(), elo: 1200.0, engine: &mut copy, limits: SearchLimits::depth(1) },
+            Reference { name: "Random".to_string(), elo: 400.0, engine: &mut random, limits: SearchLimits::default() },
+        ];
+        let config = MatchConfig {
+            games: 2,
+            max_plies: 40,
+            ..MatchConfig::default()
+        };
+        let settings = [Setting::new("Depth 1", SearchLimits::depth(1)), Setting::new("Depth 2", SearchLimits::depth(2))];
+        let curve = calibrate(&mut engine, &settings, &mut references, &config).unwrap();
+        assert_eq!(curve.points.len(), 2);
+        let point = curve.point("Depth 1").unwrap();
+        assert_eq!(point.results[0], ("Copy".to_string(), 2, Score::WIN));
+        assert_eq!(point.games, 4);
+        assert!(point.elo > 400.0 - 800.0 && point.elo < 1200.0 + 800.0);
+        assert!(point.margin >= 0.0);
+
+        // Case 3: The curve picks the weakest setting strong enough, the strongest one for more
+        let weakest = curve.points.iter().map(|point| point.elo).fold(f64::INFINITY, f64::min);
+        let strongest = curve.points.iter().map(|point| point.elo).fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(curve.setting_for(weakest - 100.0).unwrap().elo, weakest);
+        assert_eq!(curve.setting_for(strongest + 100.0).unwrap().elo, strongest);
+        assert!(curve.to_text().starts_with("Setting  Games  Score    Elo  Margin\nDepth 1      4"));
+    }
+
+    #[test]
+    fn position_complexity() {
+        use crate::complexity::*;
+
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        let limits = SearchLimits::depth(1);
+
+        // Case 1: A back rank mate among quiet rook moves is an only move in a sharp position
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let complexity = analyze_complexity(&board, &mut engine, &limits).unwrap();
+        assert_eq!(complexity.best(), Some((Move { from: coord!(7, 0), to: coord!(0, 0), promotion: None }, SCORE_CLAMP)));
+        assert_eq!(complexity.legal_moves(), board.legal_moves().len());
+        assert_eq!(complexity.near_best, 1);
+        assert!(complexity.is_only_move() && complexity.is_sharp() && !complexity.is_complex());
+        assert!(complexity.sharpness > 0.9);
+
+        // Case 2: Every move of the start position is worth the same to a material count
+        let complexity = analyze_complexity(&Board::default(), &mut engine, &limits).unwrap();
+        assert_eq!((complexity.near_best, complexity.gap, complexity.volatility, complexity.sharpness), (20, Some(0), 0.0, 0.0));
+        assert!(!complexity.is_sharp() && !complexity.is_complex() && !complexity.is_only_move());
+
+        // Case 3: A single root move has no gap, a finished game has nothing to analyze
+        let only = SearchLimits {
+            searchmoves: vec![Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None }],
+            ..SearchLimits::depth(1)
+        };
+        let complexity = analyze_complexity(&Board::default(), &mut engine, &only).unwrap();
+        assert_eq!((complexity.gap, complexity.near_best), (None, 1));
+        let mated = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(matches!(analyze_complexity(&mated, &mut engine, &limits), Err(ChessError::NoLegalMoves)));
+    }
+
+    #[test]
+    fn win_probability_and_accuracy() {
+        use crate::accuracy::*;
+        use crate::analysis::Analysis;
+
+        let close = |a: f64, b: f64| (a - b).abs() < 0.01;
+
+        // Case 1: Win models, the Elo one is the curve of the tree search, scores are clamped
+        assert!(close(WinModel::LICHESS.win_percent(0), 50.0));
+        assert!(close(WinModel::LICHESS.win_percent(100), 59.10));
+        assert!(close(WinModel::ELO.win_probability(400), crate::mcts::win_probability(400)));
+        assert_eq!(WinModel::default().win_percent(5000), WinModel::default().win_percent(1000));
+        assert!(close(WinModel::new(0.01, 300).win_probability(-1000), WinModel::new(0.01, 300).win_probability(-300)));
+
+        // Case 2: Move accuracy from the winning chances given away
+        assert_eq!(move_accuracy(50.0, 50.0), 100.0);
+        assert_eq!(move_accuracy(40.0, 60.0), 100.0);
+        assert!(close(move_accuracy(60.0, 50.0), 64.58));
+        assert_eq!(move_accuracy(100.0, 0.0), 0.0);
+
+        // Case 3: Game accuracy, a blunder weighs a lot where the chances swing
+        let accuracy = game_accuracy(Color::White, &[20; 11], &WinModel::LICHESS);
+        assert_eq!((accuracy.of(Color::White), accuracy.of(Color::Black)), (Some(100.0), Some(100.0)));
+        assert_eq!(accuracy.centipawn_loss, [Some(0.0), Some(0.0)]);
+        let accuracy = game_accuracy(Color::White, &[0, 0, 0, -500, -500], &WinModel::LICHESS);
+        assert!(close(accuracy.moves[0][1], 19.06));
+        assert!(close(accuracy.of(Color::White).unwrap(), 27.16));
+        assert_eq!(accuracy.of(Color::Black), Some(100.0));
+        assert_eq!(accuracy.centipawn_loss, [Some(250.0), Some(0.0)]);
+        assert_eq!(game_accuracy(Color::Black, &[0, 0], &WinModel::LICHESS).accuracy, [None, Some(100.0)]);
+
+        // Case 4: The main line of an analysis, it needs an evaluation of every position but the mate
+        let mut analysis = Analysis::new(Board::default());
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        for text in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            let mv = analysis.board().legal_moves().into_iter().find(|mv| mv.to_string() == text).unwrap();
+            analysis.play(mv).unwrap();
+        }
+        assert_eq!(analysis.accuracy(&WinModel::LICHESS), None);
+        analysis.go_to(Analysis::ROOT);
+        for _ in 0..4 {
+            analysis.analyze(&mut engine, &SearchLimits::depth(2), 1);
+            analysis.forward();
+        }
+        let accuracy = analysis.accuracy(&WinModel::LICHESS).unwrap();
+        assert_eq!((accuracy.moves[0].len(), accuracy.moves[1].len()), (2, 2));
+        assert!(accuracy.of(Color::White).unwrap() < 50.0);
+        assert!(accuracy.of(Color::Black).unwrap() > 90.0);
+    }
+
+    #[test]
+    fn post_game_report() {
+        use crate::accuracy::WinModel;
+        use crate::notation::Notation;
+        use crate::moments::{Advantage, MomentKind};
+        use crate::opening::*;
+        use crate::report::*;
+
+        let play = |moves: &str| {
+            let mut game = Game::default();
+            for text in moves.split_whitespace() {
+                let mv = Notation::San.parse(game.board(), text).unwrap();
+                game.make_move(mv).unwrap();
+            }
+            return game;
+        };
+
+        // Case 1: Openings are known by their position, every line of the list is legal
+        assert!(OPENINGS.iter().all(|opening| opening.board().is_some()));
+        let (opening, ply) = identify_opening(&play("Nf3 d5 d4 Nf6 Bf4 e6")).unwrap();
+        assert_eq!((opening.eco, ply), ("D02", 5));
+        assert_eq!(identify_opening(&play("e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be2")).unwrap().0.name, "Sicilian Defence: Najdorf Variation");
+        assert_eq!(identify_opening(&Game::default()), None);
+
+        // Case 2: Given evaluations, the blunder is judged and turns the game
+        let game = play("e4 e5 Nf3 Nc6 Bc4 Bc5");
+        let nf6 = Notation::San.parse(&game.position_at(5).unwrap(), "Nf6").unwrap();
+        let evals = [(20, None), (-30, None), (25, None), (-30, None), (25, None), (-30, Some(nf6)), (400, None)];
+        let report = GameReport::from_evals(&game, &evals, &WinModel::LICHESS).unwrap();
+        assert!(GameReport::from_evals(&game, &evals[..6], &WinModel::LICHESS).is_none());
+        assert_eq!(report.opening.unwrap().name, "Italian Game: Giuoco Piano");
+        assert_eq!(report.mistakes().len(), 1);
+        assert_eq!((report.moves[5].judgment, report.moves[5].best_san.as_deref()), (Some(MoveJudgment::Blunder), Some("Nf6")));
+        assert_eq!((report.count(Color::Black, MoveJudgment::Blunder), report.count(Color::White, MoveJudgment::Blunder)), (1, 0));
+        let kinds: Vec<(usize, MomentKind)> = report.key_moments.iter().map(|moment| (moment.ply, moment.kind)).collect();
+        assert_eq!(kinds, vec![(5, MomentKind::Swing { from: Advantage::Equal, to: Advantage::White }), (5, MomentKind::DecisiveMistake)]);
+        assert_eq!(report.accuracy.of(Color::White), Some(100.0));
+
+        let text = report.to_text();
+        assert!(text.starts_with("White - Black *\nOpening: C50 Italian Game: Giuoco Piano\n"));
+        assert!(text.contains("\nWhite      100.0     0             0         0         0\n"));
+        assert!(text.contains("\nMistakes\n3... Bc5?? (0.30 -> 4.00), Nf6 was best\nr . b q k b n r\n"));
+        assert!(text.ends_with("Key moments\n3... Bc5: White takes over (0.30 -> 4.00)\n3... Bc5: the decisive mistake (0.30 -> 4.00), Nf6 was best\n"));
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| White | 100.0 | 0 | 0 | 0 | 0 |"));
+        assert!(markdown.contains("- 3... Bc5?? (0.30 -> 4.00), Nf6 was best\n\n```text\nr . b q k b n r\n"));
+
+        // The annotations go into the PGN
+        let mut writer = PgnWriter::new(PgnOptions::default());
+        report.annotate(&mut writer);
+        let pgn = writer.write(&game).replace('\n', " ");
+        assert!(pgn.contains("1. e4 {[%eval 0.30]} 1... e5 {[%eval 0.25]}"));
+        assert!(pgn.contains("3... Bc5 $4 {[%eval 4.00] Blunder, Nf6 was best} (3... Nf6) *"));
+
+        // Case 3: The engine evaluates the positions, a mate at the end needs no search
+        let game = play("e4 e5 Bc4 Nc6 Qh5 Nf6 Qxf7#");
+        let report = GameReport::new(&game, &mut AlphaBeta::new(MaterialEvaluator), &SearchLimits::depth(2), &WinModel::LICHESS).unwrap();
+        assert_eq!((report.result, report.opening.unwrap().eco), (Some(Outcome::WhiteWins), "C23"));
+        assert_eq!(report.moves[5].judgment, Some(MoveJudgment::Blunder));
+        assert!(report.accuracy.of(Color::Black).unwrap() < report.accuracy.of(Color::White).unwrap());
+    }
+
+    #[test]
+    fn key_moments() {
+        use crate::accuracy::WinModel;
+        use crate::moments::*;
+        use crate::notation::Notation;
+
+        let play = |moves: &str| {
+            let mut game = Game::default();
+            for text in moves.split_whitespace() {
+                let mv = Notation::San.parse(game.board(), text).unwrap();
+                game.make_move(mv).unwrap();
+            }
+            return game;
+        };
+
+        // Case 1: Swings, a missed win and the decisive mistake of a game black went on to win
+        let game = play("e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6");
+        let d4 = Notation::San.parse(&game.position_at(4).unwrap(), "d4").unwrap();
+        let white = [20, 20, 500, 0, 0, -500, -500, -500, -500];
+        let evals: Vec<(i32, Option<Move>)> = white
+            .iter()
+            .enumerate()
+            .map(|(ply, eval)| (if ply % 2 == 0 { *eval } else { -eval }, if ply == 4 { Some(d4) } else { None }))
+            .collect();
+        let moments = key_moments(&game, &evals, &WinModel::LICHESS);
+        let kinds: Vec<(usize, MomentKind)> = moments.iter().map(|moment| (moment.ply, moment.kind)).collect();
+        assert_eq!(kinds, vec![
+            (1, MomentKind::Swing { from: Advantage::Equal, to: Advantage::White }),
+            (2, MomentKind::Swing { from: Advantage::White, to: Advantage::Equal }),
+            (2, MomentKind::MissedWin),
+            (4, MomentKind::Swing { from: Advantage::Equal, to: Advantage::Black }),
+            (4, MomentKind::DecisiveMistake),
+        ]);
+        let decisive = moments[4];
+        assert_eq!((decisive.color, decisive.before, decisive.after, decisive.best), (Color::White, 0, -500, Some(d4)));
+        assert_eq!(decisive.description(), "the decisive mistake");
+        assert_eq!(moments[1].description(), "the game is level again");
+
+        // Case 2: Winning from the first move on leaves nothing to decide, a short list is cut
+        let won: Vec<(i32, Option<Move>)> = (0..9).map(|ply| (if ply == 0 { 20 } else if ply % 2 == 0 { 500 } else { -500 }, None)).collect();
+        let moments = key_moments(&game, &won, &WinModel::LICHESS);
+        assert_eq!(moments.len(), 1);
+        assert_eq!(moments[0].kind, MomentKind::Swing { from: Advantage::Equal, to: Advantage::White });
+        assert!(key_moments(&game, &won[..1], &WinModel::LICHESS).is_empty());
+        assert_eq!(key_moments(&game, &won[..3], &WinModel::LICHESS).len(), 1);
+
+        // Case 3: The engine finds the move that let the mate in
+        let game = play("e4 e5 Bc4 Nc6 Qh5 Nf6 Qxf7#");
+        let mut engine = AlphaBeta::new(MaterialEvaluator);
+        assert_eq!(game.evaluate(&mut engine, &SearchLimits::depth(2)).unwrap().len(), 8);
+        let moments = game.key_moments(&mut engine, &SearchLimits::depth(2)).unwrap();
+        let decisive = moments.iter().find(|moment| moment.kind == MomentKind::DecisiveMistake).unwrap();
+        assert_eq!((decisive.ply, decisive.color), (5, Color::Black));
+        assert!(decisive.best.is_some());
+    }
+
+    #[test]
+    fn opening_repertoire() {
+        use crate::notation::Notation;
+        use crate::pgn::*;
+        use crate::repertoire::*;
+
+        let line = |moves: &str| {
+            let mut board = Board::default();
+            let mut line = Vec::new();
+            for text in moves.split_whitespace() {
+                let mv = Notation::San.parse(&board, text).unwrap();
+                board.make_move(mv);
+                line.push(mv);
+            }
+            return line;
+        };
+
+        // Case 1: Studies are read with their variations, comments and NAGs
+        let study = "\
+[Event \"Repertoire: Caro-Kann\"]
+
+1. e4 c6 {The Caro-Kann} 2. d4 (2. Nc3 d5 3. Nf3 Bg4) 2... d5 3. e5 $1 (3. Nc3 dxe4) 3... Bf5 *
+
+[Event \"Repertoire: Sicilian\"]
+
+1. e4 c5 2. Nf3 (2. c3 {Alapin} d5) 2... d6 *
+";
+        assert_eq!(split_pgn(study).len(), 2);
+        let tree = read_pgn_tree(&split_pgn(study)[0], ParseMode::Strict).unwrap();
+        let c6 = &tree.moves[0].children[0];
+        assert_eq!(c6.comment.as_deref(), Some("The Caro-Kann"));
+        assert_eq!(c6.children.len(), 2);
+        assert_eq!(c6.children[1].mv, line("e4 c6 Nc3")[2]);
+        assert_eq!(c6.children[0].children[0].children[0].nags, vec![1]);
+        assert!(read_pgn_tree("1. e4 (1. d4 e5", ParseMode::Strict).is_err());
+        assert!(read_pgn_tree("1. e4 {open", ParseMode::Strict).is_err());
+
+        // Case 2: The repertoire for black, the prepared responses are the moves in its positions
+        let mut repertoire = Repertoire::new(Color::Black);
+        assert!(repertoire.is_empty());
+        assert_eq!(repertoire.import_pgn(study, ParseMode::Strict).unwrap(), 17);
+        let after_e4 = repertoire.child(Repertoire::ROOT, line("e4")[0]).unwrap();
+        assert_eq!(repertoire.responses(after_e4), line("e4 c6")[1..].iter().chain(&line("e4 c5")[1..]).copied().collect::<Vec<Move>>());
+        assert!(repertoire.responses(Repertoire::ROOT).is_empty());
+        let node = repertoire.add_line(&line("e4 c5 Nf3 d6")).unwrap();
+        assert_eq!(repertoire.len(), 18);
+        assert_eq!(repertoire.moves_to(node), line("e4 c5 Nf3 d6"));
+        let mut other = Repertoire::from_board(Color::White, Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+        assert!(other.import_pgn(study, ParseMode::Strict).is_err());
+
+        // Case 3: Drills start with every prepared position due, the earliest first
+        let question = repertoire.next_question(0).unwrap();
+        assert_eq!((question.node, question.moves.clone()), (after_e4, line("e4")));
+        assert_eq!(repertoire.due(0).len(), 8);
+        let answer = repertoire.answer(question.node, line("e4 c6")[1], 0).unwrap();
+        assert!(answer.right);
+        assert_eq!((answer.next_due, answer.comment.as_deref()), (1, Some("The Caro-Kann")));
+        assert_eq!(repertoire.due(0).len(), 7);
+        assert!(!repertoire.due(0).contains(&after_e4));
+
+        // Right answers push the card further away, a wrong one brings it back tomorrow
+        assert_eq!(repertoire.answer(after_e4, line("e4 c5")[1], 1).unwrap().next_due, 7);
+        assert_eq!(repertoire.answer(after_e4, line("e4 c5")[1], 7).unwrap().next_due, 22);
+        let answer = repertoire.answer(after_e4, line("e4 e5")[1], 22).unwrap();
+        assert_eq!((answer.right, answer.next_due), (false, 23));
+        assert_eq!(repertoire.node(after_e4).unwrap().card.ease, START_EASE - WRONG_EASE);
+        assert!(repertoire.answer(Repertoire::ROOT, line("e4")[0], 0).is_err());
+        assert_eq!(repertoire.due(1000).last(), Some(&after_e4));
+    }
+
+
+    #[test]
+    fn repertoire_transpositions() {
+        use crate::notation::Notation;
+        use crate::repertoire::*;
+
+        let line = |moves: &str| {
+            let mut board = Board::default();
+            let mut line = Vec::new();
+            for text in moves.split_whitespace() {
+                let mv = Notation::San.parse(&board, text).unwrap();
+                board.make_move(mv);
+                line.push(mv);
+            }
+            return line;
+        };
+
+        // Case 1: The second move order reaching a position points to the first one
+        let study = "\
+[Event \"Nimzo-Indian\"]
+
+1. d4 Nf6 2. c4 {Main move order} e6 3. Nc3 Bb4 *
+
+[Event \"Queen's Indian\"]
+
+1. c4 Nf6 2. d4 {Via the English} e6 3. Nf3 b6 *
+";
+        let mut repertoire = Repertoire::new(Color::Black);
+        repertoire.import_pgn(study, ParseMode::Strict).unwrap();
+        let main = repertoire.add_line(&line("d4 Nf6 c4")).unwrap();
+        let english = repertoire.add_line(&line("c4 Nf6 d4")).unwrap();
+        assert_eq!(repertoire.position(english), main);
+        assert_eq!(repertoire.transpositions(), vec![(english, main)]);
+        assert!(repertoire.node(english).unwrap().children.is_empty());
+        let board = repertoire.node(english).unwrap().board;
+        assert_eq!(repertoire.find(&board), Some(main));
+
+        // The moves after both move orders are merged in the first one
+        assert_eq!(repertoire.responses(english), line("d4 Nf6 c4 e6")[3..].to_vec());
+        let after_e6 = repertoire.child(english, line("d4 Nf6 c4 e6")[3]).unwrap();
+        assert_eq!(repertoire.node(after_e6).unwrap().parent, Some(main));
+        assert_eq!(repertoire.node(after_e6).unwrap().children.len(), 2);
+        assert_eq!(repertoire.len(), 12);
+        assert_eq!(repertoire.node(main).unwrap().comment.as_deref(), Some("Main move order Via the English"));
+
+        // Case 2: Drilling the position once, by either move order
+        assert_eq!(repertoire.due(0).iter().filter(|node| **node == main || **node == english).count(), 1);
+        let answer = repertoire.answer(english, line("d4 Nf6 c4 e6")[3], 0).unwrap();
+        assert!(answer.right);
+        assert_eq!(repertoire.node(main).unwrap().card.due, 1);
+
+        // Case 3: Comments can stay with their move order
+        let mut repertoire = Repertoire::new(Color::Black);
+        repertoire.keep_move_order_comments = true;
+        repertoire.import_pgn(study, ParseMode::Strict).unwrap();
+        let main = repertoire.add_line(&line("d4 Nf6 c4")).unwrap();
+        let english = repertoire.add_line(&line("c4 Nf6 d4")).unwrap();
+        assert_eq!(repertoire.node(main).unwrap().comment.as_deref(), Some("Main move order"));
+        assert_eq!(repertoire.node(english).unwrap().comment.as_deref(), Some("Via the English"));
+
+        // Case 4: Repeating an earlier position of the same line isn't merged
+        let node = repertoire.add_line(&line("Nf3 Nf6 Ng1 Ng8")).unwrap();
+        assert_eq!(repertoire.position(node), node);
+    }
+
+    #[test]
+    fn feature_vector() {
+        use crate::features::*;
+
+        // Case 1: The start position
+        let features = Board::default().feature_vector();
+        let names = feature_names();
+        assert_eq!((features.len(), names.len()), (FEATURE_COUNT, FEATURE_COUNT));
+        let value = |features: &[f32], name: &str| features[names.iter().position(|other| other == name).unwrap()];
+        assert_eq!(value(&features, "white_king_e1"), 1.0);
+        assert_eq!(value(&features, "black_pawn_d7"), 1.0);
+        assert_eq!(value(&features, "black_pawn_d2"), 0.0);
+        assert_eq!(features[..PLANES].iter().sum::<f32>(), 32.0);
+        assert_eq!(value(&features, "white_to_move"), 1.0);
+        assert_eq!(value(&features, "black_queen_side"), 1.0);
+        assert_eq!((value(&features, "white_moves"), value(&features, "black_moves")), (20.0, 20.0));
+        assert_eq!((value(&features, "white_pawns"), value(&features, "white_islands")), (8.0, 1.0));
+
+        // Case 2: En passant, castling and pawn structure flags
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/P1P5/4K2R w K d6 0 1").unwrap();
+        let features = board.feature_vector();
+        assert_eq!(value(&features, "en_passant_d"), 1.0);
+        assert_eq!(value(&features, "en_passant_e"), 0.0);
+        assert_eq!((value(&features, "white_king_side"), value(&features, "white_queen_side")), (1.0, 0.0));
+        assert_eq!((value(&features, "white_isolated"), value(&features, "white_islands")), (3.0, 3.0));
+        assert_eq!((value(&features, "white_passed"), value(&features, "black_passed")), (2.0, 0.0));
+
+        // Case 3: Batch export of a PGN database, one line per position
+        let database = "\
+[Event \"One\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event \"Two\"]
+[Result \"*\"]
+
+1. d4 *
+";
+        let mut out = Vec::new();
+        assert_eq!(export_features(database, ParseMode::Strict, &mut out).unwrap(), 10);
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert!(lines[0].starts_with("game,ply,result,white_king_a1,"));
+        assert_eq!(lines[1].split(',').count(), FEATURE_COUNT + 3);
+        assert!(lines[8].starts_with("0,7,1,"));
+        assert!(lines[10].starts_with("1,1,,"));
+        assert!(export_features("1. e5 *", ParseMode::Strict, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn legal_moves_batch() {
+        use crate::batch::*;
+
+        let boards: Vec<Board> = BENCH_POSITIONS.iter().cycle().take(200).map(|fen| Board::from_fen(fen).unwrap()).collect();
+        let expected: Vec<Vec<Move>> = boards.iter().map(|board| board.legal_moves()).collect();
+
+        // Case 1: Same moves in the same order as one position at a time
+        assert_eq!(generate_legal_moves_batch(&boards), expected);
+        assert!(generate_legal_moves_batch(&[]).is_empty());
+
+        // Case 2: A batch kept between calls, on any number of threads
+        let mut batch = MoveBatch::new();
+        for threads in [1, 3, 8] {
+            batch.generate(&boards, threads);
+            assert_eq!(batch.len(), 200);
+            assert_eq!(batch.iter().map(|moves| moves.to_vec()).collect::<Vec<Vec<Move>>>(), expected);
+        }
+        batch.generate(&boards[..5], 8);
+        assert_eq!(batch.len(), 5);
+        assert_eq!(batch.moves(4), expected[4].as_slice());
+
+        // Case 3: Moves added to a buffer that is used again
+        let mut buffer = vec![Move { from: coord!(6, 4), to: coord!(4, 4), promotion: None }];
+        boards[1].legal_moves_into(&mut buffer);
+        assert_eq!(buffer.len(), expected[1].len() + 1);
+        assert_eq!(&buffer[1..], expected[1].as_slice());
+    }
+
+    #[test]
+    fn seeded_randomness() {
+        let play = |engine: &mut dyn Engine, limits: &SearchLimits| {
+            let mut board = Board::default();
+            let mut moves = Vec::new();
+            for _ in 0..12 {
+                let mv = engine.choose_move(&board, limits).unwrap();
+                board.make_move(mv);
+                moves.push(mv);
+            }
+            return moves;
+        };
+
+        // Case 1: The same seed plays the same moves
+        let mut first = RandomMover::new();
+        let mut second = RandomMover::new();
+        first.seed(42);
+        second.seed(42);
+        assert_eq!(play(&mut first, &SearchLimits::default()), play(&mut second, &SearchLimits::default()));
+        second.seed(43);
+        first.seed(42);
+        assert_ne!(play(&mut first, &SearchLimits::default()), play(&mut second, &SearchLimits::default()));
+        first.set_rng(Rng::new(42));
+        second.seed(42);
+        assert_eq!(play(&mut first, &SearchLimits::default()), play(&mut second, &SearchLimits::default()));
+
+        // Case 2: Playouts and weaker play
+        let mcts = |seed: u64| {
+            let mut engine = Mcts::new(MaterialEvaluator, Playout::Random);
+            engine.seed(seed);
+            return engine.choose_move(&Board::default(), &SearchLimits { nodes: Some(200), ..SearchLimits::default() }).unwrap();
+        };
+        assert_eq!(mcts(7), mcts(7));
+        let weak = |seed: u64| {
+            let mut engine = AlphaBeta::new(MaterialEvaluator);
+            let mut options = EngineOptions::default();
+            options.skill_level = 0;
+            engine.set_options(&options);
+            engine.seed(seed);
+            return play(&mut engine, &SearchLimits::depth(1));
+        };
+        assert_eq!(weak(7), weak(7));
+
+        // Case 3: One seed covers the self-play games and the engine playing them
+        let samples = |seed: u64| {
+            let config = SelfPlayConfig { games: 1, opening_plies: 6, max_plies: 4, limits: SearchLimits::depth(1) };
+            let mut selfplay = SelfPlay::new(MaterialEvaluator, config);
+            selfplay.seed(seed);
+            let mut data = Vec::new();
+            selfplay.run(&mut data).unwrap();
+            return data;
+        };
+        assert_eq!(samples(7), samples(7));
+        assert_ne!(samples(7), samples(8));
+    }
+
+    #[test]
+    fn board_invariants() {
+        // Case 1: Everything the moves do keeps the board consistent
+        for fen in BENCH_POSITIONS {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.check_invariants(), Ok(()));
+            for mv in board.legal_moves() {
+                let mut child = board;
+                child.make_move(mv);
+                assert_eq!(child.check_invariants(), Ok(()), "{} after {}", fen, mv);
+                for reply in child.legal_moves() {
+                    let mut grandchild = child;
+                    grandchild.make_move(reply);
+                    grandchild.debug_assert_consistent();
+                }
+            }
+        }
+        let mut game = Game::default();
+        let mut engine = RandomMover::new();
+        engine.seed(3);
+        while game.moves().len() < 80 && !game.board().legal_moves().is_empty() {
+            let mv = engine.choose_move(game.board(), &SearchLimits::default()).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        assert_eq!(game.check_invariants(), Ok(()));
+        // A promotion swaps the pawn before the attacks are worked out, the new queen gives check
+        let mut board = Board::from_fen("3k4/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        board.make_move(Move { from: coord!(1, 1), to: coord!(0, 1), promotion: Some(Pieces::Queen) });
+        assert_eq!(board.check_invariants(), Ok(()));
+        assert!(board.is_in_check(Color::Black));
+
+        // Case 2: Changing one copy of the pieces and not the other
+        let mut board = Board::default();
+        board.board[0][0] = None;
+        assert_eq!(board.check_invariants(), Err(InvariantViolation::PieceMismatch(coord!(0, 0))));
+        let mut board = Board::default();
+        board.black_pieces.insert(coord!(7, 0).as_number(), Piece { breed: Pieces::Rook, color: Color::Black });
+        assert_eq!(board.check_invariants(), Err(InvariantViolation::PieceMismatch(coord!(7, 0))));
+        assert!(std::panic::catch_unwind(|| board.debug_assert_consistent()).is_err());
+
+        // Castling rights and the en passant square that don't fit the pieces
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        board.castling_rights[1] = true;
+        assert_eq!(board.check_invariants(), Err(InvariantViolation::CastlingRights(1)));
+        let mut board = Board::default();
+        board.en_passant_target_sq = Some(coord!(2, 4).as_number());
+        assert_eq!(board.check_invariants(), Err(InvariantViolation::EnPassant(coord!(2, 4))));
+
+        // Both copies changed, but the attacks weren't worked out again
+        let mut board = Board::default();
+        board.board[7][3] = None;
+        board.white_pieces.remove(&coord!(7, 3).as_number());
+        assert_eq!(board.check_invariants(), Err(InvariantViolation::StaleAttacks(Color::White)));
+    }
+
+    #[test]
+    fn fuzz_entry_points() {
+        use crate::fuzz::*;
+
+        // Case 1: Anything goes in, garbage is turned down without a panic
+        for data in [&b""[..], b"\xff\xfe", b"8/8/8/8/8/8/8/8 w - - 0", b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", b"x/x/x w"] {
+            fuzz_fen(data);
+            fuzz_san(data);
+            fuzz_pgn(data);
+        }
+
+        // Case 2: Inputs that used to panic or overflow the stack
+        fuzz_san(b"\x01B6");
+        fuzz_san(b"\x00Bx=6");
+        fuzz_pgn(format!("1. e4 {}", "(1. d4 ".repeat(10_000)).as_bytes());
+        fuzz_pgn(format!("1. e4 e5 {}", "(".repeat(10_000)).as_bytes());
+        fuzz_pgn("1. Nf3 Nf6 2. Ng1 Ng8 ".repeat(1500).as_bytes());
+        fuzz_fen(b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4294967295 4294967295");
+        fuzz_fen(b"999999999999999999/8/8/8/8/8/8/8 w - - 0 1");
+        assert!(matches!(Board::from_fen("99/8/8/8/8/8/8/8 w - - 0 1"), Err(ChessError::InvalidFen(_, _))));
+        fuzz_pgn(b"[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 4294967295 4294967295\"]\n\n1... e5 2. e4 *");
+
+        // Case 3: The limits of the tree reader
+        let nested = format!("1. e4 {}{}", "(1. d4 ".repeat(MAX_VARIATION_NESTING + 1), ")".repeat(MAX_VARIATION_NESTING + 1));
+        assert!(read_pgn_tree(&nested, ParseMode::Strict).is_err());
+        let nested = format!("1. e4 {}{}", "(1. d4 ".repeat(MAX_VARIATION_NESTING), ")".repeat(MAX_VARIATION_NESTING));
+        assert_eq!(read_pgn_tree(&nested, ParseMode::Strict).unwrap().moves.len(), 1 + MAX_VARIATION_NESTING);
+        let long = "1. Nf3 Nf6 2. Ng1 Ng8 ".repeat(MAX_TREE_PLIES / 4);
+        assert!(read_pgn_tree(&long, ParseMode::Strict).is_ok());
+        assert!(read_pgn_tree(&format!("{} Nf3", long), ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn coordinate_notations() {
+        // Case 1: Every kind of move in Smith and coordinate notation, parsed back
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let cases = [
+            ("b7a8rQ", "b7a8q"),
+            ("b7b8N", "b7b8n"),
+            ("e5d6E", "e5d6"),
+            ("e1g1c", "e1g1"),
+            ("e1c1C", "e1c1"),
+            ("a1a8r", "a1a8"),
+            ("e5e6", "e5e6"),
+        ];
+        for (smith, coordinate) in cases {
+            let mv = Notation::Smith.parse(&board, smith).unwrap();
+            assert_eq!(Notation::Smith.format(&board, mv), smith);
+            assert_eq!(Notation::Coordinate.format(&board, mv), coordinate);
+            assert_eq!(Notation::Coordinate.parse(&board, coordinate), Some(mv));
+        }
+
+        // Case 2: Lenient mode reads either one, with any case and marks between the squares
+        let queen = Notation::Smith.parse(&board, "b7a8rQ");
+        assert_eq!(Notation::Smith.parse(&board, "b7a8q"), None);
+        assert_eq!(Notation::Smith.parse_with(&board, "b7a8q", ParseMode::Lenient), queen);
+        assert_eq!(Notation::Coordinate.parse_with(&board, "B7xA8", ParseMode::Lenient), queen);
+        assert_eq!(Notation::Coordinate.parse_with(&board, "e1-g1c", ParseMode::Lenient), Notation::Smith.parse(&board, "e1g1c"));
+        assert_eq!(Notation::Coordinate.parse_with(&board, "e1e3", ParseMode::Lenient), None);
+
+        // Case 3: A move log written and read back
+        let mut game = Game::new(Board::default());
+        for text in ["e2e4", "d7d5", "e4d5", "d8d5", "b1c3"] {
+            let mv = Notation::Coordinate.parse(game.board(), text).unwrap();
+            game.make_move(mv).unwrap();
+        }
+        let log = Notation::Smith.write_log(&game);
+        assert_eq!(log, "e2e4\nd7d5\ne4d5p\nd8d5p\nb1c3\n");
+        let again = Notation::Smith.read_log(Board::default(), &log, ParseMode::Strict).unwrap();
+        assert_eq!(again.moves(), game.moves());
+
+        // Case 4: Move numbers and blank lines of ICS logs, a bad line says which one it is
+        let again = Notation::Coordinate.read_log(Board::default(), "1. e2e4\n1... d7d5\n\n2. e4d5\n2... d8d5\n3. b1c3\n", ParseMode::Lenient).unwrap();
+        assert_eq!(again.moves(), game.moves());
+        assert_eq!(
+            Notation::Coordinate.read_log(Board::default(), "e2e4\ne2e4\n", ParseMode::Strict).unwrap_err(),
+            ChessError::InvalidMoveLog("line 2: no legal move \"e2e4\"".to_string())
+        );
+    }
+
+    #[test]
+    fn ics_style12() {
+        // Case 1: The line after 1. e4, read and written back the same
+        let line = "<12> rnbqkbnr pppppppp -------- -------- ----P--- -------- PPPP-PPP RNBQKBNR B 4 1 1 1 1 0 7 Newton Einstein 1 2 12 39 39 119 122 1 P/e2-e4 (0:06) e4 0 1 0";
+        let style12 = Style12::parse(line).unwrap();
+        assert_eq!(style12.board.get_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!((style12.white.as_str(), style12.black.as_str()), ("Newton", "Einstein"));
+        assert_eq!(style12.relation, Relation::MyMove);
+        assert_eq!((style12.white_clock, style12.black_clock), (119, 122));
+        assert_eq!(style12.last_move.as_deref(), Some("P/e2-e4"));
+        assert_eq!(style12.last_move_time, 6000);
+        assert_eq!((style12.clock_ticking, style12.lag), (Some(true), Some(0)));
+        assert_eq!(style12.to_string(), line);
+
+        // Case 2: A board on its own, without the FICS fields, back to the same board
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/4K2R w Kkq - 12 40").unwrap();
+        let style12 = Style12::from_board(&board);
+        assert_eq!((style12.white_material, style12.black_material), (5, 10));
+        let again = Style12::parse(&style12.to_string()).unwrap();
+        assert_eq!(again.board.get_fen(), board.get_fen());
+        assert_eq!(again.last_move, None);
+        assert_eq!(again.to_string(), style12.to_string());
+
+        // Case 3: Lines that aren't style 12
+        assert!(Style12::parse("<10> rnbqkbnr").is_err());
+        assert!(Style12::parse(&line.replace(" B 4 ", " X 4 ")).is_err());
+        assert!(Style12::parse(&line.replace(" Einstein 1 ", " Einstein 5 ")).is_err());
+        assert!(matches!(Style12::parse(&line.replace("pppppppp", "ppppppp")), Err(ChessError::InvalidStyle12(_))));
+    }
+
+    #[test]
+    fn correspondence_games() {
+        let parse = |game: &CorrespondenceGame, text: &str| Notation::Coordinate.parse(game.game().board(), text).unwrap();
+        let line = |game: &CorrespondenceGame, texts: &[&str]| {
+            let mut board = *game.game().board();
+            return texts.iter().map(|text| {
+                let mv = Notation::Coordinate.parse(&board, text).unwrap();
+                board.make_move(mv);
+                return mv;
+            }).collect::<Vec<Move>>();
+        };
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+
+        // Case 1: Conditional moves answer the opponent at once, the clock charges the time taken and
+        // gives the increment for the reply too
+        let mut game = CorrespondenceGame::new(Game::default());
+        game.clock = Some(Clock { time: [3 * day, 3 * day], increment: [day, day], moves_to_go: None });
+        game.make_move(parse(&game, "e2e4"), 1000).unwrap();
+        game.add_conditional(line(&game, &["e7e5", "g1f3"])).unwrap();
+        game.add_conditional(line(&game, &["c7c5", "g1f3", "d7d6", "d2d4"])).unwrap();
+        assert!(matches!(game.add_conditional(line(&game, &["e7e5", "b1c3"])), Err(ChessError::InvalidCorrespondence(_))));
+        assert!(matches!(game.add_conditional(line(&game, &["e7e5"])), Err(ChessError::InvalidCorrespondence(_))));
+        let expected = line(&game, &["c7c5", "g1f3"]);
+        assert_eq!(game.make_move(expected[0], 4600).unwrap(), expected);
+        assert_eq!(game.conditional_moves(), [line(&game, &["d7d6", "d2d4"])]);
+        assert_eq!(game.clock.unwrap().time, [5 * day, 4 * day - std::time::Duration::from_secs(3600)]);
+
+        // Case 2: Everything comes back from the file the same
+        game.tags.push(("White".to_string(), "Ana".to_string()));
+        game.tags.push(("Event".to_string(), "Club \\ championship\nround 2".to_string()));
+        let variation = vec![Notation::Coordinate.parse(&game.game().position_at(2).unwrap(), "b1c3").unwrap()];
+        assert!(game.annotate(2, PgnAnnotation { variations: vec![line(&game, &["d7d6"])], ..PgnAnnotation::default() }).is_err());
+        game.annotate(2, PgnAnnotation {
+            nags: vec![1],
+            comment: Some("The open Sicilian".to_string()),
+            eval: Some(30),
+            clock: Some(4 * day),
+            variations: vec![variation],
+        }).unwrap();
+        assert_eq!(game.annotate(3, PgnAnnotation::default()), Err(ChessError::PlyOutOfRange(3)));
+        game.game_mut().offer_draw(Color::White).unwrap();
+
+        let text = game.to_text();
+        assert!(text.starts_with("chyes correspondence 1\n"));
+        let path = std::env::temp_dir().join(format!("chyes-correspondence-{}.txt", std::process::id()));
+        game.save(&path).unwrap();
+        let again = CorrespondenceGame::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(again.to_text(), text);
+        assert_eq!(again.game().moves(), game.game().moves());
+        assert_eq!(again.tag("Event"), Some("Club \\ championship\nround 2"));
+        assert_eq!(again.conditional_moves(), game.conditional_moves());
+        assert_eq!(again.annotation(2), game.annotation(2));
+        assert_eq!(again.clock, game.clock);
+        assert_eq!((again.last_move_at, again.game().draw_offer()), (Some(4600), Some(Color::White)));
+        assert!(again.pgn(PgnOptions::default()).contains("The open Sicilian}"));
+
+        // Case 3: A resignation is kept, broken and newer files are refused
+        let mut resigned = again.clone();
+        resigned.game_mut().resign(Color::Black).unwrap();
+        let loaded = CorrespondenceGame::from_text(&resigned.to_text()).unwrap();
+        assert_eq!(loaded.game().termination(), Some((Outcome::WhiteWins, TerminationReason::Resignation)));
+        assert!(CorrespondenceGame::from_text(&text.replace("correspondence 1", "correspondence 2")).is_err());
+        assert!(CorrespondenceGame::from_text(&text.replace("last_move_at", "last_move")).is_err());
+        assert!(CorrespondenceGame::from_text(&text.replace("moves e2e4", "moves e2e5")).is_err());
+
+        // Case 4: A move after the time ran out loses on time
+        let mut late = again;
+        let mv = parse(&late, "d7d6");
+        assert_eq!(late.make_move(mv, 4600 + 5 * 24 * 60 * 60), Err(ChessError::GameOver));
+        assert_eq!(late.game().termination(), Some((Outcome::WhiteWins, TerminationReason::Timeout)));
+        assert!(late.conditional_moves().is_empty());
+    }
+
+    #[test]
+    fn match_game_endings() {
+        // Plays the same moves over and over
+        struct Shuffler {
+            moves: Vec<&'static str>,
+            next: usize,
+        }
+
+        impl Engine for Shuffler {
+            fn choose_move(&mut self, board: &Board, _limits: &SearchLimits) -> Result<Move, ChessError> {
+                let text = self.moves[self.next % self.moves.len()];
+                self.next += 1;
+                return Notation::Coordinate.parse(board, text).ok_or(ChessError::NoLegalMoves);
+            }
+        }
+
+        // Case 1: Fivefold repetition ends the game, the record replays into the same game
+        let mut white = Shuffler { moves: vec!["g1f3", "f3g1"], next: 0 };
+        let mut black = Shuffler { moves: vec!["b8c6", "c6b8"], next: 0 };
+        let record = play_game(&mut white, &mut black, &MatchConfig::default()).unwrap();
+        assert_eq!((record.outcome, record.reason, record.moves.len()), (Outcome::Draw, Reason::FivefoldRepetition, 16));
+        let pgn = record.to_pgn(PgnOptions::default()).unwrap();
+        assert!(pgn.contains("[Result \"1/2-1/2\"]"));
+        assert_eq!(record.game().unwrap().termination(), Some((Outcome::Draw, TerminationReason::DrawRule(GameResult::FivefoldRepetition))));
+
+        // Case 2: The 75 move rule
+        let config = MatchConfig { start_fen: "4k3/8/8/3p4/3P4/8/8/4K3 w - - 0 1".to_string(), ..MatchConfig::default() };
+        let (mut white, mut black) = (RandomMover::new(), RandomMover::new());
+        white.seed(1);
+        black.seed(101);
+        let record = play_game(&mut white, &mut black, &config).unwrap();
+        assert_eq!((record.outcome, record.reason, record.moves.len()), (Outcome::Draw, Reason::SeventyFiveMoveRule, 150));
+        assert!(record.to_pgn(PgnOptions::default()).is_ok());
+
+        // Case 3: A game over before the first move
+        let config = MatchConfig { start_fen: "4k3/8/8/8/8/8/8/3NK3 w - - 0 1".to_string(), ..MatchConfig::default() };
+        let record = play_game(&mut RandomMover::new(), &mut RandomMover::new(), &config).unwrap();
+        assert_eq!((record.reason, record.moves.len()), (Reason::InsufficientMaterial, 0));
+        assert!(record.to_pgn(PgnOptions::default()).is_ok());
+
+        // Case 4: Random games from the start always replay
+        for seed in 0..20 {
+            let (mut white, mut black) = (RandomMover::new(), RandomMover::new());
+            white.seed(seed);
+            black.seed(seed + 100);
+            let record = play_game(&mut white, &mut black, &MatchConfig::default()).unwrap();
+            assert!(record.to_pgn(PgnOptions::default()).is_ok(), "seed {}: {:?}", seed, record.reason);
+        }
+    }
 }
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use crate::chess::Color;
+use crate::engine::SearchLimits;
+
+// Deciding how long to think about a move when playing on a clock
+/*
+Every move gets a share of the remaining time plus most of the increment, that is the soft
+limit, no new iteration starts after it. The hard limit stops the search whatever it is doing,
+it is a few soft limits but never more than most of what is left on the clock. The time the
+GUI and the network lose on every move (the move overhead) is taken off first
+*/
+
+// Moves the rest of the game is assumed to last when the clock doesn't say
+pub const DEFAULT_MOVES_TO_GO: u32 = 30;
+// Least time a move gets, even with the flag about to fall
+pub const MIN_MOVE_TIME: Duration = Duration::from_millis(1);
+// Most of the remaining time one move can use
+const MAX_SHARE: f64 = 0.8;
+// Part of the increment spent on top of the share of the remaining time
+const INCREMENT_SHARE: f64 = 0.75;
+// How many soft limits fit in the hard one
+const HARD_FACTOR: u32 = 3;
+
+// The game clock as "go wtime .. btime .. winc .. binc .. movestogo .." gives it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Clock {
+	// Indexed by color, white first
+	pub time: [Duration; 2],
+	pub increment: [Duration; 2],
+	// Moves until the next time control, None for sudden death
+	pub moves_to_go: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBudget {
+	pub soft: Duration,
+	pub hard: Duration,
+}
+
+impl TimeBudget {
+	pub fn new(limits: &SearchLimits, side: Color, overhead: Duration) -> Option<TimeBudget> {
+		// None when the search is not limited by time at all
+		if let Some(movetime) = limits.movetime {
+			let time = movetime.saturating_sub(overhead).max(MIN_MOVE_TIME);
+			return Some(TimeBudget { soft: time, hard: time });
+		}
+
+		let clock = limits.clock?;
+		let remaining = clock.time[side as usize].saturating_sub(overhead);
+		let increment = clock.increment[side as usize];
+		let moves = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+		let soft = remaining / moves + increment.mul_f64(INCREMENT_SHARE);
+		let hard = (soft * HARD_FACTOR).min(remaining.mul_f64(MAX_SHARE)).max(MIN_MOVE_TIME);
+		return Some(TimeBudget { soft: soft.min(hard), hard });
+	}
+
+	pub fn soft_limit(&self, stable_iterations: u32) -> Duration {
+		// A best move that just changed is worth more time to settle, up to the hard limit
+		let scale = match stable_iterations {
+			0 => 2.0,
+			1 => 1.5,
+			_ => 1.0,
+		};
+		return self.soft.mul_f64(scale).min(self.hard);
+	}
+}
@@ -0,0 +1,218 @@
+use crate::chess::*;
+use crate::engine::{score_after_move, Engine, SearchLimits};
+use crate::error::ChessError;
+use crate::game::Game;
+use crate::rng::Rng;
+
+// Guess the move, playing through a master game one guess at a time
+/*
+The player takes one side (or both) and guesses every move that side played in the game. The
+move that was played scores MAX_POINTS, any other legal guess is scored against it with the
+engine: what the guess is worth compared to the game move, one point off for every
+CENTIPAWNS_PER_POINT it gives away, never more than MAX_POINTS - 1 and never less than 0.
+After a guess the game move is played whatever the guess was, moves of the other side are
+played straight away
+*/
+
+pub const MAX_POINTS: u32 = 10;
+pub const CENTIPAWNS_PER_POINT: i32 = 30;
+
+#[derive(Eq, Hash, Clone, Copy, PartialEq, Debug)]
+pub struct GuessResult {
+	pub ply: usize,
+	pub guess: Move,
+	pub actual: Move,
+	// Centipawns for the side that moves, None when the guess was the game move
+	pub guess_score: Option<i32>,
+	pub actual_score: Option<i32>,
+	pub points: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuessTheMove {
+	game: Game,
+	// None when every move is guessed
+	side: Option<Color>,
+	ply: usize,
+	results: Vec<GuessResult>,
+}
+
+impl GuessTheMove {
+	pub fn new(game: Game, side: Option<Color>) -> Self {
+		let mut session = GuessTheMove { game, side, ply: 0, results: Vec::new() };
+		session.skip_to_guess();
+		return session;
+	}
+
+	fn skip_to_guess(&mut self) {
+		// function to play the other side's moves until it is the player's turn
+		while let Some(board) = self.game.position_at(self.ply) {
+			if self.ply == self.game.moves().len() || self.side.is_none_or(|side| board.turn == side) {
+				return;
+			}
+			self.ply += 1;
+		}
+	}
+
+	pub fn position(&self) -> Option<Board> {
+		// The position to guess in, None once the game is over
+		if self.is_finished() {
+			return None;
+		}
+		return self.game.position_at(self.ply);
+	}
+
+	pub fn is_finished(&self) -> bool {
+		return self.ply >= self.game.moves().len();
+	}
+
+	pub fn guess(&mut self, engine: &mut impl Engine, guess: Move, limits: &SearchLimits) -> Result<GuessResult, ChessError> {
+		let board = self.position().ok_or(ChessError::GameOver)?;
+		if !board.legal_moves().contains(&guess) {
+			return Err(ChessError::IllegalMove(guess));
+		}
+
+		let actual = self.game.moves()[self.ply];
+		let mut result = GuessResult {
+			ply: self.ply,
+			guess,
+			actual,
+			guess_score: None,
+			actual_score: None,
+			points: MAX_POINTS,
+		};
+
+		if guess != actual {
+			let guess_score = score_after_move(engine, &board, guess, limits)?;
+			let actual_score = score_after_move(engine, &board, actual, limits)?;
+			let lost = (actual_score - guess_score).max(0) / CENTIPAWNS_PER_POINT;
+			result.guess_score = Some(guess_score);
+			result.actual_score = Some(actual_score);
+			result.points = (MAX_POINTS as i32 - 1 - lost).max(0) as u32;
+		}
+
+		self.results.push(result);
+		self.ply += 1;
+		self.skip_to_guess();
+		return Ok(result);
+	}
+
+	pub fn results(&self) -> &[GuessResult] {
+		return &self.results;
+	}
+
+	pub fn score(&self) -> u32 {
+		return self.results.iter().map(|result| result.points).sum();
+	}
+
+	pub fn max_score(&self) -> u32 {
+		// What the guesses so far could have scored
+		return self.results.len() as u32 * MAX_POINTS;
+	}
+}
+
+// Board vision training, the pieces move on a board that only has the obstacles on it
+/*
+A square to name or to tell the color of, the shortest way for a knight from one square to
+another, and whether a piece gets from one square to another in one move. Obstacles block
+sliders and can be captured, pawns only move diagonally onto one and never straight into one
+*/
+#[derive(Eq, Hash, Clone, PartialEq, Debug)]
+pub struct SquareQuestion {
+	pub square: Coordinate,
+	pub name: String,
+	pub light: bool,
+}
+
+impl SquareQuestion {
+	pub fn random(rng: &mut Rng) -> Self {
+		let square = Coordinate::from_number(rng.below(64) as i8);
+		SquareQuestion {
+			square,
+			name: square.to_string(),
+			light: square.is_light(),
+		}
+	}
+
+	pub fn is_correct(&self, answer: &str) -> bool {
+		return Coordinate::from_algebraic(answer.trim()) == Ok(self.square);
+	}
+}
+
+pub fn shortest_knight_path(from: Coordinate, to: Coordinate) -> Vec<Coordinate> {
+	// Both ends included, breadth first so the first path found is the shortest
+	// NOTE: Empty when a square is off the board
+	if !from.is_valid() || !to.is_valid() {
+		return Vec::new();
+	}
+
+	let mut previous: [Option<i8>; 64] = [None; 64];
+	let mut queue = std::collections::VecDeque::from([from]);
+	previous[from.as_number() as usize] = Some(from.as_number());
+	while let Some(square) = queue.pop_front() {
+		if square == to {
+			break;
+		}
+		for (row_diff, col_diff) in KNIGHT_STEPS {
+			let next = coord!(square.row + row_diff, square.col + col_diff);
+			if next.is_valid() && previous[next.as_number() as usize].is_none() {
+				previous[next.as_number() as usize] = Some(square.as_number());
+				queue.push_back(next);
+			}
+		}
+	}
+
+	let mut path = vec![to];
+	let mut square = to;
+	while square != from {
+		square = Coordinate::from_number(previous[square.as_number() as usize].expect("every square is reachable"));
+		path.push(square);
+	}
+	path.reverse();
+	return path;
+}
+
+pub fn can_piece_reach(piece: Piece, from: Coordinate, to: Coordinate, obstacles: &[Coordinate]) -> bool {
+	// In one move, castling aside
+	if !from.is_valid() || !to.is_valid() || from == to {
+		return false;
+	}
+	let blocked = |square: Coordinate| obstacles.contains(&square);
+	let (row_diff, col_diff) = (to.row - from.row, to.col - from.col);
+
+	let slides = |steps: &[(i8, i8)]| {
+		return steps.iter().any(|(step_row, step_col)| {
+			let mut square = coord!(from.row + step_row, from.col + step_col);
+			while square.is_valid() {
+				if square == to {
+					return true;
+				}
+				if blocked(square) {
+					return false;
+				}
+				square = coord!(square.row + step_row, square.col + step_col);
+			}
+			return false;
+		});
+	};
+
+	use Pieces::*;
+	return match piece.breed {
+		King => KING_STEPS.contains(&(row_diff, col_diff)),
+		Knight => KNIGHT_STEPS.contains(&(row_diff, col_diff)),
+		Bishop => slides(&DIAGONAL_STEPS),
+		Rook => slides(&LINEAR_STEPS),
+		Queen => slides(&DIAGONAL_STEPS) || slides(&LINEAR_STEPS),
+		Pawn => {
+			// Rows go down the board, white pawns move to smaller rows
+			let (forward, start_row) = match piece.color {
+				Color::White => (-1, 6),
+				Color::Black => (1, 1),
+			};
+			let push = col_diff == 0 && !blocked(to)
+			&& (row_diff == forward || (row_diff == 2 * forward && from.row == start_row && !blocked(coord!(from.row + forward, from.col))));
+			let capture = row_diff == forward && col_diff.abs() == 1 && blocked(to);
+			push || capture
+		}
+	};
+}
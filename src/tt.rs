@@ -0,0 +1,194 @@
+use std::io::{self, Read, Write};
+use std::mem;
+
+use crate::chess::*;
+
+// Transposition table, what the search found out about a position remembered by Zobrist hash
+/*
+An entry keeps the depth the position was searched to, the score, whether the score is exact
+or only a bound (the search was cut off there) and the best move. The score is only reused by
+a search of the same depth: a deeper one would be worth more, but the search would then depend
+on what was searched before it and play differently with the same limits. The move is tried
+first in any case. One entry per slot, a new one replaces the old one unless that is a deeper
+search of another position. The whole hash is stored to tell positions sharing a slot apart
+
+Scores that went through a draw by repetition or the 50-move rule are not stored, those draws
+depend on the moves that led to the position and the hash doesn't know about them
+
+Mate scores are stored as the distance to the mate from the position of the entry, not from
+the root, the search converts them (see search.rs)
+*/
+/*
+File layout, all numbers little endian:
+header - "CHYESTT" and the format version as one byte, then the number of entries as 8 bytes
+entry  - 19 bytes: hash (8 bytes), score (4), depth (1), bound (1: 0 exact, 1 lower, 2 upper),
+         the move as its from and to square numbers (1 each, a1 is 0, 255 for no move) and the
+         promotion (1: 0 none, 1 queen, 2 rook, 3 bishop, 4 knight), then 2 reserved bytes
+         written as zero and skipped when reading
+
+The file doesn't depend on the size of the table, the entries are stored again when it is read
+*/
+
+pub const TT_MAGIC: &[u8; 7] = b"CHYESTT";
+// Bumped with every change of the file layout, files of other versions are refused
+pub const TT_VERSION: u8 = 1;
+const ENTRY_BYTES: usize = 19;
+const NO_SQUARE: u8 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+	Exact,
+	// The score is at least this much (a beta cutoff)
+	Lower,
+	// The score is at most this much (no move raised alpha)
+	Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TtEntry {
+	pub key: u64,
+	pub depth: u32,
+	pub score: i32,
+	pub bound: Bound,
+	pub best_move: Option<Move>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TranspositionTable {
+	// A power of two slots, empty when the table is off
+	entries: Vec<Option<TtEntry>>,
+}
+
+impl TranspositionTable {
+	pub fn new(size_mb: u32) -> Self {
+		// As many slots as fit in the size, rounded down to a power of two
+		let slots = size_mb as usize * 1024 * 1024 / mem::size_of::<Option<TtEntry>>();
+		let slots = if slots == 0 { 0 } else { 1 << slots.ilog2() };
+		TranspositionTable { entries: vec![None; slots] }
+	}
+
+	pub fn len(&self) -> usize {
+		// Number of slots
+		return self.entries.len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.entries.is_empty();
+	}
+
+	pub fn filled(&self) -> usize {
+		// Number of slots holding an entry
+		return self.entries.iter().filter(|entry| entry.is_some()).count();
+	}
+
+	pub fn clear(&mut self) {
+		self.entries.fill(None);
+	}
+
+	pub fn probe(&self, key: u64) -> Option<TtEntry> {
+		if self.entries.is_empty() {
+			return None;
+		}
+		let index = key as usize & (self.entries.len() - 1);
+		return self.entries[index].filter(|entry| entry.key == key);
+	}
+
+	pub fn store(&mut self, entry: TtEntry) {
+		if self.entries.is_empty() {
+			return;
+		}
+		let index = entry.key as usize & (self.entries.len() - 1);
+		if let Some(old) = self.entries[index] {
+			if old.key != entry.key && old.depth > entry.depth {
+				return;
+			}
+		}
+		self.entries[index] = Some(entry);
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_all(TT_MAGIC)?;
+		writer.write_all(&[TT_VERSION])?;
+		writer.write_all(&(self.filled() as u64).to_le_bytes())?;
+
+		for entry in self.entries.iter().flatten() {
+			let mut bytes = [0u8; ENTRY_BYTES];
+			bytes[0..8].copy_from_slice(&entry.key.to_le_bytes());
+			bytes[8..12].copy_from_slice(&entry.score.to_le_bytes());
+			bytes[12] = entry.depth.min(u8::MAX as u32) as u8;
+			bytes[13] = match entry.bound {
+				Bound::Exact => 0,
+				Bound::Lower => 1,
+				Bound::Upper => 2,
+			};
+			let (from, to, promotion) = match entry.best_move {
+				Some(mv) => {
+					let promotion = match mv.promotion {
+						None => 0,
+						Some(Pieces::Queen) => 1,
+						Some(Pieces::Rook) => 2,
+						Some(Pieces::Bishop) => 3,
+						Some(_) => 4,
+					};
+					(mv.from.as_number() as u8, mv.to.as_number() as u8, promotion)
+				}
+				None => (NO_SQUARE, NO_SQUARE, 0),
+			};
+			bytes[14..17].copy_from_slice(&[from, to, promotion]);
+			writer.write_all(&bytes)?;
+		}
+		return Ok(());
+	}
+
+	pub fn read(reader: &mut impl Read, size_mb: u32) -> io::Result<TranspositionTable> {
+		// The entries of the file in a table of `size_mb`, a smaller table keeps the deeper ones
+		let invalid = |text: String| io::Error::new(io::ErrorKind::InvalidData, text);
+
+		let mut header = [0u8; 16];
+		reader.read_exact(&mut header)?;
+		if &header[0..7] != TT_MAGIC {
+			return Err(invalid("not a transposition table file".to_string()));
+		}
+		if header[7] != TT_VERSION {
+			return Err(invalid(format!("unsupported transposition table version {}", header[7])));
+		}
+		let count = u64::from_le_bytes(header[8..16].try_into().unwrap());
+
+		let mut table = TranspositionTable::new(size_mb);
+		let mut bytes = [0u8; ENTRY_BYTES];
+		for _ in 0..count {
+			reader.read_exact(&mut bytes)?;
+			let bound = match bytes[13] {
+				0 => Bound::Exact,
+				1 => Bound::Lower,
+				2 => Bound::Upper,
+				other => return Err(invalid(format!("invalid bound {}", other))),
+			};
+			let best_move = match (bytes[14], bytes[15]) {
+				(NO_SQUARE, NO_SQUARE) => None,
+				(from, to) if from < 64 && to < 64 => Some(Move {
+					from: Coordinate::from_number(from as i8),
+					to: Coordinate::from_number(to as i8),
+					promotion: match bytes[16] {
+						0 => None,
+						1 => Some(Pieces::Queen),
+						2 => Some(Pieces::Rook),
+						3 => Some(Pieces::Bishop),
+						4 => Some(Pieces::Knight),
+						other => return Err(invalid(format!("invalid promotion {}", other))),
+					},
+				}),
+				(from, to) => return Err(invalid(format!("invalid move squares {} {}", from, to))),
+			};
+
+			table.store(TtEntry {
+				key: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+				score: i32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+				depth: bytes[12] as u32,
+				bound,
+				best_move,
+			});
+		}
+		return Ok(table);
+	}
+}
@@ -0,0 +1,327 @@
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::chess::*;
+use crate::engine::{SearchControl, SearchLimits};
+use crate::eval::Evaluator;
+use crate::game::Game;
+use crate::mcts::win_probability;
+use crate::notation::Notation;
+use crate::pgn::eval_text;
+use crate::render::RenderTheme;
+use crate::search::{AlphaBeta, SearchResult, MATE_SCORE, MAX_DEPTH};
+
+// Analysis screen for stepping through a game while the engine thinks about every position
+/*
+8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜   ░░
+7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟   ░░
+6 . . . . . . . .   ░░
+5 . . . . . . . .   ░░
+4 . . . . ♙*. . .   ██
+3 . . . . . . . .   ██
+2 ♙ ♙ ♙ ♙ .*♙ ♙ ♙   ██
+1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖   ██
+  a b c d e f g h
+ply 1/40  1. e4  depth 6
+1. -0.25  1... e5 2. Nf3 Nc6
+2. -0.30  1... c5 2. Nf3 d6
+3. -0.40  1... e6 2. d4 d5
+*/
+/*
+The eval bar is white's chance of winning (see mcts::win_probability), white from the bottom
+the way Lichess draws it, mates fill it up. Scores are from white's point of view everywhere
+
+The engine runs on its own thread and searches the shown position until it runs out of depth
+or the position changes, every finished iteration redraws the screen with the best LINES moves.
+Commands are read a line at a time, so they work on any terminal without a raw mode:
+
+n or an empty line - next move      p - previous move
+f - start of the game               l - end of the game
+<number> - position after that ply  q - quit
+*/
+
+// Number of moves shown with their lines
+pub const LINES: usize = 3;
+// Rows of the eval bar, one for every rank
+pub const BAR_HEIGHT: usize = 8;
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+pub fn eval_bar(score: Option<i32>, height: usize) -> Vec<String> {
+	// Top row first, every row two characters wide, half full without a score
+	let white = match score {
+		Some(score) if score.abs() >= MATE_SCORE - MAX_DEPTH as i32 => if score > 0 { 1.0 } else { 0.0 },
+		Some(score) => win_probability(score),
+		None => 0.5,
+	};
+	let filled = (white * height as f64).round() as usize;
+	return (0..height).map(|row| if height - row <= filled { "██" } else { "░░" }.to_string()).collect();
+}
+
+// A game and the position on screen
+#[derive(Debug, Clone)]
+pub struct GameCursor {
+	pub game: Game,
+	ply: usize,
+}
+
+impl GameCursor {
+	pub fn new(game: Game) -> Self {
+		// Starts at the beginning of the game
+		GameCursor { game, ply: 0 }
+	}
+
+	pub fn ply(&self) -> usize {
+		return self.ply;
+	}
+
+	pub fn len(&self) -> usize {
+		return self.game.moves().len();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.game.moves().is_empty();
+	}
+
+	pub fn board(&self) -> Board {
+		return self.game.position_at(self.ply).unwrap();
+	}
+
+	pub fn last_move(&self) -> Option<Move> {
+		// The move that led to the position, None at the start
+		return self.ply.checked_sub(1).map(|ply| self.game.moves()[ply]);
+	}
+
+	pub fn history(&self) -> Vec<u64> {
+		// Hashes of the positions before this one since the last capture or pawn move, for the search
+		let board = self.board();
+		let from = self.ply.saturating_sub(board.halfmove_clock() as usize);
+		return (from..self.ply).filter_map(|ply| self.game.hash_at(ply)).collect();
+	}
+
+	pub fn goto(&mut self, ply: usize) -> bool {
+		// False when the game doesn't have that ply
+		if ply > self.len() {
+			return false;
+		}
+		self.ply = ply;
+		return true;
+	}
+
+	pub fn forward(&mut self) -> bool {
+		return self.goto(self.ply + 1);
+	}
+
+	pub fn back(&mut self) -> bool {
+		return self.ply > 0 && self.goto(self.ply - 1);
+	}
+
+	pub fn to_start(&mut self) {
+		self.ply = 0;
+	}
+
+	pub fn to_end(&mut self) {
+		self.ply = self.len();
+	}
+}
+
+fn white_score(board: &Board, score: i32) -> i32 {
+	return if board.turn == Color::White { score } else { -score };
+}
+
+pub fn frame(cursor: &GameCursor, lines: &[SearchResult], theme: &RenderTheme) -> String {
+	// The whole screen for a position and the lines found for it so far, best first
+	let board = cursor.board();
+	let score = lines.first().map(|line| white_score(&board, line.score));
+	let bar = eval_bar(score, BAR_HEIGHT);
+
+	let mut out = String::new();
+	let rendered = board.render_move(&RenderTheme { labels: true, ..*theme }, cursor.last_move());
+	for (i, row) in rendered.lines().enumerate() {
+		match bar.get(i) {
+			Some(cell) => out.push_str(&format!("{}  {}\n", row, cell)),
+			None => out.push_str(&format!("{}\n", row)),
+		}
+	}
+
+	let mut status = format!("ply {}/{}", cursor.ply(), cursor.len());
+	if let Some(mv) = cursor.last_move() {
+		let before = cursor.game.position_at(cursor.ply() - 1).unwrap();
+		let number = if before.turn == Color::White { format!("{}.", before.fullmove_number()) } else { format!("{}...", before.fullmove_number()) };
+		status.push_str(&format!("  {} {}", number, Notation::San.format(&before, mv)));
+	}
+	match lines.first() {
+		Some(line) => status.push_str(&format!("  depth {}", line.depth)),
+		None if board.legal_moves().is_empty() => status.push_str("  game over"),
+		None => status.push_str("  thinking"),
+	}
+	out.push_str(&status);
+	out.push('\n');
+
+	for (i, line) in lines.iter().enumerate() {
+		out.push_str(&format!("{}. {:>6}  {}\n", i + 1, eval_text(white_score(&board, line.score)), line.pv_san(&board)));
+	}
+	return out;
+}
+
+enum Event {
+	Command(String),
+	EndOfInput,
+	// Lines of a finished iteration, with the number of the position they belong to
+	Lines(u64, Vec<SearchResult>),
+}
+
+struct Job {
+	board: Board,
+	history: Vec<u64>,
+	control: SearchControl,
+	position: u64,
+}
+
+pub struct AnalysisScreen<E: Evaluator + Send + 'static> {
+	pub cursor: GameCursor,
+	pub theme: RenderTheme,
+	lines: Vec<SearchResult>,
+	// Counts the positions sent to the engine, lines of an older one are stale
+	position: u64,
+	control: SearchControl,
+	jobs: Option<Sender<Job>>,
+	worker: Option<JoinHandle<AlphaBeta<E>>>,
+	sender: Sender<Event>,
+	events: Receiver<Event>,
+}
+
+impl<E: Evaluator + Send + 'static> AnalysisScreen<E> {
+	pub fn new(game: Game, engine: AlphaBeta<E>, theme: RenderTheme) -> Self {
+		let (sender, events) = channel();
+		let (jobs, queue) = channel::<Job>();
+
+		let results = sender.clone();
+		let worker = thread::spawn(move || {
+			let mut engine = engine;
+			for job in queue {
+				let limits = SearchLimits {
+					depth: Some(MAX_DEPTH),
+					control: job.control,
+					history: job.history,
+					..SearchLimits::default()
+				};
+				engine.search_lines(&job.board, &limits, LINES, |lines| {
+					let _ = results.send(Event::Lines(job.position, lines.to_vec()));
+				});
+			}
+			return engine;
+		});
+
+		AnalysisScreen {
+			cursor: GameCursor::new(game),
+			theme,
+			lines: Vec::new(),
+			position: 0,
+			control: SearchControl::new(),
+			jobs: Some(jobs),
+			worker: Some(worker),
+			sender,
+			events,
+		}
+	}
+
+	pub fn lines(&self) -> &[SearchResult] {
+		// What the engine found for the shown position so far
+		return &self.lines;
+	}
+
+	pub fn frame(&self) -> String {
+		return frame(&self.cursor, &self.lines, &self.theme);
+	}
+
+	fn analyze(&mut self) {
+		// Stops the search of the old position and starts one for the shown position
+		self.control.stop();
+		self.control = SearchControl::new();
+		self.position += 1;
+		self.lines.clear();
+
+		let job = Job {
+			board: self.cursor.board(),
+			history: self.cursor.history(),
+			control: self.control.clone(),
+			position: self.position,
+		};
+		if let Some(jobs) = &self.jobs {
+			let _ = jobs.send(job);
+		}
+	}
+
+	pub fn command(&mut self, command: &str) -> Option<bool> {
+		// Carries out a command, Some(true) when the position changed and None on quit
+		let moved = match command.trim() {
+			"q" | "quit" => return None,
+			"" | "n" => self.cursor.forward(),
+			"p" => self.cursor.back(),
+			"f" => {
+				self.cursor.to_start();
+				true
+			}
+			"l" => {
+				self.cursor.to_end();
+				true
+			}
+			text => match text.parse() {
+				Ok(ply) => self.cursor.goto(ply),
+				Err(_) => false,
+			},
+		};
+		if moved {
+			self.analyze();
+		}
+		return Some(moved);
+	}
+
+	pub fn finish(&mut self) -> Option<AlphaBeta<E>> {
+		// Stops the engine and gives it back, None once it was given back
+		self.control.stop();
+		self.jobs = None;
+		return self.worker.take().map(|worker| worker.join().unwrap());
+	}
+
+	pub fn run<R: BufRead + Send + 'static, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+		// Draws the screen until "q" or the end of the input
+		let sender = self.sender.clone();
+		thread::spawn(move || {
+			for line in input.lines().map_while(Result::ok) {
+				if sender.send(Event::Command(line)).is_err() {
+					return;
+				}
+			}
+			let _ = sender.send(Event::EndOfInput);
+		});
+
+		self.analyze();
+		write!(output, "{}{}", CLEAR_SCREEN, self.frame())?;
+		output.flush()?;
+
+		while let Ok(event) = self.events.recv() {
+			match event {
+				Event::Command(command) => match self.command(&command) {
+					Some(_) => {}
+					None => break,
+				},
+				Event::Lines(position, lines) if position == self.position => self.lines = lines,
+				Event::Lines(..) => continue,
+				Event::EndOfInput => break,
+			}
+			write!(output, "{}{}", CLEAR_SCREEN, self.frame())?;
+			output.flush()?;
+		}
+
+		self.finish();
+		return Ok(());
+	}
+}
+
+impl<E: Evaluator + Send + 'static> Drop for AnalysisScreen<E> {
+	fn drop(&mut self) {
+		self.finish();
+	}
+}
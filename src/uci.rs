@@ -0,0 +1,408 @@
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::chess::*;
+use crate::engine::{Engine, SearchControl, SearchLimits};
+use crate::error::ChessError;
+use crate::options::EngineOptions;
+use crate::promotion::PromotionPolicy;
+use crate::search::{MATE_SCORE, MAX_DEPTH};
+use crate::time::Clock;
+
+// Universal Chess Interface, the text protocol chess GUIs use to talk to engines
+// source: https://backscattering.de/chess/uci/
+/*
+> uci
+< id name chyes
+< option name Hash type spin default 16 min 1 max 65536
+< uciok
+> setoption name Hash value 64
+> position startpos moves e2e4 e7e5
+> go depth 4
+< info score cp 25 pv g1f3 b8c6 b1c3 g8f6
+< bestmove g1f3 ponder b8c6
+> position startpos moves e2e4 e7e5 g1f3 b8c6
+> go ponder movetime 1000
+> ponderhit
+< info score cp 0 pv f1b5 g8f6
+< bestmove f1b5 ponder g8f6
+> go depth 4 searchmoves d2d4 f1c4
+< info score cp 10 pv f1c4 g8f6
+< bestmove f1c4 ponder g8f6
+*/
+/*
+Searches run on their own thread so "stop", "ponderhit" and "isready" are answered while
+the engine thinks. The engine moves into the search thread and comes back when it is done.
+A ponder search never gives its best move before the ponderhit or a stop, an infinite search
+never before the stop, even when it has nothing left to look at. Any command that changes the
+position or the options stops the search that is running
+*/
+
+pub const ENGINE_NAME: &str = "chyes";
+pub const ENGINE_AUTHOR: &str = "bgdnrvsky";
+
+enum Event {
+	Line(String),
+	EndOfInput,
+	SearchDone,
+}
+
+struct Search<E> {
+	thread: JoinHandle<(E, Vec<String>)>,
+	control: SearchControl,
+	// "go infinite", it only ends with a stop
+	infinite: bool,
+}
+
+pub struct Uci<E: Engine + Send + 'static> {
+	// None while a search thread has it
+	engine: Option<E>,
+	search: Option<Search<E>>,
+	options: EngineOptions,
+	board: Board,
+	// Hashes of the positions the moves went through, for repetitions in the search
+	history: Vec<u64>,
+	sender: Sender<Event>,
+	events: Receiver<Event>,
+}
+
+fn score_text(score: i32) -> String {
+	// Mates are given in moves, negative when the engine gets mated
+	if score.abs() >= MATE_SCORE - MAX_DEPTH as i32 {
+		let plies = MATE_SCORE - score.abs();
+		let moves = (plies + 1) / 2;
+		return format!("mate {}", if score > 0 { moves } else { -moves });
+	}
+	return format!("cp {}", score);
+}
+
+fn parse_setoption(args: &[&str]) -> Option<(String, String)> {
+	// "name <id> [value <x>]", both the name and the value may contain spaces
+	if args.first() != Some(&"name") {
+		return None;
+	}
+
+	let value_at = args.iter().position(|arg| *arg == "value");
+	let name = args[1..value_at.unwrap_or(args.len())].join(" ");
+	let value = match value_at {
+		Some(index) => args[index + 1..].join(" "),
+		None => String::new(),
+	};
+
+	if name.is_empty() {
+		return None;
+	}
+	return Some((name, value));
+}
+
+fn pv_text(pv: &[Move]) -> String {
+	let mut text = String::new();
+	if !pv.is_empty() {
+		text.push_str(" pv");
+		for mv in pv {
+			text.push_str(&format!(" {}", mv));
+		}
+	}
+	return text;
+}
+
+fn search_output<E: Engine>(engine: &mut E, board: &Board, limits: &SearchLimits, lines: usize) -> Vec<String> {
+	let mut output: Vec<String> = Vec::new();
+
+	// MultiPV gives one info line per move, engines without it fall back to their best move
+	let results = if lines > 1 { engine.multi_pv(board, limits, lines) } else { Vec::new() };
+	let chosen = match results.first() {
+		Some(best) => best.best_move.ok_or(ChessError::NoLegalMoves),
+		None => engine.choose_move(board, limits),
+	};
+
+	match chosen {
+		Ok(mv) => {
+			let pv = engine.pv();
+			if results.is_empty() {
+				if let Some(score) = engine.score() {
+					output.push(format!("info score {}{}", score_text(score), pv_text(&pv)));
+				}
+			}
+			for (index, result) in results.iter().enumerate() {
+				output.push(format!("info depth {} multipv {} score {}{}", result.depth, index + 1, score_text(result.score), pv_text(&result.pv)));
+			}
+
+			// The expected reply is what the GUI lets the engine ponder on
+			match pv.get(1).filter(|_| pv[0] == mv) {
+				Some(reply) => output.push(format!("bestmove {} ponder {}", mv, reply)),
+				None => output.push(format!("bestmove {}", mv)),
+			}
+		}
+		// Nothing to play, GUIs expect the null move
+		Err(_) => output.push("bestmove 0000".to_string()),
+	}
+
+	return output;
+}
+
+fn parse_limits(args: &[&str], board: &Board) -> SearchLimits {
+	// Unknown or malformed parts are skipped, a GUI is never answered with an error here
+	// searchmoves takes the legal moves after it, up to the first word that isn't one
+	let mut limits = SearchLimits::default();
+	let mut clock = Clock::default();
+	let mut timed = false;
+	let mut i = 0;
+	while i < args.len() {
+		let value = args.get(i + 1).and_then(|value| value.parse::<u64>().ok());
+		match (args[i], value) {
+			("ponder", _) => limits.control = SearchControl::pondering(),
+			// Searches until told to stop
+			("infinite", _) => limits.depth = Some(MAX_DEPTH),
+			("depth", Some(depth)) => limits.depth = Some(depth as u32),
+			("nodes", Some(nodes)) => limits.nodes = Some(nodes),
+			("movetime", Some(ms)) => limits.movetime = Some(Duration::from_millis(ms)),
+			("wtime", Some(ms)) => (clock.time[0], timed) = (Duration::from_millis(ms), true),
+			("btime", Some(ms)) => (clock.time[1], timed) = (Duration::from_millis(ms), true),
+			("winc", Some(ms)) => clock.increment[0] = Duration::from_millis(ms),
+			("binc", Some(ms)) => clock.increment[1] = Duration::from_millis(ms),
+			("movestogo", Some(moves)) => clock.moves_to_go = Some(moves as u32),
+			("searchmoves", _) => {
+				let moves = board.legal_moves();
+				i += 1;
+				while let Some(mv) = args.get(i).and_then(|text| moves.iter().find(|mv| mv.to_string() == *text)) {
+					limits.searchmoves.push(*mv);
+					i += 1;
+				}
+				continue;
+			}
+			_ => {
+				i += 1;
+				continue;
+			}
+		}
+		i += 2;
+	}
+
+	if timed {
+		limits.clock = Some(clock);
+	}
+	return limits;
+}
+
+impl<E: Engine + Send + 'static> Uci<E> {
+	pub fn new(mut engine: E) -> Self {
+		let mut options = EngineOptions::default();
+		for option in engine.options() {
+			options.register(option);
+		}
+		engine.set_options(&options);
+
+		let (sender, events) = channel();
+		Uci {
+			engine: Some(engine),
+			search: None,
+			options,
+			board: Board::default(),
+			history: Vec::new(),
+			sender,
+			events,
+		}
+	}
+
+	pub fn engine(&mut self) -> &mut E {
+		// Waits for the search that is running, its output is lost
+		self.wait();
+		return self.engine.as_mut().unwrap();
+	}
+
+	pub fn options(&self) -> &EngineOptions {
+		return &self.options;
+	}
+
+	pub fn board(&self) -> &Board {
+		return &self.board;
+	}
+
+	pub fn is_searching(&self) -> bool {
+		return self.search.is_some();
+	}
+
+	pub fn handle(&mut self, line: &str) -> Option<Vec<String>> {
+		// function to answer one line from the GUI, None once it says "quit"
+		// NOTE: The best move of a search comes later, from wait or stop
+		let args: Vec<&str> = line.split_whitespace().collect();
+		let mut output: Vec<String> = Vec::new();
+
+		match args.first().copied() {
+			Some("uci") => {
+				output.push(format!("id name {}", ENGINE_NAME));
+				output.push(format!("id author {}", ENGINE_AUTHOR));
+				for option in self.options.options() {
+					output.push(option.to_string());
+				}
+				output.push("uciok".to_string());
+			}
+			Some("isready") => output.push("readyok".to_string()),
+			Some("setoption") => {
+				output.extend(self.stop());
+				match parse_setoption(&args[1..]) {
+					Some((name, value)) => match self.options.set(&name, &value) {
+						Ok(()) => self.engine.as_mut().unwrap().set_options(&self.options),
+						Err(error) => output.push(format!("info string {}", error)),
+					},
+					None => output.push("info string malformed setoption".to_string()),
+				}
+			}
+			Some("ucinewgame") => {
+				output.extend(self.stop());
+				self.board = Board::default();
+				self.history.clear();
+			}
+			Some("position") => {
+				output.extend(self.stop());
+				if let Err(text) = self.set_position(&args[1..]) {
+					output.push(format!("info string {}", text));
+				}
+			}
+			Some("go") => {
+				output.extend(self.stop());
+				self.go(&args[1..]);
+			}
+			Some("ponderhit") => {
+				if let Some(search) = &self.search {
+					search.control.ponderhit();
+					search.thread.thread().unpark();
+				}
+			}
+			Some("stop") => output.extend(self.stop()),
+			Some("quit") => {
+				self.stop();
+				return None;
+			}
+			// The protocol says to ignore anything unknown
+			_ => {}
+		}
+
+		return Some(output);
+	}
+
+	fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+		// "startpos | fen <fen>" followed by "moves <move>...", the position is only changed when all of it is valid
+		let moves_at = args.iter().position(|arg| *arg == "moves").unwrap_or(args.len());
+		let mut board = match args.first().copied() {
+			Some("startpos") => Board::default(),
+			Some("fen") => Board::from_fen(&args[1..moves_at].join(" ")).map_err(|error| error.to_string())?,
+			_ => return Err("malformed position".to_string()),
+		};
+
+		let mut history: Vec<u64> = Vec::new();
+		for text in args.iter().skip(moves_at + 1) {
+			let mut mv = board.legal_moves().into_iter().find(|mv| mv.to_string() == *text);
+			if let (None, Some(from), Some(to)) = (mv, text.get(0..2), text.get(2..)) {
+				// "e7e8" from GUIs that leave the piece out, the default policy picks one
+				if let (Ok(from), Ok(to)) = (Coordinate::from_algebraic(from), Coordinate::from_algebraic(to)) {
+					let resolved = board.resolve_promotion(Move { from, to, promotion: None }, &mut PromotionPolicy::default());
+					mv = resolved.promotion.map(|_| resolved);
+				}
+			}
+			match mv {
+				Some(mv) => {
+					history.push(board.zobrist());
+					board.make_move(mv);
+				}
+				None => return Err(format!("illegal move {}", text)),
+			}
+		}
+
+		self.board = board;
+		self.history = history;
+		return Ok(());
+	}
+
+	fn go(&mut self, args: &[&str]) {
+		let mut limits = parse_limits(args, &self.board);
+		limits.history = self.history.clone();
+		let control = limits.control.clone();
+		let mut engine = self.engine.take().unwrap();
+		let board = self.board;
+		let sender = self.sender.clone();
+		let infinite = args.contains(&"infinite");
+		let lines = self.options.multi_pv;
+
+		let thread = thread::spawn(move || {
+			let output = search_output(&mut engine, &board, &limits, lines);
+			while (infinite || limits.control.is_pondering()) && !limits.control.is_stopped() {
+				thread::park();
+			}
+			// Nobody listens once the Uci is gone
+			let _ = sender.send(Event::SearchDone);
+			return (engine, output);
+		});
+
+		self.search = Some(Search {
+			thread,
+			control,
+			infinite,
+		});
+	}
+
+	pub fn wait(&mut self) -> Vec<String> {
+		// Blocks until the search finishes and gives its output, nothing when there is no search
+		// NOTE: A ponder search only finishes after the ponderhit, an infinite one after a stop
+		return match self.search.take() {
+			Some(search) => {
+				let (engine, output) = search.thread.join().unwrap();
+				self.engine = Some(engine);
+				output
+			}
+			None => Vec::new(),
+		};
+	}
+
+	pub fn stop(&mut self) -> Vec<String> {
+		if let Some(search) = &self.search {
+			search.control.stop();
+			search.thread.thread().unpark();
+		}
+		return self.wait();
+	}
+
+	pub fn run<R: BufRead + Send + 'static, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+		// Reads commands until "quit" or the end of the input, search output is written as soon as it is ready
+		let sender = self.sender.clone();
+		thread::spawn(move || {
+			for line in input.lines().map_while(Result::ok) {
+				if sender.send(Event::Line(line)).is_err() {
+					return;
+				}
+			}
+			let _ = sender.send(Event::EndOfInput);
+		});
+
+		while let Ok(event) = self.events.recv() {
+			let lines = match event {
+				Event::Line(line) => match self.handle(&line) {
+					Some(lines) => lines,
+					None => break,
+				},
+				// Stale when the search was already stopped by a command
+				Event::SearchDone if self.search.as_ref().is_some_and(|search| search.thread.is_finished()) => self.wait(),
+				Event::SearchDone => Vec::new(),
+				Event::EndOfInput => {
+					// A search with limits gets to finish, one that waits for the GUI is stopped
+					let endless = self.search.as_ref().is_some_and(|search| search.infinite || search.control.is_pondering());
+					let lines = if endless { self.stop() } else { self.wait() };
+					for line in lines {
+						writeln!(output, "{}", line)?;
+					}
+					output.flush()?;
+					break;
+				}
+			};
+
+			for line in lines {
+				writeln!(output, "{}", line)?;
+			}
+			output.flush()?;
+		}
+		return Ok(());
+	}
+}
@@ -0,0 +1,88 @@
+use std::sync::OnceLock;
+
+use crate::chess::*;
+use crate::rng::Rng;
+
+// Zobrist hashing, every feature of the position gets a random key and the hash is their xor
+// source: https://www.chessprogramming.org/Zobrist_Hashing
+/*
+Two positions with the same hash count as the same position for the repetition rules:
+same pieces on the same squares, same side to move, same castling rights, and the same
+en passant capture, which only counts when it can actually be played
+*/
+
+// Fixed, so hashes stay the same between runs and can be stored
+const SEED: u64 = 0x6368_7965_735f_7a6f;
+
+struct Keys {
+	// Indexed by breed and color (see piece_index), then by square number
+	pieces: [[u64; 64]; 12],
+	black_to_move: u64,
+	castling: [u64; 4],
+	en_passant: [u64; 8],
+}
+
+fn keys() -> &'static Keys {
+	static KEYS: OnceLock<Keys> = OnceLock::new();
+	return KEYS.get_or_init(|| {
+		let mut rng = Rng::new(SEED);
+		let mut keys = Keys {
+			pieces: [[0; 64]; 12],
+			black_to_move: rng.next_u64(),
+			castling: [0; 4],
+			en_passant: [0; 8],
+		};
+
+		for squares in keys.pieces.iter_mut() {
+			for key in squares.iter_mut() {
+				*key = rng.next_u64();
+			}
+		}
+		for key in keys.castling.iter_mut().chain(keys.en_passant.iter_mut()) {
+			*key = rng.next_u64();
+		}
+
+		return keys;
+	});
+}
+
+fn piece_index(piece: Piece) -> usize {
+	return piece.breed as usize * 2 + piece.color as usize;
+}
+
+impl Board {
+	pub fn zobrist(&self) -> u64 {
+		let keys = keys();
+		let mut hash: u64 = 0;
+
+		for (num, piece) in self.white_pieces.iter().chain(self.black_pieces.iter()) {
+			hash ^= keys.pieces[piece_index(*piece)][*num as usize];
+		}
+
+		if self.turn == Color::Black {
+			hash ^= keys.black_to_move;
+		}
+
+		for (i, right) in self.castling_rights.iter().enumerate() {
+			if *right {
+				hash ^= keys.castling[i];
+			}
+		}
+
+		if let Some(sq) = self.en_passant_target_sq {
+			let target = Coordinate::from_number(sq);
+			// Only pawns next to the one that made the double move can take it
+			let capturable = [-1, 1].iter().any(|col_diff| {
+				let from = coord!(target.row + if self.turn == Color::White { 1 } else { -1 }, target.col + col_diff);
+				return self.get_piece(from) == Some(Piece { breed: Pieces::Pawn, color: self.turn })
+				&& self.get_moves(from).contains(&sq);
+			});
+
+			if capturable {
+				hash ^= keys.en_passant[target.col as usize];
+			}
+		}
+
+		return hash;
+	}
+}